@@ -1,17 +1,25 @@
 use axum::{
     extract::{
         ws::{Message as AxumWsMsg, WebSocket, WebSocketUpgrade},
-        ConnectInfo, State,
+        ConnectInfo, Query, State,
     },
     http::{header, HeaderMap, StatusCode, Uri},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use futures_util::{SinkExt, StreamExt};
+use pi_session_manager::auth::{self, Caller};
+use pi_session_manager::command_registry::{self, CommandHost, TerminalCreateRequest};
+use pi_session_manager::rest_api;
+use pi_session_manager::session_tail::{self, EventsQuery};
+use pi_session_manager::tls::TlsIdentity;
+use pi_session_manager::{backup, cli, mcp, scanner};
 use rust_embed::Embed;
 use serde_json::Value;
-use std::net::SocketAddr;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{error, info};
@@ -36,6 +44,51 @@ pub struct AppState {
     pub terminal_manager: Mutex<TerminalManager>,
 }
 
+impl CommandHost for AppState {
+    fn emit(&self, event: &str, payload: Value) {
+        let _ = self.event_tx.send(WsEvent {
+            event_type: "event".to_string(),
+            event: event.to_string(),
+            payload,
+        });
+    }
+
+    fn terminal_create(&self, request: TerminalCreateRequest) -> Result<String, String> {
+        self.terminals()?.create_session(
+            request.id,
+            self.event_tx.clone(),
+            request.cwd,
+            request.shell,
+            request.rows,
+            request.cols,
+        )
+    }
+
+    fn terminal_write(&self, id: &str, data: String) -> Result<(), String> {
+        self.terminals()?.write_to_session(id, data)
+    }
+
+    fn terminal_resize(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
+        self.terminals()?.resize_session(id, rows, cols)
+    }
+
+    fn terminal_close(&self, id: &str) -> Result<(), String> {
+        self.terminals()?.close_session(id)
+    }
+
+    fn shells(&self) -> Result<Vec<(String, String)>, String> {
+        Ok(terminal::scan_shells())
+    }
+}
+
+impl AppState {
+    fn terminals(&self) -> Result<std::sync::MutexGuard<'_, TerminalManager>, String> {
+        self.terminal_manager
+            .lock()
+            .map_err(|e| format!("Failed to lock terminal manager: {e}"))
+    }
+}
+
 pub type SharedState = Arc<AppState>;
 
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -48,6 +101,19 @@ struct ServerConfig {
     bind_addr: String,
     #[serde(default)]
     auth_enabled: bool,
+    /// Also serve MCP over streamable HTTP at /mcp
+    #[serde(default)]
+    mcp_enabled: bool,
+    /// Serve over TLS; without cert/key paths a self-signed certificate is generated
+    #[serde(default)]
+    tls_enabled: bool,
+    #[serde(default)]
+    tls_cert_path: Option<String>,
+    #[serde(default)]
+    tls_key_path: Option<String>,
+    /// Refuse every mutating command (delete, rename, settings, terminals, keys)
+    #[serde(default)]
+    read_only: bool,
 }
 
 fn default_true() -> bool {
@@ -67,6 +133,11 @@ impl Default for ServerConfig {
             http_port: 52131,
             bind_addr: "0.0.0.0".to_string(),
             auth_enabled: false,
+            mcp_enabled: false,
+            tls_enabled: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+            read_only: false,
         }
     }
 }
@@ -81,35 +152,15 @@ fn load_config() -> ServerConfig {
         .unwrap_or_default()
 }
 
-fn query_param(uri: &Uri, key: &str) -> Option<String> {
-    uri.query().and_then(|q| {
-        q.split('&').find_map(|pair| {
-            let mut it = pair.splitn(2, '=');
-            let k = it.next()?;
-            let v = it.next().unwrap_or("");
-            (k == key).then(|| v.to_string())
-        })
-    })
-}
-
-fn is_authorized(ip: &std::net::IpAddr, headers: &HeaderMap, uri: &Uri) -> bool {
-    let real_ip = get_real_ip(ip, headers);
-    if !pi_session_manager::auth::is_auth_required(&real_ip) {
-        return true;
-    }
-    let header_ok = headers
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.strip_prefix("Bearer "))
-        .map(pi_session_manager::auth::validate)
-        .unwrap_or(false);
-    if header_ok {
-        return true;
-    }
-    query_param(uri, "token")
-        .as_deref()
-        .map(pi_session_manager::auth::validate)
-        .unwrap_or(false)
+/// Who is calling; `None` when a token is required and missing or invalid
+fn authenticate(
+    ip: &IpAddr,
+    headers: &HeaderMap,
+    uri: &Uri,
+    transport: &'static str,
+) -> Option<Caller> {
+    let token = auth::request_token(headers, uri);
+    auth::authenticate(get_real_ip(ip, headers), token.as_deref(), transport)
 }
 
 /// Extract real client IP from X-Forwarded-For (ngrok/reverse proxy) or use socket IP
@@ -126,9 +177,15 @@ fn get_real_ip(socket_ip: &std::net::IpAddr, headers: &HeaderMap) -> std::net::I
 
 #[tokio::main]
 async fn main() {
+    cli::main(serve).await;
+}
+
+async fn serve(read_only: bool) {
     tracing_subscriber::fmt::init();
 
     let config = load_config();
+    let read_only = read_only || config.read_only;
+    command_registry::set_read_only(read_only);
     let (event_tx, _) = broadcast::channel(100);
     let state = Arc::new(AppState {
         event_tx,
@@ -148,11 +205,42 @@ async fn main() {
         info!("🔓 Auth disabled (set auth_enabled=true in config)");
     }
 
+    if read_only {
+        info!("🛡️  Read-only mode: deleting, renaming, settings, terminals and keys are disabled");
+    }
+
+    let tls = if config.tls_enabled {
+        match TlsIdentity::load(
+            config.tls_cert_path.as_deref(),
+            config.tls_key_path.as_deref(),
+            &[config.bind_addr.as_str()],
+        ) {
+            Ok(identity) => {
+                let origin = if identity.self_signed {
+                    "self-signed"
+                } else {
+                    "configured"
+                };
+                info!("🔒 TLS ({origin}), SHA-256: {}", identity.fingerprint);
+                Some(identity)
+            }
+            Err(e) => {
+                error!("Failed to load TLS certificate: {e}");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
     if !config.http_enabled {
         error!("HTTP server is disabled in config (http_enabled=false), nothing to start");
         return;
     }
 
+    tokio::spawn(scanner::forward_progress(state.clone()));
+    tokio::spawn(backup::run_scheduler());
+
     let _watcher_guard = match file_watcher::CliFileWatcher::start(state.event_tx.clone()) {
         Ok(w) => {
             info!("👀 File watcher started");
@@ -165,12 +253,17 @@ async fn main() {
     };
 
     let addr = format!("{}:{}", config.bind_addr, config.http_port);
-    info!("🌐 http://{addr}  (API + WS + Frontend)");
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("🌐 {scheme}://{addr}  (API + WS + Frontend)");
+    if config.mcp_enabled {
+        info!("🤖 MCP at {scheme}://{addr}{}", mcp::MCP_PATH);
+    }
     info!("═══════════════════════════════════════");
 
     let s = state.clone();
+    let mcp_enabled = config.mcp_enabled;
     let handle = tokio::spawn(async move {
-        if let Err(e) = run_server(s, &addr).await {
+        if let Err(e) = run_server(s, &addr, mcp_enabled, tls).await {
             error!("Server error: {e}");
         }
     });
@@ -197,14 +290,14 @@ async fn api_handler(
     uri: Uri,
     Json(body): Json<ApiReq>,
 ) -> impl IntoResponse {
-    if !is_authorized(&addr.ip(), &headers, &uri) {
+    let Some(caller) = authenticate(&addr.ip(), &headers, &uri, "http") else {
         return (
             StatusCode::UNAUTHORIZED,
             cors_headers(),
             Json(serde_json::json!({ "success": false, "error": "Unauthorized" })),
         );
-    }
-    let result = dispatch_command(&state, &body.command, &body.payload).await;
+    };
+    let result = dispatch_command(&state, &caller, &body.command, &body.payload).await;
     let resp = match result {
         Ok(data) => serde_json::json!({ "success": true, "data": data }),
         Err(e) => serde_json::json!({ "success": false, "error": e }),
@@ -222,14 +315,16 @@ async fn auth_check(
     uri: Uri,
 ) -> impl IntoResponse {
     let real_ip = get_real_ip(&addr.ip(), &headers);
-    let needs_auth = pi_session_manager::auth::is_auth_required(&real_ip);
-    let is_valid = is_authorized(&addr.ip(), &headers, &uri);
+    let needs_auth = auth::is_auth_required(&real_ip);
+    // A probe, not a request: look the token up without auditing
+    let grant = auth::request_token(&headers, &uri).and_then(|t| auth::lookup(&t));
     (
         StatusCode::OK,
         cors_headers(),
         Json(serde_json::json!({
             "needsAuth": needs_auth,
-            "authenticated": is_valid,
+            "authenticated": !needs_auth || grant.is_some(),
+            "scope": grant.map_or(auth::Scope::Admin, |g| g.scope),
         })),
     )
 }
@@ -238,75 +333,60 @@ async fn health_handler() -> Json<Value> {
     Json(serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION"), "mode": "cli" }))
 }
 
-fn extract_string(payload: &Value, key: &str) -> Result<String, String> {
-    payload
-        .get(key)
-        .and_then(Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| format!("Missing or invalid '{key}'"))
-}
-
 async fn dispatch_command(
     state: &SharedState,
+    caller: &Caller,
     command: &str,
     payload: &Value,
 ) -> Result<Value, String> {
-    match command {
-        "terminal_create" => {
-            let id = extract_string(payload, "id")?;
-            let cwd = extract_string(payload, "cwd")?;
-            let shell = extract_string(payload, "shell")?;
-            let rows = payload.get("rows").and_then(Value::as_u64).unwrap_or(24) as u16;
-            let cols = payload.get("cols").and_then(Value::as_u64).unwrap_or(80) as u16;
-            let manager = state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            let result =
-                manager.create_session(id, state.event_tx.clone(), cwd, shell, rows, cols)?;
-            Ok(serde_json::json!(result))
-        }
-        "terminal_write" => {
-            let id = extract_string(payload, "id")?;
-            let data = extract_string(payload, "data")?;
-            let manager = state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.write_to_session(&id, data)?;
-            Ok(Value::Null)
-        }
-        "terminal_resize" => {
-            let id = extract_string(payload, "id")?;
-            let rows = payload.get("rows").and_then(Value::as_u64).unwrap_or(24) as u16;
-            let cols = payload.get("cols").and_then(Value::as_u64).unwrap_or(80) as u16;
-            let manager = state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.resize_session(&id, rows, cols)?;
-            Ok(Value::Null)
-        }
-        "terminal_close" => {
-            let id = extract_string(payload, "id")?;
-            let manager = state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.close_session(&id)?;
-            Ok(Value::Null)
-        }
-        "get_default_shell" => {
-            let shells = terminal::scan_shells();
-            let fallback = if cfg!(windows) { "cmd.exe" } else { "/bin/sh" };
-            Ok(serde_json::json!(shells
-                .first()
-                .map(|(_, p)| p.as_str())
-                .unwrap_or(fallback)))
-        }
-        "get_available_shells" => Ok(serde_json::json!(terminal::scan_shells())),
-        _ => pi_session_manager::dispatch::dispatch(command, payload).await,
+    command_registry::dispatch_as(caller, command, payload.clone(), state.clone()).await
+}
+
+/// Server-sent events: `sessions-changed`, or with `?session=` the appended
+/// entries of one session
+async fn events_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    uri: Uri,
+    Query(query): Query<EventsQuery>,
+) -> Response {
+    let Some(caller) = authenticate(&addr.ip(), &headers, &uri, "sse") else {
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+    if let Some(session) = &query.session {
+        return session_tail::sse_response(caller, session, query.offset).await;
     }
+    caller.audit("subscribe_events", "ok", None);
+
+    let rx = state.event_tx.subscribe();
+    let events = futures_util::stream::unfold((rx, caller), |(mut rx, caller)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(mut e) => {
+                    if e.event == "sessions-changed"
+                        && command_registry::event_for_caller(&caller, &e.event, &mut e.payload)
+                            .await
+                    {
+                        let event = SseEvent::default()
+                            .event("sessions-changed")
+                            .data(e.payload.to_string());
+                        return Some((Ok::<_, Infallible>(event), (rx, caller)));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    (
+        [
+            ("access-control-allow-origin", "*"),
+            ("cache-control", "no-cache"),
+        ],
+        Sse::new(events).keep_alive(KeepAlive::default()),
+    )
+        .into_response()
 }
 
 async fn ws_upgrade(
@@ -316,10 +396,15 @@ async fn ws_upgrade(
     uri: Uri,
     ws: WebSocketUpgrade,
 ) -> Response {
-    let pre_authed = is_authorized(&addr.ip(), &headers, &uri);
     let real_ip = get_real_ip(&addr.ip(), &headers);
-    let needs_auth = pi_session_manager::auth::is_auth_required(&real_ip);
-    ws.on_upgrade(move |socket| handle_ws(socket, state, pre_authed, needs_auth))
+    // Without a token in the URL the client authenticates with its first message
+    let caller =
+        if auth::is_auth_required(&real_ip) && auth::request_token(&headers, &uri).is_none() {
+            None
+        } else {
+            authenticate(&addr.ip(), &headers, &uri, "ws")
+        };
+    ws.on_upgrade(move |socket| handle_ws(socket, state, caller, real_ip))
 }
 
 async fn static_handler(uri: Uri) -> Response {
@@ -348,25 +433,33 @@ async fn static_handler(uri: Uri) -> Response {
 
 // ─── Unified server ─────────────────────────────────────────
 
-async fn run_server(state: SharedState, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let app = Router::new()
+async fn run_server(
+    state: SharedState,
+    addr: &str,
+    mcp_enabled: bool,
+    tls: Option<TlsIdentity>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = Router::new()
         .route(
             "/api/auth-check",
             get(auth_check).options(preflight_handler),
         )
         .route("/api", post(api_handler).options(preflight_handler))
         .route("/health", get(health_handler))
+        .route("/api/events", get(events_handler))
         .route("/ws", get(ws_upgrade))
-        .fallback(static_handler)
-        .with_state(state);
+        .merge(rest_api::router(
+            |ip, headers| get_real_ip(&ip, headers),
+            state.clone(),
+        ));
+    if mcp_enabled {
+        app = app.merge(mcp::router(|ip, headers| get_real_ip(&ip, headers)));
+    }
+    let app = app.fallback(static_handler).with_state(state);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("Server listening on {addr}");
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await?;
+    pi_session_manager::tls::serve(listener, app, tls.as_ref()).await?;
     Ok(())
 }
 
@@ -383,31 +476,39 @@ fn cors_headers() -> [(&'static str, &'static str); 3] {
 
 // ─── WebSocket handler ──────────────────────────────────────
 
-async fn handle_ws(socket: WebSocket, state: SharedState, pre_authed: bool, needs_auth: bool) {
+async fn handle_ws(
+    socket: WebSocket,
+    state: SharedState,
+    caller: Option<Caller>,
+    client_ip: IpAddr,
+) {
     let (mut tx, mut rx) = socket.split();
 
     // Auth handshake if needed
-    if needs_auth && !pre_authed {
-        let authed = match tokio::time::timeout(std::time::Duration::from_secs(10), rx.next()).await
-        {
-            Ok(Some(Ok(AxumWsMsg::Text(text)))) => serde_json::from_str::<Value>(&text)
-                .ok()
-                .and_then(|v| v.get("auth")?.as_str().map(String::from))
-                .map(|t| pi_session_manager::auth::validate(&t))
-                .unwrap_or(false),
-            _ => false,
-        };
-        if !authed {
-            let _ = tx
-                .send(AxumWsMsg::Text(r#"{"error":"Unauthorized"}"#.into()))
-                .await;
-            let _ = tx.close().await;
-            return;
+    let caller = match caller {
+        Some(caller) => caller,
+        None => {
+            let token =
+                match tokio::time::timeout(std::time::Duration::from_secs(10), rx.next()).await {
+                    Ok(Some(Ok(AxumWsMsg::Text(text)))) => serde_json::from_str::<Value>(&text)
+                        .ok()
+                        .and_then(|v| v.get("auth")?.as_str().map(String::from)),
+                    _ => None,
+                };
+            let Some(caller) = auth::authenticate(client_ip, token.as_deref(), "ws") else {
+                let _ = tx
+                    .send(AxumWsMsg::Text(r#"{"error":"Unauthorized"}"#.into()))
+                    .await;
+                let _ = tx.close().await;
+                return;
+            };
+            let _ = tx.send(AxumWsMsg::Text(r#"{"auth":"ok"}"#.into())).await;
+            caller
         }
-        let _ = tx.send(AxumWsMsg::Text(r#"{"auth":"ok"}"#.into())).await;
-    }
+    };
 
     let mut event_rx = state.event_tx.subscribe();
+    let (tails, mut tail_rx) = session_tail::Subscriptions::new(caller.clone(), state.clone());
 
     loop {
         tokio::select! {
@@ -428,7 +529,9 @@ async fn handle_ws(socket: WebSocket, state: SharedState, pre_authed: bool, need
 
                         match serde_json::from_str::<WsReq>(&text) {
                             Ok(req) => {
-                                let result = dispatch_command(&state, &req.command, &req.payload).await;
+                                // The connection's subscriptions host subscribe_session
+                                let result = command_registry::dispatch_as(&caller, &req.command, req.payload, tails.clone())
+                                    .await;
                                 let resp = match result {
                                     Ok(data) => serde_json::json!({ "id": req.id, "command": req.command, "success": true, "data": data }),
                                     Err(e) => serde_json::json!({ "id": req.id, "command": req.command, "success": false, "error": e }),
@@ -447,8 +550,13 @@ async fn handle_ws(socket: WebSocket, state: SharedState, pre_authed: bool, need
                     _ => {}
                 }
             }
+            Some(payload) = tail_rx.recv() => {
+                let msg = serde_json::json!({ "event_type": "event", "event": session_tail::APPENDED_EVENT, "payload": payload });
+                if tx.send(AxumWsMsg::Text(msg.to_string())).await.is_err() { break; }
+            }
             evt = event_rx.recv() => {
-                if let Ok(e) = evt {
+                if let Ok(mut e) = evt {
+                    if !command_registry::event_for_caller(&caller, &e.event, &mut e.payload).await { continue; }
                     let msg = serde_json::json!({ "event_type": e.event_type, "event": e.event, "payload": e.payload });
                     if tx.send(AxumWsMsg::Text(msg.to_string())).await.is_err() { break; }
                 }
@@ -1,7 +1,8 @@
+use crate::command_registry::{CommandHost, TerminalCreateRequest};
 use crate::terminal::TerminalManager;
 use std::sync::Arc;
 use std::sync::Mutex;
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::broadcast;
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -30,6 +31,66 @@ impl AppState {
     pub fn subscribe_events(&self) -> broadcast::Receiver<WsEvent> {
         self.event_tx.subscribe()
     }
+
+    fn terminals(&self) -> Result<std::sync::MutexGuard<'_, TerminalManager>, String> {
+        self.terminal_manager
+            .lock()
+            .map_err(|e| format!("Failed to lock terminal manager: {e}"))
+    }
+}
+
+impl CommandHost for AppState {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.app_handle.emit(event, &payload);
+        let _ = self.event_tx.send(WsEvent {
+            event_type: "event".to_string(),
+            event: event.to_string(),
+            payload,
+        });
+    }
+
+    fn session_paths_changed(&self) {
+        let Some(watcher_state) = self
+            .app_handle
+            .try_state::<crate::file_watcher::FileWatcherState>()
+        else {
+            return;
+        };
+        if let Err(e) = crate::file_watcher::restart_watcher_with_config(
+            &watcher_state,
+            self.app_handle.clone(),
+        ) {
+            log::warn!("Failed to restart file watcher: {e}");
+        }
+    }
+
+    fn terminal_create(&self, request: TerminalCreateRequest) -> Result<String, String> {
+        self.terminals()?.create_session(
+            request.id,
+            self.app_handle.clone(),
+            self.event_tx.clone(),
+            request.cwd,
+            request.shell,
+            request.rows,
+            request.cols,
+        )
+    }
+
+    fn terminal_write(&self, id: &str, data: String) -> Result<(), String> {
+        self.terminals()?.write_to_session(id, data)
+    }
+
+    fn terminal_resize(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
+        self.terminals()?.resize_session(id, rows, cols)
+    }
+
+    fn terminal_close(&self, id: &str) -> Result<(), String> {
+        self.terminals()?.close_session(id)
+    }
+
+    fn shells(&self) -> Result<Vec<(String, String)>, String> {
+        Ok(crate::commands::terminal::scan_shells())
+    }
 }
 
 pub type SharedAppState = Arc<AppState>;
@@ -1,60 +1,202 @@
+use axum::http::{HeaderMap, Uri};
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use rusqlite::{params, Connection};
-use serde::Serialize;
-use std::collections::HashSet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
+/// Audit rows kept; older ones are pruned on insert
+const AUDIT_LOG_LIMIT: i64 = 10_000;
+
 lazy_static! {
-    static ref TOKENS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    static ref TOKENS: Mutex<HashMap<String, TokenGrant>> = Mutex::new(HashMap::new());
     static ref ENABLED: Mutex<bool> = Mutex::new(false);
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// What a token may do. Each scope includes the ones before it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Search commands only
+    Search,
+    /// Read sessions, tags, favorites and settings
+    Read,
+    /// Also rename, delete, tag, favorite and change app settings
+    Write,
+    /// Also terminals, API keys, server and redaction settings
+    Admin,
+}
+
+impl Scope {
+    pub fn allows(self, required: Scope) -> bool {
+        self >= required
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Search => "search",
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Scope {
+        match value {
+            "search" => Scope::Search,
+            "read" => Scope::Read,
+            "write" => Scope::Write,
+            // Tokens created before scopes existed had full access
+            _ => Scope::Admin,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct TokenInfo {
     pub name: String,
     pub key_preview: String,
     pub created_at: String,
     pub last_used: Option<String>,
+    pub scope: Scope,
+    pub expires_at: Option<String>,
+    pub allowed_paths: Vec<String>,
+    pub expired: bool,
+}
+
+/// Restrictions chosen when creating a token
+#[derive(Debug, Clone)]
+pub struct TokenOptions {
+    pub scope: Scope,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Project directories (session cwd prefixes); empty means all
+    pub allowed_paths: Vec<String>,
 }
 
-fn db_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Cannot find home directory")?;
-    let dir = home.join(".pi").join("agent").join("sessions");
-    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create dir: {e}"))?;
-    Ok(dir.join("sessions.db"))
+impl Default for TokenOptions {
+    fn default() -> Self {
+        Self {
+            scope: Scope::Admin,
+            expires_at: None,
+            allowed_paths: Vec::new(),
+        }
+    }
 }
 
-fn open_db() -> Result<Connection, String> {
-    Connection::open(db_path()?).map_err(|e| format!("Failed to open DB: {e}"))
+/// A valid token and what it grants
+#[derive(Debug, Clone)]
+pub struct TokenGrant {
+    pub key_preview: String,
+    pub scope: Scope,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub allowed_paths: Vec<String>,
 }
 
-fn ensure_columns(conn: &Connection) {
-    // Add last_used column if missing
-    let _ = conn.execute("ALTER TABLE auth_tokens ADD COLUMN last_used TEXT", []);
+impl TokenGrant {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
 }
 
-pub fn init() -> Result<String, String> {
-    let conn = open_db()?;
+/// The client behind a request. Callers without a grant (local clients, or
+/// any client while auth is off) have full access and are not audited.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub ip: IpAddr,
+    /// `http`, `ws`, `sse`, `rest` or `mcp`
+    pub transport: &'static str,
+    pub grant: Option<TokenGrant>,
+}
+
+impl Caller {
+    /// A caller with full access, e.g. the desktop webview or a CLI command
+    pub fn local(transport: &'static str) -> Self {
+        Self {
+            ip: IpAddr::from([127, 0, 0, 1]),
+            transport,
+            grant: None,
+        }
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.grant.as_ref().map_or(Scope::Admin, |g| g.scope)
+    }
+
+    pub fn allows(&self, required: Scope) -> bool {
+        self.scope().allows(required)
+    }
+
+    /// Whether the caller is limited to some project directories
+    pub fn is_path_restricted(&self) -> bool {
+        self.grant
+            .as_ref()
+            .is_some_and(|g| !g.allowed_paths.is_empty())
+    }
+
+    /// Whether a session started in `cwd` is visible to the caller
+    pub fn allows_cwd(&self, cwd: &str) -> bool {
+        let Some(grant) = &self.grant else {
+            return true;
+        };
+        grant.allowed_paths.is_empty()
+            || grant.allowed_paths.iter().any(|prefix| {
+                let prefix = prefix.trim_end_matches('/');
+                cwd == prefix || cwd.starts_with(&format!("{prefix}/"))
+            })
+    }
+
+    /// Record `command` and its outcome if this caller authenticated with a token
+    pub fn audit(&self, command: &str, outcome: &str, detail: Option<&str>) {
+        if let Some(grant) = &self.grant {
+            record_audit(
+                Some(&grant.key_preview),
+                &self.ip,
+                self.transport,
+                command,
+                outcome,
+                detail,
+            );
+        }
+    }
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS auth_tokens (
-            token TEXT PRIMARY KEY,
-            name TEXT,
-            created_at TEXT NOT NULL,
-            last_used TEXT
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create auth_tokens table: {e}"))?;
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: String,
+    pub token_preview: Option<String>,
+    pub ip: String,
+    pub transport: String,
+    pub command: String,
+    pub outcome: String,
+    pub detail: Option<String>,
+}
 
-    ensure_columns(&conn);
+pub fn init() -> Result<String, String> {
+    let token = crate::database::get()?.write(|conn| {
+        let token = default_token(conn)?;
+        reload_tokens(conn)?;
+        Ok(token)
+    })?;
+    *ENABLED.lock().unwrap() = true;
+
+    Ok(token)
+}
 
+/// The unrestricted token, created on first start
+fn default_token(conn: &Connection) -> Result<String, String> {
     let existing: Option<String> = conn
-        .query_row("SELECT token FROM auth_tokens LIMIT 1", [], |row| {
-            row.get(0)
-        })
+        .query_row(
+            "SELECT token FROM auth_tokens WHERE scope = 'admin' AND expires_at IS NULL LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
         .ok();
 
     let token = match existing {
@@ -62,26 +204,46 @@ pub fn init() -> Result<String, String> {
         None => {
             let t = "pi-session-manager".to_string();
             conn.execute(
-                "INSERT INTO auth_tokens (token, name, created_at) VALUES (?1, ?2, ?3)",
-                params![t, "default", chrono::Utc::now().to_rfc3339()],
+                "INSERT OR IGNORE INTO auth_tokens (token, name, created_at) VALUES (?1, ?2, ?3)",
+                params![t, "default", Utc::now().to_rfc3339()],
             )
             .map_err(|e| format!("Failed to insert token: {e}"))?;
             t
         }
     };
+    Ok(token)
+}
 
-    reload_tokens(&conn)?;
-    *ENABLED.lock().unwrap() = true;
+fn preview(token: &str) -> String {
+    match token.get(..8) {
+        Some(head) if token.len() > 8 => format!("{head}…"),
+        _ => token.to_string(),
+    }
+}
 
-    Ok(token)
+fn parse_time(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|t| t.with_timezone(&Utc))
 }
 
-fn reload_tokens(conn: &Connection) -> Result<(), String> {
+pub(crate) fn reload_tokens(conn: &Connection) -> Result<(), String> {
     let mut stmt = conn
-        .prepare("SELECT token FROM auth_tokens")
+        .prepare("SELECT token, scope, expires_at, allowed_paths FROM auth_tokens")
         .map_err(|e| format!("Failed to query tokens: {e}"))?;
-    let tokens: HashSet<String> = stmt
-        .query_map([], |row| row.get(0))
+    let tokens: HashMap<String, TokenGrant> = stmt
+        .query_map([], |row| {
+            let token: String = row.get(0)?;
+            let scope: String = row.get(1)?;
+            let allowed_paths: String = row.get(3)?;
+            let grant = TokenGrant {
+                key_preview: preview(&token),
+                scope: Scope::parse(&scope),
+                expires_at: parse_time(row.get(2)?),
+                allowed_paths: serde_json::from_str(&allowed_paths).unwrap_or_default(),
+            };
+            Ok((token, grant))
+        })
         .map_err(|e| format!("{e}"))?
         .filter_map(|r| r.ok())
         .collect();
@@ -90,25 +252,31 @@ fn reload_tokens(conn: &Connection) -> Result<(), String> {
 }
 
 pub fn list_tokens() -> Result<Vec<TokenInfo>, String> {
-    let conn = open_db()?;
+    crate::database::get()?.read(read_tokens)
+}
+
+fn read_tokens(conn: &Connection) -> Result<Vec<TokenInfo>, String> {
     let mut stmt = conn
         .prepare(
-            "SELECT token, name, created_at, last_used FROM auth_tokens ORDER BY created_at DESC",
+            "SELECT token, name, created_at, last_used, scope, expires_at, allowed_paths
+             FROM auth_tokens ORDER BY created_at DESC",
         )
         .map_err(|e| format!("{e}"))?;
     let rows = stmt
         .query_map([], |row| {
             let token: String = row.get(0)?;
-            let preview = if token.len() >= 8 {
-                format!("{}…", &token[..8])
-            } else {
-                token.clone()
-            };
+            let scope: String = row.get(4)?;
+            let expires_at: Option<String> = row.get(5)?;
+            let allowed_paths: String = row.get(6)?;
             Ok(TokenInfo {
                 name: row.get(1)?,
-                key_preview: preview,
+                key_preview: preview(&token),
                 created_at: row.get(2)?,
                 last_used: row.get(3)?,
+                scope: Scope::parse(&scope),
+                expired: parse_time(expires_at.clone()).is_some_and(|at| at <= Utc::now()),
+                expires_at,
+                allowed_paths: serde_json::from_str(&allowed_paths).unwrap_or_default(),
             })
         })
         .map_err(|e| format!("{e}"))?;
@@ -116,8 +284,11 @@ pub fn list_tokens() -> Result<Vec<TokenInfo>, String> {
         .map_err(|e| format!("{e}"))
 }
 
-pub fn create_token(name: &str, token_value: Option<&str>) -> Result<String, String> {
-    let conn = open_db()?;
+pub fn create_token(
+    name: &str,
+    token_value: Option<&str>,
+    options: &TokenOptions,
+) -> Result<String, String> {
     let token = match token_value {
         Some(raw) => {
             let trimmed = raw.trim();
@@ -128,54 +299,97 @@ pub fn create_token(name: &str, token_value: Option<&str>) -> Result<String, Str
         }
         None => generate_token(),
     };
+    if options.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err("Expiry must be in the future".to_string());
+    }
+    let allowed_paths: Vec<&str> = options
+        .allowed_paths
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let row = (
+        token.clone(),
+        name.to_string(),
+        options.scope.as_str(),
+        options.expires_at.map(|t| t.to_rfc3339()),
+        serde_json::to_string(&allowed_paths).unwrap_or_else(|_| "[]".to_string()),
+    );
 
-    conn.execute(
-        "INSERT INTO auth_tokens (token, name, created_at) VALUES (?1, ?2, ?3)",
-        params![token, name, chrono::Utc::now().to_rfc3339()],
-    )
-    .map_err(|e| {
-        if e.to_string().contains("UNIQUE constraint failed") {
-            "Token already exists".to_string()
-        } else {
-            format!("Failed to create token: {e}")
-        }
+    crate::database::get()?.write(move |conn| {
+        let (token, name, scope, expires_at, allowed_paths) = row;
+        conn.execute(
+            "INSERT INTO auth_tokens (token, name, created_at, scope, expires_at, allowed_paths)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                token,
+                name,
+                Utc::now().to_rfc3339(),
+                scope,
+                expires_at,
+                allowed_paths
+            ],
+        )
+        .map_err(|e| {
+            if e.to_string().contains("UNIQUE constraint failed") {
+                "Token already exists".to_string()
+            } else {
+                format!("Failed to create token: {e}")
+            }
+        })?;
+        reload_tokens(conn)
     })?;
-    reload_tokens(&conn)?;
     Ok(token)
 }
 
 pub fn revoke_token(key_preview: &str) -> Result<(), String> {
-    let conn = open_db()?;
     let prefix = key_preview.trim_end_matches('…');
     let pattern = format!("{prefix}%");
-    let deleted = conn
-        .execute(
-            "DELETE FROM auth_tokens WHERE token LIKE ?1",
-            params![pattern],
-        )
-        .map_err(|e| format!("Failed to revoke: {e}"))?;
-    if deleted == 0 {
-        return Err("Token not found".to_string());
-    }
-    reload_tokens(&conn)?;
-    Ok(())
+    crate::database::get()?.write(move |conn| {
+        let deleted = conn
+            .execute(
+                "DELETE FROM auth_tokens WHERE token LIKE ?1",
+                params![pattern],
+            )
+            .map_err(|e| format!("Failed to revoke: {e}"))?;
+        if deleted == 0 {
+            return Err("Token not found".to_string());
+        }
+        reload_tokens(conn)
+    })
 }
 
 pub fn update_last_used(token: &str) {
-    if let Ok(conn) = open_db() {
-        let _ = conn.execute(
+    let Ok(db) = crate::database::get() else {
+        return;
+    };
+    let (now, token) = (Utc::now().to_rfc3339(), token.to_string());
+    db.submit(move |conn| {
+        conn.execute(
             "UPDATE auth_tokens SET last_used = ?1 WHERE token = ?2",
-            params![chrono::Utc::now().to_rfc3339(), token],
-        );
-    }
+            params![now, token],
+        )
+        .map_err(|e| format!("Failed to update last_used: {e}"))?;
+        Ok(())
+    });
 }
 
-pub fn validate(token: &str) -> bool {
-    let valid = TOKENS.lock().unwrap().contains(token);
-    if valid {
+/// The grant for a known, unexpired token
+pub fn lookup(token: &str) -> Option<TokenGrant> {
+    let grant = TOKENS
+        .lock()
+        .unwrap()
+        .get(token)
+        .filter(|g| !g.is_expired())
+        .cloned();
+    if grant.is_some() {
         update_last_used(token);
     }
-    valid
+    grant
+}
+
+pub fn validate(token: &str) -> bool {
+    lookup(token).is_some()
 }
 
 pub fn is_auth_required(ip: &IpAddr) -> bool {
@@ -186,6 +400,124 @@ pub fn is_local(ip: &IpAddr) -> bool {
     ip.is_loopback()
 }
 
+/// Token from `Authorization: Bearer …` or the `token` query parameter
+pub fn request_token(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    bearer.or_else(|| {
+        uri.query()?.split('&').find_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            (k == "token").then(|| v.to_string())
+        })
+    })
+}
+
+/// Resolve who is calling. `None` means the request must be rejected; the
+/// attempt is then recorded in the audit log.
+pub fn authenticate(ip: IpAddr, token: Option<&str>, transport: &'static str) -> Option<Caller> {
+    if !is_auth_required(&ip) {
+        return Some(Caller {
+            ip,
+            transport,
+            grant: None,
+        });
+    }
+    match token.and_then(lookup) {
+        Some(grant) => Some(Caller {
+            ip,
+            transport,
+            grant: Some(grant),
+        }),
+        None => {
+            let detail = if token.is_some() {
+                "invalid or expired token"
+            } else {
+                "missing token"
+            };
+            record_audit(
+                token.map(preview).as_deref(),
+                &ip,
+                transport,
+                "connect",
+                "unauthorized",
+                Some(detail),
+            );
+            None
+        }
+    }
+}
+
+pub fn record_audit(
+    token_preview: Option<&str>,
+    ip: &IpAddr,
+    transport: &str,
+    command: &str,
+    outcome: &str,
+    detail: Option<&str>,
+) {
+    let Ok(db) = crate::database::get() else {
+        return;
+    };
+    let row = (
+        Utc::now().to_rfc3339(),
+        token_preview.map(str::to_string),
+        ip.to_string(),
+        transport.to_string(),
+        command.to_string(),
+        outcome.to_string(),
+        detail.map(str::to_string),
+    );
+    // Queued so request handlers never wait on the writer
+    db.submit(move |conn| {
+        let (timestamp, token_preview, ip, transport, command, outcome, detail) = row;
+        conn.execute(
+            "INSERT INTO audit_log (timestamp, token_preview, ip, transport, command, outcome, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![timestamp, token_preview, ip, transport, command, outcome, detail],
+        )
+        .map_err(|e| format!("Failed to write audit log: {e}"))?;
+        let _ = conn.execute(
+            "DELETE FROM audit_log WHERE id <= (SELECT MAX(id) FROM audit_log) - ?1",
+            params![AUDIT_LOG_LIMIT],
+        );
+        Ok(())
+    });
+}
+
+/// Most recent audit entries first. Read on the writer thread so entries
+/// `record_audit` has queued are included.
+pub fn list_audit_log(limit: usize) -> Result<Vec<AuditEntry>, String> {
+    crate::database::get()?.write(move |conn| read_audit_log(conn, limit))
+}
+
+fn read_audit_log(conn: &Connection, limit: usize) -> Result<Vec<AuditEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, timestamp, token_preview, ip, transport, command, outcome, detail
+             FROM audit_log ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| format!("{e}"))?;
+    let rows = stmt
+        .query_map(params![limit as i64], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                token_preview: row.get(2)?,
+                ip: row.get(3)?,
+                transport: row.get(4)?,
+                command: row.get(5)?,
+                outcome: row.get(6)?,
+                detail: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("{e}"))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{e}"))
+}
+
 fn generate_token() -> String {
     let mut buf = [0u8; 32];
     #[cfg(unix)]
@@ -0,0 +1,354 @@
+use crate::config::Config;
+use crate::{database, migrations, sqlite_cache};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const FORMAT: &str = "pi-session-manager/user-data";
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Tables of the user database worth carrying to another machine, and the
+/// columns that identify a row across databases. The cache database is
+/// rebuilt from the session files.
+const USER_TABLES: &[(&str, &[&str])] = &[
+    ("tags", &["id"]),
+    ("session_tags", &["session_id", "tag_id"]),
+    ("favorites", &["id"]),
+    ("settings", &["key"]),
+    ("config_versions", &["file_path", "created_at"]),
+    ("auth_tokens", &["token"]),
+];
+
+const TOKENS_TABLE: &str = "auth_tokens";
+
+/// Portable copy of the user-data tables
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserData {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Schema version of the user database the rows came from
+    pub schema_version: i64,
+    /// Rows per table, each a column-to-value object
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+/// Rows written per table
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct UserDataSummary {
+    pub rows: BTreeMap<String, usize>,
+}
+
+impl UserData {
+    pub fn summary(&self) -> UserDataSummary {
+        UserDataSummary {
+            rows: self
+                .tables
+                .iter()
+                .map(|(table, rows)| (table.clone(), rows.len()))
+                .collect(),
+        }
+    }
+}
+
+/// Read the user-data tables. API tokens are credentials and only included
+/// when asked for.
+pub fn export(conn: &Connection, include_tokens: bool) -> Result<UserData, String> {
+    let mut tables = BTreeMap::new();
+    for (table, _) in USER_TABLES {
+        if *table == TOKENS_TABLE && !include_tokens {
+            continue;
+        }
+        tables.insert(table.to_string(), read_rows(conn, table)?);
+    }
+    Ok(UserData {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        exported_at: Utc::now(),
+        schema_version: migrations::current_version(conn, migrations::Schema::User)?,
+        tables,
+    })
+}
+
+fn read_rows(conn: &Connection, table: &str) -> Result<Vec<Map<String, Value>>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {table}"))
+        .map_err(|e| format!("Failed to read {table}: {e}"))?;
+    let names: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt
+        .query([])
+        .map_err(|e| format!("Failed to read {table}: {e}"))?;
+    let mut out = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to read {table}: {e}"))?
+    {
+        let mut object = Map::new();
+        for (i, name) in names.iter().enumerate() {
+            let value = match row
+                .get_ref(i)
+                .map_err(|e| format!("Failed to read {table}.{name}: {e}"))?
+            {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(n) => Value::from(n),
+                ValueRef::Real(f) => Value::from(f),
+                ValueRef::Text(t) | ValueRef::Blob(t) => {
+                    Value::from(String::from_utf8_lossy(t).into_owned())
+                }
+            };
+            object.insert(name.clone(), value);
+        }
+        out.push(object);
+    }
+    Ok(out)
+}
+
+/// Merge `data` into the database in one transaction. Rows replace the ones
+/// with the same identifying columns; columns this database does not have
+/// are skipped, and generated row ids are left to SQLite.
+pub fn import(conn: &Connection, data: &UserData) -> Result<UserDataSummary, String> {
+    if data.format != FORMAT {
+        return Err(format!("Not a user data export: {}", data.format));
+    }
+    if data.version > FORMAT_VERSION {
+        return Err(format!(
+            "User data format version {} is newer than supported ({FORMAT_VERSION})",
+            data.version
+        ));
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start import: {e}"))?;
+    let mut summary = UserDataSummary::default();
+    // USER_TABLES order, so tags exist before their assignments
+    for (table, key) in USER_TABLES {
+        let Some(rows) = data.tables.get(*table) else {
+            continue;
+        };
+        let columns = importable_columns(&tx, table, key)?;
+        for row in rows {
+            import_row(&tx, table, key, &columns, row)?;
+        }
+        summary.rows.insert(table.to_string(), rows.len());
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit import: {e}"))?;
+
+    if data.tables.contains_key(TOKENS_TABLE) {
+        crate::auth::reload_tokens(conn)?;
+    }
+    Ok(summary)
+}
+
+/// Columns of `table`, without an INTEGER PRIMARY KEY that is not part of
+/// the identifying key (a local row id)
+fn importable_columns(conn: &Connection, table: &str, key: &[&str]) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .map_err(|e| format!("Failed to inspect {table}: {e}"))?;
+    let columns = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to inspect {table}: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to inspect {table}: {e}"))?;
+    Ok(columns
+        .into_iter()
+        .filter(|(name, column_type, pk)| {
+            !(*pk > 0
+                && column_type.eq_ignore_ascii_case("INTEGER")
+                && !key.contains(&name.as_str()))
+        })
+        .map(|(name, _, _)| name)
+        .collect())
+}
+
+fn import_row(
+    conn: &Connection,
+    table: &str,
+    key: &[&str],
+    columns: &[String],
+    row: &Map<String, Value>,
+) -> Result<(), String> {
+    let key_values = key
+        .iter()
+        .map(|k| {
+            row.get(*k)
+                .map(sql_value)
+                .ok_or_else(|| format!("{table} row without {k}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let matches = key
+        .iter()
+        .map(|k| format!("{k} = ?"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    conn.execute(
+        &format!("DELETE FROM {table} WHERE {matches}"),
+        params_from_iter(key_values),
+    )
+    .map_err(|e| format!("Failed to import {table}: {e}"))?;
+
+    let present: Vec<&String> = columns.iter().filter(|c| row.contains_key(*c)).collect();
+    let names = present
+        .iter()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = vec!["?"; present.len()].join(", ");
+    conn.execute(
+        &format!("INSERT INTO {table} ({names}) VALUES ({placeholders})"),
+        params_from_iter(present.iter().map(|c| sql_value(&row[*c]))),
+    )
+    .map_err(|e| format!("Failed to import {table}: {e}"))?;
+    Ok(())
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+pub fn write_file(path: &Path, data: &UserData) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize user data: {e}"))?;
+    // Written aside and renamed, so a crash never leaves half a backup
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("user-data");
+    let tmp = path.with_file_name(format!(".{file_name}.tmp"));
+    fs::write(&tmp, json).map_err(|e| format!("Failed to write {tmp:?}: {e}"))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write {path:?}: {e}"))
+}
+
+pub fn read_file(path: &Path) -> Result<UserData, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path:?}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid user data in {path:?}: {e}"))
+}
+
+/// Directory holding the rotating backups, next to the database. Backups
+/// carry API tokens, so the directory is private to the owner.
+pub fn backup_dir() -> Result<PathBuf, String> {
+    let db_path = sqlite_cache::get_db_path()?;
+    let dir = db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("backups");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup dir: {e}"))?;
+    restrict_permissions(&dir, 0o700);
+    Ok(dir)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode));
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) {}
+
+/// Backups, newest first
+pub fn list_backups() -> Result<Vec<PathBuf>, String> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir()?)
+        .map_err(|e| format!("Failed to list backups: {e}"))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("user-data-") && n.ends_with(".json"))
+        })
+        .collect();
+    // Timestamped names sort chronologically
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Back up the user-data tables now and drop all but the newest `keep`
+pub fn backup_now(keep: usize) -> Result<PathBuf, String> {
+    let data = database::get()?.read(|conn| export(conn, true))?;
+    let path = backup_dir()?.join(format!(
+        "user-data-{}.json",
+        data.exported_at.format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    write_file(&path, &data)?;
+    restrict_permissions(&path, 0o600);
+    for old in list_backups()?.into_iter().skip(keep.max(1)) {
+        if let Err(e) = fs::remove_file(&old) {
+            warn!("Failed to remove old backup {:?}: {}", old, e);
+        }
+    }
+    Ok(path)
+}
+
+/// Back up when the newest backup is older than the configured interval
+pub fn backup_if_due(config: &Config) -> Result<Option<PathBuf>, String> {
+    if config.backup_interval_hours == 0 {
+        return Ok(None);
+    }
+    let interval = Duration::from_secs(config.backup_interval_hours * 3600);
+    let latest_age = list_backups()?
+        .first()
+        .and_then(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+        .and_then(|modified| modified.elapsed().ok());
+    if latest_age.is_some_and(|age| age < interval) {
+        return Ok(None);
+    }
+    backup_now(config.backup_keep).map(Some)
+}
+
+/// Import the newest backup that can be read. Returns its path, or None
+/// when there is no readable backup.
+pub fn restore_latest(conn: &Connection) -> Result<Option<PathBuf>, String> {
+    for path in list_backups()? {
+        match read_file(&path) {
+            Ok(data) => {
+                import(conn, &data)?;
+                return Ok(Some(path));
+            }
+            Err(e) => warn!("Skipping unreadable backup: {}", e),
+        }
+    }
+    Ok(None)
+}
+
+/// Check hourly whether a backup is due. Runs for the life of the process.
+pub async fn run_scheduler() {
+    let mut ticker = tokio::time::interval(Duration::from_secs(3600));
+    loop {
+        ticker.tick().await;
+        let result =
+            tokio::task::spawn_blocking(|| backup_if_due(&Config::load().unwrap_or_default()))
+                .await;
+        match result {
+            Ok(Ok(Some(path))) => info!("Backed up user data to {:?}", path),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => warn!("User data backup failed: {}", e),
+            Err(e) => warn!("User data backup task failed: {}", e),
+        }
+    }
+}
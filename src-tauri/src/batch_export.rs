@@ -0,0 +1,389 @@
+use crate::export::{self, ExportOptions};
+use crate::models::SessionInfo;
+use crate::session_parser::{self, SessionDetails};
+use crate::{scanner, sqlite_cache};
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Event name used for progress notifications on the event channel
+pub const PROGRESS_EVENT: &str = "export-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ArchiveFormat {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz", alias = "tgz")]
+    TarGz,
+}
+
+/// Which sessions to export and how.
+///
+/// Explicit `paths` take precedence; otherwise every scanned session matching
+/// all of `query`, `tag` and `project` is exported.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchExportRequest {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Full-text query in the `full_text_search` grammar
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Tag name (case-insensitive) or id
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Project directory; matches the session cwd and its subdirectories
+    #[serde(default)]
+    pub project: Option<String>,
+    pub format: String,
+    #[serde(default)]
+    pub options: ExportOptions,
+    pub archive: ArchiveFormat,
+    #[serde(alias = "output_path")]
+    pub output_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchExportProgress {
+    pub output_path: String,
+    pub current: usize,
+    pub total: usize,
+    pub session_path: Option<String>,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BatchExportResult {
+    pub output_path: String,
+    pub exported: usize,
+    /// Sessions below `options.min_messages`
+    pub skipped: Vec<String>,
+    pub failed: Vec<FailedExport>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FailedExport {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub generated_at: DateTime<Utc>,
+    pub format: String,
+    pub sessions: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub path: String,
+    /// Path of the exported file inside the archive
+    pub file: String,
+    pub cwd: String,
+    pub name: Option<String>,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub tags: Vec<String>,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    pub details: SessionDetails,
+}
+
+/// Export the selected sessions into a single archive.
+///
+/// `on_progress` is called once before the first session, after each
+/// session, and once more when the archive is complete.
+pub async fn export_batch<F>(
+    request: BatchExportRequest,
+    on_progress: F,
+) -> Result<BatchExportResult, String>
+where
+    F: Fn(BatchExportProgress) + Send + 'static,
+{
+    export::file_extension(&request.format)
+        .ok_or_else(|| format!("Unsupported format: {}", request.format))?;
+
+    let sessions = select_sessions(&request).await?;
+    if sessions.is_empty() {
+        return Err("No sessions match the export selection".to_string());
+    }
+    let tags = match crate::database::get() {
+        Ok(db) => db
+            .read_async(sqlite_cache::get_session_tag_names)
+            .await
+            .unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    };
+
+    tokio::task::spawn_blocking(move || write_archive(&request, &sessions, &tags, on_progress))
+        .await
+        .map_err(|e| format!("Batch export task failed: {e}"))?
+}
+
+/// Resolve the request to a list of sessions. Only scanned sessions can be
+/// selected, so explicit paths outside the session directories are refused.
+pub async fn select_sessions(request: &BatchExportRequest) -> Result<Vec<SessionInfo>, String> {
+    let scanned = scanner::scan_sessions().await?;
+
+    if !request.paths.is_empty() {
+        let by_path: HashMap<&str, &SessionInfo> =
+            scanned.iter().map(|s| (s.path.as_str(), s)).collect();
+        return request
+            .paths
+            .iter()
+            .map(|path| {
+                by_path
+                    .get(path.as_str())
+                    .map(|session| (*session).clone())
+                    .ok_or_else(|| format!("Session not found: {path}"))
+            })
+            .collect();
+    }
+
+    let mut sessions = scanned;
+
+    if let Some(project) = request.project.as_deref().filter(|p| !p.is_empty()) {
+        let project = project.trim_end_matches('/');
+        let prefix = format!("{project}/");
+        sessions.retain(|s| s.cwd == project || s.cwd.starts_with(&prefix));
+    }
+
+    if let Some(tag) = request.tag.as_deref().filter(|t| !t.is_empty()) {
+        let db = crate::database::get()?;
+        let tag_ids: HashSet<String> = db
+            .read_async(sqlite_cache::get_all_tags)
+            .await?
+            .into_iter()
+            .filter(|t| t.id == tag || t.name.eq_ignore_ascii_case(tag))
+            .map(|t| t.id)
+            .collect();
+        let tagged: HashSet<String> = db
+            .read_async(sqlite_cache::get_all_session_tags)
+            .await?
+            .into_iter()
+            .filter(|st| tag_ids.contains(&st.tag_id))
+            .map(|st| st.session_id)
+            .collect();
+        sessions.retain(|s| tagged.contains(&s.id));
+    }
+
+    if let Some(query) = request.query.as_deref().filter(|q| !q.trim().is_empty()) {
+        let matched = matching_paths(query).await?;
+        sessions.retain(|s| matched.contains(&s.path));
+    }
+
+    Ok(sessions)
+}
+
+/// Paths of the sessions with a message matching `query`, in the
+/// `full_text_search` grammar with every bare term required
+async fn matching_paths(query: &str) -> Result<HashSet<String>, String> {
+    const PAGE_SIZE: usize = 500;
+    // Sessions still in the write buffer are not searchable yet
+    crate::write_buffer::flush_to_db()?;
+    let mut paths = HashSet::new();
+    for page in 0.. {
+        let response = crate::full_text_search(
+            query.to_string(),
+            "all".to_string(),
+            None,
+            page,
+            PAGE_SIZE,
+            Some("all".to_string()),
+        )
+        .await?;
+        paths.extend(response.hits.into_iter().map(|hit| hit.session_path));
+        if !response.has_more {
+            break;
+        }
+    }
+    Ok(paths)
+}
+
+fn write_archive<F>(
+    request: &BatchExportRequest,
+    sessions: &[SessionInfo],
+    tags: &HashMap<String, Vec<String>>,
+    on_progress: F,
+) -> Result<BatchExportResult, String>
+where
+    F: Fn(BatchExportProgress),
+{
+    let extension = export::file_extension(&request.format).unwrap_or("txt");
+    let total = sessions.len();
+    let progress = |current: usize, session_path: Option<&str>, done: bool| {
+        on_progress(BatchExportProgress {
+            output_path: request.output_path.clone(),
+            current,
+            total,
+            session_path: session_path.map(String::from),
+            done,
+        })
+    };
+
+    if let Some(parent) = Path::new(&request.output_path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create output directory: {e}"))?;
+        }
+    }
+    let mut archive = ArchiveWriter::create(&request.output_path, request.archive)?;
+
+    let mut manifest = Manifest {
+        version: 1,
+        generated_at: Utc::now(),
+        format: request.format.clone(),
+        sessions: Vec::with_capacity(total),
+    };
+    let mut failed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut used_names = HashSet::new();
+    // Dataset formats also get every conversation concatenated into one file
+    let mut dataset = export::is_dataset_format(&request.format).then(String::new);
+
+    progress(0, None, false);
+    for (i, session) in sessions.iter().enumerate() {
+        let rendered = fs::read_to_string(&session.path)
+            .map_err(|e| format!("Failed to read session file: {e}"))
+            .and_then(|content| {
+                let details = session_parser::parse_session_details(&content);
+                if details.user_messages + details.assistant_messages < request.options.min_messages
+                {
+                    return Ok(None);
+                }
+                export::render_session_for_path(
+                    &session.path,
+                    &content,
+                    &request.format,
+                    &request.options,
+                )
+                .map(|out| Some((details, out)))
+            });
+        match rendered {
+            Ok(None) => skipped.push(session.path.clone()),
+            Ok(Some((details, rendered))) => {
+                let file = unique_name(&mut used_names, &session.id, extension);
+                archive.add(&file, rendered.as_bytes())?;
+                if let Some(dataset) = dataset.as_mut() {
+                    dataset.push_str(&rendered);
+                }
+
+                manifest.sessions.push(ManifestEntry {
+                    id: session.id.clone(),
+                    path: session.path.clone(),
+                    file,
+                    cwd: session.cwd.clone(),
+                    name: session.name.clone(),
+                    created: session.created,
+                    modified: session.modified,
+                    tags: tags.get(&session.id).cloned().unwrap_or_default(),
+                    total_tokens: details.total_tokens(),
+                    total_cost: details.total_cost(),
+                    details,
+                });
+            }
+            Err(error) => failed.push(FailedExport {
+                path: session.path.clone(),
+                error,
+            }),
+        }
+        progress(i + 1, Some(&session.path), false);
+    }
+
+    if let Some(dataset) = dataset {
+        archive.add("dataset.jsonl", dataset.as_bytes())?;
+    }
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {e}"))?;
+    archive.add("manifest.json", manifest_json.as_bytes())?;
+    archive.finish()?;
+    progress(total, None, true);
+
+    Ok(BatchExportResult {
+        output_path: request.output_path.clone(),
+        exported: manifest.sessions.len(),
+        skipped,
+        failed,
+    })
+}
+
+fn unique_name(used: &mut HashSet<String>, id: &str, extension: &str) -> String {
+    let stem: String = id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut name = format!("sessions/{stem}.{extension}");
+    let mut n = 1;
+    while !used.insert(name.clone()) {
+        n += 1;
+        name = format!("sessions/{stem}-{n}.{extension}");
+    }
+    name
+}
+
+enum ArchiveWriter {
+    Zip(ZipWriter<File>),
+    TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+impl ArchiveWriter {
+    fn create(path: &str, format: ArchiveFormat) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create archive: {e}"))?;
+        Ok(match format {
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new(file)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))),
+        })
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to add {name} to archive: {e}");
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+                zip.start_file(name, options).map_err(|e| err(&e))?;
+                zip.write_all(data).map_err(|e| err(&e))
+            }
+            ArchiveWriter::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp().max(0) as u64);
+                header.set_cksum();
+                tar.append_data(&mut header, name, data)
+                    .map_err(|e| err(&e))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to finish archive: {e}");
+        match self {
+            ArchiveWriter::Zip(zip) => zip.finish().map(|_| ()).map_err(|e| err(&e)),
+            ArchiveWriter::TarGz(tar) => tar
+                .into_inner()
+                .and_then(|gz| gz.finish())
+                .map(|_| ())
+                .map_err(|e| err(&e)),
+        }
+    }
+}
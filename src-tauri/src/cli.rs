@@ -0,0 +1,809 @@
+use crate::export::{self, ExportOptions};
+use crate::models::SessionInfo;
+use crate::search::{self, RoleFilter, SearchMode};
+use crate::{
+    config, database, migrations, scanner, session_parser, sqlite_cache, stats, write_buffer,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+
+/// Command line of the CLI binaries
+#[derive(Parser)]
+#[command(
+    name = "pi-session-cli",
+    version,
+    about = "Pi Session Manager without the GUI"
+)]
+pub struct Cli {
+    /// Print machine-readable JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Start the HTTP/WebSocket server (default)
+    Serve {
+        /// Disable every mutating command, whatever the config file says
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Serve session history to agents over the Model Context Protocol (stdio)
+    Mcp,
+    #[command(flatten)]
+    Session(Command),
+}
+
+/// Parse the process arguments and run them. Each binary brings its own
+/// server, started by `serve(read_only)` when no subcommand or `serve` is given.
+pub async fn main<F, Fut>(serve: F)
+where
+    F: FnOnce(bool) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let args = Cli::parse();
+    match args.command {
+        None => serve(false).await,
+        Some(CliCommand::Serve { read_only }) => serve(read_only).await,
+        Some(CliCommand::Mcp) => {
+            // stdout carries protocol messages only
+            tracing_subscriber::fmt()
+                .with_writer(std::io::stderr)
+                .init();
+            if let Err(e) = crate::mcp::serve_stdio().await {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(CliCommand::Session(command)) => match run(command, args.json).await {
+            Ok(output) => {
+                print!("{}", output.text);
+                if output.failed {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Subcommands shared by the CLI binaries; each runs against the session
+/// files and the SQLite cache directly, without a server
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// List sessions, newest first
+    List(ListArgs),
+    /// Search session names or message contents
+    Search(SearchArgs),
+    /// Show a session's metadata and conversation
+    Show {
+        /// Session id, id prefix or file path
+        id: String,
+    },
+    /// Export a session to a file or stdout
+    Export(ExportArgs),
+    /// Aggregate usage statistics
+    Stats(FilterArgs),
+    /// Add or remove session tags
+    #[command(subcommand)]
+    Tag(TagCommand),
+    /// Rename a session
+    Rename {
+        /// Session id, id prefix or file path
+        id: String,
+        name: String,
+    },
+    /// Re-read every session file into the cache
+    Reindex {
+        /// Drop the cache before re-reading
+        #[arg(long)]
+        full: bool,
+    },
+    /// Apply pending database schema migrations
+    Migrate {
+        /// List the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check configuration, session directories and the database
+    Doctor,
+    /// Browse sessions in an interactive terminal UI
+    Tui,
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct FilterArgs {
+    /// Project directory; matches the session cwd and its subdirectories
+    #[arg(long)]
+    pub cwd: Option<String>,
+    /// Modified on or after (YYYY-MM-DD, RFC 3339 or a relative age like 7d)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Modified before (same formats as --since)
+    #[arg(long)]
+    pub until: Option<String>,
+    /// Tag name or id
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum RoleArg {
+    #[default]
+    All,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SearchArgs {
+    pub query: String,
+    /// Match session names and first messages only
+    #[arg(long)]
+    pub name: bool,
+    #[arg(long, value_enum, default_value_t = RoleArg::All)]
+    pub role: RoleArg,
+    /// Also search tool calls and tool output
+    #[arg(long)]
+    pub include_tools: bool,
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    /// Session id, id prefix or file path
+    pub id: String,
+    /// html, md, json, openai-jsonl or sharegpt
+    #[arg(long, short, default_value = "md")]
+    pub format: String,
+    /// Output file; stdout when omitted
+    #[arg(long, short)]
+    pub output: Option<String>,
+    #[arg(long)]
+    pub drop_thinking: bool,
+    /// Export every branch instead of only the active one
+    #[arg(long)]
+    pub all_branches: bool,
+    #[arg(long, default_value_t = 0)]
+    pub min_messages: usize,
+    /// Include the system prompt in dataset formats
+    #[arg(long)]
+    pub system_prompt: bool,
+    /// Replace detected secrets
+    #[arg(long)]
+    pub redact: bool,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum TagCommand {
+    /// Assign a tag to a session
+    Add {
+        /// Session id, id prefix or file path
+        id: String,
+        /// Tag name or id
+        tag: String,
+    },
+    /// Remove a tag from a session
+    Rm {
+        /// Session id, id prefix or file path
+        id: String,
+        /// Tag name or id
+        tag: String,
+    },
+}
+
+/// What a command printed and whether the process should exit non-zero
+#[derive(Debug, Clone)]
+pub struct CliOutput {
+    pub text: String,
+    pub failed: bool,
+}
+
+impl CliOutput {
+    fn ok(text: String) -> Self {
+        Self {
+            text,
+            failed: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub path: String,
+    pub cwd: String,
+    pub name: Option<String>,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub message_count: usize,
+    pub first_message: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+pub async fn run(command: Command, json_output: bool) -> Result<CliOutput, String> {
+    match command {
+        Command::List(args) => list(args, json_output).await,
+        Command::Search(args) => search(args, json_output).await,
+        Command::Show { id } => show(&id, json_output).await,
+        Command::Export(args) => export(args, json_output).await,
+        Command::Stats(filter) => stats(filter, json_output).await,
+        Command::Tag(command) => tag(command, json_output).await,
+        Command::Rename { id, name } => rename(&id, name, json_output).await,
+        Command::Reindex { full } => reindex(full, json_output).await,
+        Command::Migrate { dry_run } => migrate(dry_run, json_output),
+        Command::Doctor => Ok(doctor(json_output)),
+        Command::Tui => crate::tui::run()
+            .await
+            .map(|()| CliOutput::ok(String::new())),
+    }
+}
+
+async fn list(args: ListArgs, json_output: bool) -> Result<CliOutput, String> {
+    let tags = tag_names();
+    let mut sessions = filter_sessions(load_sessions().await?, &args.filter)?;
+    if let Some(limit) = args.limit {
+        sessions.truncate(limit);
+    }
+    let summaries: Vec<SessionSummary> = sessions.iter().map(|s| summarize(s, &tags)).collect();
+    if json_output {
+        return to_json(&summaries);
+    }
+
+    let mut out = String::new();
+    for s in &summaries {
+        let title = s.name.as_deref().unwrap_or(&s.first_message);
+        let _ = writeln!(
+            out,
+            "{}  {}  {:>4}  {}  {}",
+            short_id(&s.id),
+            s.modified.format("%Y-%m-%d %H:%M"),
+            s.message_count,
+            s.cwd,
+            one_line(title, 60)
+        );
+    }
+    let _ = writeln!(out, "{} session(s)", summaries.len());
+    Ok(CliOutput::ok(out))
+}
+
+async fn search(args: SearchArgs, json_output: bool) -> Result<CliOutput, String> {
+    let sessions = filter_sessions(load_sessions().await?, &args.filter)?;
+    let mode = if args.name {
+        SearchMode::Name
+    } else {
+        SearchMode::Content
+    };
+    let role = match args.role {
+        RoleArg::All => RoleFilter::All,
+        RoleArg::User => RoleFilter::User,
+        RoleArg::Assistant => RoleFilter::Assistant,
+    };
+    let mut results =
+        search::search_sessions(&sessions, &args.query, mode, role, args.include_tools);
+    results.truncate(args.limit);
+    if json_output {
+        return to_json(&results);
+    }
+
+    let mut out = String::new();
+    for r in &results {
+        let title = r.session_name.as_deref().unwrap_or(&r.first_message);
+        let _ = writeln!(out, "{}  {}", short_id(&r.session_id), one_line(title, 70));
+        let _ = writeln!(out, "    {}", r.session_path);
+        for m in r.matches.iter().take(3) {
+            let _ = writeln!(out, "    [{}] {}", m.role, one_line(&m.snippet, 100));
+        }
+    }
+    let _ = writeln!(out, "{} matching session(s)", results.len());
+    Ok(CliOutput::ok(out))
+}
+
+async fn show(id: &str, json_output: bool) -> Result<CliOutput, String> {
+    let session = resolve_session(&load_sessions().await?, id)?;
+    let content = fs::read_to_string(&session.path)
+        .map_err(|e| format!("Failed to read session file: {e}"))?;
+    let details = session_parser::parse_session_details(&content);
+    let summary = summarize(&session, &tag_names());
+
+    if json_output {
+        let entries = session_parser::parse_entries(&content);
+        let value = json!({ "session": summary, "details": details, "entries": entries });
+        return to_json(&value);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "id:        {}", summary.id);
+    let _ = writeln!(out, "path:      {}", summary.path);
+    let _ = writeln!(out, "cwd:       {}", summary.cwd);
+    if let Some(name) = &summary.name {
+        let _ = writeln!(out, "name:      {name}");
+    }
+    let _ = writeln!(out, "created:   {}", summary.created.to_rfc3339());
+    let _ = writeln!(out, "modified:  {}", summary.modified.to_rfc3339());
+    let _ = writeln!(
+        out,
+        "messages:  {} user, {} assistant, {} tool results",
+        details.user_messages, details.assistant_messages, details.tool_results
+    );
+    let _ = writeln!(
+        out,
+        "tokens:    {} (${:.4})",
+        details.total_tokens(),
+        details.total_cost()
+    );
+    if !details.models.is_empty() {
+        let _ = writeln!(out, "models:    {}", details.models.join(", "));
+    }
+    if !summary.tags.is_empty() {
+        let _ = writeln!(out, "tags:      {}", summary.tags.join(", "));
+    }
+    out.push('\n');
+    out.push_str(&export::render_session(&content, "md")?);
+    Ok(CliOutput::ok(out))
+}
+
+async fn export(args: ExportArgs, json_output: bool) -> Result<CliOutput, String> {
+    let session = resolve_session(&load_sessions().await?, &args.id)?;
+    let options = ExportOptions {
+        drop_thinking: args.drop_thinking,
+        active_branch_only: !args.all_branches,
+        min_messages: args.min_messages,
+        include_system_prompt: args.system_prompt,
+        redact: args.redact,
+    };
+
+    let Some(output) = args.output else {
+        let content = fs::read_to_string(&session.path)
+            .map_err(|e| format!("Failed to read session file: {e}"))?;
+        let count = export::count_messages(&content);
+        if count < options.min_messages {
+            return Err(format!(
+                "Session has {count} messages, fewer than the minimum of {}",
+                options.min_messages
+            ));
+        }
+        let rendered =
+            export::render_session_for_path(&session.path, &content, &args.format, &options)?;
+        return Ok(CliOutput::ok(rendered));
+    };
+
+    export::export_session_with_options(&session.path, &args.format, &output, &options).await?;
+    if json_output {
+        return to_json(&json!({ "session": session.id, "output": output }));
+    }
+    Ok(CliOutput::ok(format!(
+        "Exported {} to {output}\n",
+        session.id
+    )))
+}
+
+async fn stats(filter: FilterArgs, json_output: bool) -> Result<CliOutput, String> {
+    let sessions = filter_sessions(load_sessions().await?, &filter)?;
+    let stats = stats::calculate_stats(&sessions);
+    if json_output {
+        return to_json(&stats);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "sessions:  {}", stats.total_sessions);
+    let _ = writeln!(
+        out,
+        "messages:  {} ({} user, {} assistant, {:.1} per session)",
+        stats.total_messages,
+        stats.user_messages,
+        stats.assistant_messages,
+        stats.average_messages_per_session
+    );
+    let tokens = &stats.token_details;
+    let _ = writeln!(
+        out,
+        "tokens:    {} in, {} out, {} cache read, {} cache write",
+        tokens.total_input, tokens.total_output, tokens.total_cache_read, tokens.total_cache_write
+    );
+    let _ = writeln!(out, "cost:      ${:.2}", tokens.total_cost);
+    push_top(&mut out, "projects", &stats.sessions_by_project);
+    push_top(&mut out, "models", &stats.sessions_by_model);
+    Ok(CliOutput::ok(out))
+}
+
+async fn tag(command: TagCommand, json_output: bool) -> Result<CliOutput, String> {
+    let (id, tag, add) = match command {
+        TagCommand::Add { id, tag } => (id, tag, true),
+        TagCommand::Rm { id, tag } => (id, tag, false),
+    };
+    let session = resolve_session(&load_sessions().await?, &id)?;
+    let db = database::get()?;
+    let tag = db
+        .read(|conn| sqlite_cache::find_tag(conn, &tag))?
+        .ok_or_else(|| format!("Unknown tag: {tag}"))?;
+    let (session_id, tag_id) = (session.id.clone(), tag.id.clone());
+    db.write(move |conn| {
+        if add {
+            sqlite_cache::assign_tag(conn, &session_id, &tag_id)
+        } else {
+            sqlite_cache::remove_tag_from_session(conn, &session_id, &tag_id)
+        }
+    })?;
+
+    if json_output {
+        return to_json(&json!({ "session": session.id, "tag": tag.name, "assigned": add }));
+    }
+    let verb = if add { "Tagged" } else { "Untagged" };
+    Ok(CliOutput::ok(format!(
+        "{verb} {} with {}\n",
+        session.id, tag.name
+    )))
+}
+
+async fn rename(id: &str, name: String, json_output: bool) -> Result<CliOutput, String> {
+    let session = resolve_session(&load_sessions().await?, id)?;
+    crate::rename_session(session.path.clone(), name.clone()).await?;
+    scanner::invalidate_cache();
+    if json_output {
+        return to_json(&json!({ "session": session.id, "name": name }));
+    }
+    Ok(CliOutput::ok(format!("Renamed {} to {name}\n", session.id)))
+}
+
+async fn reindex(full: bool, json_output: bool) -> Result<CliOutput, String> {
+    let config = config::load_config()?;
+    let result = tokio::task::spawn_blocking(move || -> Result<(usize, usize), String> {
+        let db = database::get_with_config(&config)?;
+        if full {
+            db.write(sqlite_cache::clear_all_cache)?;
+        }
+        let mut indexed = 0;
+        let mut failed = 0;
+        let batch = crate::tantivy_search::batch();
+        for path in scanner::list_session_files(&config) {
+            let modified = fs::metadata(&path).and_then(|m| m.modified());
+            match (scanner::parse_session_info(&path), modified) {
+                (Ok((info, entries)), Ok(modified)) => {
+                    db.write(move |conn| {
+                        sqlite_cache::upsert_session(conn, &info, modified.into(), Some(&entries))
+                    })?;
+                    indexed += 1;
+                }
+                _ => failed += 1,
+            }
+        }
+        drop(batch);
+        if config.search_engine == config::SearchEngine::Tantivy {
+            db.read(crate::tantivy_search::rebuild_from_db)?;
+        }
+        Ok((indexed, failed))
+    })
+    .await
+    .map_err(|e| format!("Reindex task failed: {e}"))??;
+    scanner::invalidate_cache();
+
+    let (indexed, failed) = result;
+    if json_output {
+        return to_json(&json!({ "indexed": indexed, "failed": failed }));
+    }
+    Ok(CliOutput::ok(format!(
+        "Indexed {indexed} session(s), {failed} unreadable\n"
+    )))
+}
+
+fn migrate(dry_run: bool, json_output: bool) -> Result<CliOutput, String> {
+    let path = sqlite_cache::get_db_path()?;
+    let reports = if dry_run {
+        migrations::dry_run(&path)?
+    } else {
+        let conn = rusqlite::Connection::open(&path)
+            .map_err(|e| format!("Failed to open database: {e}"))?;
+        let reports = migrations::migrate(&conn, &path)?;
+        drop(conn);
+        // Builtin tags and the search indexes
+        database::get_with_config(&config::load_config()?)?;
+        reports
+    };
+    if json_output {
+        return to_json(&reports);
+    }
+
+    let mut out = String::new();
+    for report in &reports {
+        let name = match report.schema {
+            migrations::Schema::Cache => "Cache database",
+            migrations::Schema::User => "User database",
+        };
+        if report.pending.is_empty() {
+            let _ = writeln!(
+                out,
+                "{name} is up to date (version {})",
+                report.latest_version
+            );
+            continue;
+        }
+        if let Some(backup) = &report.backup {
+            let _ = writeln!(out, "Backed up to {backup}");
+        }
+        let _ = writeln!(
+            out,
+            "{name}: {} version {} to {}:",
+            if dry_run { "would migrate" } else { "migrated" },
+            report.current_version,
+            report.latest_version
+        );
+        for step in &report.pending {
+            let _ = writeln!(out, "  {:>3}  {}", step.version, step.description);
+        }
+    }
+    Ok(CliOutput::ok(out))
+}
+
+fn doctor(json_output: bool) -> CliOutput {
+    let mut checks = Vec::new();
+    let mut check = |name: &str, result: Result<String, String>| {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        checks.push(DoctorCheck {
+            name: name.to_string(),
+            ok,
+            detail,
+        });
+    };
+
+    let config = config::load_config();
+    check(
+        "config",
+        config
+            .as_ref()
+            .map(|c| format!("search engine {:?}", c.search_engine))
+            .map_err(Clone::clone),
+    );
+    let config = config.unwrap_or_default();
+
+    for dir in scanner::get_all_session_dirs(&config) {
+        let result = if dir.is_dir() {
+            Ok(dir.display().to_string())
+        } else {
+            Err(format!("{} does not exist", dir.display()))
+        };
+        check("sessions dir", result);
+    }
+    let files = scanner::list_session_files(&config).len();
+    check("session files", Ok(files.to_string()));
+
+    let db = sqlite_cache::get_db_path()
+        .and_then(|path| Ok((path, database::get_with_config(&config)?)));
+    match db {
+        Ok((path, db)) => {
+            check("database", Ok(path.display().to_string()));
+            check(
+                "user database",
+                Ok(migrations::user_db_path(&path).display().to_string()),
+            );
+            check(
+                "schema version",
+                db.read(sqlite_cache::schema_version).map(|v| v.to_string()),
+            );
+            check(
+                "schema",
+                db.read(migrations::verify_schema).and_then(|problems| {
+                    if problems.is_empty() {
+                        Ok(format!(
+                            "matches version {} (user {})",
+                            migrations::latest_version(migrations::Schema::Cache),
+                            migrations::latest_version(migrations::Schema::User)
+                        ))
+                    } else {
+                        Err(problems.join("; "))
+                    }
+                }),
+            );
+            check(
+                "integrity",
+                db.read(sqlite_cache::quick_check)
+                    .and_then(|r| if r == "ok" { Ok(r) } else { Err(r) }),
+            );
+            check(
+                "cached sessions",
+                db.read(sqlite_cache::get_session_count).map(|n| {
+                    if n < files {
+                        format!("{n} of {files} (run `reindex` to fill the cache)")
+                    } else {
+                        n.to_string()
+                    }
+                }),
+            );
+        }
+        Err(e) => check("database", Err(e)),
+    }
+
+    let failed = checks.iter().any(|c| !c.ok);
+    let text = if json_output {
+        serde_json::to_string_pretty(&checks).unwrap_or_default() + "\n"
+    } else {
+        let mut out = String::new();
+        for c in &checks {
+            let mark = if c.ok { "ok  " } else { "FAIL" };
+            let _ = writeln!(out, "{mark}  {:<16} {}", c.name, c.detail);
+        }
+        out
+    };
+    CliOutput { text, failed }
+}
+
+/// Scanned sessions plus any the scan only buffered (older sessions seen for
+/// the first time), which are written to the cache so later runs find them
+pub(crate) async fn load_sessions() -> Result<Vec<SessionInfo>, String> {
+    let mut sessions = scanner::scan_sessions().await?;
+    let known: HashSet<String> = sessions.iter().map(|s| s.path.clone()).collect();
+    let flushed = write_buffer::flush_to_db()?;
+    if !flushed.is_empty() {
+        // The cached scan result predates these rows
+        scanner::invalidate_cache();
+    }
+    for session in flushed {
+        if !known.contains(&session.path) {
+            sessions.push(session);
+        }
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+    Ok(sessions)
+}
+
+pub(crate) fn filter_sessions(
+    mut sessions: Vec<SessionInfo>,
+    filter: &FilterArgs,
+) -> Result<Vec<SessionInfo>, String> {
+    if let Some(cwd) = filter.cwd.as_deref().filter(|c| !c.is_empty()) {
+        let cwd = cwd.trim_end_matches('/');
+        let prefix = format!("{cwd}/");
+        sessions.retain(|s| s.cwd == cwd || s.cwd.starts_with(&prefix));
+    }
+    if let Some(since) = filter.since.as_deref() {
+        let since = parse_date(since)?;
+        sessions.retain(|s| s.modified >= since);
+    }
+    if let Some(until) = filter.until.as_deref() {
+        let until = parse_date(until)?;
+        sessions.retain(|s| s.modified < until);
+    }
+    if let Some(tag) = filter.tag.as_deref() {
+        let db = database::get()?;
+        let tag = db
+            .read(|conn| sqlite_cache::find_tag(conn, tag))?
+            .ok_or_else(|| format!("Unknown tag: {tag}"))?;
+        let tagged: HashSet<String> = db
+            .read(sqlite_cache::get_all_session_tags)?
+            .into_iter()
+            .filter(|st| st.tag_id == tag.id)
+            .map(|st| st.session_id)
+            .collect();
+        sessions.retain(|s| tagged.contains(&s.id));
+    }
+    Ok(sessions)
+}
+
+/// YYYY-MM-DD (UTC midnight), RFC 3339, or an age such as `12h`, `7d`, `2w`
+pub fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    let age = value.strip_suffix(['h', 'd', 'w']).and_then(|number| {
+        let n = number.parse::<i64>().ok()?;
+        match value.chars().last() {
+            Some('h') => Duration::try_hours(n),
+            Some('d') => Duration::try_days(n),
+            _ => Duration::try_weeks(n),
+        }
+    });
+    age.and_then(|age| Utc::now().checked_sub_signed(age))
+        .ok_or_else(|| format!("Invalid date: {value} (use YYYY-MM-DD, RFC 3339 or e.g. 7d)"))
+}
+
+/// Match a session by exact id, exact path, or unique id prefix
+pub fn resolve_session(sessions: &[SessionInfo], id: &str) -> Result<SessionInfo, String> {
+    if let Some(s) = sessions.iter().find(|s| s.id == id || s.path == id) {
+        return Ok(s.clone());
+    }
+    if Path::new(id).is_file() {
+        return scanner::parse_session_info(Path::new(id)).map(|(info, _)| info);
+    }
+    let matches: Vec<&SessionInfo> = sessions.iter().filter(|s| s.id.starts_with(id)).collect();
+    match matches.as_slice() {
+        [session] => Ok((*session).clone()),
+        [] => Err(format!("No session matches '{id}'")),
+        _ => Err(format!(
+            "'{id}' is ambiguous: {} sessions match",
+            matches.len()
+        )),
+    }
+}
+
+pub(crate) fn summarize(
+    session: &SessionInfo,
+    tags: &HashMap<String, Vec<String>>,
+) -> SessionSummary {
+    SessionSummary {
+        id: session.id.clone(),
+        path: session.path.clone(),
+        cwd: session.cwd.clone(),
+        name: session.name.clone(),
+        created: session.created,
+        modified: session.modified,
+        message_count: session.message_count,
+        first_message: session.first_message.clone(),
+        tags: tags.get(&session.id).cloned().unwrap_or_default(),
+    }
+}
+
+/// Session id -> tag names; empty if the database is unavailable
+pub(crate) fn tag_names() -> HashMap<String, Vec<String>> {
+    database::get()
+        .and_then(|db| db.read(sqlite_cache::get_session_tag_names))
+        .unwrap_or_default()
+}
+
+fn push_top(out: &mut String, label: &str, counts: &HashMap<String, usize>) {
+    let mut top: Vec<(&String, &usize)> = counts.iter().collect();
+    top.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    if top.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "{label}:");
+    for (name, count) in top.into_iter().take(10) {
+        let _ = writeln!(out, "  {count:>5}  {name}");
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<CliOutput, String> {
+    serde_json::to_string_pretty(value)
+        .map(|s| CliOutput::ok(s + "\n"))
+        .map_err(|e| format!("Failed to serialize output: {e}"))
+}
+
+pub(crate) fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+pub(crate) fn one_line(text: &str, max_chars: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= max_chars {
+        return line;
+    }
+    let mut cut: String = line.chars().take(max_chars - 1).collect();
+    cut.push('…');
+    cut
+}
@@ -0,0 +1,1628 @@
+use crate::auth::{Caller, Scope};
+use crate::batch_export::{BatchExportRequest, BatchExportResult};
+use crate::export::ExportOptions;
+use crate::models::SessionInfo;
+use crate::redaction::RedactionSettings;
+use crate::session_tail::{SubscribeRequest, Subscribed};
+use crate::stats::SessionStatsInput;
+use crate::ServerSettings;
+use futures_util::future::BoxFuture;
+use lazy_static::lazy_static;
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+const TERMINAL_UNAVAILABLE: &str = "Terminal is not available in this mode";
+const SUBSCRIPTIONS_UNAVAILABLE: &str = "Session subscriptions need a WebSocket connection";
+
+/// Capabilities that only the running server or desktop app can provide.
+/// Every method has a default so plain library callers can use [`NoHost`].
+pub trait CommandHost: Send + Sync {
+    /// Broadcast an event to connected clients (WebSocket/SSE and the webview)
+    fn emit(&self, _event: &str, _payload: Value) {}
+
+    /// Called after the configured session directories changed
+    fn session_paths_changed(&self) {}
+
+    fn terminal_create(&self, _request: TerminalCreateRequest) -> Result<String, String> {
+        Err(TERMINAL_UNAVAILABLE.to_string())
+    }
+
+    fn terminal_write(&self, _id: &str, _data: String) -> Result<(), String> {
+        Err(TERMINAL_UNAVAILABLE.to_string())
+    }
+
+    fn terminal_resize(&self, _id: &str, _rows: u16, _cols: u16) -> Result<(), String> {
+        Err(TERMINAL_UNAVAILABLE.to_string())
+    }
+
+    fn terminal_close(&self, _id: &str) -> Result<(), String> {
+        Err(TERMINAL_UNAVAILABLE.to_string())
+    }
+
+    /// Installed shells as `(label, path)` pairs
+    fn shells(&self) -> Result<Vec<(String, String)>, String> {
+        Err(TERMINAL_UNAVAILABLE.to_string())
+    }
+
+    /// Stream a session's appended entries to the calling connection
+    fn subscribe_session(
+        &self,
+        _request: SubscribeRequest,
+    ) -> BoxFuture<'_, Result<Subscribed, String>> {
+        Box::pin(async { Err(SUBSCRIPTIONS_UNAVAILABLE.to_string()) })
+    }
+
+    /// Stop a stream opened by `subscribe_session`; false if there was none
+    fn unsubscribe_session(
+        &self,
+        _request: SubscribeRequest,
+    ) -> BoxFuture<'_, Result<bool, String>> {
+        Box::pin(async { Err(SUBSCRIPTIONS_UNAVAILABLE.to_string()) })
+    }
+}
+
+pub struct NoHost;
+
+impl CommandHost for NoHost {}
+
+type Handler = Box<
+    dyn Fn(Value, Arc<dyn CommandHost>) -> BoxFuture<'static, Result<Value, String>> + Send + Sync,
+>;
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub group: &'static str,
+    /// Least token scope allowed to run the command
+    pub scope: Scope,
+    /// Changes files, settings or state; refused while the server is read-only
+    pub mutating: bool,
+    /// Returns session contents; redacted for remote callers
+    pub session_content: bool,
+    pub description: &'static str,
+    request_schema: fn(&mut SchemaGenerator) -> Schema,
+    response_schema: fn(&mut SchemaGenerator) -> Schema,
+    handler: Handler,
+}
+
+impl CommandSpec {
+    /// Schema of the payload; named types are added to the generator's definitions
+    pub fn request_schema(&self, generator: &mut SchemaGenerator) -> Schema {
+        (self.request_schema)(generator)
+    }
+
+    pub fn response_schema(&self, generator: &mut SchemaGenerator) -> Schema {
+        (self.response_schema)(generator)
+    }
+}
+
+/// A command as reported by `list_commands`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CommandInfo {
+    pub name: String,
+    pub group: String,
+    pub scope: Scope,
+    pub mutating: bool,
+    pub session_content: bool,
+    pub description: String,
+    pub request: Value,
+    pub response: Value,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SessionDigest {
+    pub version: u64,
+    pub count: usize,
+}
+
+/// Commands the desktop app exposes to its own webview only
+pub const DESKTOP_ONLY: &[&str] = &[
+    "open_session_in_browser",
+    "open_session_in_terminal",
+    "toggle_devtools",
+];
+
+/// Commands behind the write or admin scope that only read
+const NON_MUTATING: &[&str] = &[
+    "list_api_keys",
+    "list_audit_log",
+    "get_default_shell",
+    "get_available_shells",
+];
+
+/// Commands whose responses carry message text, names or system prompts
+const SESSION_CONTENT: &[&str] = &[
+    "scan_sessions",
+    "read_session_file",
+    "read_session_file_incremental",
+    "get_session_entries",
+    "get_session_tree",
+    "get_session_by_path",
+    "get_session_system_prompt",
+    "search_sessions",
+    "search_sessions_fts",
+    "full_text_search",
+];
+
+/// Commands that write session contents to a file; remote callers always
+/// get the redacted rendering
+const EXPORTS: &[&str] = &["export_session", "export_sessions_batch"];
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Refuse mutating commands on the server adapters (HTTP, WebSocket, REST),
+/// local clients included. The desktop webview keeps working so the setting
+/// can be turned off again.
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+// ─── Requests ────────────────────────────────────────────────
+//
+// Payload keys are camelCase; multi-word fields also accept their snake_case
+// spelling because older clients send both. Unknown keys are rejected.
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NoArgs {}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PathRequest {
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IdRequest {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VersionIdRequest {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsRequest<T> {
+    pub settings: T,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReadIncrementalRequest {
+    pub path: String,
+    #[serde(alias = "from_line")]
+    pub from_line: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportSessionRequest {
+    pub path: String,
+    pub format: String,
+    #[serde(alias = "output_path")]
+    pub output_path: String,
+    #[serde(default)]
+    pub options: Option<ExportOptions>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RenameSessionRequest {
+    pub path: String,
+    #[serde(alias = "new_name")]
+    pub new_name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StatsRequest {
+    #[serde(default)]
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StatsLightRequest {
+    #[serde(default)]
+    pub sessions: Vec<SessionStatsInput>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchSessionsRequest {
+    #[serde(default)]
+    pub sessions: Vec<SessionInfo>,
+    pub query: String,
+    /// `content` (default) or `name`
+    #[serde(default = "default_search_mode", alias = "search_mode")]
+    pub search_mode: String,
+    /// `all` (default), `user` or `assistant`
+    #[serde(default = "default_role_filter", alias = "role_filter")]
+    pub role_filter: String,
+    #[serde(default, alias = "include_tools")]
+    pub include_tools: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FtsSearchRequest {
+    pub query: String,
+    #[serde(default = "default_fts_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FullTextSearchRequest {
+    pub query: String,
+    #[serde(alias = "role_filter")]
+    pub role_filter: String,
+    #[serde(default, alias = "glob_pattern")]
+    pub glob_pattern: Option<String>,
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "default_page_size", alias = "page_size")]
+    pub page_size: usize,
+    #[serde(default, alias = "match_mode")]
+    pub match_mode: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FavoriteRequest {
+    pub id: String,
+    #[serde(alias = "favorite_type")]
+    pub favorite_type: String,
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SkillRequest {
+    #[serde(alias = "skill_name")]
+    pub skill_name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PromptRequest {
+    #[serde(alias = "prompt_name")]
+    pub prompt_name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CwdRequest {
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SavePiSettingRequest {
+    pub key: String,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ToggleResourceRequest {
+    #[serde(alias = "resource_type")]
+    pub resource_type: String,
+    pub path: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_scope")]
+    pub scope: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReadResourceRequest {
+    pub path: String,
+    #[serde(default = "default_scope")]
+    pub scope: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ConfigVersionsRequest {
+    #[serde(default, alias = "file_path")]
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AuditRequest {
+    #[serde(default)]
+    pub full: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportUserDataRequest {
+    #[serde(alias = "output_path")]
+    pub output_path: String,
+    /// Also export API tokens
+    #[serde(default, alias = "include_tokens")]
+    pub include_tokens: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ImportUserDataRequest {
+    #[serde(alias = "input_path")]
+    pub input_path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SessionPathsRequest {
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListModelsRequest {
+    #[serde(default)]
+    pub search: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TestModelRequest {
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TestModelsBatchRequest {
+    /// `[provider, model]` pairs
+    #[serde(default)]
+    pub models: Vec<(String, String)>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateTagRequest {
+    pub name: String,
+    pub color: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default, alias = "parent_id")]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateTagRequest {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default, alias = "sort_order")]
+    pub sort_order: Option<i64>,
+    /// Absent leaves the parent unchanged, `null` moves the tag to the root
+    #[serde(default, alias = "parent_id", deserialize_with = "present")]
+    pub parent_id: Option<Option<String>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SessionTagRequest {
+    #[serde(alias = "session_id")]
+    pub session_id: String,
+    #[serde(alias = "tag_id")]
+    pub tag_id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MoveSessionTagRequest {
+    #[serde(alias = "session_id")]
+    pub session_id: String,
+    #[serde(default, alias = "from_tag_id")]
+    pub from_tag_id: Option<String>,
+    #[serde(alias = "to_tag_id")]
+    pub to_tag_id: String,
+    #[serde(default)]
+    pub position: i64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReorderTagsRequest {
+    #[serde(default, alias = "tag_ids")]
+    pub tag_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TagAutoRulesRequest {
+    pub id: String,
+    #[serde(default, alias = "auto_rules")]
+    pub auto_rules: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EvaluateAutoRulesRequest {
+    #[serde(alias = "session_id")]
+    pub session_id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateApiKeyRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+    /// `search`, `read`, `write` or `admin` (default)
+    #[serde(default)]
+    pub scope: Option<Scope>,
+    /// Days until the key stops working; absent or 0 never expires
+    #[serde(default, rename = "expiresInDays", alias = "expires_in_days")]
+    pub expires_in_days: Option<u32>,
+    /// Project directories the key is limited to; empty allows all
+    #[serde(default, rename = "allowedPaths", alias = "allowed_paths")]
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AuditLogRequest {
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RevokeApiKeyRequest {
+    #[serde(alias = "key_preview")]
+    pub key_preview: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TerminalCreateRequest {
+    pub id: String,
+    pub cwd: String,
+    pub shell: String,
+    #[serde(default = "default_rows")]
+    pub rows: u16,
+    #[serde(default = "default_cols")]
+    pub cols: u16,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TerminalWriteRequest {
+    pub id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TerminalResizeRequest {
+    pub id: String,
+    #[serde(default = "default_rows")]
+    pub rows: u16,
+    #[serde(default = "default_cols")]
+    pub cols: u16,
+}
+
+fn default_search_mode() -> String {
+    "content".to_string()
+}
+
+fn default_role_filter() -> String {
+    "all".to_string()
+}
+
+fn default_scope() -> String {
+    "user".to_string()
+}
+
+fn default_fts_limit() -> usize {
+    50
+}
+
+fn default_page_size() -> usize {
+    20
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_rows() -> u16 {
+    24
+}
+
+fn default_cols() -> u16 {
+    80
+}
+
+/// Keeps an explicit `null` distinct from a missing field
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+// ─── Registry ────────────────────────────────────────────────
+
+struct Registry {
+    commands: Vec<CommandSpec>,
+    index: HashMap<&'static str, usize>,
+}
+
+impl Registry {
+    fn add<Req, Resp, F, Fut>(
+        &mut self,
+        name: &'static str,
+        group: &'static str,
+        scope: Scope,
+        description: &'static str,
+        handler: F,
+    ) where
+        Req: DeserializeOwned + JsonSchema + 'static,
+        Resp: Serialize + JsonSchema + 'static,
+        F: Fn(Req, Arc<dyn CommandHost>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, String>> + Send + 'static,
+    {
+        let handler: Handler =
+            Box::new(
+                move |payload, host| match parse_payload::<Req>(name, payload) {
+                    Ok(request) => {
+                        let fut = handler(request, host);
+                        Box::pin(async move {
+                            let response = fut.await?;
+                            serde_json::to_value(response)
+                                .map_err(|e| format!("Failed to serialize {name} response: {e}"))
+                        })
+                    }
+                    Err(e) => Box::pin(async move { Err(e) }),
+                },
+            );
+        self.index.insert(name, self.commands.len());
+        self.commands.push(CommandSpec {
+            name,
+            group,
+            scope,
+            mutating: scope >= Scope::Write && !NON_MUTATING.contains(&name),
+            session_content: SESSION_CONTENT.contains(&name),
+            description,
+            request_schema: SchemaGenerator::subschema_for::<Req>,
+            response_schema: SchemaGenerator::subschema_for::<Resp>,
+            handler,
+        });
+    }
+}
+
+fn parse_payload<T: DeserializeOwned>(command: &str, payload: Value) -> Result<T, String> {
+    let payload = match payload {
+        Value::Null => Value::Object(Default::default()),
+        other => other,
+    };
+    serde_json::from_value(payload).map_err(|e| format!("Invalid payload for {command}: {e}"))
+}
+
+lazy_static! {
+    static ref REGISTRY: Registry = build_registry();
+}
+
+fn build_registry() -> Registry {
+    let mut r = Registry {
+        commands: Vec::new(),
+        index: HashMap::new(),
+    };
+
+    // Sessions
+    r.add(
+        "scan_sessions",
+        "sessions",
+        Scope::Read,
+        "List all sessions",
+        |_: NoArgs, _| crate::scan_sessions(),
+    );
+    r.add(
+        "session_digest",
+        "sessions",
+        Scope::Read,
+        "Cheap change marker for the session list",
+        |_: NoArgs, _| async {
+            let (version, count) = crate::scanner::get_session_digest();
+            Ok(SessionDigest { version, count })
+        },
+    );
+    r.add(
+        "read_session_file",
+        "sessions",
+        Scope::Read,
+        "Raw JSONL content of a session file",
+        |req: PathRequest, _| crate::read_session_file(req.path),
+    );
+    r.add(
+        "read_session_file_incremental",
+        "sessions",
+        Scope::Read,
+        "Lines appended to a session file since `fromLine`, with the new line count",
+        |req: ReadIncrementalRequest, _| {
+            crate::read_session_file_incremental(req.path, req.from_line)
+        },
+    );
+    r.add(
+        "get_file_stats",
+        "sessions",
+        Scope::Read,
+        "Size and modification time of a file",
+        |req: PathRequest, _| crate::get_file_stats(req.path),
+    );
+    r.add(
+        "get_session_entries",
+        "sessions",
+        Scope::Read,
+        "Parsed entries of a session file",
+        |req: PathRequest, _| crate::get_session_entries(req.path),
+    );
+    r.add(
+        "get_session_tree",
+        "sessions",
+        Scope::Read,
+        "Branch tree of a session file",
+        |req: PathRequest, _| crate::get_session_tree(req.path),
+    );
+    r.add(
+        "subscribe_session",
+        "sessions",
+        Scope::Read,
+        "Stream entries appended to a session over this WebSocket connection",
+        |req: SubscribeRequest, host: Arc<dyn CommandHost>| async move {
+            host.subscribe_session(req).await
+        },
+    );
+    r.add(
+        "unsubscribe_session",
+        "sessions",
+        Scope::Read,
+        "Stop streaming a session; false if it was not subscribed",
+        |req: SubscribeRequest, host: Arc<dyn CommandHost>| async move {
+            host.unsubscribe_session(req).await
+        },
+    );
+    r.add(
+        "get_session_by_path",
+        "sessions",
+        Scope::Read,
+        "Session summary for a file path",
+        |req: PathRequest, _| crate::get_session_by_path(req.path),
+    );
+    r.add(
+        "delete_session",
+        "sessions",
+        Scope::Write,
+        "Delete a session file and its cache entries",
+        |req: PathRequest, _| crate::delete_session(req.path),
+    );
+    r.add(
+        "export_session",
+        "sessions",
+        Scope::Write,
+        "Export one session to a file",
+        |req: ExportSessionRequest, _| {
+            crate::export_session(req.path, req.format, req.output_path, req.options)
+        },
+    );
+    r.add(
+        "export_sessions_batch",
+        "sessions",
+        Scope::Write,
+        "Export many sessions into one archive, emitting progress events",
+        |req: BatchExportRequest, host: Arc<dyn CommandHost>| async move {
+            let result: BatchExportResult =
+                crate::batch_export::export_batch(req, move |progress| {
+                    host.emit(
+                        crate::batch_export::PROGRESS_EVENT,
+                        serde_json::to_value(progress).unwrap_or(Value::Null),
+                    );
+                })
+                .await?;
+            Ok(result)
+        },
+    );
+    r.add(
+        "rename_session",
+        "sessions",
+        Scope::Write,
+        "Set the display name of a session",
+        |req: RenameSessionRequest, _| crate::rename_session(req.path, req.new_name),
+    );
+    r.add(
+        "get_session_stats",
+        "sessions",
+        Scope::Read,
+        "Aggregate statistics for the given sessions",
+        |req: StatsRequest, _| crate::get_session_stats(req.sessions),
+    );
+    r.add(
+        "get_session_stats_light",
+        "sessions",
+        Scope::Read,
+        "Aggregate statistics from trimmed session summaries",
+        |req: StatsLightRequest, _| crate::get_session_stats_light(req.sessions),
+    );
+
+    // Search
+    r.add(
+        "search_sessions",
+        "search",
+        Scope::Search,
+        "Search the given sessions by name or content",
+        |req: SearchSessionsRequest, _| {
+            crate::search_sessions(
+                req.sessions,
+                req.query,
+                req.search_mode,
+                req.role_filter,
+                req.include_tools,
+            )
+        },
+    );
+    r.add(
+        "search_sessions_fts",
+        "search",
+        Scope::Search,
+        "Sessions matching a full-text query",
+        |req: FtsSearchRequest, _| crate::search_sessions_fts(req.query, req.limit),
+    );
+    r.add(
+        "full_text_search",
+        "search",
+        Scope::Search,
+        "Paged message-level full-text search",
+        |req: FullTextSearchRequest, _| {
+            crate::full_text_search(
+                req.query,
+                req.role_filter,
+                req.glob_pattern,
+                req.page,
+                req.page_size,
+                req.match_mode,
+            )
+        },
+    );
+
+    // Favorites
+    r.add(
+        "get_all_favorites",
+        "favorites",
+        Scope::Read,
+        "List favorites",
+        |_: NoArgs, _| crate::get_all_favorites(),
+    );
+    r.add(
+        "add_favorite",
+        "favorites",
+        Scope::Write,
+        "Add a favorite",
+        |req: FavoriteRequest, _| {
+            crate::add_favorite(req.id, req.favorite_type, req.name, req.path)
+        },
+    );
+    r.add(
+        "remove_favorite",
+        "favorites",
+        Scope::Write,
+        "Remove a favorite",
+        |req: IdRequest, _| crate::remove_favorite(req.id),
+    );
+    r.add(
+        "is_favorite",
+        "favorites",
+        Scope::Read,
+        "Whether an item is a favorite",
+        |req: IdRequest, _| crate::is_favorite(req.id),
+    );
+    r.add(
+        "toggle_favorite",
+        "favorites",
+        Scope::Write,
+        "Toggle a favorite, returning the new state",
+        |req: FavoriteRequest, _| {
+            crate::toggle_favorite(req.id, req.favorite_type, req.name, req.path)
+        },
+    );
+
+    // Skills, prompts & resources
+    r.add(
+        "scan_skills",
+        "resources",
+        Scope::Read,
+        "List installed skills",
+        |_: NoArgs, _| crate::scan_skills_internal(),
+    );
+    r.add(
+        "scan_prompts",
+        "resources",
+        Scope::Read,
+        "List prompt templates",
+        |_: NoArgs, _| crate::scan_prompts_internal(),
+    );
+    r.add(
+        "get_skill_content",
+        "resources",
+        Scope::Read,
+        "SKILL.md of an installed skill",
+        |req: SkillRequest, _| crate::get_skill_content(req.skill_name),
+    );
+    r.add(
+        "get_prompt_content",
+        "resources",
+        Scope::Read,
+        "Content of a prompt template",
+        |req: PromptRequest, _| crate::get_prompt_content(req.prompt_name),
+    );
+    r.add(
+        "get_system_prompt",
+        "resources",
+        Scope::Read,
+        "The appended system prompt",
+        |_: NoArgs, _| crate::get_system_prompt(),
+    );
+    r.add(
+        "get_session_system_prompt",
+        "resources",
+        Scope::Read,
+        "System prompt recorded in a session",
+        |req: PathRequest, _| crate::get_session_system_prompt_internal(req.path),
+    );
+    r.add(
+        "scan_all_resources",
+        "resources",
+        Scope::Read,
+        "Skills, prompts, extensions and themes for a project",
+        |req: CwdRequest, _| crate::scan_all_resources_internal(req.cwd),
+    );
+    r.add(
+        "toggle_resource",
+        "resources",
+        Scope::Write,
+        "Enable or disable a resource in settings.json",
+        |req: ToggleResourceRequest, _| {
+            crate::toggle_resource_internal(req.resource_type, req.path, req.enabled, req.scope)
+        },
+    );
+    r.add(
+        "read_resource_file",
+        "resources",
+        Scope::Read,
+        "Content of a resource file",
+        |req: ReadResourceRequest, _| crate::read_resource_file_internal(req.path, req.scope),
+    );
+
+    // Pi settings
+    r.add(
+        "load_pi_settings",
+        "pi-settings",
+        Scope::Read,
+        "Load pi settings",
+        |_: NoArgs, _| crate::load_pi_settings_internal(),
+    );
+    r.add(
+        "save_pi_settings",
+        "pi-settings",
+        Scope::Write,
+        "Save pi settings",
+        |req: SettingsRequest<crate::PiSettings>, _| crate::save_pi_settings(req.settings),
+    );
+    r.add(
+        "load_pi_settings_full",
+        "pi-settings",
+        Scope::Read,
+        "Load the full pi settings.json",
+        |_: NoArgs, _| crate::load_pi_settings_full_internal(),
+    );
+    r.add(
+        "save_pi_setting",
+        "pi-settings",
+        Scope::Write,
+        "Set one key in pi settings.json",
+        |req: SavePiSettingRequest, _| crate::save_pi_setting_internal(req.key, req.value),
+    );
+    r.add(
+        "list_model_options_fast",
+        "pi-settings",
+        Scope::Read,
+        "Models from models.json",
+        |_: NoArgs, _| crate::list_model_options_fast_internal(),
+    );
+    r.add(
+        "list_model_options_full",
+        "pi-settings",
+        Scope::Read,
+        "Models reported by the pi CLI",
+        |_: NoArgs, _| crate::list_model_options_full_internal(),
+    );
+    r.add(
+        "list_config_versions",
+        "pi-settings",
+        Scope::Read,
+        "Saved snapshots of config files",
+        |req: ConfigVersionsRequest, _| crate::list_config_versions_internal(req.file_path),
+    );
+    r.add(
+        "get_config_version",
+        "pi-settings",
+        Scope::Read,
+        "One config snapshot",
+        |req: VersionIdRequest, _| crate::get_config_version_internal(req.id),
+    );
+    r.add(
+        "restore_config_version",
+        "pi-settings",
+        Scope::Write,
+        "Restore a config snapshot",
+        |req: VersionIdRequest, _| crate::restore_config_version_internal(req.id),
+    );
+
+    // App settings
+    r.add(
+        "load_app_settings",
+        "settings",
+        Scope::Read,
+        "Load app settings",
+        |_: NoArgs, _| crate::load_app_settings_internal(),
+    );
+    r.add(
+        "save_app_settings",
+        "settings",
+        Scope::Write,
+        "Save app settings",
+        |req: SettingsRequest<Value>, _| crate::save_app_settings(req.settings),
+    );
+    r.add(
+        "load_server_settings",
+        "settings",
+        Scope::Read,
+        "Load server settings",
+        |_: NoArgs, _| crate::load_server_settings(),
+    );
+    r.add(
+        "save_server_settings",
+        "settings",
+        Scope::Admin,
+        "Save server settings",
+        |req: SettingsRequest<ServerSettings>, _| crate::save_server_settings(req.settings),
+    );
+    r.add(
+        "load_redaction_settings",
+        "settings",
+        Scope::Read,
+        "Load redaction settings",
+        |_: NoArgs, _| crate::load_redaction_settings(),
+    );
+    r.add(
+        "save_redaction_settings",
+        "settings",
+        Scope::Admin,
+        "Save redaction settings",
+        |req: SettingsRequest<RedactionSettings>, _| crate::save_redaction_settings(req.settings),
+    );
+    r.add(
+        "audit_secrets",
+        "settings",
+        Scope::Read,
+        "Scan sessions for leaked secrets",
+        |req: AuditRequest, _| crate::audit_secrets(req.full),
+    );
+    r.add(
+        "get_session_paths",
+        "settings",
+        Scope::Read,
+        "Extra session directories",
+        |_: NoArgs, _| crate::get_session_paths(),
+    );
+    r.add(
+        "save_session_paths",
+        "settings",
+        Scope::Admin,
+        "Set the extra session directories",
+        |req: SessionPathsRequest, host: Arc<dyn CommandHost>| async move {
+            crate::save_session_paths_core(req.paths).await?;
+            host.session_paths_changed();
+            Ok(())
+        },
+    );
+    r.add(
+        "get_all_session_dirs",
+        "settings",
+        Scope::Read,
+        "All session directories that are scanned",
+        |_: NoArgs, _| crate::get_all_session_dirs(),
+    );
+    r.add(
+        "clear_cache",
+        "settings",
+        Scope::Write,
+        "Drop the session cache and search index",
+        |_: NoArgs, _| crate::clear_cache(),
+    );
+    r.add(
+        "export_user_data",
+        "settings",
+        Scope::Admin,
+        "Write tags, favorites, settings and config versions to a JSON file",
+        |req: ExportUserDataRequest, _| {
+            crate::export_user_data(req.output_path, req.include_tokens)
+        },
+    );
+    r.add(
+        "import_user_data",
+        "settings",
+        Scope::Admin,
+        "Merge user data from a JSON file written by export_user_data",
+        |req: ImportUserDataRequest, _| crate::import_user_data(req.input_path),
+    );
+
+    // Models
+    r.add(
+        "list_models",
+        "models",
+        Scope::Read,
+        "Models available to pi",
+        |req: ListModelsRequest, _| crate::list_models(req.search),
+    );
+    r.add(
+        "test_model",
+        "models",
+        Scope::Write,
+        "Send a test prompt to a model",
+        |req: TestModelRequest, _| crate::test_model(req.provider, req.model, req.prompt),
+    );
+    r.add(
+        "test_models_batch",
+        "models",
+        Scope::Write,
+        "Test several models",
+        |req: TestModelsBatchRequest, _| crate::test_models_batch(req.models, req.prompt),
+    );
+
+    // Tags
+    r.add(
+        "get_all_tags",
+        "tags",
+        Scope::Read,
+        "List tags",
+        |_: NoArgs, _| crate::get_all_tags(),
+    );
+    r.add(
+        "create_tag",
+        "tags",
+        Scope::Write,
+        "Create a tag",
+        |req: CreateTagRequest, _| crate::create_tag(req.name, req.color, req.icon, req.parent_id),
+    );
+    r.add(
+        "update_tag",
+        "tags",
+        Scope::Write,
+        "Update a tag",
+        |req: UpdateTagRequest, _| {
+            crate::update_tag(
+                req.id,
+                req.name,
+                req.color,
+                req.icon,
+                req.sort_order,
+                req.parent_id,
+            )
+        },
+    );
+    r.add(
+        "delete_tag",
+        "tags",
+        Scope::Write,
+        "Delete a tag",
+        |req: IdRequest, _| crate::delete_tag(req.id),
+    );
+    r.add(
+        "get_all_session_tags",
+        "tags",
+        Scope::Read,
+        "All session/tag assignments",
+        |_: NoArgs, _| crate::get_all_session_tags(),
+    );
+    r.add(
+        "assign_tag",
+        "tags",
+        Scope::Write,
+        "Tag a session",
+        |req: SessionTagRequest, _| crate::assign_tag(req.session_id, req.tag_id),
+    );
+    r.add(
+        "remove_tag_from_session",
+        "tags",
+        Scope::Write,
+        "Untag a session",
+        |req: SessionTagRequest, _| crate::remove_tag_from_session(req.session_id, req.tag_id),
+    );
+    r.add(
+        "move_session_tag",
+        "tags",
+        Scope::Write,
+        "Move a session between tag columns",
+        |req: MoveSessionTagRequest, _| {
+            crate::move_session_tag(req.session_id, req.from_tag_id, req.to_tag_id, req.position)
+        },
+    );
+    r.add(
+        "reorder_tags",
+        "tags",
+        Scope::Write,
+        "Set the tag order",
+        |req: ReorderTagsRequest, _| crate::reorder_tags(req.tag_ids),
+    );
+    r.add(
+        "update_tag_auto_rules",
+        "tags",
+        Scope::Write,
+        "Set the auto-tagging rules of a tag",
+        |req: TagAutoRulesRequest, _| crate::update_tag_auto_rules(req.id, req.auto_rules),
+    );
+    r.add(
+        "evaluate_auto_rules",
+        "tags",
+        Scope::Write,
+        "Tags whose auto rules match the text",
+        |req: EvaluateAutoRulesRequest, _| crate::evaluate_auto_rules(req.session_id, req.text),
+    );
+
+    // Auth
+    r.add(
+        "list_api_keys",
+        "auth",
+        Scope::Admin,
+        "List API keys",
+        |_: NoArgs, _| crate::list_api_keys(),
+    );
+    r.add(
+        "create_api_key",
+        "auth",
+        Scope::Admin,
+        "Create an API key, returning the token",
+        |req: CreateApiKeyRequest, _| {
+            crate::create_api_key(
+                req.name,
+                req.key,
+                req.value,
+                req.scope,
+                req.expires_in_days,
+                req.allowed_paths,
+            )
+        },
+    );
+    r.add(
+        "revoke_api_key",
+        "auth",
+        Scope::Admin,
+        "Revoke an API key",
+        |req: RevokeApiKeyRequest, _| crate::revoke_api_key(req.key_preview),
+    );
+    r.add(
+        "list_audit_log",
+        "auth",
+        Scope::Admin,
+        "Recent remote requests made with API keys, newest first",
+        |req: AuditLogRequest, _| crate::list_audit_log(req.limit),
+    );
+
+    // Terminal
+    r.add(
+        "terminal_create",
+        "terminal",
+        Scope::Admin,
+        "Open a terminal session",
+        |req: TerminalCreateRequest, host: Arc<dyn CommandHost>| async move {
+            host.terminal_create(req)
+        },
+    );
+    r.add(
+        "terminal_write",
+        "terminal",
+        Scope::Admin,
+        "Write input to a terminal",
+        |req: TerminalWriteRequest, host: Arc<dyn CommandHost>| async move {
+            host.terminal_write(&req.id, req.data)
+        },
+    );
+    r.add(
+        "terminal_resize",
+        "terminal",
+        Scope::Admin,
+        "Resize a terminal",
+        |req: TerminalResizeRequest, host: Arc<dyn CommandHost>| async move {
+            host.terminal_resize(&req.id, req.rows, req.cols)
+        },
+    );
+    r.add(
+        "terminal_close",
+        "terminal",
+        Scope::Admin,
+        "Close a terminal",
+        |req: IdRequest, host: Arc<dyn CommandHost>| async move { host.terminal_close(&req.id) },
+    );
+    r.add(
+        "get_default_shell",
+        "terminal",
+        Scope::Admin,
+        "Path of the preferred shell",
+        |_: NoArgs, host: Arc<dyn CommandHost>| async move {
+            let fallback = if cfg!(windows) { "cmd.exe" } else { "/bin/sh" };
+            Ok(host
+                .shells()?
+                .into_iter()
+                .next()
+                .map(|(_, path)| path)
+                .unwrap_or_else(|| fallback.to_string()))
+        },
+    );
+    r.add(
+        "get_available_shells",
+        "terminal",
+        Scope::Admin,
+        "Installed shells as [label, path] pairs",
+        |_: NoArgs, host: Arc<dyn CommandHost>| async move { host.shells() },
+    );
+
+    r.add(
+        "list_commands",
+        "meta",
+        Scope::Search,
+        "Every command with its request and response JSON schema",
+        |_: NoArgs, _| async { Ok(list_commands()) },
+    );
+
+    r
+}
+
+// ─── Public API ──────────────────────────────────────────────
+
+pub fn commands() -> &'static [CommandSpec] {
+    &REGISTRY.commands
+}
+
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    REGISTRY.index.get(name).map(|&i| &REGISTRY.commands[i])
+}
+
+pub fn list_commands() -> Vec<CommandInfo> {
+    commands()
+        .iter()
+        .map(|spec| CommandInfo {
+            name: spec.name.to_string(),
+            group: spec.group.to_string(),
+            scope: spec.scope,
+            mutating: spec.mutating,
+            session_content: spec.session_content,
+            description: spec.description.to_string(),
+            request: standalone_schema(
+                SchemaSettings::draft2020_12().for_deserialize(),
+                spec.request_schema,
+            ),
+            response: standalone_schema(
+                SchemaSettings::draft2020_12().for_serialize(),
+                spec.response_schema,
+            ),
+        })
+        .collect()
+}
+
+fn standalone_schema(
+    settings: SchemaSettings,
+    schema: fn(&mut SchemaGenerator) -> Schema,
+) -> Value {
+    let mut generator = settings.into_generator();
+    let mut schema = schema(&mut generator);
+    let definitions = generator.take_definitions(true);
+    if !definitions.is_empty() {
+        schema.insert("$defs".to_string(), Value::Object(definitions));
+    }
+    schema.to_value()
+}
+
+/// Run a command with the given host. Every adapter (Tauri IPC, HTTP,
+/// WebSocket, CLI server) goes through here.
+pub async fn dispatch(
+    command: &str,
+    payload: Value,
+    host: Arc<dyn CommandHost>,
+) -> Result<Value, String> {
+    if DESKTOP_ONLY.contains(&command) {
+        return Err(format!("{command} is desktop-only"));
+    }
+    let spec = find(command).ok_or_else(|| format!("Unknown command: {command}"))?;
+    (spec.handler)(payload, host).await
+}
+
+/// Groups a path-restricted token may use. Everything else (settings,
+/// resources, models) is global and would leak other projects.
+const PATH_SCOPED_GROUPS: &[&str] = &["sessions", "search", "favorites", "tags", "meta"];
+
+/// Run a command on behalf of a remote caller: check the token scope and
+/// allowed paths, redact session contents for non-local clients, then record
+/// the outcome in the audit log.
+pub async fn dispatch_as(
+    caller: &Caller,
+    command: &str,
+    payload: Value,
+    host: Arc<dyn CommandHost>,
+) -> Result<Value, String> {
+    let result = dispatch_checked(caller, command, payload, host).await;
+    match &result {
+        Ok(_) => caller.audit(command, "ok", None),
+        Err(Denied(reason)) => caller.audit(command, "denied", Some(reason)),
+        Err(Failed(error)) => caller.audit(command, "error", Some(error)),
+    }
+    result.map_err(|e| match e {
+        Denied(reason) => format!("Forbidden: {reason}"),
+        Failed(error) => error,
+    })
+}
+
+enum Refusal {
+    Denied(String),
+    Failed(String),
+}
+
+use Refusal::{Denied, Failed};
+
+async fn dispatch_checked(
+    caller: &Caller,
+    command: &str,
+    mut payload: Value,
+    host: Arc<dyn CommandHost>,
+) -> Result<Value, Refusal> {
+    let Some(spec) = find(command) else {
+        return dispatch(command, payload, host).await.map_err(Failed);
+    };
+    if spec.mutating && is_read_only() {
+        return Err(Denied(format!(
+            "{command} is disabled because the server is in read-only mode"
+        )));
+    }
+    if !caller.allows(spec.scope) {
+        return Err(Denied(format!(
+            "{command} requires the {} scope",
+            spec.scope.as_str()
+        )));
+    }
+    let redact = crate::redaction::redacts_for(&caller.ip);
+    if redact && EXPORTS.contains(&command) {
+        force_redacted_export(&mut payload);
+    }
+
+    let response = if caller.is_path_restricted() {
+        if !PATH_SCOPED_GROUPS.contains(&spec.group) {
+            return Err(Denied(format!(
+                "{command} is not available to path-restricted tokens"
+            )));
+        }
+        let index = SessionIndex::load().await.map_err(Failed)?;
+        index
+            .check_request(caller, command, spec.group, &mut payload)
+            .map_err(Denied)?;
+        let mut response = dispatch(command, payload, host).await.map_err(Failed)?;
+        index.filter_response(caller, &mut response);
+        response
+    } else {
+        dispatch(command, payload, host).await.map_err(Failed)?
+    };
+
+    if redact && spec.session_content {
+        return Ok(crate::redaction::redact_remote_value(&caller.ip, response));
+    }
+    Ok(response)
+}
+
+/// Turn on `options.redact` in an export payload
+fn force_redacted_export(payload: &mut Value) {
+    if payload.is_null() {
+        *payload = Value::Object(Default::default());
+    }
+    if let Some(fields) = payload.as_object_mut() {
+        let options = fields
+            .entry("options")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if options.is_null() {
+            *options = Value::Object(Default::default());
+        }
+        if let Some(options) = options.as_object_mut() {
+            options.insert("redact".to_string(), Value::Bool(true));
+        }
+    }
+}
+
+/// Drop list items outside a path-restricted caller's allowed paths; a no-op
+/// for everyone else
+pub async fn filter_for_caller(caller: &Caller, value: &mut Value) -> Result<(), String> {
+    if caller.is_path_restricted() {
+        SessionIndex::load().await?.filter_response(caller, value);
+    }
+    Ok(())
+}
+
+/// Whether a broadcast event should reach the caller, narrowing its payload
+/// to the caller's allowed paths. Terminal output needs the admin scope.
+pub async fn event_for_caller(caller: &Caller, event: &str, payload: &mut Value) -> bool {
+    if event.starts_with("terminal-") && !caller.allows(Scope::Admin) {
+        return false;
+    }
+    filter_for_caller(caller, payload).await.is_ok()
+}
+
+/// Session path/id → cwd, used to apply a token's allowed paths
+struct SessionIndex {
+    cwd_by_key: HashMap<String, String>,
+}
+
+lazy_static! {
+    /// Built from the scan cache and tagged with its version, so requests and
+    /// broadcast events reuse it until the session list changes
+    static ref SESSION_INDEX: Mutex<Option<(u64, Arc<SessionIndex>)>> = Mutex::new(None);
+}
+
+impl SessionIndex {
+    async fn load() -> Result<Arc<Self>, String> {
+        let cached = SESSION_INDEX.lock().ok().and_then(|guard| {
+            guard
+                .as_ref()
+                .filter(|(version, _)| *version == crate::scanner::cache_version())
+                .map(|(_, index)| index.clone())
+        });
+        if let Some(index) = cached {
+            return Ok(index);
+        }
+
+        // Read before scanning: a change during the scan then forces a rebuild
+        // instead of tagging an older list with the newer version
+        let version = crate::scanner::cache_version();
+        let sessions = crate::scanner::scan_sessions().await?;
+        let mut cwd_by_key = HashMap::with_capacity(sessions.len() * 2);
+        for session in sessions {
+            cwd_by_key.insert(session.id, session.cwd.clone());
+            cwd_by_key.insert(session.path, session.cwd);
+        }
+        let index = Arc::new(Self { cwd_by_key });
+        if let Ok(mut guard) = SESSION_INDEX.lock() {
+            *guard = Some((version, index.clone()));
+        }
+        Ok(index)
+    }
+
+    fn visible(&self, caller: &Caller, key: &str) -> bool {
+        self.cwd_by_key
+            .get(key)
+            .is_some_and(|cwd| caller.allows_cwd(cwd))
+    }
+
+    /// Reject payloads that name a session outside the allowed paths, and
+    /// narrow client-supplied session lists to the visible ones.
+    fn check_request(
+        &self,
+        caller: &Caller,
+        command: &str,
+        group: &str,
+        payload: &mut Value,
+    ) -> Result<(), String> {
+        let Some(fields) = payload.as_object_mut() else {
+            return Ok(());
+        };
+        let keys: &[&str] = match group {
+            "sessions" => &["path"],
+            "favorites" => &["id", "path"],
+            "tags" => &["sessionId", "session_id"],
+            _ => &[],
+        };
+        for key in keys {
+            if let Some(value) = fields.get(*key).and_then(Value::as_str) {
+                if !self.visible(caller, value) {
+                    return Err(format!("{value} is outside the token's allowed paths"));
+                }
+            }
+        }
+        let paths = fields.get("paths").and_then(Value::as_array);
+        // Without paths a batch export selects from every scanned session
+        if command == "export_sessions_batch" && paths.is_none_or(Vec::is_empty) {
+            return Err("path-restricted tokens must list the sessions to export".to_string());
+        }
+        if let Some(paths) = paths {
+            if let Some(path) = paths
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|p| !self.visible(caller, p))
+            {
+                return Err(format!("{path} is outside the token's allowed paths"));
+            }
+        }
+        if let Some(Value::Array(sessions)) = fields.get_mut("sessions") {
+            sessions.retain(|s| {
+                s.get("cwd")
+                    .and_then(Value::as_str)
+                    .is_some_and(|cwd| caller.allows_cwd(cwd))
+            });
+        }
+        Ok(())
+    }
+
+    /// Drop list items that belong to sessions outside the allowed paths
+    fn filter_response(&self, caller: &Caller, value: &mut Value) {
+        match value {
+            Value::Array(items) => {
+                items.retain(|item| self.item_visible(caller, item));
+                for item in items {
+                    self.filter_response(caller, item);
+                }
+            }
+            Value::Object(fields) => {
+                for field in fields.values_mut() {
+                    self.filter_response(caller, field);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn item_visible(&self, caller: &Caller, item: &Value) -> bool {
+        let Some(fields) = item.as_object() else {
+            return true;
+        };
+        if let Some(cwd) = fields.get("cwd").and_then(Value::as_str) {
+            return caller.allows_cwd(cwd);
+        }
+        for key in ["session_path", "sessionPath", "session_id", "sessionId"] {
+            if let Some(value) = fields.get(key).and_then(Value::as_str) {
+                return self.visible(caller, value);
+            }
+        }
+        // Favorites point at a session file or a project directory
+        match fields.get("path").and_then(Value::as_str) {
+            Some(path) => match self.cwd_by_key.get(path) {
+                Some(cwd) => caller.allows_cwd(cwd),
+                None => caller.allows_cwd(path),
+            },
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,6 @@
+use crate::secret_audit::{self, SecretAuditReport};
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn audit_secrets(full: Option<bool>) -> Result<SecretAuditReport, String> {
+    secret_audit::audit_secrets(full.unwrap_or(false)).await
+}
@@ -1,8 +1,20 @@
 use crate::auth;
+use chrono::{Duration, Utc};
+
+/// Run a token-store call off the async runtime; it goes through the database
+async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("API key task failed: {e}"))?
+}
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn list_api_keys() -> Result<Vec<auth::TokenInfo>, String> {
-    auth::list_tokens()
+    blocking(auth::list_tokens).await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -10,6 +22,9 @@ pub async fn create_api_key(
     name: Option<String>,
     key: Option<String>,
     value: Option<String>,
+    scope: Option<auth::Scope>,
+    expires_in_days: Option<u32>,
+    allowed_paths: Option<Vec<String>>,
 ) -> Result<String, String> {
     let final_name = key
         .as_deref()
@@ -19,14 +34,30 @@ pub async fn create_api_key(
         .unwrap_or("unnamed")
         .to_string();
 
+    let options = auth::TokenOptions {
+        scope: scope.unwrap_or(auth::Scope::Admin),
+        expires_at: expires_in_days
+            .filter(|days| *days > 0)
+            .map(|days| Utc::now() + Duration::days(days.into())),
+        allowed_paths: allowed_paths.unwrap_or_default(),
+    };
+
     match (key, value) {
-        (Some(_), Some(v)) => auth::create_token(&final_name, Some(v.as_str())),
-        (None, None) => auth::create_token(&final_name, None),
+        (Some(_), Some(v)) => {
+            blocking(move || auth::create_token(&final_name, Some(v.as_str()), &options)).await
+        }
+        (None, None) => blocking(move || auth::create_token(&final_name, None, &options)).await,
         _ => Err("Both key and value are required for manual key creation".to_string()),
     }
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn revoke_api_key(key_preview: String) -> Result<(), String> {
-    auth::revoke_token(&key_preview)
+    blocking(move || auth::revoke_token(&key_preview)).await
+}
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn list_audit_log(limit: Option<usize>) -> Result<Vec<auth::AuditEntry>, String> {
+    let limit = limit.unwrap_or(200).min(10_000);
+    blocking(move || auth::list_audit_log(limit)).await
 }
@@ -0,0 +1,24 @@
+use crate::backup::{self, UserDataSummary};
+use crate::database;
+use std::path::Path;
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn export_user_data(
+    output_path: String,
+    include_tokens: Option<bool>,
+) -> Result<UserDataSummary, String> {
+    let include_tokens = include_tokens.unwrap_or(false);
+    let data = database::get()?
+        .read_async(move |conn| backup::export(conn, include_tokens))
+        .await?;
+    backup::write_file(Path::new(&output_path), &data)?;
+    Ok(data.summary())
+}
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn import_user_data(input_path: String) -> Result<UserDataSummary, String> {
+    let data = backup::read_file(Path::new(&input_path))?;
+    database::get()?
+        .write_async(move |conn| backup::import(conn, &data))
+        .await
+}
@@ -1,6 +1,6 @@
-use crate::{config, sqlite_cache};
+use crate::{config, database, sqlite_cache};
 
-#[derive(serde::Serialize, Clone, Debug)]
+#[derive(serde::Serialize, Clone, Debug, schemars::JsonSchema)]
 pub struct ClearCacheResult {
     pub sessions_deleted: usize,
     pub details_deleted: usize,
@@ -9,8 +9,9 @@ pub struct ClearCacheResult {
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn clear_cache() -> Result<ClearCacheResult, String> {
     let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    let (sessions_deleted, details_deleted) = sqlite_cache::clear_all_cache(&conn)?;
+    let (sessions_deleted, details_deleted) = database::get_with_config(&config)?
+        .write_async(sqlite_cache::clear_all_cache)
+        .await?;
     Ok(ClearCacheResult {
         sessions_deleted,
         details_deleted,
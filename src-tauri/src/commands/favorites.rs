@@ -1,6 +1,6 @@
-use crate::{config, sqlite_cache};
+use crate::{database, sqlite_cache};
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct FavoriteItem {
     pub id: String,
     #[serde(rename = "type")]
@@ -17,25 +17,25 @@ pub async fn add_favorite(
     name: String,
     path: String,
 ) -> Result<(), String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    sqlite_cache::add_favorite(&conn, &id, &favorite_type, &name, &path)?;
-    Ok(())
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::add_favorite(conn, &id, &favorite_type, &name, &path)
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn remove_favorite(id: String) -> Result<(), String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    sqlite_cache::remove_favorite(&conn, &id)?;
-    Ok(())
+    database::get()?
+        .write_async(move |conn| sqlite_cache::remove_favorite(conn, &id))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn get_all_favorites() -> Result<Vec<FavoriteItem>, String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    let db_favorites = sqlite_cache::get_all_favorites(&conn)?;
+    let db_favorites = database::get()?
+        .read_async(sqlite_cache::get_all_favorites)
+        .await?;
     Ok(db_favorites
         .into_iter()
         .map(|f| FavoriteItem {
@@ -50,9 +50,9 @@ pub async fn get_all_favorites() -> Result<Vec<FavoriteItem>, String> {
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn is_favorite(id: String) -> Result<bool, String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    sqlite_cache::is_favorite(&conn, &id)
+    database::get()?
+        .read_async(move |conn| sqlite_cache::is_favorite(conn, &id))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -62,7 +62,9 @@ pub async fn toggle_favorite(
     name: String,
     path: String,
 ) -> Result<bool, String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    sqlite_cache::toggle_favorite(&conn, &id, &favorite_type, &name, &path)
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::toggle_favorite(conn, &id, &favorite_type, &name, &path)
+        })
+        .await
 }
@@ -1,4 +1,6 @@
+mod audit;
 mod auth_cmds;
+mod backup;
 mod cache;
 mod favorites;
 mod models;
@@ -10,7 +12,9 @@ mod tags;
 #[cfg(feature = "gui")]
 pub mod terminal;
 
+pub use audit::*;
 pub use auth_cmds::*;
+pub use backup::*;
 pub use cache::*;
 pub use favorites::*;
 pub use models::*;
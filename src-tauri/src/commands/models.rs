@@ -1,7 +1,7 @@
 use std::process::{Command, Stdio};
 use std::time::Instant;
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct ModelInfo {
     pub provider: String,
     pub model: String,
@@ -12,7 +12,7 @@ pub struct ModelInfo {
     pub status: String,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct ModelTestResult {
     pub provider: String,
     pub model: String,
@@ -1,6 +1,6 @@
 use crate::metrics;
 use crate::models::{FullTextSearchHit, FullTextSearchResponse, SessionInfo};
-use crate::{config, search, sqlite_cache};
+use crate::{config, database, search, search_query, sqlite_cache, tantivy_search};
 use chrono::{DateTime, Utc};
 use rusqlite::ToSql;
 use std::collections::HashMap;
@@ -38,18 +38,20 @@ pub async fn search_sessions(
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn search_sessions_fts(query: String, limit: usize) -> Result<Vec<SessionInfo>, String> {
     let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
+    database::get_with_config(&config)?
+        .read_async(move |conn| {
+            let paths = sqlite_cache::search_fts5(conn, &query, limit)?;
 
-    let paths = sqlite_cache::search_fts5(&conn, &query, limit)?;
-
-    let mut sessions = Vec::new();
-    for path in paths {
-        if let Some(session) = sqlite_cache::get_session(&conn, &path)? {
-            sessions.push(session);
-        }
-    }
+            let mut sessions = Vec::new();
+            for path in paths {
+                if let Some(session) = sqlite_cache::read_session_row(conn, &path)? {
+                    sessions.push(session);
+                }
+            }
 
-    Ok(sessions)
+            Ok(sessions)
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -82,226 +84,215 @@ pub async fn full_text_search(
             // Load config (blocking file I/O)
             let config = config::load_config()
                 .map_err(|e| format!("Failed to load config: {e}"))?;
-            // Open database
-            let conn = sqlite_cache::init_db_with_config(&config)
-                .map_err(|e| format!("Failed to init database: {e}"))?;
-            // Set query timeout at SQLite level (in milliseconds)
-            conn.execute("PRAGMA query_timeout = 5000", [])
-                .map_err(|e| format!("Failed to set query_timeout: {e}"))?;
+            let db = database::get_with_config(&config)?;
+            db.read(|conn| {
+                // Set query timeout at SQLite level (in milliseconds)
+                conn.execute("PRAGMA query_timeout = 5000", [])
+                    .map_err(|e| format!("Failed to set query_timeout: {e}"))?;
 
-            // Determine role filter for message FTS (case-insensitive)
-            let role_filter = role_filter.to_lowercase();
-            let role_opt = match role_filter.as_str() {
-                "user" => Some("user"),
-                "assistant" => Some("assistant"),
-                _ => None,
-            };
+                // Determine role filter for message FTS (case-insensitive)
+                let role_filter = role_filter.to_lowercase();
+                let role_opt = match role_filter.as_str() {
+                    "user" => Some("user"),
+                    "assistant" => Some("assistant"),
+                    _ => None,
+                };
 
-            // Build FTS query based on match_mode
-            // Determine match mode: default "any"
-            let mode = match match_mode.as_deref() {
-                Some("all") => "all",
-                Some("phrase") => "phrase",
-                _ => "any", // default to any (OR)
-            };
+                // Parse the query grammar; match_mode picks the operator between bare terms
+                // ("phrase" keeps the legacy behaviour of matching the whole input literally)
+                let expr = match match_mode.as_deref() {
+                    Some("phrase") => search_query::Expr::Phrase(trimmed.to_string()),
+                    Some("all") => search_query::parse(trimmed, search_query::DefaultOperator::And)
+                        .map_err(|e| format!("Invalid query: {e}"))?,
+                    _ => search_query::parse(trimmed, search_query::DefaultOperator::Or)
+                        .map_err(|e| format!("Invalid query: {e}"))?,
+                };
+                let plan = search_query::plan(expr).map_err(|e| format!("Invalid query: {e}"))?;
 
-            // Helper to escape a word for FTS5
-            fn escape_word(word: &str) -> String {
-                let mut escaped = String::new();
-                for ch in word.chars() {
-                    match ch {
-                        '"' => escaped.push_str("\"\""),
-                        '\\' => escaped.push_str("\\\\"),
-                        _ => escaped.push(ch),
-                    }
+                if config.search_engine == config::SearchEngine::Tantivy {
+                    let response = tantivy_search::search(
+                        conn,
+                        &plan,
+                        role_opt,
+                        glob_pattern.as_deref(),
+                        page,
+                        page_size,
+                    )?;
+                    metrics::record_search_latency(start.elapsed());
+                    metrics::inc_search_queries();
+                    metrics::add_search_results(response.hits.len());
+                    return Ok(response);
                 }
-                escaped
-            }
 
-            // Build the FTS query string
-            let fts_query = if mode == "phrase" {
-                // Treat entire query as a phrase
-                let escaped = escape_word(trimmed);
-                format!("\"{escaped}\"")
-            } else {
-                // Split into words; for 'all' we use space (AND), for 'any' we use OR
-                let words: Vec<&str> = trimmed.split_whitespace().collect();
-                let escaped_words: Vec<String> = words.iter().map(|w| escape_word(w)).collect();
-                if mode == "all" {
-                    escaped_words.join(" ")
-                } else {
-                    // any mode
-                    escaped_words.join(" OR ")
-                }
-            };
+                let fts_query = plan.fts5_match();
+                let (filter_conditions, filter_params) = plan.sql_conditions();
 
-            // Build the base WHERE clause for FTS and role filter
-            let role_condition = match role_opt {
-                Some("user") => "m.role = 'user'",
-                Some("assistant") => "m.role = 'assistant'",
-                _ => "1=1",
-            };
-            let mut where_clause = format!("WHERE message_fts MATCH ? AND {role_condition}");
-            let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
-            params.push(&fts_query);
+                // Build the base WHERE clause for FTS and role filter
+                let role_condition = match role_opt {
+                    Some("user") => "m.role = 'user'",
+                    Some("assistant") => "m.role = 'assistant'",
+                    _ => "1=1",
+                };
+                let mut where_clause = format!("WHERE message_fts MATCH ? AND {role_condition}");
+                let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+                params.push(&fts_query);
 
-            // Include glob pattern if provided: convert to LIKE with escaping
-            let mut like_pattern = String::new(); // allocate early to extend lifetime
-            if let Some(pattern_str) = &glob_pattern {
-                if !pattern_str.is_empty() {
-                    // Convert glob pattern (* and ?) to LIKE patterns (% and _) and escape LIKE wildcards (% and _) and backslash
-                    like_pattern.clear();
-                    for ch in pattern_str.chars() {
-                        match ch {
-                            '*' => like_pattern.push('%'),
-                            '?' => like_pattern.push('_'),
-                            '%' | '_' => {
-                                like_pattern.push('\\');
-                                like_pattern.push(ch);
-                            }
-                            '\\' => {
-                                like_pattern.push('\\');
-                                like_pattern.push('\\');
-                            }
-                            _ => like_pattern.push(ch),
-                        }
-                    }
+                // Include glob pattern if provided: convert to LIKE with escaping
+                let like_pattern = glob_pattern
+                    .as_deref()
+                    .filter(|p| !p.is_empty())
+                    .map(search_query::glob_to_like);
+                if let Some(like_pattern) = &like_pattern {
                     where_clause = format!("{where_clause} AND m.session_path LIKE ? ESCAPE '\\'");
-                    params.push(&like_pattern);
+                    params.push(like_pattern);
                 }
-            }
 
-            // --- Count total hits after per-session limit (max 3 per session) ---
-            let count_sql = format!(
-                "SELECT COUNT(*) FROM (
-                    SELECT 1 FROM (
-                        SELECT
-                            ROW_NUMBER() OVER (PARTITION BY m.session_path ORDER BY m.timestamp DESC) as rn_in_session
-                        FROM message_entries m JOIN message_fts ON m.rowid = message_fts.rowid
-                        {where_clause}
-                    ) WHERE rn_in_session <= 3
-                )"
-            );
+                // Field qualifiers from the query (role:, cwd:, model:, tag:, after:, before:)
+                for condition in &filter_conditions {
+                    where_clause = format!("{where_clause} AND {condition}");
+                }
+                for param in &filter_params {
+                    params.push(param);
+                }
 
-            let total_hits: usize = {
-                let mut stmt = conn
-                    .prepare(&count_sql)
-                    .map_err(|e| format!("Failed to prepare total count query: {e}"))?;
-                let count: i64 = match stmt.query_row(params.as_slice(), |row| row.get(0)) {
-                    Ok(c) => c,
-                    Err(e) => return Err(format!("Failed to get total hits count: {e}")),
-                };
-                count as usize
-            };
+                // --- Count total hits after per-session limit (max 3 per session) ---
+                let count_sql = format!(
+                    "SELECT COUNT(*) FROM (
+                        SELECT 1 FROM (
+                            SELECT
+                                ROW_NUMBER() OVER (PARTITION BY m.session_path ORDER BY m.timestamp DESC) as rn_in_session
+                            FROM message_entries m
+                            JOIN message_fts ON m.rowid = message_fts.rowid
+                            JOIN sessions s ON s.path = m.session_path
+                            {where_clause}
+                        ) WHERE rn_in_session <= 3
+                    )"
+                );
 
-            // --- Fetch the page of hits with global ordering and per-session limit ---
-            let offset = page * page_size;
-            let limit = page_size;
-            let data_sql = format!(
-                "WITH ranked AS (
-                    SELECT
-                        m.id,
-                        m.session_path,
-                        m.role,
-                        m.timestamp,
-                        message_fts.rank as rank,
-                        ROW_NUMBER() OVER (PARTITION BY m.session_path ORDER BY m.timestamp DESC) as rn_in_session
-                    FROM message_entries m
-                    JOIN message_fts ON m.rowid = message_fts.rowid
-                    {where_clause}
-                ),
-                filtered AS (
-                    SELECT
-                        id, session_path, role, timestamp, rank,
-                        ROW_NUMBER() OVER (ORDER BY rank) as global_rn
-                    FROM ranked
-                    WHERE rn_in_session <= 3
-                )
-                SELECT f.id, f.session_path, f.role, m.content, f.timestamp, f.rank
-                FROM filtered f
-                JOIN message_entries m ON f.id = m.id
-                WHERE f.global_rn > ? AND f.global_rn <= ?
-                ORDER BY f.rank"
-            );
+                let total_hits: usize = {
+                    let mut stmt = conn
+                        .prepare(&count_sql)
+                        .map_err(|e| format!("Failed to prepare total count query: {e}"))?;
+                    let count: i64 = match stmt.query_row(params.as_slice(), |row| row.get(0)) {
+                        Ok(c) => c,
+                        Err(e) => return Err(format!("Failed to get total hits count: {e}")),
+                    };
+                    count as usize
+                };
 
-            // Prepare parameters for data query: base params (fts_query, optional glob) plus offset and limit for global_rn
-            let offset_i64 = offset as i64;
-            let limit_i64 = (offset + limit) as i64;
-            let mut data_params: Vec<&dyn rusqlite::ToSql> = params.clone();
-            data_params.push(&offset_i64);
-            data_params.push(&limit_i64);
+                // --- Fetch the page of hits with global ordering and per-session limit ---
+                let offset = page * page_size;
+                let limit = page_size;
+                let data_sql = format!(
+                    "WITH ranked AS (
+                        SELECT
+                            m.id,
+                            m.session_path,
+                            m.role,
+                            m.timestamp,
+                            message_fts.rank as rank,
+                            ROW_NUMBER() OVER (PARTITION BY m.session_path ORDER BY m.timestamp DESC) as rn_in_session
+                        FROM message_entries m
+                        JOIN message_fts ON m.rowid = message_fts.rowid
+                        JOIN sessions s ON s.path = m.session_path
+                        {where_clause}
+                    ),
+                    filtered AS (
+                        SELECT
+                            id, session_path, role, timestamp, rank,
+                            ROW_NUMBER() OVER (ORDER BY rank) as global_rn
+                        FROM ranked
+                        WHERE rn_in_session <= 3
+                    )
+                    SELECT f.id, f.session_path, f.role, m.content, f.timestamp, f.rank
+                    FROM filtered f
+                    JOIN message_entries m ON f.id = m.id
+                    WHERE f.global_rn > ? AND f.global_rn <= ?
+                    ORDER BY f.rank"
+                );
 
-            let mut stmt = conn
-                .prepare(&data_sql)
-                .map_err(|e| format!("Failed to prepare data query: {e}"))?;
+                // Prepare parameters for data query: base params (fts_query, optional glob) plus offset and limit for global_rn
+                let offset_i64 = offset as i64;
+                let limit_i64 = (offset + limit) as i64;
+                let mut data_params: Vec<&dyn rusqlite::ToSql> = params.clone();
+                data_params.push(&offset_i64);
+                data_params.push(&limit_i64);
 
-            let rows = stmt
-                .query_map(data_params.as_slice(), |row| {
-                    Ok((
-                        row.get::<_, String>(0)?, // entry_id
-                        row.get::<_, String>(1)?, // session_path
-                        row.get::<_, String>(2)?, // role
-                        row.get::<_, String>(3)?, // content
-                        row.get::<_, String>(4)?, // timestamp
-                        row.get::<_, f32>(5)?,    // rank
-                    ))
-                })
-                .map_err(|e| format!("Failed to query message FTS: {e}"))?
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| format!("Failed to collect message FTS results: {e}"))?;
+                let mut stmt = conn
+                    .prepare(&data_sql)
+                    .map_err(|e| format!("Failed to prepare data query: {e}"))?;
 
-            // Batch fetch session details and build hits
-            let mut all_hits = Vec::new();
-            let mut sessions_cache: HashMap<String, SessionInfo> = HashMap::new();
+                let rows = stmt
+                    .query_map(data_params.as_slice(), |row| {
+                        Ok((
+                            row.get::<_, String>(0)?, // entry_id
+                            row.get::<_, String>(1)?, // session_path
+                            row.get::<_, String>(2)?, // role
+                            row.get::<_, String>(3)?, // content
+                            row.get::<_, String>(4)?, // timestamp
+                            row.get::<_, f32>(5)?,    // rank
+                        ))
+                    })
+                    .map_err(|e| format!("Failed to query message FTS: {e}"))?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Failed to collect message FTS results: {e}"))?;
 
-            for (entry_id, session_path, role, content, timestamp_str, rank) in rows {
-                // Get session from cache or DB
-                let session = if let Some(sess) = sessions_cache.get(&session_path) {
-                    sess.clone()
-                } else if let Some(sess) = sqlite_cache::get_session(&conn, &session_path)? {
-                    sessions_cache.insert(session_path.clone(), sess.clone());
-                    sess
-                } else {
-                    continue;
-                };
+                // Batch fetch session details and build hits
+                let mut all_hits = Vec::new();
+                let mut sessions_cache: HashMap<String, SessionInfo> = HashMap::new();
 
-                // Parse timestamp
-                let timestamp = match chrono::DateTime::parse_from_rfc3339(&timestamp_str) {
-                    Ok(dt) => dt.with_timezone(&chrono::Utc),
-                    Err(e) => {
-                        eprintln!(
-                            "[FTS] Invalid timestamp '{timestamp_str}' for entry {entry_id}: {e}"
-                        );
+                for (entry_id, session_path, role, content, timestamp_str, rank) in rows {
+                    // Get session from cache or DB
+                    let session = if let Some(sess) = sessions_cache.get(&session_path) {
+                        sess.clone()
+                    } else if let Some(sess) = sqlite_cache::read_session_row(conn, &session_path)? {
+                        sessions_cache.insert(session_path.clone(), sess.clone());
+                        sess
+                    } else {
                         continue;
-                    }
-                };
+                    };
 
-                all_hits.push(FullTextSearchHit {
-                    session_id: session.id.clone(),
-                    session_path: session.path.clone(),
-                    session_name: session.name.clone(),
-                    entry_id,
-                    role,
-                    content,
-                    timestamp,
-                    score: rank,
-                });
-            }
+                    // Entries without a usable timestamp fall back to the session's creation
+                    // time, so every counted hit is returned
+                    let timestamp = match chrono::DateTime::parse_from_rfc3339(&timestamp_str) {
+                        Ok(dt) => dt.with_timezone(&chrono::Utc),
+                        Err(_) if timestamp_str.is_empty() => session.created,
+                        Err(e) => {
+                            log::warn!(
+                                "[FTS] Invalid timestamp '{timestamp_str}' for entry {entry_id}: {e}"
+                            );
+                            session.created
+                        }
+                    };
+
+                    all_hits.push(FullTextSearchHit {
+                        session_id: session.id.clone(),
+                        session_path: session.path.clone(),
+                        session_name: session.name.clone(),
+                        entry_id,
+                        role,
+                        content,
+                        snippet: None,
+                        timestamp,
+                        score: rank,
+                    });
+                }
 
-            // Rows are already ordered by global_rn, so all_hits is in correct order.
+                // Rows are already ordered by global_rn, so all_hits is in correct order.
 
-            let has_more = (page + 1) * page_size < total_hits;
+                let has_more = (page + 1) * page_size < total_hits;
 
-            // Record metrics
-            let latency = start.elapsed();
-            metrics::record_search_latency(latency);
-            metrics::inc_search_queries();
-            metrics::add_search_results(all_hits.len());
+                // Record metrics
+                let latency = start.elapsed();
+                metrics::record_search_latency(latency);
+                metrics::inc_search_queries();
+                metrics::add_search_results(all_hits.len());
 
-            Ok(FullTextSearchResponse {
-                hits: all_hits,
-                total_hits,
-                has_more,
+                Ok(FullTextSearchResponse {
+                    hits: all_hits,
+                    total_hits,
+                    has_more,
+                })
             })
         })
     ).await;
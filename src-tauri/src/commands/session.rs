@@ -1,10 +1,13 @@
+use crate::export::ExportOptions;
 use crate::models::{SessionEntry, SessionInfo};
-use crate::{config, export, scanner, sqlite_cache, stats};
+use crate::session_tree::SessionTree;
+use crate::{database, export, scanner, session_cursor, session_parser, sqlite_cache, stats};
 use serde_json::Value;
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct FileStats {
     pub size: u64,
     pub modified_at: u64,
@@ -21,25 +24,20 @@ pub async fn read_session_file(path: String) -> Result<String, String> {
     fs::read_to_string(&path).map_err(|e| format!("Failed to read session file: {e}"))
 }
 
+/// Lines from `from_line` on, with the new total line count. Reading starts
+/// at the scanner's saved cursor when it is still valid for the file, so a
+/// viewer following a large session does not re-read it from the top.
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn read_session_file_incremental(
     path: String,
     from_line: usize,
 ) -> Result<(usize, String), String> {
-    let content =
-        fs::read_to_string(&path).map_err(|e| format!("Failed to read session file: {e}"))?;
-
-    let lines: Vec<&str> = content.lines().collect();
-    let total_lines = lines.len();
-
-    if from_line >= total_lines {
-        return Ok((total_lines, String::new()));
-    }
+    let cursor = database::get()
+        .ok()
+        .and_then(|db| scanner::stored_cursor(&db, &path).ok().flatten())
+        .map(|(_, cursor)| cursor);
 
-    let new_lines: Vec<&str> = lines[from_line..].to_vec();
-    let new_content = new_lines.join("\n");
-
-    Ok((total_lines, new_content))
+    session_cursor::read_lines_from(Path::new(&path), from_line, cursor.as_ref())
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -67,43 +65,27 @@ pub async fn get_session_entries(path: String) -> Result<Vec<SessionEntry>, Stri
     let content =
         fs::read_to_string(&path).map_err(|e| format!("Failed to read session file: {e}"))?;
 
-    let mut entries = Vec::new();
-
-    for line in content.lines() {
-        if line.trim().is_empty() {
-            continue;
-        }
+    Ok(session_parser::parse_entries(&content))
+}
 
-        if let Ok(value) = serde_json::from_str::<Value>(line) {
-            let entry_type = value["type"].as_str().unwrap_or("unknown").to_string();
-            let id = value["id"].as_str().unwrap_or("").to_string();
-            let parent_id = value["parentId"].as_str().map(|s| s.to_string());
-            let timestamp_str = value["timestamp"].as_str().unwrap_or("");
-
-            let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .unwrap_or_else(|_| chrono::Utc::now());
-
-            let message = value
-                .get("message")
-                .and_then(|m| serde_json::from_value(m.clone()).ok());
-
-            entries.push(SessionEntry {
-                entry_type,
-                id,
-                parent_id,
-                timestamp,
-                message,
-            });
-        }
-    }
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn get_session_tree(path: String) -> Result<SessionTree, String> {
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read session file: {e}"))?;
 
-    Ok(entries)
+    Ok(SessionTree::build(session_parser::parse_entries(&content)))
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn delete_session(path: String) -> Result<(), String> {
-    fs::remove_file(&path).map_err(|e| format!("Failed to delete session: {e}"))
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete session: {e}"))?;
+    if let Ok(db) = database::get() {
+        let path = path.clone();
+        db.write_async(move |conn| sqlite_cache::delete_session(conn, &path))
+            .await?;
+    }
+    scanner::invalidate_cache();
+    Ok(())
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -111,8 +93,10 @@ pub async fn export_session(
     path: String,
     format: String,
     output_path: String,
+    options: Option<ExportOptions>,
 ) -> Result<(), String> {
-    export::export_session(&path, &format, &output_path).await
+    let options = options.unwrap_or_default();
+    export::export_session_with_options(&path, &format, &output_path, &options).await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -157,6 +141,7 @@ pub async fn rename_session(path: String, new_name: String) -> Result<(), String
     }
 
     fs::write(&path, lines.join("\n")).map_err(|e| format!("Failed to write session file: {e}"))?;
+    scanner::invalidate_cache();
 
     Ok(())
 }
@@ -291,7 +276,8 @@ pub async fn open_session_in_browser(path: String) -> Result<(), String> {
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn get_session_by_path(path: String) -> Result<Option<SessionInfo>, String> {
-    let config = config::load_config()?;
-    let conn = sqlite_cache::init_db_with_config(&config)?;
-    sqlite_cache::get_session(&conn, &path)
+    // Looking a session up counts as an access, so this goes to the writer
+    database::get()?
+        .write_async(move |conn| sqlite_cache::get_session(conn, &path))
+        .await
 }
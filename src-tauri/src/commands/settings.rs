@@ -1,14 +1,12 @@
+use crate::redaction::RedactionSettings;
 use serde_json::Value;
 use std::fs;
-#[cfg(feature = "gui")]
-use tauri::Manager;
-use tracing::warn;
 
 const APP_SETTINGS_KEY: &str = "app_settings";
 const SERVER_SETTINGS_KEY: &str = "server_settings";
 const SESSION_PATHS_KEY: &str = "session_paths";
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct ServerSettings {
     pub ws_enabled: bool,
     pub ws_port: u16,
@@ -17,6 +15,17 @@ pub struct ServerSettings {
     pub auth_enabled: bool,
     #[serde(default = "default_bind_addr")]
     pub bind_addr: String,
+    /// Serve HTTP and WebSocket over TLS
+    #[serde(default)]
+    pub tls_enabled: bool,
+    /// PEM certificate chain and key; leave both unset for a generated self-signed certificate
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+    /// Refuse mutating commands (delete, rename, settings, terminals, keys) from remote clients
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 fn default_bind_addr() -> String {
@@ -32,6 +41,10 @@ impl Default for ServerSettings {
             http_port: 52131,
             auth_enabled: true,
             bind_addr: default_bind_addr(),
+            tls_enabled: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+            read_only: false,
         }
     }
 }
@@ -47,7 +60,20 @@ pub async fn load_server_settings() -> Result<ServerSettings, String> {
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn save_server_settings(settings: ServerSettings) -> Result<(), String> {
-    crate::settings_store::set(SERVER_SETTINGS_KEY, &settings)
+    crate::settings_store::set(SERVER_SETTINGS_KEY, &settings)?;
+    // Read-only mode applies at once; the other server settings need a restart
+    crate::command_registry::set_read_only(settings.read_only);
+    Ok(())
+}
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn load_redaction_settings() -> Result<RedactionSettings, String> {
+    Ok(crate::redaction::load_settings())
+}
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub async fn save_redaction_settings(settings: RedactionSettings) -> Result<(), String> {
+    crate::redaction::save_settings(&settings)
 }
 
 pub async fn load_app_settings_internal() -> Result<Value, String> {
@@ -98,26 +124,6 @@ pub async fn save_session_paths_core(paths: Vec<String>) -> Result<(), String> {
     Ok(())
 }
 
-/// Save session paths to config and sync to settings store
-#[cfg(feature = "gui")]
-#[tauri::command]
-pub async fn save_session_paths(
-    paths: Vec<String>,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    save_session_paths_core(paths).await?;
-
-    // Restart file watcher with new paths
-    let watcher_state: tauri::State<'_, crate::file_watcher::FileWatcherState> = app_handle.state();
-    if let Err(e) =
-        crate::file_watcher::restart_watcher_with_config(&watcher_state, app_handle.clone())
-    {
-        warn!("Failed to restart file watcher: {}", e);
-    }
-
-    Ok(())
-}
-
 /// Get all resolved session directories (default + configured)
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn get_all_session_dirs() -> Result<Vec<String>, String> {
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct SkillInfo {
     pub name: String,
     pub path: String,
@@ -9,7 +9,7 @@ pub struct SkillInfo {
     pub enabled: bool,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct PromptInfo {
     pub name: String,
     pub path: String,
@@ -17,7 +17,7 @@ pub struct PromptInfo {
     pub enabled: bool,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct PiSettings {
     pub skills: Vec<String>,
     pub prompts: Vec<String>,
@@ -26,7 +26,7 @@ pub struct PiSettings {
 
 // --- Pi Config TUI aligned types ---
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceMetadata {
     pub source: String,
@@ -34,7 +34,7 @@ pub struct ResourceMetadata {
     pub origin: String,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceInfo {
     pub name: String,
@@ -623,7 +623,7 @@ pub async fn scan_all_resources(cwd: Option<String>) -> Result<Vec<ResourceInfo>
 
 // --- Pi Settings Full (task-2) ---
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CompactionSettings {
     pub enabled: Option<bool>,
@@ -631,7 +631,7 @@ pub struct CompactionSettings {
     pub keep_recent_tokens: Option<u32>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RetrySettings {
     pub enabled: Option<bool>,
@@ -640,33 +640,33 @@ pub struct RetrySettings {
     pub max_delay_ms: Option<u32>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TerminalSettings {
     pub show_images: Option<bool>,
     pub clear_on_shrink: Option<bool>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImagesSettings {
     pub auto_resize: Option<bool>,
     pub block_images: Option<bool>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MarkdownSettings {
     pub code_block_indent: Option<String>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BranchSummarySettings {
     pub reserve_tokens: Option<u32>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PiSettingsFull {
     // Model
@@ -845,7 +845,7 @@ pub async fn toggle_resource(
 
 // ─── Model Options ───────────────────────────────────────────────────────────
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ModelOption {
     pub provider: String,
@@ -956,7 +956,7 @@ pub async fn list_model_options_full() -> Result<Vec<ModelOption>, String> {
 
 const MAX_CONFIG_VERSIONS: usize = 50;
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigVersion {
     pub id: i64,
@@ -966,7 +966,7 @@ pub struct ConfigVersion {
     pub size_bytes: usize,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigVersionMeta {
     pub id: i64,
@@ -975,51 +975,43 @@ pub struct ConfigVersionMeta {
     pub size_bytes: usize,
 }
 
-fn get_config_db() -> Result<rusqlite::Connection, String> {
-    let db_path = crate::sqlite_cache::get_db_path()?;
-    let conn = rusqlite::Connection::open(&db_path).map_err(|e| format!("Open config DB: {e}"))?;
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS config_versions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            file_path TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at TEXT NOT NULL DEFAULT (datetime('now')),
-            size_bytes INTEGER NOT NULL DEFAULT 0
-        );
-        CREATE INDEX IF NOT EXISTS idx_cv_file_path ON config_versions(file_path);
-        CREATE INDEX IF NOT EXISTS idx_cv_created ON config_versions(created_at DESC);",
-    )
-    .map_err(|e| format!("Init config_versions table: {e}"))?;
-    Ok(conn)
-}
-
 fn save_config_snapshot(file_path: &str, content: &str) -> Result<(), String> {
-    let conn = get_config_db()?;
-    let size = content.len();
+    let (file_path, content) = (file_path.to_string(), content.to_string());
+    crate::database::get()?.write(move |conn| {
+        let size = content.len();
 
-    conn.execute(
-        "INSERT INTO config_versions (file_path, content, size_bytes) VALUES (?1, ?2, ?3)",
-        rusqlite::params![file_path, content, size],
-    )
-    .map_err(|e| format!("Insert snapshot: {e}"))?;
-
-    // Prune: keep only the latest MAX_CONFIG_VERSIONS per file_path
-    conn.execute(
-        "DELETE FROM config_versions WHERE file_path = ?1 AND id NOT IN (
-            SELECT id FROM config_versions WHERE file_path = ?1
-            ORDER BY id DESC LIMIT ?2
-        )",
-        rusqlite::params![file_path, MAX_CONFIG_VERSIONS],
-    )
-    .map_err(|e| format!("Prune snapshots: {e}"))?;
+        conn.execute(
+            "INSERT INTO config_versions (file_path, content, size_bytes) VALUES (?1, ?2, ?3)",
+            rusqlite::params![file_path, content, size],
+        )
+        .map_err(|e| format!("Insert snapshot: {e}"))?;
+
+        // Prune: keep only the latest MAX_CONFIG_VERSIONS per file_path
+        conn.execute(
+            "DELETE FROM config_versions WHERE file_path = ?1 AND id NOT IN (
+                SELECT id FROM config_versions WHERE file_path = ?1
+                ORDER BY id DESC LIMIT ?2
+            )",
+            rusqlite::params![file_path, MAX_CONFIG_VERSIONS],
+        )
+        .map_err(|e| format!("Prune snapshots: {e}"))?;
 
-    Ok(())
+        Ok(())
+    })
 }
 
 pub async fn list_config_versions_internal(
     file_path: Option<String>,
 ) -> Result<Vec<ConfigVersionMeta>, String> {
-    let conn = get_config_db()?;
+    crate::database::get()?
+        .read_async(move |conn| read_config_versions(conn, file_path))
+        .await
+}
+
+fn read_config_versions(
+    conn: &rusqlite::Connection,
+    file_path: Option<String>,
+) -> Result<Vec<ConfigVersionMeta>, String> {
     let mut stmt = if let Some(ref fp) = file_path {
         let mut s = conn
             .prepare(
@@ -1069,21 +1061,24 @@ pub async fn list_config_versions(
 }
 
 pub async fn get_config_version_internal(id: i64) -> Result<ConfigVersion, String> {
-    let conn = get_config_db()?;
-    conn.query_row(
-        "SELECT id, file_path, content, created_at, size_bytes FROM config_versions WHERE id = ?1",
-        rusqlite::params![id],
-        |row| {
-            Ok(ConfigVersion {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                content: row.get(2)?,
-                created_at: row.get(3)?,
-                size_bytes: row.get(4)?,
-            })
-        },
-    )
-    .map_err(|e| format!("Get version {id}: {e}"))
+    crate::database::get()?
+        .read_async(move |conn| {
+            conn.query_row(
+                "SELECT id, file_path, content, created_at, size_bytes FROM config_versions WHERE id = ?1",
+                rusqlite::params![id],
+                |row| {
+                    Ok(ConfigVersion {
+                        id: row.get(0)?,
+                        file_path: row.get(1)?,
+                        content: row.get(2)?,
+                        created_at: row.get(3)?,
+                        size_bytes: row.get(4)?,
+                    })
+                },
+            )
+            .map_err(|e| format!("Get version {id}: {e}"))
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
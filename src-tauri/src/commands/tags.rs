@@ -1,6 +1,6 @@
-use crate::{config, sqlite_cache};
+use crate::{database, sqlite_cache};
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TagItem {
     pub id: String,
@@ -14,7 +14,7 @@ pub struct TagItem {
     pub parent_id: Option<String>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionTagItem {
     pub session_id: String,
@@ -50,18 +50,12 @@ impl From<sqlite_cache::DbSessionTag> for SessionTagItem {
     }
 }
 
-fn get_conn() -> Result<rusqlite::Connection, String> {
-    let config = config::load_config()?;
-    sqlite_cache::init_db_with_config(&config)
-}
-
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn get_all_tags() -> Result<Vec<TagItem>, String> {
-    let conn = get_conn()?;
-    Ok(sqlite_cache::get_all_tags(&conn)?
-        .into_iter()
-        .map(TagItem::from)
-        .collect())
+    let tags = database::get()?
+        .read_async(sqlite_cache::get_all_tags)
+        .await?;
+    Ok(tags.into_iter().map(TagItem::from).collect())
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -71,21 +65,24 @@ pub async fn create_tag(
     icon: Option<String>,
     parent_id: Option<String>,
 ) -> Result<TagItem, String> {
-    let conn = get_conn()?;
     let id = format!("tag-{}", chrono::Utc::now().timestamp_millis());
-    sqlite_cache::create_tag(
-        &conn,
-        &id,
-        &name,
-        &color,
-        icon.as_deref(),
-        parent_id.as_deref(),
-    )?;
-    sqlite_cache::get_all_tags(&conn)?
-        .into_iter()
-        .find(|t| t.id == id)
-        .map(TagItem::from)
-        .ok_or_else(|| "Failed to find created tag".to_string())
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::create_tag(
+                conn,
+                &id,
+                &name,
+                &color,
+                icon.as_deref(),
+                parent_id.as_deref(),
+            )?;
+            sqlite_cache::get_all_tags(conn)?
+                .into_iter()
+                .find(|t| t.id == id)
+                .map(TagItem::from)
+                .ok_or_else(|| "Failed to find created tag".to_string())
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -97,43 +94,48 @@ pub async fn update_tag(
     sort_order: Option<i64>,
     parent_id: Option<Option<String>>,
 ) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::update_tag(
-        &conn,
-        &id,
-        name.as_deref(),
-        color.as_deref(),
-        icon.as_deref(),
-        sort_order,
-        parent_id.as_ref().map(|p| p.as_deref()),
-    )
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::update_tag(
+                conn,
+                &id,
+                name.as_deref(),
+                color.as_deref(),
+                icon.as_deref(),
+                sort_order,
+                parent_id.as_ref().map(|p| p.as_deref()),
+            )
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn delete_tag(id: String) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::delete_tag(&conn, &id)
+    database::get()?
+        .write_async(move |conn| sqlite_cache::delete_tag(conn, &id))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn get_all_session_tags() -> Result<Vec<SessionTagItem>, String> {
-    let conn = get_conn()?;
-    Ok(sqlite_cache::get_all_session_tags(&conn)?
-        .into_iter()
-        .map(SessionTagItem::from)
-        .collect())
+    let tags = database::get()?
+        .read_async(sqlite_cache::get_all_session_tags)
+        .await?;
+    Ok(tags.into_iter().map(SessionTagItem::from).collect())
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn assign_tag(session_id: String, tag_id: String) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::assign_tag(&conn, &session_id, &tag_id)
+    database::get()?
+        .write_async(move |conn| sqlite_cache::assign_tag(conn, &session_id, &tag_id))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn remove_tag_from_session(session_id: String, tag_id: String) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::remove_tag_from_session(&conn, &session_id, &tag_id)
+    database::get()?
+        .write_async(move |conn| sqlite_cache::remove_tag_from_session(conn, &session_id, &tag_id))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
@@ -143,30 +145,38 @@ pub async fn move_session_tag(
     to_tag_id: String,
     position: i64,
 ) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::move_session_tag(
-        &conn,
-        &session_id,
-        from_tag_id.as_deref(),
-        &to_tag_id,
-        position,
-    )
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::move_session_tag(
+                conn,
+                &session_id,
+                from_tag_id.as_deref(),
+                &to_tag_id,
+                position,
+            )
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn reorder_tags(tag_ids: Vec<String>) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::reorder_tags(&conn, &tag_ids)
+    database::get()?
+        .write_async(move |conn| sqlite_cache::reorder_tags(conn, &tag_ids))
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn update_tag_auto_rules(id: String, auto_rules: Option<String>) -> Result<(), String> {
-    let conn = get_conn()?;
-    sqlite_cache::update_tag_auto_rules(&conn, &id, auto_rules.as_deref())
+    database::get()?
+        .write_async(move |conn| {
+            sqlite_cache::update_tag_auto_rules(conn, &id, auto_rules.as_deref())
+        })
+        .await
 }
 
 #[cfg_attr(feature = "gui", tauri::command)]
 pub async fn evaluate_auto_rules(session_id: String, text: String) -> Result<Vec<String>, String> {
-    let conn = get_conn()?;
-    sqlite_cache::evaluate_auto_rules(&conn, &session_id, &text)
+    database::get()?
+        .read_async(move |conn| sqlite_cache::evaluate_auto_rules(conn, &session_id, &text))
+        .await
 }
@@ -4,6 +4,17 @@ use std::path::PathBuf;
 
 const CONFIG_FILE: &str = "session-manager-config.toml";
 
+/// Backend used by `full_text_search`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchEngine {
+    /// SQLite FTS5 over `message_entries` (default)
+    #[default]
+    Fts5,
+    /// On-disk Tantivy index with BM25 ranking
+    Tantivy,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "default_realtime_cutoff_days")]
@@ -29,6 +40,21 @@ pub struct Config {
 
     #[serde(default = "default_metrics_port")]
     pub metrics_port: u16,
+
+    #[serde(default)]
+    pub search_engine: SearchEngine,
+
+    /// Threads parsing session files during a scan; 0 picks one per CPU
+    #[serde(default)]
+    pub scan_workers: usize,
+
+    /// Hours between backups of tags, favorites and other user data; 0 disables
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: u64,
+
+    /// Backups kept before the oldest is removed
+    #[serde(default = "default_backup_keep")]
+    pub backup_keep: usize,
 }
 
 fn default_realtime_cutoff_days() -> i64 {
@@ -59,6 +85,14 @@ fn default_metrics_port() -> u16 {
     9090
 }
 
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -70,6 +104,10 @@ impl Default for Config {
             session_paths: vec![],
             metrics_enabled: false,
             metrics_port: 9090,
+            search_engine: SearchEngine::Fts5,
+            scan_workers: 0,
+            backup_interval_hours: 24,
+            backup_keep: 7,
         }
     }
 }
@@ -79,6 +117,16 @@ impl Config {
         load_config()
     }
 
+    /// Number of scan workers to run, resolving 0 to the available parallelism
+    pub fn scan_worker_count(&self) -> usize {
+        match self.scan_workers {
+            0 => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            n => n,
+        }
+    }
+
     pub fn load_config() -> Result<Self, String> {
         load_config()
     }
@@ -0,0 +1,330 @@
+use crate::config::Config;
+use crate::sqlite_cache;
+use rusqlite::{Connection, OpenFlags};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Read-only connections kept open per database
+const MAX_READERS: usize = 4;
+
+/// How long a connection waits on a lock held by another process
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+type WriteJob = Box<dyn FnOnce(&Connection) + Send>;
+
+thread_local! {
+    static ON_WRITER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The single service every module reaches the sessions database through.
+/// One thread owns the only read-write connection and runs write jobs in
+/// order; reads borrow a connection from a small pool of read-only ones, so
+/// with WAL they never wait on the writer. Every connection opens the cache
+/// database with the user database attached. Async callers use `write_async`
+/// and `read_async`, which never block a runtime thread.
+pub struct Database {
+    path: PathBuf,
+    writer: Mutex<mpsc::Sender<WriteJob>>,
+    writer_thread: Option<thread::JoinHandle<()>>,
+    readers: Mutex<ReaderPool>,
+    reader_returned: Condvar,
+    search_engine: Mutex<Option<crate::config::SearchEngine>>,
+}
+
+#[derive(Default)]
+struct ReaderPool {
+    idle: Vec<Connection>,
+    open: usize,
+}
+
+static DATABASES: OnceLock<Mutex<HashMap<PathBuf, Arc<Database>>>> = OnceLock::new();
+
+fn databases() -> &'static Mutex<HashMap<PathBuf, Arc<Database>>> {
+    DATABASES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The service for the current database file, started on first use with the
+/// saved config
+pub fn get() -> Result<Arc<Database>, String> {
+    let path = sqlite_cache::get_db_path()?;
+    if let Some(db) = lookup(&path)? {
+        return Ok(db);
+    }
+    get_with_config(&Config::load().unwrap_or_default())
+}
+
+/// Like `get`, and brings the search index in line with `config`
+pub fn get_with_config(config: &Config) -> Result<Arc<Database>, String> {
+    let path = sqlite_cache::get_db_path()?;
+    let db = match lookup(&path)? {
+        Some(db) => db,
+        None => {
+            let mut map = databases()
+                .lock()
+                .map_err(|e| format!("Failed to lock database registry: {e}"))?;
+            match map.get(&path) {
+                Some(db) => db.clone(),
+                None => {
+                    let db = Arc::new(Database::start(path.clone(), config)?);
+                    map.insert(path, db.clone());
+                    db
+                }
+            }
+        }
+    };
+    db.sync_config(config)?;
+    Ok(db)
+}
+
+/// Registered service for `path`, dropping it if the file has since been
+/// deleted (corruption recovery, tests)
+fn lookup(path: &PathBuf) -> Result<Option<Arc<Database>>, String> {
+    let mut map = databases()
+        .lock()
+        .map_err(|e| format!("Failed to lock database registry: {e}"))?;
+    if !path.exists() {
+        map.remove(path);
+        return Ok(None);
+    }
+    Ok(map.get(path).cloned())
+}
+
+/// Stop the service for the current database file. Connections close once
+/// the last handle is dropped, right away when nobody else holds one; the
+/// next `get` opens the file again.
+pub fn close() {
+    let Ok(path) = sqlite_cache::get_db_path() else {
+        return;
+    };
+    // Dropped outside the registry lock, since dropping waits for the writer
+    let removed = databases()
+        .lock()
+        .ok()
+        .and_then(|mut map| map.remove(&path));
+    drop(removed);
+}
+
+impl Database {
+    fn start(path: PathBuf, config: &Config) -> Result<Self, String> {
+        // Schema creation and migrations run once, on the writer connection
+        let conn = sqlite_cache::init_db_with_config(config)?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| format!("Failed to set busy timeout: {e}"))?;
+
+        let (tx, rx) = mpsc::channel::<WriteJob>();
+        let writer_thread = thread::Builder::new()
+            .name("db-writer".to_string())
+            .spawn(move || {
+                ON_WRITER.with(|w| w.set(true));
+                for job in rx {
+                    job(&conn);
+                }
+            })
+            .map_err(|e| format!("Failed to start database writer: {e}"))?;
+
+        info!("Database service started for {:?}", path);
+        Ok(Self {
+            path,
+            writer: Mutex::new(tx),
+            writer_thread: Some(writer_thread),
+            readers: Mutex::new(ReaderPool::default()),
+            reader_returned: Condvar::new(),
+            search_engine: Mutex::new(Some(config.search_engine)),
+        })
+    }
+
+    /// Open or close the search index when the configured engine changed
+    fn sync_config(&self, config: &Config) -> Result<(), String> {
+        let mut engine = self
+            .search_engine
+            .lock()
+            .map_err(|e| format!("Failed to lock search engine state: {e}"))?;
+        if *engine == Some(config.search_engine) {
+            return Ok(());
+        }
+        *engine = Some(config.search_engine);
+        let config = config.clone();
+        self.write(move |conn| {
+            sqlite_cache::sync_search_engine(conn, &config);
+            Ok(())
+        })
+    }
+
+    /// Run `f` on the writer thread and wait for its result. Jobs run one at
+    /// a time in submission order; `f` may read as well as write. Blocks the
+    /// calling thread, so async code uses `write_async`.
+    pub fn write<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+        self.send_write(f, move |result| {
+            let _ = reply_tx.send(result);
+        })?;
+        reply_rx
+            .recv()
+            .map_err(|_| "Database writer stopped".to_string())?
+    }
+
+    /// Like `write`, awaiting the writer's reply instead of blocking
+    pub async fn write_async<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send_write(f, move |result| {
+            let _ = reply_tx.send(result);
+        })?;
+        reply_rx
+            .await
+            .map_err(|_| "Database writer stopped".to_string())?
+    }
+
+    /// Queue `f` on the writer thread, handing its result to `reply`
+    fn send_write<T, F, R>(&self, f: F, reply: R) -> Result<(), String>
+    where
+        F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+        R: FnOnce(Result<T, String>) + Send + 'static,
+    {
+        if ON_WRITER.with(|w| w.get()) {
+            return Err("Nested database write from the writer thread".to_string());
+        }
+        let job: WriteJob = Box::new(move |conn| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(conn)))
+                .unwrap_or_else(|_| Err("Database write panicked".to_string()));
+            reply(result);
+        });
+        self.writer
+            .lock()
+            .map_err(|e| format!("Failed to lock database writer: {e}"))?
+            .send(job)
+            .map_err(|_| "Database writer stopped".to_string())
+    }
+
+    /// Queue `f` on the writer thread without waiting for it; failures are
+    /// only logged. For bookkeeping writes on request paths.
+    pub fn submit<F>(&self, f: F)
+    where
+        F: FnOnce(&Connection) -> Result<(), String> + Send + 'static,
+    {
+        let job: WriteJob =
+            Box::new(
+                move |conn| match panic::catch_unwind(AssertUnwindSafe(|| f(conn))) {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => warn!("Queued database write failed: {}", e),
+                    Err(_) => warn!("Queued database write panicked"),
+                },
+            );
+        let sent = self
+            .writer
+            .lock()
+            .map(|writer| writer.send(job).is_ok())
+            .unwrap_or(false);
+        if !sent {
+            warn!("Database writer stopped, dropping queued write");
+        }
+    }
+
+    /// Run `f` on a pooled read-only connection on the calling thread. May
+    /// wait for a connection to be returned, so async code uses `read_async`.
+    pub fn read<T, F>(&self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&Connection) -> Result<T, String>,
+    {
+        let conn = self.checkout()?;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&conn)));
+        self.checkin(conn);
+        match result {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Like `read`, on the blocking thread pool
+    pub async fn read_async<T, F>(self: &Arc<Self>, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, String> + Send + 'static,
+    {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || db.read(f))
+            .await
+            .map_err(|e| format!("Database read task failed: {e}"))?
+    }
+
+    fn checkout(&self) -> Result<Connection, String> {
+        let mut pool = self
+            .readers
+            .lock()
+            .map_err(|e| format!("Failed to lock reader pool: {e}"))?;
+        loop {
+            if let Some(conn) = pool.idle.pop() {
+                return Ok(conn);
+            }
+            if pool.open < MAX_READERS {
+                pool.open += 1;
+                drop(pool);
+                return self.open_reader().inspect_err(|_| {
+                    if let Ok(mut pool) = self.readers.lock() {
+                        pool.open -= 1;
+                    }
+                });
+            }
+            pool = self
+                .reader_returned
+                .wait(pool)
+                .map_err(|e| format!("Failed to wait for a reader: {e}"))?;
+        }
+    }
+
+    fn checkin(&self, conn: Connection) {
+        match self.readers.lock() {
+            Ok(mut pool) => pool.idle.push(conn),
+            Err(e) => warn!("Dropping reader connection: {}", e),
+        }
+        self.reader_returned.notify_one();
+    }
+
+    fn open_reader(&self) -> Result<Connection, String> {
+        let conn = Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| format!("Failed to set busy timeout: {e}"))?;
+        // Read-only like the cache it is attached to
+        crate::migrations::attach_user_db(&conn, &self.path)?;
+        Ok(conn)
+    }
+}
+
+impl Drop for Database {
+    /// Let the writer finish queued jobs and wait for it, so the file is
+    /// closed (and its WAL checkpointed) once the last handle is gone
+    fn drop(&mut self) {
+        // Read-only connections cannot checkpoint, so they close first and
+        // the writer is the last connection
+        if let Ok(pool) = self.readers.get_mut() {
+            pool.idle.clear();
+        }
+        if let Ok(writer) = self.writer.get_mut() {
+            *writer = mpsc::channel().0;
+        }
+        if ON_WRITER.with(|w| w.get()) {
+            return;
+        }
+        if let Some(handle) = self.writer_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
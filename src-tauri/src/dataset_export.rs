@@ -0,0 +1,239 @@
+use crate::export::ExportOptions;
+use crate::models::{Content, Message, SessionEntry};
+use crate::session_parser;
+use crate::session_tree::SessionTree;
+use serde_json::{json, Value};
+
+/// OpenAI chat fine-tuning format: one `{"messages": [...]}` line per
+/// conversation (per branch unless `active_branch_only` is set)
+pub fn render_openai_jsonl(
+    content: &str,
+    options: &ExportOptions,
+    system_prompt: Option<&str>,
+) -> String {
+    let tree = SessionTree::build(session_parser::parse_entries(content));
+    let mut lines = Vec::new();
+
+    for path in conversations(&tree, options) {
+        let mut messages = Vec::new();
+        if let Some(prompt) = system_prompt.filter(|p| !p.is_empty()) {
+            messages.push(json!({ "role": "system", "content": prompt }));
+        }
+
+        for entry in path {
+            if let Some(message) = &entry.message {
+                messages.extend(openai_messages(message, options));
+            } else if let Some(custom) = &entry.custom_message {
+                let text = join_text(&custom.content);
+                if !text.is_empty() {
+                    messages.push(json!({ "role": "user", "content": text }));
+                }
+            }
+        }
+
+        if messages.iter().any(|m| m["role"] == "assistant") {
+            lines.push(json!({ "messages": messages }).to_string());
+        }
+    }
+
+    join_lines(lines)
+}
+
+fn openai_messages(message: &Message, options: &ExportOptions) -> Vec<Value> {
+    match message.role.as_str() {
+        "user" => non_empty_user(join_text(&message.content)),
+        "assistant" => {
+            let text = join_text(&message.content);
+            let tool_calls: Vec<Value> = message
+                .content
+                .iter()
+                .filter_map(|c| match c {
+                    Content::ToolCall {
+                        name,
+                        id,
+                        arguments,
+                    } => Some(json!({
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": arguments.to_string() },
+                    })),
+                    _ => None,
+                })
+                .collect();
+            if text.is_empty() && tool_calls.is_empty() {
+                return Vec::new();
+            }
+
+            let mut msg = json!({
+                "role": "assistant",
+                "content": if text.is_empty() { Value::Null } else { Value::String(text) },
+            });
+            if !tool_calls.is_empty() {
+                msg["tool_calls"] = Value::Array(tool_calls);
+            }
+            if !options.drop_thinking {
+                let thinking = join_thinking(&message.content);
+                if !thinking.is_empty() {
+                    msg["reasoning_content"] = Value::String(thinking);
+                }
+            }
+            vec![msg]
+        }
+        "toolResult" => tool_outputs(message)
+            .map(|(id, output)| json!({ "role": "tool", "tool_call_id": id, "content": output }))
+            .collect(),
+        // User-run shell commands are fed back to the model as user input
+        "bashExecution" => tool_outputs(message)
+            .flat_map(|(_, output)| non_empty_user(output.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// ShareGPT format: one `{"id", "system", "conversations": [...]}` line per
+/// conversation, using `human` / `gpt` / `function_call` / `observation` turns
+pub fn render_sharegpt(
+    content: &str,
+    options: &ExportOptions,
+    system_prompt: Option<&str>,
+) -> String {
+    let session_id = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|value| value["type"] == "session")
+        .and_then(|header| header["id"].as_str().map(String::from))
+        .unwrap_or_default();
+    let tree = SessionTree::build(session_parser::parse_entries(content));
+    let mut lines = Vec::new();
+
+    for (i, path) in conversations(&tree, options).into_iter().enumerate() {
+        let mut turns = Vec::new();
+        for entry in path {
+            if let Some(message) = &entry.message {
+                turns.extend(sharegpt_turns(message, options));
+            } else if let Some(custom) = &entry.custom_message {
+                let text = join_text(&custom.content);
+                if !text.is_empty() {
+                    turns.push(turn("human", text));
+                }
+            }
+        }
+        if !turns.iter().any(|t| t["from"] == "gpt") {
+            continue;
+        }
+
+        let id = if i == 0 {
+            session_id.clone()
+        } else {
+            format!("{session_id}#{i}")
+        };
+        let mut line = json!({ "id": id, "conversations": turns });
+        if let Some(prompt) = system_prompt.filter(|p| !p.is_empty()) {
+            line["system"] = Value::String(prompt.to_string());
+        }
+        lines.push(line.to_string());
+    }
+
+    join_lines(lines)
+}
+
+fn sharegpt_turns(message: &Message, options: &ExportOptions) -> Vec<Value> {
+    match message.role.as_str() {
+        "user" => {
+            let text = join_text(&message.content);
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![turn("human", text)]
+            }
+        }
+        "assistant" => {
+            let mut turns = Vec::new();
+            let mut text = join_text(&message.content);
+            if !options.drop_thinking {
+                let thinking = join_thinking(&message.content);
+                if !thinking.is_empty() {
+                    text = format!("<think>\n{thinking}\n</think>\n\n{text}");
+                }
+            }
+            if !text.trim().is_empty() {
+                turns.push(turn("gpt", text));
+            }
+            for c in &message.content {
+                if let Content::ToolCall {
+                    name, arguments, ..
+                } = c
+                {
+                    let call = json!({ "name": name, "arguments": arguments });
+                    turns.push(turn("function_call", call.to_string()));
+                }
+            }
+            turns
+        }
+        "toolResult" => tool_outputs(message)
+            .map(|(_, output)| turn("observation", output.to_string()))
+            .collect(),
+        "bashExecution" => tool_outputs(message)
+            .map(|(_, output)| turn("human", output.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn conversations<'a>(tree: &'a SessionTree, options: &ExportOptions) -> Vec<Vec<&'a SessionEntry>> {
+    if options.active_branch_only {
+        vec![tree.active_entries().collect()]
+    } else {
+        tree.leaf_paths()
+    }
+}
+
+fn tool_outputs(message: &Message) -> impl Iterator<Item = (&str, &str)> {
+    message.content.iter().filter_map(|c| match c {
+        Content::ToolResult {
+            tool_call_id,
+            output,
+            ..
+        } => Some((tool_call_id.as_str(), output.as_str())),
+        _ => None,
+    })
+}
+
+fn non_empty_user(text: String) -> Vec<Value> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![json!({ "role": "user", "content": text })]
+    }
+}
+
+fn turn(from: &str, value: String) -> Value {
+    json!({ "from": from, "value": value })
+}
+
+fn join_text(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(Content::as_text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn join_thinking(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Thinking { thinking } => Some(thinking.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn join_lines(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        String::new()
+    } else {
+        lines.join("\n") + "\n"
+    }
+}
@@ -1,35 +1,151 @@
+use crate::dataset_export;
+use crate::html_export;
+use crate::models::{Content, SessionEntry};
+use crate::redaction;
+use crate::session_parser;
+use crate::session_tree::SessionTree;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::process::Command;
 
+/// Options for single-session and batch exports
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ExportOptions {
+    /// Omit thinking blocks from dataset formats
+    #[serde(alias = "drop_thinking")]
+    pub drop_thinking: bool,
+    /// Export only the active branch instead of every branch
+    #[serde(alias = "active_branch_only")]
+    pub active_branch_only: bool,
+    /// Skip sessions with fewer user + assistant messages than this
+    #[serde(alias = "min_messages")]
+    pub min_messages: usize,
+    /// Prepend the system prompt (see `extract_system_prompt`)
+    #[serde(alias = "include_system_prompt")]
+    pub include_system_prompt: bool,
+    /// Replace detected secrets (see `redaction`) before rendering
+    pub redact: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            drop_thinking: false,
+            active_branch_only: true,
+            min_messages: 0,
+            include_system_prompt: false,
+            redact: false,
+        }
+    }
+}
+
 pub async fn export_session(
     session_path: &str,
     format: &str,
     output_path: &str,
 ) -> Result<(), String> {
+    export_session_with_options(session_path, format, output_path, &ExportOptions::default()).await
+}
+
+pub async fn export_session_with_options(
+    session_path: &str,
+    format: &str,
+    output_path: &str,
+    options: &ExportOptions,
+) -> Result<(), String> {
+    let content = fs::read_to_string(session_path)
+        .map_err(|e| format!("Failed to read session file: {e}"))?;
+
+    let message_count = count_messages(&content);
+    if message_count < options.min_messages {
+        return Err(format!(
+            "Session has {message_count} messages, fewer than the minimum of {}",
+            options.min_messages
+        ));
+    }
+
+    let rendered = render_session_for_path(session_path, &content, format, options)?;
+
+    fs::write(output_path, rendered).map_err(|e| format!("Failed to write export file: {e}"))?;
+
+    Ok(())
+}
+
+/// Render session file contents in one of the export formats
+pub fn render_session(content: &str, format: &str) -> Result<String, String> {
+    render_session_with_options(content, format, &ExportOptions::default(), None)
+}
+
+pub fn render_session_with_options(
+    content: &str,
+    format: &str,
+    options: &ExportOptions,
+    system_prompt: Option<&str>,
+) -> Result<String, String> {
     match format {
-        "html" => export_using_pi_command(session_path, output_path),
-        "json" => export_as_json(session_path, output_path),
-        "md" | "markdown" => export_as_markdown(session_path, output_path),
+        "html" => Ok(html_export::render_session_html(content)),
+        "json" => render_json(content),
+        "md" | "markdown" => Ok(render_markdown(content)),
+        "openai-jsonl" => Ok(dataset_export::render_openai_jsonl(
+            content,
+            options,
+            system_prompt,
+        )),
+        "sharegpt" => Ok(dataset_export::render_sharegpt(
+            content,
+            options,
+            system_prompt,
+        )),
         _ => Err(format!("Unsupported format: {format}")),
     }
 }
 
-fn export_using_pi_command(session_path: &str, output_path: &str) -> Result<(), String> {
-    // 使用 PI 的 export 命令生成 HTML
-    let output = Command::new("pi")
-        .arg("--export")
-        .arg(session_path)
-        .arg(output_path)
-        .output()
-        .map_err(|e| format!("Failed to execute pi command: {e}"))?;
+/// Like `render_session_with_options`, resolving the system prompt for
+/// `session_path` when the options ask for it
+pub fn render_session_for_path(
+    session_path: &str,
+    content: &str,
+    format: &str,
+    options: &ExportOptions,
+) -> Result<String, String> {
+    let mut system_prompt = if options.include_system_prompt && is_dataset_format(format) {
+        Some(extract_system_prompt(session_path)?)
+    } else {
+        None
+    };
+    if !options.redact {
+        return render_session_with_options(content, format, options, system_prompt.as_deref());
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Pi export command failed: {stderr}"));
+    let redactor = redaction::Redactor::load();
+    let content = redactor.redact(content);
+    system_prompt = system_prompt.map(|prompt| redactor.redact(&prompt));
+    render_session_with_options(&content, format, options, system_prompt.as_deref())
+}
+
+/// File extension used for an export format
+pub fn file_extension(format: &str) -> Option<&'static str> {
+    match format {
+        "html" => Some("html"),
+        "json" => Some("json"),
+        "md" | "markdown" => Some("md"),
+        "openai-jsonl" | "sharegpt" => Some("jsonl"),
+        _ => None,
     }
+}
 
-    Ok(())
+/// Formats producing one JSON line per conversation, suitable for concatenation
+pub fn is_dataset_format(format: &str) -> bool {
+    matches!(format, "openai-jsonl" | "sharegpt")
+}
+
+/// User + assistant messages on the active branch
+pub fn count_messages(content: &str) -> usize {
+    let details = session_parser::parse_session_details(content);
+    details.user_messages + details.assistant_messages
 }
 
 /// Build the system prompt for a session by calling pi's buildSystemPrompt via node.
@@ -142,82 +258,123 @@ fn which_pi_module() -> Option<String> {
     None
 }
 
-fn export_as_json(session_path: &str, output_path: &str) -> Result<(), String> {
-    let content = fs::read_to_string(session_path)
-        .map_err(|e| format!("Failed to read session file: {e}"))?;
-
+fn render_json(content: &str) -> Result<String, String> {
     let entries: Vec<Value> = content
         .lines()
         .filter(|line| !line.trim().is_empty())
         .filter_map(|line| serde_json::from_str(line).ok())
         .collect();
 
-    let json_content = serde_json::to_string_pretty(&entries)
-        .map_err(|e| format!("Failed to serialize JSON: {e}"))?;
-
-    fs::write(output_path, json_content)
-        .map_err(|e| format!("Failed to write export file: {e}"))?;
-
-    Ok(())
+    serde_json::to_string_pretty(&entries).map_err(|e| format!("Failed to serialize JSON: {e}"))
 }
 
-fn export_as_markdown(session_path: &str, output_path: &str) -> Result<(), String> {
-    let content = fs::read_to_string(session_path)
-        .map_err(|e| format!("Failed to read session file: {e}"))?;
-
+fn render_markdown(content: &str) -> String {
     let mut md = String::new();
     let mut session_name = String::from("Session Export");
     let mut session_date = String::new();
 
-    for line in content.lines() {
-        if line.trim().is_empty() {
-            continue;
+    let header = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|value| value["type"] == "session");
+    if let Some(header) = header {
+        if let Some(name) = header["name"].as_str() {
+            session_name = name.to_string();
         }
+        if let Some(ts) = header["timestamp"].as_str() {
+            session_date = ts.to_string();
+        }
+        md.push_str(&format!("# {session_name}\n\n"));
+        md.push_str(&format!("**Date:** {session_date}\n\n"));
+        md.push_str("---\n\n");
+    }
 
-        if let Ok(entry) = serde_json::from_str::<Value>(line) {
-            let entry_type = entry["type"].as_str().unwrap_or("unknown");
+    // Only the active branch; abandoned ones show up through their branch summaries
+    let tree = SessionTree::build(session_parser::parse_entries(content));
+    for entry in tree.active_entries() {
+        push_markdown_entry(&mut md, entry);
+    }
 
-            if entry_type == "session" {
-                if let Some(name) = entry["name"].as_str() {
-                    session_name = name.to_string();
-                }
-                if let Some(ts) = entry["timestamp"].as_str() {
-                    session_date = ts.to_string();
+    md
+}
+
+fn push_markdown_entry(md: &mut String, entry: &SessionEntry) {
+    let timestamp = entry
+        .timestamp
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true));
+    // Shown after each entry heading; entries without a valid time get none
+    let stamp = timestamp
+        .as_deref()
+        .map(|t| format!(" *{t}*"))
+        .unwrap_or_default();
+
+    if let Some(message) = &entry.message {
+        let role_label = match message.role.as_str() {
+            "user" => "**User**".to_string(),
+            "assistant" => "**Assistant**".to_string(),
+            "toolResult" => "**Tool Result**".to_string(),
+            "bashExecution" => "**Bash**".to_string(),
+            role => format!("**{role}**"),
+        };
+        md.push_str(&format!("{role_label}{stamp}\n\n"));
+        push_markdown_content(md, &message.content);
+    } else if let Some(compaction) = &entry.compaction {
+        md.push_str(&format!("**Compaction**{stamp}\n\n"));
+        md.push_str(&compaction.summary);
+        md.push_str("\n\n");
+    } else if let Some(branch) = &entry.branch_summary {
+        md.push_str(&format!("**Branch Summary**{stamp}\n\n"));
+        md.push_str(&branch.summary);
+        md.push_str("\n\n");
+    } else if let Some(custom) = &entry.custom_message {
+        if !custom.display {
+            return;
+        }
+        md.push_str(&format!("**{}**{stamp}\n\n", custom.custom_type));
+        push_markdown_content(md, &custom.content);
+    } else if let Some(change) = &entry.model_change {
+        let model = match &change.provider {
+            Some(provider) => format!("{provider}/{}", change.model_id),
+            None => change.model_id.clone(),
+        };
+        let at = timestamp.map(|t| format!(" at {t}")).unwrap_or_default();
+        md.push_str(&format!("*Model changed to `{model}`{at}*\n\n"));
+    } else {
+        return;
+    }
+
+    md.push_str("---\n\n");
+}
+
+fn push_markdown_content(md: &mut String, content: &[Content]) {
+    for item in content {
+        match item {
+            Content::Text { text } => {
+                md.push_str(text);
+            }
+            Content::Thinking { thinking } => {
+                for line in thinking.lines() {
+                    md.push_str(&format!("> {line}\n"));
                 }
-                md.push_str(&format!("# {session_name}\n\n"));
-                md.push_str(&format!("**Date:** {session_date}\n\n"));
-                md.push_str("---\n\n");
             }
-
-            if entry_type == "message" {
-                if let Some(message) = entry.get("message") {
-                    let role = message["role"].as_str().unwrap_or("unknown");
-                    let timestamp = entry["timestamp"].as_str().unwrap_or("");
-
-                    let role_label = match role {
-                        "user" => "**User**",
-                        "assistant" => "**Assistant**",
-                        _ => &format!("**{role}**"),
-                    };
-
-                    md.push_str(&format!("{role_label} *{timestamp}*\n\n"));
-
-                    if let Some(content_arr) = message["content"].as_array() {
-                        for item in content_arr {
-                            if let Some(text) = item["text"].as_str() {
-                                md.push_str(text);
-                                md.push_str("\n\n");
-                            }
-                        }
-                    }
-
-                    md.push_str("---\n\n");
+            Content::ToolCall {
+                name, arguments, ..
+            } => {
+                let args = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                md.push_str(&format!("Tool call `{name}`:\n\n```json\n{args}\n```"));
+            }
+            Content::ToolResult {
+                is_error, output, ..
+            } => {
+                if *is_error {
+                    md.push_str("Error:\n\n");
                 }
+                md.push_str(&format!("```\n{output}\n```"));
+            }
+            Content::Image { mime, size } => {
+                md.push_str(&format!("*[image: {mime}, {size} bytes]*"));
             }
         }
+        md.push_str("\n\n");
     }
-
-    fs::write(output_path, md).map_err(|e| format!("Failed to write export file: {e}"))?;
-
-    Ok(())
 }
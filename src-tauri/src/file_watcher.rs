@@ -177,7 +177,23 @@ fn process_events_with_merge(rx: Receiver<DebounceEventResult>, app_handle: AppH
             Ok(event_result) => match event_result {
                 Ok(events) => {
                     for event in &events {
-                        for path in &event.paths {
+                        // A renamed or moved project directory arrives as one
+                        // event for the directory; its sessions are looked at
+                        // one by one so the cache can follow them
+                        let paths = event.paths.iter().flat_map(|path| {
+                            let listed: Vec<PathBuf> = if path.is_dir() {
+                                std::fs::read_dir(path)
+                                    .into_iter()
+                                    .flatten()
+                                    .flatten()
+                                    .map(|entry| entry.path())
+                                    .collect()
+                            } else {
+                                vec![path.clone()]
+                            };
+                            listed
+                        });
+                        for path in paths {
                             if path.extension().map(|ext| ext == "jsonl").unwrap_or(false) {
                                 // Skip non-pi-session files: subagent artifacts and
                                 // gateway transcripts use different JSONL formats.
@@ -186,7 +202,7 @@ fn process_events_with_merge(rx: Receiver<DebounceEventResult>, app_handle: AppH
                                     s == "subagent-artifacts" || s == "transcripts"
                                 });
                                 if !dominated_by_excluded {
-                                    pending_paths.insert(path.clone());
+                                    pending_paths.insert(path);
                                 }
                             }
                         }
@@ -0,0 +1,357 @@
+use crate::models::{Content, SessionEntry};
+use crate::session_parser::{self, SessionDetails};
+use crate::session_tree::{Branch, SessionTree};
+use lazy_static::lazy_static;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde_json::Value;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME: Theme = ThemeSet::load_defaults()
+        .themes
+        .remove("InspiredGitHub")
+        .expect("bundled theme");
+}
+
+const STYLE: &str = r#"
+:root { --fg: #1f2328; --muted: #656d76; --border: #d0d7de; --bg-soft: #f6f8fa; --user: #0969da; --assistant: #8250df; --error: #cf222e; }
+* { box-sizing: border-box; }
+body { margin: 0; font: 15px/1.55 -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; color: var(--fg); background: #fff; }
+main { max-width: 920px; margin: 0 auto; padding: 24px 16px 64px; }
+header h1 { margin: 0 0 4px; font-size: 24px; }
+.meta { color: var(--muted); font-size: 13px; }
+.stats { display: flex; flex-wrap: wrap; gap: 8px; margin: 16px 0 24px; }
+.stat { border: 1px solid var(--border); border-radius: 6px; padding: 6px 10px; background: var(--bg-soft); font-size: 13px; }
+.stat b { display: block; font-size: 15px; }
+.entry { border-left: 3px solid var(--border); padding: 4px 0 4px 14px; margin: 18px 0; }
+.entry.user { border-color: var(--user); }
+.entry.assistant { border-color: var(--assistant); }
+.entry .role { font-weight: 600; font-size: 13px; }
+.entry .time { color: var(--muted); font-size: 12px; margin-left: 8px; }
+.entry.notice { border-color: transparent; color: var(--muted); font-size: 13px; font-style: italic; }
+details { border: 1px solid var(--border); border-radius: 6px; margin: 8px 0; background: var(--bg-soft); }
+details > summary { cursor: pointer; padding: 4px 10px; font-size: 13px; color: var(--muted); }
+details > .body { padding: 0 10px 8px; }
+details.error { border-color: var(--error); }
+details.error > summary { color: var(--error); }
+details.branch { background: #fff; border-style: dashed; }
+pre { overflow-x: auto; padding: 10px; border-radius: 6px; background: var(--bg-soft); font-size: 13px; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+.image { color: var(--muted); font-size: 13px; }
+"#;
+
+/// Render a session file's contents as a standalone HTML page.
+///
+/// The active branch is rendered in order; abandoned branches are shown
+/// collapsed beneath the entry they forked from.
+pub fn render_session_html(jsonl_content: &str) -> String {
+    let header = jsonl_content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|value| value["type"] == "session")
+        .unwrap_or(Value::Null);
+    let title = header["name"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| "Session Export".to_string());
+
+    let tree = SessionTree::build(session_parser::parse_entries(jsonl_content));
+    let details = session_parser::details_from_tree(&tree);
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    html.push_str(&format!(
+        "<style>{STYLE}</style>\n</head>\n<body>\n<main>\n"
+    ));
+
+    push_header(&mut html, &title, &header, &details, tree.branches.len());
+
+    for entry in tree.active_entries() {
+        push_entry(&mut html, entry);
+        for branch in tree
+            .branches
+            .iter()
+            .filter(|b| b.fork_id.as_deref() == Some(entry.id.as_str()))
+        {
+            push_branch(&mut html, &tree, branch);
+        }
+    }
+    for branch in tree.branches.iter().filter(|b| b.fork_id.is_none()) {
+        push_branch(&mut html, &tree, branch);
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+fn push_header(
+    html: &mut String,
+    title: &str,
+    header: &Value,
+    details: &SessionDetails,
+    branch_count: usize,
+) {
+    html.push_str(&format!("<header>\n<h1>{}</h1>\n", escape(title)));
+    let mut meta = Vec::new();
+    if let Some(ts) = header["timestamp"].as_str() {
+        meta.push(escape(ts));
+    }
+    if let Some(cwd) = header["cwd"].as_str() {
+        meta.push(format!("<code>{}</code>", escape(cwd)));
+    }
+    if !details.models.is_empty() {
+        let mut models = details.models.clone();
+        models.sort();
+        meta.push(escape(&models.join(", ")));
+    }
+    html.push_str(&format!(
+        "<div class=\"meta\">{}</div>\n</header>\n",
+        meta.join(" · ")
+    ));
+
+    let stats = [
+        ("Messages", details.total_messages().to_string()),
+        ("Input tokens", details.input_tokens.to_string()),
+        ("Output tokens", details.output_tokens.to_string()),
+        ("Cache read", details.cache_read_tokens.to_string()),
+        ("Cache write", details.cache_write_tokens.to_string()),
+        ("Cost", format!("${:.4}", details.total_cost())),
+        ("Branches", (branch_count + 1).to_string()),
+    ];
+    html.push_str("<div class=\"stats\">\n");
+    for (label, value) in stats {
+        html.push_str(&format!(
+            "<div class=\"stat\"><b>{value}</b>{label}</div>\n"
+        ));
+    }
+    html.push_str("</div>\n");
+}
+
+fn push_branch(html: &mut String, tree: &SessionTree, branch: &Branch) {
+    let label = match &branch.summary {
+        Some(summary) => format!("Abandoned branch: {}", summary.summary),
+        None => format!("Abandoned branch ({} messages)", branch.message_count),
+    };
+    html.push_str(&format!(
+        "<details class=\"branch\"><summary>{}</summary><div class=\"body\">\n",
+        escape(&label)
+    ));
+    for node in tree
+        .nodes
+        .iter()
+        .filter(|n| branch.entry_ids.contains(&n.entry.id))
+    {
+        push_entry(html, &node.entry);
+    }
+    html.push_str("</div></details>\n");
+}
+
+fn push_entry(html: &mut String, entry: &SessionEntry) {
+    let time = entry
+        .timestamp
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default();
+
+    if let Some(message) = &entry.message {
+        let (class, label) = match message.role.as_str() {
+            "user" => ("user", "User".to_string()),
+            "assistant" => ("assistant", "Assistant".to_string()),
+            "toolResult" => ("tool", "Tool result".to_string()),
+            "bashExecution" => ("tool", "Bash".to_string()),
+            role => ("other", role.to_string()),
+        };
+        let model = match (&message.provider, &message.model) {
+            (Some(provider), Some(model)) => format!(" · {provider}/{model}"),
+            (None, Some(model)) => format!(" · {model}"),
+            _ => String::new(),
+        };
+        html.push_str(&format!(
+            "<section class=\"entry {class}\" id=\"{}\">\n<div><span class=\"role\">{}</span><span class=\"time\">{}{}</span></div>\n",
+            escape(&entry.id),
+            escape(&label),
+            time,
+            escape(&model)
+        ));
+        push_content(html, &message.content);
+        html.push_str("</section>\n");
+    } else if let Some(compaction) = &entry.compaction {
+        let tokens = compaction
+            .tokens_before
+            .map(|t| format!(" ({t} tokens)"))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            "<details class=\"entry-compaction\"><summary>Context compacted{tokens}</summary><div class=\"body\">{}</div></details>\n",
+            render_markdown(&compaction.summary)
+        ));
+    } else if let Some(branch) = &entry.branch_summary {
+        html.push_str(&format!(
+            "<details class=\"entry-branch-summary\"><summary>Branch summary</summary><div class=\"body\">{}</div></details>\n",
+            render_markdown(&branch.summary)
+        ));
+    } else if let Some(custom) = &entry.custom_message {
+        if custom.display {
+            html.push_str(&format!(
+                "<section class=\"entry other\"><div><span class=\"role\">{}</span><span class=\"time\">{time}</span></div>\n",
+                escape(&custom.custom_type)
+            ));
+            push_content(html, &custom.content);
+            html.push_str("</section>\n");
+        }
+    } else if let Some(change) = &entry.model_change {
+        let model = match &change.provider {
+            Some(provider) => format!("{provider}/{}", change.model_id),
+            None => change.model_id.clone(),
+        };
+        html.push_str(&format!(
+            "<div class=\"entry notice\">Model changed to <code>{}</code>{}</div>\n",
+            escape(&model),
+            if time.is_empty() {
+                String::new()
+            } else {
+                format!(" · {time}")
+            }
+        ));
+    }
+}
+
+fn push_content(html: &mut String, content: &[Content]) {
+    for item in content {
+        match item {
+            Content::Text { text } => html.push_str(&render_markdown(text)),
+            Content::Thinking { thinking } => html.push_str(&format!(
+                "<details class=\"thinking\"><summary>Thinking</summary><div class=\"body\">{}</div></details>\n",
+                render_markdown(thinking)
+            )),
+            Content::ToolCall {
+                name, arguments, ..
+            } => {
+                let args = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                html.push_str(&format!(
+                    "<details class=\"tool-call\"><summary>Tool call: <code>{}</code></summary><div class=\"body\">{}</div></details>\n",
+                    escape(name),
+                    highlight(&args, "json")
+                ));
+            }
+            Content::ToolResult {
+                is_error, output, ..
+            } => {
+                let class = if *is_error {
+                    "tool-result error"
+                } else {
+                    "tool-result"
+                };
+                let label = if *is_error { "Error" } else { "Output" };
+                html.push_str(&format!(
+                    "<details class=\"{class}\"><summary>{label} ({} lines)</summary><div class=\"body\"><pre><code>{}</code></pre></div></details>\n",
+                    output.lines().count(),
+                    escape(output)
+                ));
+            }
+            Content::Image { mime, size } => html.push_str(&format!(
+                "<p class=\"image\">[image: {}, {size} bytes]</p>\n",
+                escape(mime)
+            )),
+        }
+    }
+}
+
+/// Markdown to HTML with highlighted fenced code blocks. Raw HTML in the
+/// source is escaped rather than passed through, and links or images to
+/// anything but http, https, mailto or a relative URL are dropped, keeping
+/// their text.
+fn render_markdown(text: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    // One entry per open link or image: whether its tags were dropped
+    let mut dropped = Vec::new();
+
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        lang.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, source)) = code.take() {
+                    events.push(Event::Html(highlight(&source, &lang).into()));
+                }
+            }
+            Event::Text(t) if code.is_some() => {
+                if let Some((_, source)) = code.as_mut() {
+                    source.push_str(&t);
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::Start(tag @ (Tag::Link { .. } | Tag::Image { .. })) => {
+                let (Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) = &tag else {
+                    unreachable!()
+                };
+                let safe = is_safe_url(dest_url);
+                dropped.push(!safe);
+                if safe {
+                    events.push(Event::Start(tag));
+                }
+            }
+            Event::End(end @ (TagEnd::Link | TagEnd::Image)) => {
+                if !dropped.pop().unwrap_or(false) {
+                    events.push(Event::End(end));
+                }
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+/// http, https, mailto, or a URL without a scheme. Browsers ignore
+/// whitespace and control characters inside a scheme, so they are too.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+fn highlight(code: &str, lang: &str) -> String {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    highlighted_html_for_string(code, &SYNTAX_SET, syntax, &THEME)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape(code)))
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -1,9 +1,12 @@
-use crate::app_state::SharedAppState;
-use crate::auth;
+use crate::app_state::{SharedAppState, WsEvent};
+use crate::auth::{self, Caller};
+use crate::rest_api;
+use crate::session_tail;
+use crate::tls::TlsIdentity;
 use crate::ws_adapter::dispatch;
 use axum::body::Body;
 use axum::extract::ws::{Message as AxumWsMsg, WebSocket, WebSocketUpgrade};
-use axum::extract::{ConnectInfo, State};
+use axum::extract::{ConnectInfo, Query, State};
 use axum::http::{header, HeaderMap, StatusCode, Uri};
 use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
 use axum::response::{IntoResponse, Response};
@@ -15,7 +18,7 @@ use rust_embed::Embed;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::convert::Infallible;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use tokio::sync::broadcast;
 
 #[derive(Embed)]
@@ -60,23 +63,14 @@ fn query_param(uri: &Uri, key: &str) -> Option<String> {
     })
 }
 
-fn is_authorized(ip: &std::net::IpAddr, headers: &HeaderMap, uri: &Uri) -> bool {
-    if !auth::is_auth_required(ip) {
-        return true;
-    }
-    let header_ok = headers
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.strip_prefix("Bearer "))
-        .map(auth::validate)
-        .unwrap_or(false);
-    if header_ok {
-        return true;
-    }
-    query_param(uri, "token")
-        .as_deref()
-        .map(auth::validate)
-        .unwrap_or(false)
+fn authenticate(
+    ip: &IpAddr,
+    headers: &HeaderMap,
+    uri: &Uri,
+    transport: &'static str,
+) -> Option<Caller> {
+    let token = auth::request_token(headers, uri);
+    auth::authenticate(*ip, token.as_deref(), transport)
 }
 
 // ─── HTTP POST /api ──────────────────────────────────────────
@@ -96,7 +90,7 @@ async fn handle_command(
     uri: Uri,
     Json(req): Json<HttpRequest>,
 ) -> impl IntoResponse {
-    if !is_authorized(&addr.ip(), &headers, &uri) {
+    let Some(caller) = authenticate(&addr.ip(), &headers, &uri, "http") else {
         return (
             StatusCode::UNAUTHORIZED,
             cors_headers(),
@@ -107,7 +101,7 @@ async fn handle_command(
             }),
         )
             .into_response();
-    }
+    };
 
     let gzip_requested = accepts_gzip(&headers);
     // 服务端强制压缩开关：设置环境变量 PSM_FORCE_GZIP=1 可强制启用压缩
@@ -121,7 +115,7 @@ async fn handle_command(
         headers.get("accept-encoding")
     );
 
-    let result = dispatch(&app_state, &req.command, &req.payload).await;
+    let result = dispatch(&app_state, &caller, &req.command, &req.payload).await;
     let resp = match result {
         Ok(data) => HttpResponse {
             success: true,
@@ -183,18 +177,25 @@ async fn handle_sse(
     State(app_state): State<SharedAppState>,
     headers: HeaderMap,
     uri: Uri,
+    Query(query): Query<session_tail::EventsQuery>,
 ) -> impl IntoResponse {
-    if !is_authorized(&addr.ip(), &headers, &uri) {
+    let Some(caller) = authenticate(&addr.ip(), &headers, &uri, "sse") else {
         return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+    if let Some(session) = &query.session {
+        return session_tail::sse_response(caller, session, query.offset).await;
     }
+    caller.audit("subscribe_events", "ok", None);
 
     let mut rx = app_state.subscribe_events();
 
     let stream = async_stream::stream! {
         loop {
             match rx.recv().await {
-                Ok(ws_event) => {
-                    if ws_event.event == "sessions-changed" {
+                Ok(mut ws_event) => {
+                    if ws_event.event == "sessions-changed"
+                        && crate::command_registry::event_for_caller(&caller, &ws_event.event, &mut ws_event.payload).await
+                    {
                         let data = serde_json::to_string(&ws_event.payload)
                             .unwrap_or_default();
                         yield Ok::<_, Infallible>(SseEvent::default()
@@ -229,43 +230,51 @@ async fn handle_ws_upgrade(
     uri: Uri,
     ws: WebSocketUpgrade,
 ) -> Response {
-    let pre_authed = is_authorized(&addr.ip(), &headers, &uri);
-    let needs_auth = auth::is_auth_required(&addr.ip());
+    let client_ip = addr.ip();
+    // Without a token in the URL the client authenticates with its first message
+    let caller =
+        if auth::is_auth_required(&client_ip) && auth::request_token(&headers, &uri).is_none() {
+            None
+        } else {
+            authenticate(&client_ip, &headers, &uri, "ws")
+        };
 
-    ws.on_upgrade(move |socket| handle_ws_connection(socket, app_state, pre_authed, needs_auth))
+    ws.on_upgrade(move |socket| handle_ws_connection(socket, app_state, caller, client_ip))
 }
 
 async fn handle_ws_connection(
     socket: WebSocket,
     app_state: SharedAppState,
-    pre_authed: bool,
-    needs_auth: bool,
+    caller: Option<Caller>,
+    client_ip: IpAddr,
 ) {
     let (mut tx, mut rx) = socket.split();
 
     // Auth: pre-authed via query param, or need first message with { auth: "token" }
-    if needs_auth && !pre_authed {
-        let authed = match tokio::time::timeout(std::time::Duration::from_secs(10), rx.next()).await
-        {
-            Ok(Some(Ok(AxumWsMsg::Text(text)))) => serde_json::from_str::<Value>(&text)
-                .ok()
-                .and_then(|v| v.get("auth")?.as_str().map(String::from))
-                .map(|t| auth::validate(&t))
-                .unwrap_or(false),
-            _ => false,
-        };
-
-        if !authed {
-            let _ = tx
-                .send(AxumWsMsg::Text(r#"{"error":"Unauthorized"}"#.into()))
-                .await;
-            let _ = tx.close().await;
-            return;
+    let caller = match caller {
+        Some(caller) => caller,
+        None => {
+            let token =
+                match tokio::time::timeout(std::time::Duration::from_secs(10), rx.next()).await {
+                    Ok(Some(Ok(AxumWsMsg::Text(text)))) => serde_json::from_str::<Value>(&text)
+                        .ok()
+                        .and_then(|v| v.get("auth")?.as_str().map(String::from)),
+                    _ => None,
+                };
+            let Some(caller) = auth::authenticate(client_ip, token.as_deref(), "ws") else {
+                let _ = tx
+                    .send(AxumWsMsg::Text(r#"{"error":"Unauthorized"}"#.into()))
+                    .await;
+                let _ = tx.close().await;
+                return;
+            };
+            let _ = tx.send(AxumWsMsg::Text(r#"{"auth":"ok"}"#.into())).await;
+            caller
         }
-        let _ = tx.send(AxumWsMsg::Text(r#"{"auth":"ok"}"#.into())).await;
-    }
+    };
 
     let mut event_rx = app_state.subscribe_events();
+    let (tails, mut tail_rx) = session_tail::Subscriptions::new(caller.clone(), app_state.clone());
 
     loop {
         tokio::select! {
@@ -282,7 +291,9 @@ async fn handle_ws_connection(
 
                         match serde_json::from_str::<WsReq>(&text) {
                             Ok(req) => {
-                                let result = dispatch(&app_state, &req.command, &req.payload).await;
+                                // The connection's subscriptions host subscribe_session
+                                let result = crate::command_registry::dispatch_as(&caller, &req.command, req.payload, tails.clone())
+                                    .await;
                                 let resp = match result {
                                     Ok(data) => serde_json::json!({ "id": req.id, "command": req.command, "success": true, "data": data }),
                                     Err(e) => serde_json::json!({ "id": req.id, "command": req.command, "success": false, "error": e }),
@@ -309,9 +320,21 @@ async fn handle_ws_connection(
                     _ => {}
                 }
             }
+            Some(payload) = tail_rx.recv() => {
+                let ws_event = WsEvent {
+                    event_type: "event".to_string(),
+                    event: session_tail::APPENDED_EVENT.to_string(),
+                    payload,
+                };
+                let text = serde_json::to_string(&ws_event).unwrap_or_default();
+                if tx.send(AxumWsMsg::Text(text)).await.is_err() { break; }
+            }
             event = event_rx.recv() => {
                 match event {
-                    Ok(ws_event) => {
+                    Ok(mut ws_event) => {
+                        if !crate::command_registry::event_for_caller(&caller, &ws_event.event, &mut ws_event.payload).await {
+                            continue;
+                        }
                         let text = serde_json::to_string(&ws_event).unwrap_or_default();
                         if tx.send(AxumWsMsg::Text(text)).await.is_err() { break; }
                     }
@@ -373,7 +396,7 @@ pub async fn init_http_adapter(
     bind_addr: &str,
     port: u16,
 ) -> Result<(), String> {
-    init_http_adapter_with_options(app_state, bind_addr, port, true).await
+    init_http_adapter_with_options(app_state, bind_addr, port, true, None).await
 }
 
 pub async fn init_http_adapter_with_options(
@@ -381,6 +404,7 @@ pub async fn init_http_adapter_with_options(
     bind_addr: &str,
     port: u16,
     serve_frontend: bool,
+    tls: Option<TlsIdentity>,
 ) -> Result<(), String> {
     let has_frontend = FrontendAssets::get("index.html").is_some();
 
@@ -399,6 +423,7 @@ pub async fn init_http_adapter_with_options(
         .route("/api/events", get(handle_sse))
         .route("/ws", get(handle_ws_upgrade))
         .route("/metrics", get(handle_metrics))
+        .merge(rest_api::router(|ip, _| ip, app_state.clone()))
         .with_state(app_state);
 
     // Only serve static files in CLI mode or production GUI mode
@@ -411,12 +436,10 @@ pub async fn init_http_adapter_with_options(
         .await
         .map_err(|e| format!("Failed to bind HTTP: {e}"))?;
 
-    log::info!("HTTP+WS server listening on http://{addr}");
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    log::info!("HTTP+WS server listening on {scheme}://{addr}");
 
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
-    .map_err(|e| format!("HTTP server error: {e}"))
+    crate::tls::serve(listener, app, tls.as_ref())
+        .await
+        .map_err(|e| format!("HTTP server error: {e}"))
 }
@@ -1,29 +1,50 @@
 pub mod auth;
+pub mod backup;
+pub mod batch_export;
+pub mod command_registry;
 pub mod commands;
 pub mod compression;
 pub mod config;
+pub mod database;
+pub mod dataset_export;
 pub mod dispatch;
 pub mod export;
+pub mod html_export;
 pub mod metrics;
+pub mod migrations;
 pub mod models;
+pub mod redaction;
+pub mod rest_api;
 pub mod scanner;
 pub mod scanner_scheduler;
 pub mod search;
+pub mod search_query;
+pub mod secret_audit;
+pub mod session_cursor;
 pub mod session_parser;
+pub mod session_tail;
+pub mod session_tree;
 pub mod settings_store;
 pub mod sqlite_cache;
 pub mod stats;
 pub mod tantivy_search;
+pub mod tls;
 pub mod write_buffer;
 
 #[cfg(feature = "gui")]
 pub mod app_state;
+#[cfg(feature = "cli")]
+pub mod cli;
 #[cfg(feature = "gui")]
 pub mod file_watcher;
 #[cfg(feature = "gui")]
 pub mod http_adapter;
+#[cfg(feature = "cli")]
+pub mod mcp;
 #[cfg(feature = "gui")]
 pub mod terminal;
+#[cfg(feature = "cli")]
+pub mod tui;
 #[cfg(feature = "gui")]
 pub mod ws_adapter;
 
@@ -33,88 +54,60 @@ use std::sync::Mutex;
 #[cfg(feature = "gui")]
 use tauri::{Listener, Manager};
 
+/// Tauri IPC handler. Desktop-only commands are plain Tauri commands; every
+/// other command goes through the shared registry, so the webview sees the
+/// same payloads and errors as HTTP and WebSocket clients.
+#[cfg(feature = "gui")]
+pub fn tauri_handler() -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    let desktop = tauri::generate_handler![
+        toggle_devtools,
+        open_session_in_browser,
+        open_session_in_terminal
+    ];
+    move |invoke: tauri::ipc::Invoke| {
+        let command = invoke.message.command().to_string();
+        if command_registry::DESKTOP_ONLY.contains(&command.as_str()) {
+            return desktop(invoke);
+        }
+        let payload = match invoke.message.payload() {
+            tauri::ipc::InvokeBody::Json(value) => value.clone(),
+            tauri::ipc::InvokeBody::Raw(_) => {
+                invoke
+                    .resolver
+                    .reject(format!("{command} expects a JSON payload"));
+                return true;
+            }
+        };
+        let Some(state) = invoke
+            .message
+            .webview_ref()
+            .try_state::<app_state::SharedAppState>()
+            .map(|s| s.inner().clone())
+        else {
+            invoke.resolver.reject("App state is not ready");
+            return true;
+        };
+        invoke.resolver.respond_async(async move {
+            command_registry::dispatch(&command, payload, state)
+                .await
+                .map_err(tauri::ipc::InvokeError::from)
+        });
+        true
+    }
+}
+
 #[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
-            scan_sessions,
-            read_session_file,
-            read_session_file_incremental,
-            get_file_stats,
-            get_session_entries,
-            get_session_by_path,
-            search_sessions,
-            search_sessions_fts,
-            full_text_search,
-            delete_session,
-            export_session,
-            rename_session,
-            get_session_stats,
-            open_session_in_browser,
-            open_session_in_terminal,
-            scan_skills,
-            scan_prompts,
-            get_skill_content,
-            get_prompt_content,
-            get_system_prompt,
-            get_session_system_prompt,
-            load_pi_settings,
-            save_pi_settings,
-            list_models,
-            test_model,
-            test_models_batch,
-            load_app_settings,
-            save_app_settings,
-            load_server_settings,
-            save_server_settings,
-            get_session_paths,
-            save_session_paths,
-            get_all_session_dirs,
-            add_favorite,
-            remove_favorite,
-            get_all_favorites,
-            is_favorite,
-            toggle_favorite,
-            clear_cache,
-            toggle_devtools,
-            terminal_create,
-            terminal_write,
-            terminal_resize,
-            terminal_close,
-            get_default_shell,
-            get_available_shells,
-            get_all_tags,
-            create_tag,
-            update_tag,
-            delete_tag,
-            get_all_session_tags,
-            assign_tag,
-            remove_tag_from_session,
-            move_session_tag,
-            reorder_tags,
-            update_tag_auto_rules,
-            evaluate_auto_rules,
-            list_api_keys,
-            create_api_key,
-            revoke_api_key,
-            scan_all_resources,
-            load_pi_settings_full,
-            save_pi_setting,
-            toggle_resource,
-            list_model_options_fast,
-            list_model_options_full,
-            read_resource_file,
-            list_config_versions,
-            get_config_version,
-            restore_config_version
-        ])
+        .invoke_handler(tauri_handler())
         .setup(|app| {
             // Create and manage app state
             let app_state = app_state::create_app_state(app.handle().clone());
             app.manage(app_state.clone());
+            tauri::async_runtime::spawn(backup::run_scheduler());
             // 启动定期刷新缓冲的任务
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -124,26 +117,16 @@ pub fn run() {
                     if let Some((sessions, details)) = write_buffer::check_and_take_flush_data() {
                         let sessions_count = sessions.len();
                         let details_count = details.len();
-                        if let Ok(conn) = sqlite_cache::init_db() {
-                            for entry in sessions {
-                                let _ = sqlite_cache::upsert_session(
-                                    &conn,
-                                    &entry.session,
-                                    entry.file_modified,
-                                    None,
-                                );
-                            }
-                            for entry in details {
-                                let _ = sqlite_cache::upsert_session_details_cache(
-                                    &conn,
-                                    &entry.path,
-                                    entry.file_modified,
-                                    &entry.details,
-                                );
-                            }
-                            log::trace!(
+                        let saved = database::get().and_then(|db| {
+                            db.write(move |conn| {
+                                write_buffer::save_entries(conn, &sessions, &details)
+                            })
+                        });
+                        match saved {
+                            Ok(()) => log::trace!(
                                 "Flushed {sessions_count} sessions and {details_count} details to database"
-                            );
+                            ),
+                            Err(e) => log::warn!("Failed to flush write buffer: {e}"),
                         }
                     }
                 }
@@ -153,23 +136,11 @@ pub fn run() {
             let app_handle_clone = app.handle().clone();
             app_handle_clone.listen("tauri://exit", |_| {
                 if let Some((sessions, details)) = write_buffer::force_flush_all() {
-                    if let Ok(conn) = sqlite_cache::init_db() {
-                        for entry in sessions {
-                            let _ = sqlite_cache::upsert_session(
-                                &conn,
-                                &entry.session,
-                                entry.file_modified,
-                                None,
-                            );
-                        }
-                        for entry in details {
-                            let _ = sqlite_cache::upsert_session_details_cache(
-                                &conn,
-                                &entry.path,
-                                entry.file_modified,
-                                &entry.details,
-                            );
-                        }
+                    let saved = database::get().and_then(|db| {
+                        db.write(move |conn| write_buffer::save_entries(conn, &sessions, &details))
+                    });
+                    if let Err(e) = saved {
+                        log::warn!("Failed to flush write buffer on exit: {e}");
                     }
                 }
             });
@@ -1,3 +1,4 @@
+use pi_session_manager::{cli, command_registry};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::{error, info};
@@ -31,9 +32,14 @@ pub type SharedCliState = Arc<CliAppState>;
 
 #[tokio::main]
 async fn main() {
+    cli::main(serve).await;
+}
+
+async fn serve(read_only: bool) {
     tracing_subscriber::fmt::init();
 
     info!("Starting Pi Session Manager - CLI Mode");
+    command_registry::set_read_only(read_only);
 
     // 加载配置
     let server_cfg = load_server_settings();
@@ -26,6 +26,29 @@ fn main() {
                 }
             }
 
+            // Load the TLS certificate; the servers stay off rather than fall back to cleartext
+            let tls = if server_cfg.tls_enabled {
+                match pi_session_manager::tls::TlsIdentity::load(
+                    server_cfg.tls_cert_path.as_deref(),
+                    server_cfg.tls_key_path.as_deref(),
+                    &[server_cfg.bind_addr.as_str()],
+                ) {
+                    Ok(identity) => Some(identity),
+                    Err(e) => {
+                        eprintln!("Failed to load TLS certificate: {e}");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let serve = !server_cfg.tls_enabled || tls.is_some();
+
+            pi_session_manager::command_registry::set_read_only(server_cfg.read_only);
+            if server_cfg.read_only {
+                log::info!("Read-only mode: mutating commands are disabled for server clients");
+            }
+
             // Initialize auth (only if enabled)
             if server_cfg.auth_enabled {
                 match pi_session_manager::auth::init() {
@@ -37,20 +60,35 @@ fn main() {
                     Err(e) => eprintln!("Failed to init auth: {e}"),
                 }
             }
+            if let Some(identity) = &tls {
+                if cli_mode {
+                    log::info!("TLS certificate SHA-256: {}", identity.fingerprint);
+                }
+            }
 
             // Initialize AppState and manage it
             let app_state = pi_session_manager::app_state::create_app_state(app_handle);
             app.manage(app_state.clone());
 
+            // Forward scan progress to the window and WS/HTTP clients
+            tauri::async_runtime::spawn(pi_session_manager::scanner::forward_progress(
+                app_state.clone(),
+            ));
+
+            // Periodic backups of tags, favorites and other user data
+            tauri::async_runtime::spawn(pi_session_manager::backup::run_scheduler());
+
             // Initialize WebSocket adapter
-            if server_cfg.ws_enabled {
+            if server_cfg.ws_enabled && serve {
+                let ws_tls = tls.clone();
                 let ws_state = app_state.clone();
                 let ws_port = server_cfg.ws_port;
                 let ws_bind = server_cfg.bind_addr.clone();
                 tauri::async_runtime::spawn(async move {
-                    if let Err(e) =
-                        pi_session_manager::ws_adapter::init_ws_adapter(ws_state, &ws_bind, ws_port)
-                            .await
+                    if let Err(e) = pi_session_manager::ws_adapter::init_ws_adapter(
+                        ws_state, &ws_bind, ws_port, ws_tls,
+                    )
+                    .await
                     {
                         eprintln!("Failed to init WebSocket adapter: {e}");
                     }
@@ -58,7 +96,8 @@ fn main() {
             }
 
             // Initialize HTTP adapter
-            if server_cfg.http_enabled {
+            if server_cfg.http_enabled && serve {
+                let http_tls = tls.clone();
                 let http_state = app_state.clone();
                 let http_port = server_cfg.http_port;
                 let http_bind = server_cfg.bind_addr.clone();
@@ -68,7 +107,7 @@ fn main() {
                     // In CLI mode, serve embedded static files
                     if let Err(e) =
                         pi_session_manager::http_adapter::init_http_adapter_with_options(
-                            http_state, &http_bind, http_port, is_cli,
+                            http_state, &http_bind, http_port, is_cli, http_tls,
                         )
                         .await
                     {
@@ -78,16 +117,21 @@ fn main() {
             }
 
             if cli_mode {
+                let (ws_scheme, http_scheme) = if tls.is_some() {
+                    ("wss", "https")
+                } else {
+                    ("ws", "http")
+                };
                 let mut info = String::from("CLI mode:");
                 if server_cfg.ws_enabled {
                     info.push_str(&format!(
-                        " WS ws://{}:{}",
+                        " WS {ws_scheme}://{}:{}",
                         server_cfg.bind_addr, server_cfg.ws_port
                     ));
                 }
                 if server_cfg.http_enabled {
                     info.push_str(&format!(
-                        " | HTTP http://{}:{}/api",
+                        " | HTTP {http_scheme}://{}:{}/api",
                         server_cfg.bind_addr, server_cfg.http_port
                     ));
                 }
@@ -119,78 +163,7 @@ fn main() {
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            pi_session_manager::scan_sessions,
-            pi_session_manager::read_session_file,
-            pi_session_manager::read_session_file_incremental,
-            pi_session_manager::get_file_stats,
-            pi_session_manager::get_session_entries,
-            pi_session_manager::get_session_by_path,
-            pi_session_manager::search_sessions,
-            pi_session_manager::search_sessions_fts,
-            pi_session_manager::full_text_search,
-            pi_session_manager::delete_session,
-            pi_session_manager::export_session,
-            pi_session_manager::rename_session,
-            pi_session_manager::get_session_stats,
-            pi_session_manager::get_session_stats_light,
-            pi_session_manager::open_session_in_browser,
-            pi_session_manager::open_session_in_terminal,
-            pi_session_manager::scan_skills,
-            pi_session_manager::scan_prompts,
-            pi_session_manager::get_skill_content,
-            pi_session_manager::get_prompt_content,
-            pi_session_manager::get_system_prompt,
-            pi_session_manager::get_session_system_prompt,
-            pi_session_manager::load_pi_settings,
-            pi_session_manager::save_pi_settings,
-            pi_session_manager::list_models,
-            pi_session_manager::test_model,
-            pi_session_manager::test_models_batch,
-            pi_session_manager::add_favorite,
-            pi_session_manager::remove_favorite,
-            pi_session_manager::get_all_favorites,
-            pi_session_manager::is_favorite,
-            pi_session_manager::toggle_favorite,
-            pi_session_manager::toggle_devtools,
-            pi_session_manager::load_app_settings,
-            pi_session_manager::save_app_settings,
-            pi_session_manager::load_server_settings,
-            pi_session_manager::save_server_settings,
-            pi_session_manager::get_session_paths,
-            pi_session_manager::save_session_paths,
-            pi_session_manager::get_all_session_dirs,
-            pi_session_manager::terminal_create,
-            pi_session_manager::terminal_write,
-            pi_session_manager::terminal_resize,
-            pi_session_manager::terminal_close,
-            pi_session_manager::get_default_shell,
-            pi_session_manager::get_available_shells,
-            pi_session_manager::get_all_tags,
-            pi_session_manager::create_tag,
-            pi_session_manager::update_tag,
-            pi_session_manager::delete_tag,
-            pi_session_manager::get_all_session_tags,
-            pi_session_manager::assign_tag,
-            pi_session_manager::remove_tag_from_session,
-            pi_session_manager::move_session_tag,
-            pi_session_manager::reorder_tags,
-            pi_session_manager::update_tag_auto_rules,
-            pi_session_manager::evaluate_auto_rules,
-            pi_session_manager::list_api_keys,
-            pi_session_manager::create_api_key,
-            pi_session_manager::revoke_api_key,
-            pi_session_manager::scan_all_resources,
-            pi_session_manager::load_pi_settings_full,
-            pi_session_manager::save_pi_setting,
-            pi_session_manager::toggle_resource,
-            pi_session_manager::list_model_options_fast,
-            pi_session_manager::list_model_options_full,
-            pi_session_manager::read_resource_file,
-            pi_session_manager::list_config_versions,
-            pi_session_manager::get_config_version,
-            pi_session_manager::restore_config_version
-        ])
+        .invoke_handler(pi_session_manager::tauri_handler())
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,610 @@
+use crate::auth::{self, Caller, Scope};
+use crate::cli::{
+    filter_sessions, load_sessions, one_line, resolve_session, summarize, tag_names, FilterArgs,
+    SessionSummary,
+};
+use crate::models::{Content, SessionEntry, SessionInfo};
+use crate::rest_api::{cors_headers, ClientIp};
+use crate::{database, redaction, session_parser, sqlite_cache};
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use futures_util::future::{BoxFuture, FutureExt};
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+pub const MCP_PATH: &str = "/mcp";
+
+/// Newest first; clients that ask for another version get this one back
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const INSTRUCTIONS: &str = "Read-only access to past pi coding-agent sessions. \
+Use search_sessions to find how a problem was handled before, then get_session_summary \
+or get_session for the details.";
+
+// ─── Tool arguments ──────────────────────────────────────────
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SearchSessionsArgs {
+    /// Phrase to look for in message text
+    pub query: String,
+    /// Only sessions started in this directory or below it
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// `user` or `assistant`; both by default
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Maximum number of sessions (default 10)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GetSessionArgs {
+    /// Session id, unique id prefix or file path
+    pub id: String,
+    /// Index of the first message to return (default 0)
+    #[serde(default)]
+    pub from: usize,
+    /// Maximum number of messages (default 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Include tool calls and tool output (default false)
+    #[serde(default)]
+    pub include_tools: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RecentSessionsArgs {
+    /// Project directory; subdirectories match too
+    pub cwd: String,
+    /// Maximum number of sessions (default 10)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SessionIdArgs {
+    /// Session id, unique id prefix or file path
+    pub id: String,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NoArgs {}
+
+// ─── Tool results ────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub session: SessionSummary,
+    pub matches: Vec<MatchedMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchedMessage {
+    pub entry_id: String,
+    pub role: String,
+    pub timestamp: String,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranscriptMessage {
+    pub index: usize,
+    pub id: String,
+    pub role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionOverview {
+    #[serde(flatten)]
+    pub session: SessionSummary,
+    pub last_message: String,
+    pub models: Vec<String>,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    /// Every user prompt, shortened to one line
+    pub user_prompts: Vec<String>,
+    /// Compaction and branch summaries written during the session
+    pub summaries: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagWithCount {
+    pub id: String,
+    pub name: String,
+    pub parent_id: Option<String>,
+    pub sessions: usize,
+}
+
+// ─── Tool table ──────────────────────────────────────────────
+
+type ToolHandler = fn(Value, Caller) -> BoxFuture<'static, Result<Value, String>>;
+
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    input_schema: fn() -> Value,
+    scope: Scope,
+    handler: ToolHandler,
+}
+
+fn input_schema<T: JsonSchema>() -> Value {
+    let mut settings = SchemaSettings::draft2020_12().for_deserialize();
+    settings.inline_subschemas = true;
+    let mut schema = settings.into_generator().into_root_schema_for::<T>();
+    schema.remove("$schema");
+    schema.remove("title");
+    if schema.get("properties").is_none() {
+        schema.insert("properties".to_string(), json!({}));
+    }
+    schema.to_value()
+}
+
+fn args<T: DeserializeOwned>(arguments: Value) -> Result<T, String> {
+    let arguments = if arguments.is_null() {
+        json!({})
+    } else {
+        arguments
+    };
+    serde_json::from_value(arguments).map_err(|e| format!("Invalid arguments: {e}"))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {e}"))
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "search_sessions",
+        description: "Full-text search over the messages of past sessions. Returns matching \
+                      sessions, most recent first, with the matching message snippets.",
+        input_schema: input_schema::<SearchSessionsArgs>,
+        scope: Scope::Search,
+        handler: |a, c| async move { to_value(search_sessions(args(a)?, &c).await?) }.boxed(),
+    },
+    Tool {
+        name: "get_session",
+        description: "The conversation of one session as plain-text messages, paged with \
+                      `from` and `limit`.",
+        input_schema: input_schema::<GetSessionArgs>,
+        scope: Scope::Read,
+        handler: |a, c| async move { get_session(args(a)?, &c).await }.boxed(),
+    },
+    Tool {
+        name: "list_recent_sessions_for_cwd",
+        description: "Most recently modified sessions for a project directory.",
+        input_schema: input_schema::<RecentSessionsArgs>,
+        scope: Scope::Read,
+        handler: |a, c| async move { to_value(recent_sessions(args(a)?, &c).await?) }.boxed(),
+    },
+    Tool {
+        name: "get_session_summary",
+        description: "Metadata, models, token usage, user prompts and compaction summaries \
+                      of one session, without the full conversation.",
+        input_schema: input_schema::<SessionIdArgs>,
+        scope: Scope::Read,
+        handler: |a, c| async move { to_value(session_summary(args(a)?, &c).await?) }.boxed(),
+    },
+    Tool {
+        name: "list_tags",
+        description: "Tags with the number of sessions carrying each one.",
+        input_schema: input_schema::<NoArgs>,
+        scope: Scope::Read,
+        handler: |a, c| {
+            async move {
+                args::<NoArgs>(a)?;
+                to_value(list_tags(&c).await?)
+            }
+            .boxed()
+        },
+    },
+];
+
+/// Sessions the caller may see; loading flushes freshly scanned sessions, so
+/// their messages are indexed
+async fn visible_sessions(caller: &Caller) -> Result<Vec<SessionInfo>, String> {
+    let mut sessions = load_sessions().await?;
+    sessions.retain(|s| caller.allows_cwd(&s.cwd));
+    Ok(sessions)
+}
+
+async fn search_sessions(
+    args: SearchSessionsArgs,
+    caller: &Caller,
+) -> Result<Vec<SearchHit>, String> {
+    let sessions = visible_sessions(caller).await?;
+    let filter = FilterArgs {
+        cwd: args.cwd,
+        ..FilterArgs::default()
+    };
+    let sessions = filter_sessions(sessions, &filter)?;
+    let by_path: HashMap<&str, &SessionInfo> =
+        sessions.iter().map(|s| (s.path.as_str(), s)).collect();
+
+    let rows = database::get()?
+        .read_async(move |conn| {
+            sqlite_cache::search_message_fts(conn, &args.query, args.role.as_deref(), 1000)
+        })
+        .await?;
+    let mut matches: HashMap<&str, Vec<MatchedMessage>> = HashMap::new();
+    for (entry_id, path, role, snippet, timestamp, _) in rows {
+        let Some((path, _)) = by_path.get_key_value(path.as_str()) else {
+            continue;
+        };
+        matches.entry(path).or_default().push(MatchedMessage {
+            entry_id,
+            role,
+            timestamp,
+            snippet: one_line(&snippet.replace("<b>", "").replace("</b>", ""), 300),
+        });
+    }
+
+    let tags = tag_names();
+    let mut hits: Vec<SearchHit> = matches
+        .into_iter()
+        .map(|(path, mut matches)| {
+            matches.truncate(5);
+            SearchHit {
+                session: summarize(by_path[path], &tags),
+                matches,
+            }
+        })
+        .collect();
+    hits.sort_by_key(|h| std::cmp::Reverse(h.session.modified));
+    hits.truncate(args.limit.unwrap_or(10));
+    Ok(hits)
+}
+
+async fn get_session(args: GetSessionArgs, caller: &Caller) -> Result<Value, String> {
+    let session = resolve_session(&visible_sessions(caller).await?, &args.id)?;
+    let content = fs::read_to_string(&session.path)
+        .map_err(|e| format!("Failed to read session file: {e}"))?;
+    let messages: Vec<TranscriptMessage> = session_parser::parse_entries(&content)
+        .iter()
+        .filter_map(|entry| transcript_message(entry, args.include_tools))
+        .enumerate()
+        .map(|(index, mut message)| {
+            message.index = index;
+            message
+        })
+        .collect();
+    let total = messages.len();
+    let limit = args.limit.unwrap_or(50);
+    let page: Vec<TranscriptMessage> = messages.into_iter().skip(args.from).take(limit).collect();
+    let next_from = (args.from + page.len() < total).then_some(args.from + page.len());
+    Ok(json!({
+        "session": summarize(&session, &tag_names()),
+        "total_messages": total,
+        "next_from": next_from,
+        "messages": page,
+    }))
+}
+
+fn transcript_message(entry: &SessionEntry, include_tools: bool) -> Option<TranscriptMessage> {
+    let message = entry.message.as_ref()?;
+    let mut tools = Vec::new();
+    if include_tools {
+        for content in &message.content {
+            match content {
+                Content::ToolCall {
+                    name, arguments, ..
+                } => tools.push(json!({ "call": name, "arguments": arguments })),
+                Content::ToolResult {
+                    is_error, output, ..
+                } => tools.push(json!({
+                    "output": output.chars().take(2000).collect::<String>(),
+                    "is_error": is_error,
+                })),
+                _ => {}
+            }
+        }
+    }
+    let text = message.text();
+    if text.is_empty() && tools.is_empty() {
+        return None;
+    }
+    Some(TranscriptMessage {
+        index: 0,
+        id: entry.id.clone(),
+        role: message.role.clone(),
+        timestamp: entry.timestamp,
+        text,
+        tools,
+    })
+}
+
+async fn recent_sessions(
+    args: RecentSessionsArgs,
+    caller: &Caller,
+) -> Result<Vec<SessionSummary>, String> {
+    let filter = FilterArgs {
+        cwd: Some(args.cwd),
+        ..FilterArgs::default()
+    };
+    let mut sessions = filter_sessions(visible_sessions(caller).await?, &filter)?;
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+    sessions.truncate(args.limit.unwrap_or(10));
+    let tags = tag_names();
+    Ok(sessions.iter().map(|s| summarize(s, &tags)).collect())
+}
+
+async fn session_summary(args: SessionIdArgs, caller: &Caller) -> Result<SessionOverview, String> {
+    let session = resolve_session(&visible_sessions(caller).await?, &args.id)?;
+    let content = fs::read_to_string(&session.path)
+        .map_err(|e| format!("Failed to read session file: {e}"))?;
+    let details = session_parser::parse_session_details(&content);
+    let mut user_prompts = Vec::new();
+    let mut summaries = Vec::new();
+    for entry in session_parser::parse_entries(&content) {
+        if let Some(message) = entry.message.as_ref().filter(|m| m.role == "user") {
+            let text = message.text();
+            if !text.trim().is_empty() {
+                user_prompts.push(one_line(&text, 200));
+            }
+        }
+        if let Some(compaction) = &entry.compaction {
+            summaries.push(compaction.summary.clone());
+        }
+        if let Some(branch) = &entry.branch_summary {
+            summaries.push(branch.summary.clone());
+        }
+    }
+    Ok(SessionOverview {
+        session: summarize(&session, &tag_names()),
+        last_message: session.last_message.clone(),
+        total_tokens: details.total_tokens(),
+        total_cost: details.total_cost(),
+        models: details.models,
+        user_prompts,
+        summaries,
+    })
+}
+
+async fn list_tags(caller: &Caller) -> Result<Vec<TagWithCount>, String> {
+    // Path-restricted callers only count sessions they can see
+    let visible: Option<HashSet<String>> = if caller.is_path_restricted() {
+        Some(
+            visible_sessions(caller)
+                .await?
+                .into_iter()
+                .map(|s| s.id)
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let db = database::get()?;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for st in db.read_async(sqlite_cache::get_all_session_tags).await? {
+        if visible
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&st.session_id))
+        {
+            *counts.entry(st.tag_id).or_default() += 1;
+        }
+    }
+    Ok(db
+        .read_async(sqlite_cache::get_all_tags)
+        .await?
+        .into_iter()
+        .map(|t| TagWithCount {
+            sessions: counts.get(&t.id).copied().unwrap_or(0),
+            id: t.id,
+            name: t.name,
+            parent_id: t.parent_id,
+        })
+        .collect())
+}
+
+// ─── JSON-RPC ────────────────────────────────────────────────
+
+fn rpc_result(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn rpc_error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+/// Handle one JSON-RPC message. Notifications get no reply (`None`).
+/// The caller's address decides whether session contents are redacted, and
+/// its token which tools and sessions it may use.
+pub async fn handle_message(message: Value, caller: &Caller) -> Option<Value> {
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        // Responses to requests we never send, or garbage
+        return message
+            .get("id")
+            .map(|id| rpc_error(id.clone(), INVALID_REQUEST, "Expected a JSON-RPC request"));
+    };
+    let id = message.get("id").cloned()?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    Some(match method {
+        "initialize" => {
+            let requested = params.get("protocolVersion").and_then(Value::as_str);
+            let version = requested
+                .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                .unwrap_or(PROTOCOL_VERSIONS[0]);
+            rpc_result(
+                id,
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": {
+                        "name": "pi-session-manager",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                    "instructions": INSTRUCTIONS,
+                }),
+            )
+        }
+        "ping" => rpc_result(id, json!({})),
+        "tools/list" => {
+            let tools: Vec<Value> = TOOLS
+                .iter()
+                .map(|t| {
+                    json!({
+                        "name": t.name,
+                        "description": t.description,
+                        "inputSchema": (t.input_schema)(),
+                        "annotations": { "readOnlyHint": true },
+                    })
+                })
+                .collect();
+            rpc_result(id, json!({ "tools": tools }))
+        }
+        "tools/call" => {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+            let Some(tool) = TOOLS.iter().find(|t| t.name == name) else {
+                return Some(rpc_error(
+                    id,
+                    INVALID_PARAMS,
+                    format!("Unknown tool: {name}"),
+                ));
+            };
+            let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+            let outcome = if caller.allows(tool.scope) {
+                (tool.handler)(arguments, caller.clone()).await
+            } else {
+                Err(format!(
+                    "Forbidden: {name} requires the {} scope",
+                    tool.scope.as_str()
+                ))
+            };
+            match &outcome {
+                Ok(_) => caller.audit(name, "ok", None),
+                Err(e) if e.starts_with("Forbidden") => caller.audit(name, "denied", Some(e)),
+                Err(e) => caller.audit(name, "error", Some(e)),
+            }
+            let result = match outcome {
+                Ok(data) => {
+                    let data = redaction::redact_remote_value(&caller.ip, data);
+                    let text = serde_json::to_string_pretty(&data).unwrap_or_default();
+                    json!({
+                        "content": [{ "type": "text", "text": text }],
+                        "isError": false,
+                    })
+                }
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": e }],
+                    "isError": true,
+                }),
+            };
+            rpc_result(id, result)
+        }
+        _ => rpc_error(id, METHOD_NOT_FOUND, format!("Method not found: {method}")),
+    })
+}
+
+async fn handle_line(line: &str, caller: &Caller) -> Option<Value> {
+    match serde_json::from_str::<Value>(line) {
+        Ok(message) if message.is_object() => handle_message(message, caller).await,
+        Ok(_) => Some(rpc_error(
+            Value::Null,
+            INVALID_REQUEST,
+            "Expected a JSON-RPC object",
+        )),
+        Err(e) => Some(rpc_error(
+            Value::Null,
+            PARSE_ERROR,
+            format!("Parse error: {e}"),
+        )),
+    }
+}
+
+/// Serve MCP over stdio: one JSON-RPC message per line until stdin closes.
+/// Logging must go to stderr; stdout carries only protocol messages.
+pub async fn serve_stdio() -> Result<(), String> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    let local = Caller::local("mcp");
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read stdin: {e}"))?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(reply) = handle_line(&line, &local).await {
+            let mut out = reply.to_string();
+            out.push('\n');
+            stdout
+                .write_all(out.as_bytes())
+                .await
+                .map_err(|e| format!("Failed to write stdout: {e}"))?;
+            stdout
+                .flush()
+                .await
+                .map_err(|e| format!("Failed to write stdout: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Streamable HTTP transport at [`MCP_PATH`]. Every request is answered with a
+/// single JSON body; there is no server-initiated stream.
+pub fn router<S>(client_ip: ClientIp) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let handler = move |ConnectInfo(addr): ConnectInfo<SocketAddr>,
+                        headers: HeaderMap,
+                        uri: Uri,
+                        body: String| async move {
+        let ip = client_ip(addr.ip(), &headers);
+        let token = auth::request_token(&headers, &uri);
+        let Some(caller) = auth::authenticate(ip, token.as_deref(), "mcp") else {
+            return (
+                StatusCode::UNAUTHORIZED,
+                cors_headers(),
+                Json(json!({ "error": "Unauthorized" })),
+            )
+                .into_response();
+        };
+        match handle_line(&body, &caller).await {
+            Some(reply) => (StatusCode::OK, cors_headers(), Json(reply)).into_response(),
+            None => (StatusCode::ACCEPTED, cors_headers()).into_response(),
+        }
+    };
+    Router::new().route(
+        MCP_PATH,
+        post(handler)
+            .get(|| async { method_not_allowed() })
+            .options(|| async { (StatusCode::NO_CONTENT, cors_headers()) }),
+    )
+}
+
+fn method_not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, cors_headers()).into_response()
+}
@@ -0,0 +1,783 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OpenFlags};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// One step of the schema history. `up` runs in a transaction together with
+/// the version bump, so a failing step leaves the database as it was.
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Connection) -> Result<(), String>,
+}
+
+/// The two database files. The cache holds what the session files can
+/// rebuild and is opened as `main`; the user database holds what the user
+/// curated and is attached to every connection as `user`. Table names are
+/// unique across both, so queries need no schema prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Schema {
+    Cache,
+    User,
+}
+
+impl Schema {
+    /// Name of the schema on a connection
+    pub fn name(self) -> &'static str {
+        match self {
+            Schema::Cache => "main",
+            Schema::User => USER_SCHEMA,
+        }
+    }
+
+    fn migrations(self) -> &'static [Migration] {
+        match self {
+            Schema::Cache => MIGRATIONS,
+            Schema::User => USER_MIGRATIONS,
+        }
+    }
+}
+
+/// Schema name the user database is attached under
+pub const USER_SCHEMA: &str = "user";
+
+/// Every table of the cache database, oldest first. Append new steps at
+/// the end; never edit one that has shipped.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Session cache, favorites and tags",
+        up: migration_1,
+    },
+    Migration {
+        version: 2,
+        description: "Index message entries by session and time",
+        up: migration_2,
+    },
+    Migration {
+        version: 3,
+        description: "Record the model on message entries",
+        up: migration_3,
+    },
+    Migration {
+        version: 4,
+        description: "Per-file parse cursors",
+        up: migration_4,
+    },
+    Migration {
+        version: 5,
+        description: "Secret audit results",
+        up: migration_5,
+    },
+    Migration {
+        version: 6,
+        description: "Settings",
+        up: migration_6,
+    },
+    Migration {
+        version: 7,
+        description: "API tokens and audit log",
+        up: migration_7,
+    },
+    Migration {
+        version: 8,
+        description: "Config file snapshots",
+        up: migration_8,
+    },
+    Migration {
+        version: 9,
+        description: "Move user data to the user database",
+        up: migration_9,
+    },
+];
+
+/// Every table of the user database, oldest first. Statements name the
+/// `user` schema explicitly.
+const USER_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Tags, favorites, settings, API tokens and config snapshots",
+    up: user_migration_1,
+}];
+
+/// Tables that moved from the cache to the user database in version 9
+const USER_TABLES: &[&str] = &[
+    "tags",
+    "session_tags",
+    "favorites",
+    "settings",
+    "auth_tokens",
+    "audit_log",
+    "config_versions",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct MigrationStep {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Where one database stands relative to its migration list
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MigrationReport {
+    pub schema: Schema,
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub pending: Vec<MigrationStep>,
+    /// Copy taken before migrating, when there was data to protect
+    pub backup: Option<String>,
+}
+
+pub fn latest_version(schema: Schema) -> i64 {
+    schema.migrations().last().map_or(0, |m| m.version)
+}
+
+/// The user database belonging to the cache database at `db_path`
+pub fn user_db_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("user.db")
+}
+
+/// Attach the user database belonging to `db_path` as `user`, unless it
+/// already is. Must not run inside a transaction.
+pub fn attach_user_db(conn: &Connection, db_path: &Path) -> Result<(), String> {
+    if is_attached(conn, USER_SCHEMA)? {
+        return Ok(());
+    }
+    let user_path = user_db_path(db_path);
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {USER_SCHEMA}"),
+        params![user_path.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("Failed to open user database {user_path:?}: {e}"))?;
+    Ok(())
+}
+
+fn is_attached(conn: &Connection, schema: &str) -> Result<bool, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA database_list")
+        .map_err(|e| format!("Failed to list databases: {e}"))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Failed to list databases: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to list databases: {e}"))?;
+    Ok(names.iter().any(|n| n == schema))
+}
+
+/// Schema version of one database on `conn`; 0 before its first migration
+pub fn current_version(conn: &Connection, schema: Schema) -> Result<i64, String> {
+    if !table_exists(conn, schema, "schema_version")? {
+        return Ok(0);
+    }
+    conn.query_row(
+        &format!(
+            "SELECT version FROM {}.schema_version LIMIT 1",
+            schema.name()
+        ),
+        [],
+        |row| row.get(0),
+    )
+    .or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(0),
+        e => Err(format!("Failed to get schema version: {e}")),
+    })
+}
+
+/// Migrations one database on `conn` is missing, without changing anything
+pub fn pending(conn: &Connection, schema: Schema) -> Result<MigrationReport, String> {
+    let current_version = current_version(conn, schema)?;
+    Ok(MigrationReport {
+        schema,
+        current_version,
+        latest_version: latest_version(schema),
+        pending: schema
+            .migrations()
+            .iter()
+            .filter(|m| m.version > current_version)
+            .map(|m| MigrationStep {
+                version: m.version,
+                description: m.description.to_string(),
+            })
+            .collect(),
+        backup: None,
+    })
+}
+
+/// Dry run against the cache database at `db_path` and its user database,
+/// both opened read-only. Reports come in the order `migrate` runs them.
+pub fn dry_run(db_path: &Path) -> Result<Vec<MigrationReport>, String> {
+    let conn = if db_path.exists() {
+        Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+    } else {
+        Connection::open_in_memory()
+    }
+    .map_err(|e| format!("Failed to open database: {e}"))?;
+    if user_db_path(db_path).exists() {
+        attach_user_db(&conn, db_path)?;
+    } else {
+        conn.execute(&format!("ATTACH DATABASE ':memory:' AS {USER_SCHEMA}"), [])
+            .map_err(|e| format!("Failed to open user database: {e}"))?;
+    }
+    Ok(vec![
+        pending(&conn, Schema::User)?,
+        pending(&conn, Schema::Cache)?,
+    ])
+}
+
+/// Bring the cache database at `db_path` and its user database up to the
+/// latest versions, attaching the user database first. A database with data
+/// is copied next to its file before migrating, so a failed or unwanted
+/// upgrade can be undone. The user database goes first: cache migration 9
+/// moves rows into it.
+pub fn migrate(conn: &Connection, db_path: &Path) -> Result<Vec<MigrationReport>, String> {
+    attach_user_db(conn, db_path)?;
+    Ok(vec![
+        migrate_schema(conn, &user_db_path(db_path), Schema::User)?,
+        migrate_schema(conn, db_path, Schema::Cache)?,
+    ])
+}
+
+/// Bring the user database file at `user_path` up to date on its own, so a
+/// damaged or unmigratable user database is found before the cache is opened
+pub fn migrate_user_db(user_path: &Path) -> Result<MigrationReport, String> {
+    let conn = Connection::open_in_memory().map_err(|e| format!("Failed to open database: {e}"))?;
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {USER_SCHEMA}"),
+        params![user_path.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("Failed to open user database {user_path:?}: {e}"))?;
+    migrate_schema(&conn, user_path, Schema::User)
+}
+
+fn migrate_schema(
+    conn: &Connection,
+    file: &Path,
+    schema: Schema,
+) -> Result<MigrationReport, String> {
+    let mut report = pending(conn, schema)?;
+    if report.pending.is_empty() {
+        return Ok(report);
+    }
+    if has_data(conn, schema)? {
+        let backup = backup(conn, schema, file, report.current_version)?;
+        info!(
+            "[Migration] Backed up database to {:?} before migrating",
+            backup
+        );
+        report.backup = Some(backup.display().to_string());
+    }
+    apply(conn, schema, report.current_version)?;
+    Ok(report)
+}
+
+fn apply(conn: &Connection, schema: Schema, from_version: i64) -> Result<(), String> {
+    let name = schema.name();
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {name}.schema_version (
+                version INTEGER NOT NULL
+            )"
+        ),
+        [],
+    )
+    .map_err(|e| format!("Failed to create schema_version table: {e}"))?;
+
+    for migration in schema
+        .migrations()
+        .iter()
+        .filter(|m| m.version > from_version)
+    {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start migration {}: {e}", migration.version))?;
+        (migration.up)(&tx).map_err(|e| {
+            format!(
+                "Migration {} ({}) failed: {e}",
+                migration.version, migration.description
+            )
+        })?;
+        tx.execute(&format!("DELETE FROM {name}.schema_version"), [])
+            .and_then(|_| {
+                tx.execute(
+                    &format!("INSERT INTO {name}.schema_version (version) VALUES (?1)"),
+                    params![migration.version],
+                )
+            })
+            .map_err(|e| format!("Failed to set schema version: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit migration {}: {e}", migration.version))?;
+        info!(
+            "[Migration] Applied {} {}: {}",
+            name, migration.version, migration.description
+        );
+    }
+    Ok(())
+}
+
+/// Whether the database holds anything worth backing up
+fn has_data(conn: &Connection, schema: Schema) -> Result<bool, String> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {}.sqlite_master WHERE type = 'table' AND name != 'schema_version'",
+            schema.name()
+        ),
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n > 0)
+    .map_err(|e| format!("Failed to inspect database: {e}"))
+}
+
+fn backup(
+    conn: &Connection,
+    schema: Schema,
+    file: &Path,
+    from_version: i64,
+) -> Result<PathBuf, String> {
+    let file_name = file
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("sessions.db");
+    let backup_path = file.with_file_name(format!(
+        "{file_name}.pre-v{from_version}.{}",
+        Utc::now().timestamp()
+    ));
+    // VACUUM INTO includes pages still in the WAL, unlike a file copy
+    conn.execute(
+        &format!("VACUUM {} INTO ?1", schema.name()),
+        params![backup_path.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("Failed to back up database to {backup_path:?}: {e}"))?;
+    Ok(backup_path)
+}
+
+/// Differences between the live schemas and the ones the migrations
+/// produce: missing tables, indexes and columns, and columns of another
+/// type. Tables the lists do not know (FTS indexes) are ignored.
+pub fn verify_schema(conn: &Connection) -> Result<Vec<String>, String> {
+    let expected =
+        Connection::open_in_memory().map_err(|e| format!("Failed to open database: {e}"))?;
+    expected
+        .execute(&format!("ATTACH DATABASE ':memory:' AS {USER_SCHEMA}"), [])
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    apply(&expected, Schema::User, 0)?;
+    apply(&expected, Schema::Cache, 0)?;
+
+    let mut problems = Vec::new();
+    for schema in [Schema::Cache, Schema::User] {
+        if !is_attached(conn, schema.name())? {
+            problems.push(format!("{} database is not attached", schema.name()));
+            continue;
+        }
+        let version = current_version(conn, schema)?;
+        if version != latest_version(schema) {
+            problems.push(format!(
+                "{} schema version {version}, expected {}",
+                schema.name(),
+                latest_version(schema)
+            ));
+        }
+        let live_indexes = schema_objects(conn, schema, "index")?;
+        for index in schema_objects(&expected, schema, "index")? {
+            if !live_indexes.contains(&index) {
+                problems.push(format!("missing index {index}"));
+            }
+        }
+        for table in schema_objects(&expected, schema, "table")? {
+            if !table_exists(conn, schema, &table)? {
+                problems.push(format!("missing table {table}"));
+                continue;
+            }
+            let live = schema_columns(conn, schema, &table)?;
+            for (column, column_type) in schema_columns(&expected, schema, &table)? {
+                match live.get(&column) {
+                    None => problems.push(format!("{table}: missing column {column}")),
+                    Some(t) if !t.eq_ignore_ascii_case(&column_type) => problems.push(format!(
+                        "{table}: column {column} is {t}, expected {column_type}"
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn table_exists(conn: &Connection, schema: Schema, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {}.sqlite_master WHERE type = 'table' AND name = ?1",
+            schema.name()
+        ),
+        params![table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n > 0)
+    .map_err(|e| format!("Failed to inspect database: {e}"))
+}
+
+/// Named tables or indexes, without SQLite's internal ones
+fn schema_objects(conn: &Connection, schema: Schema, kind: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT name FROM {}.sqlite_master
+             WHERE type = ?1 AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            schema.name()
+        ))
+        .map_err(|e| format!("Failed to inspect database: {e}"))?;
+    let names = stmt
+        .query_map(params![kind], |row| row.get(0))
+        .map_err(|e| format!("Failed to inspect database: {e}"))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("Failed to inspect database: {e}"))?;
+    Ok(names)
+}
+
+/// Column name to declared type
+fn schema_columns(
+    conn: &Connection,
+    schema: Schema,
+    table: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({table})", schema.name()))
+        .map_err(|e| format!("Failed to prepare PRAGMA table_info for {table}: {e}"))?;
+    let columns = stmt
+        .query_map([], |row| Ok((row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to query columns for {table}: {e}"))?
+        .collect::<Result<BTreeMap<String, String>, _>>()
+        .map_err(|e| format!("Failed to collect columns for {table}: {e}"))?;
+    Ok(columns)
+}
+
+/// Columns of a cache table; the cache migrations run before the move
+fn columns(conn: &Connection, table: &str) -> Result<BTreeMap<String, String>, String> {
+    schema_columns(conn, Schema::Cache, table)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, String> {
+    Ok(columns(conn, table)?.contains_key(column))
+}
+
+fn add_column(conn: &Connection, table: &str, column: &str, def: &str) -> Result<(), String> {
+    if !column_exists(conn, table, column)? {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {def}"),
+            [],
+        )
+        .map_err(|e| format!("Failed to add {table}.{column}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Migration to version 1: the tables that predate versioning, plus the
+/// columns that used to be added by ad-hoc ALTER TABLE.
+fn migration_1(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL UNIQUE,
+            cwd TEXT NOT NULL,
+            name TEXT,
+            created TEXT NOT NULL,
+            modified TEXT NOT NULL,
+            file_modified TEXT NOT NULL,
+            message_count INTEGER NOT NULL,
+            first_message TEXT,
+            all_messages_text TEXT,
+            user_messages_text TEXT,
+            assistant_messages_text TEXT,
+            last_message TEXT,
+            last_message_role TEXT,
+            cached_at TEXT NOT NULL,
+            access_count INTEGER DEFAULT 0,
+            last_accessed TEXT
+        );
+        CREATE TABLE IF NOT EXISTS session_details_cache (
+            path TEXT PRIMARY KEY,
+            file_modified TEXT NOT NULL,
+            user_messages INTEGER NOT NULL,
+            assistant_messages INTEGER NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cache_read_tokens INTEGER NOT NULL,
+            cache_write_tokens INTEGER NOT NULL,
+            input_cost REAL NOT NULL,
+            output_cost REAL NOT NULL,
+            cache_read_cost REAL NOT NULL,
+            cache_write_cost REAL NOT NULL,
+            models_json TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_modified ON sessions(modified DESC);
+        CREATE INDEX IF NOT EXISTS idx_cwd ON sessions(cwd);
+        CREATE INDEX IF NOT EXISTS idx_file_modified ON sessions(file_modified);
+        CREATE TABLE IF NOT EXISTS favorites (
+            id TEXT PRIMARY KEY,
+            type TEXT NOT NULL CHECK(type IN ('session', 'project')),
+            name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            added_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS tags (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT 'info',
+            icon TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            is_builtin INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS session_tags (
+            session_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            assigned_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, tag_id)
+        );
+        CREATE TABLE IF NOT EXISTS message_entries (
+            id TEXT PRIMARY KEY,
+            session_path TEXT NOT NULL,
+            role TEXT NOT NULL CHECK(role IN ('user', 'assistant')),
+            content TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY (session_path) REFERENCES sessions(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_message_entries_session ON message_entries(session_path);",
+    )
+    .map_err(|e| format!("Failed to create tables: {e}"))?;
+
+    add_column(conn, "sessions", "last_message", "TEXT")?;
+    add_column(conn, "sessions", "last_message_role", "TEXT")?;
+    add_column(conn, "sessions", "user_messages_text", "TEXT")?;
+    add_column(conn, "sessions", "assistant_messages_text", "TEXT")?;
+    add_column(conn, "sessions", "access_count", "INTEGER DEFAULT 0")?;
+    add_column(conn, "sessions", "last_accessed", "TEXT")?;
+    add_column(conn, "tags", "auto_rules", "TEXT")?;
+    add_column(conn, "tags", "parent_id", "TEXT")?;
+    Ok(())
+}
+
+/// Migration to version 2: add performance indexes.
+fn migration_2(conn: &Connection) -> Result<(), String> {
+    // Composite index on session_path and timestamp for per-session ordering
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_message_entries_session_time ON message_entries(session_path, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Migration to version 3: record the provider-qualified model on message entries
+/// so searches can filter by `model:`.
+fn migration_3(conn: &Connection) -> Result<(), String> {
+    add_column(conn, "message_entries", "model", "TEXT")?;
+    // Existing rows have no model; clearing them lets the message_entries
+    // backfill re-read the session files.
+    conn.execute("DELETE FROM message_entries", [])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Migration to version 4: per-file parse cursors, so appended sessions are
+/// parsed from where the previous scan stopped.
+fn migration_4(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_cursors (
+            path TEXT PRIMARY KEY,
+            byte_offset INTEGER NOT NULL,
+            line_count INTEGER NOT NULL,
+            partial BLOB NOT NULL,
+            unterminated INTEGER NOT NULL DEFAULT 0,
+            header_hash TEXT NOT NULL,
+            state_json TEXT NOT NULL,
+            FOREIGN KEY (path) REFERENCES sessions(path) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Migration to version 5: secret audit results, rescanned when a file's
+/// mtime or the detector settings change.
+fn migration_5(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS secret_audit_files (
+            path TEXT PRIMARY KEY,
+            file_modified TEXT NOT NULL,
+            detectors TEXT NOT NULL,
+            scanned_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS secret_findings (
+            session_path TEXT NOT NULL,
+            entry_id TEXT NOT NULL,
+            detector TEXT NOT NULL,
+            preview TEXT NOT NULL,
+            FOREIGN KEY (session_path) REFERENCES secret_audit_files(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_secret_findings_session ON secret_findings(session_path);",
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Migration to version 6: key/value settings shared by the app and servers.
+fn migration_6(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Migration to version 7: API tokens and the request audit log. The token
+/// columns after `last_used` were added one release at a time.
+fn migration_7(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS auth_tokens (
+            token TEXT PRIMARY KEY,
+            name TEXT,
+            created_at TEXT NOT NULL,
+            last_used TEXT
+        );
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            token_preview TEXT,
+            ip TEXT NOT NULL,
+            transport TEXT NOT NULL,
+            command TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            detail TEXT
+        );",
+    )
+    .map_err(|e| e.to_string())?;
+    add_column(conn, "auth_tokens", "last_used", "TEXT")?;
+    add_column(
+        conn,
+        "auth_tokens",
+        "scope",
+        "TEXT NOT NULL DEFAULT 'admin'",
+    )?;
+    add_column(conn, "auth_tokens", "expires_at", "TEXT")?;
+    add_column(
+        conn,
+        "auth_tokens",
+        "allowed_paths",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    Ok(())
+}
+
+/// Migration to version 8: snapshots of config files taken before each save.
+fn migration_8(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS config_versions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_path TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            size_bytes INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_cv_file_path ON config_versions(file_path);
+        CREATE INDEX IF NOT EXISTS idx_cv_created ON config_versions(created_at DESC);",
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Migration to version 9: tags, favorites, settings, tokens, the audit log
+/// and config snapshots live in the user database, which is never deleted
+/// to recover from corruption. Rows the user database already has win.
+fn migration_9(conn: &Connection) -> Result<(), String> {
+    for table in USER_TABLES {
+        if !table_exists(conn, Schema::Cache, table)? {
+            continue;
+        }
+        let target = schema_columns(conn, Schema::User, table)?;
+        let shared = columns(conn, table)?
+            .into_keys()
+            .filter(|c| target.contains_key(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute_batch(&format!(
+            "INSERT OR IGNORE INTO {USER_SCHEMA}.{table} ({shared}) SELECT {shared} FROM main.{table};
+             DROP TABLE main.{table};"
+        ))
+        .map_err(|e| format!("Failed to move {table}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// User database version 1: the final shape of the tables cache versions
+/// 1 to 8 built up.
+fn user_migration_1(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS user.tags (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT 'info',
+            icon TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            is_builtin INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            auto_rules TEXT,
+            parent_id TEXT
+        );
+        CREATE TABLE IF NOT EXISTS user.session_tags (
+            session_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            assigned_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, tag_id)
+        );
+        CREATE TABLE IF NOT EXISTS user.favorites (
+            id TEXT PRIMARY KEY,
+            type TEXT NOT NULL CHECK(type IN ('session', 'project')),
+            name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            added_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS user.settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS user.auth_tokens (
+            token TEXT PRIMARY KEY,
+            name TEXT,
+            created_at TEXT NOT NULL,
+            last_used TEXT,
+            scope TEXT NOT NULL DEFAULT 'admin',
+            expires_at TEXT,
+            allowed_paths TEXT NOT NULL DEFAULT '[]'
+        );
+        CREATE TABLE IF NOT EXISTS user.audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            token_preview TEXT,
+            ip TEXT NOT NULL,
+            transport TEXT NOT NULL,
+            command TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            detail TEXT
+        );
+        CREATE TABLE IF NOT EXISTS user.config_versions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_path TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            size_bytes INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS user.idx_cv_file_path ON config_versions(file_path);
+        CREATE INDEX IF NOT EXISTS user.idx_cv_created ON config_versions(created_at DESC);",
+    )
+    .map_err(|e| e.to_string())
+}
@@ -1,7 +1,8 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionInfo {
     pub path: String,
     pub id: String,
@@ -11,7 +12,7 @@ pub struct SessionInfo {
     pub modified: DateTime<Utc>,
     pub message_count: usize,
     pub first_message: String,
-    #[serde(skip_serializing)]
+    #[serde(default, skip_serializing)]
     pub all_messages_text: String,
     pub user_messages_text: String,
     pub assistant_messages_text: String,
@@ -26,30 +27,139 @@ pub struct SessionsDiff {
     pub removed: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One line of a session file, parsed by `session_parser::parse_entry`.
+/// Type-specific payloads are set only for their entry type.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionEntry {
     #[serde(rename = "type")]
     pub entry_type: String,
     pub id: String,
     pub parent_id: Option<String>,
-    pub timestamp: DateTime<Utc>,
+    /// `None` when the line has no valid timestamp
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
     pub message: Option<Message>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<Compaction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_summary: Option<BranchSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_message: Option<CustomMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_change: Option<ModelChange>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Message {
     pub role: String,
     pub content: Vec<Content>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Content {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub text: Option<String>,
+impl Message {
+    /// Concatenated text parts (no thinking, tool calls or tool output)
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(Content::as_text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Content {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolCall {
+        name: String,
+        id: String,
+        arguments: serde_json::Value,
+    },
+    /// Output of a `toolResult` (or `bashExecution`) message
+    ToolResult {
+        tool_call_id: String,
+        is_error: bool,
+        output: String,
+    },
+    /// Image attachment; the payload itself is not kept
+    Image {
+        mime: String,
+        size: usize,
+    },
+}
+
+impl Content {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Content::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Usage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<UsageCost>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UsageCost {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_write: f64,
+}
+
+/// Context compaction: everything before `first_kept_entry_id` was summarized
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Compaction {
+    pub summary: String,
+    pub first_kept_entry_id: Option<String>,
+    pub tokens_before: Option<u64>,
+}
+
+/// Summary of a branch the user navigated away from
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BranchSummary {
+    pub from_id: Option<String>,
+    pub summary: String,
+}
+
+/// Extension-injected message
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CustomMessage {
+    pub custom_type: String,
+    pub content: Vec<Content>,
+    pub display: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModelChange {
+    pub provider: Option<String>,
+    pub model_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     pub session_id: String,
     pub session_path: String,
@@ -59,14 +169,15 @@ pub struct SearchResult {
     pub score: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Match {
     pub entry_id: String,
     pub role: String,
     pub snippet: String,
-    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FullTextSearchHit {
     pub session_id: String,
     pub session_path: String,
@@ -74,11 +185,14 @@ pub struct FullTextSearchHit {
     pub entry_id: String,
     pub role: String,
     pub content: String,
+    /// Highlighted excerpt (`<b>` around matches), set by the Tantivy engine
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
     pub timestamp: DateTime<Utc>,
     pub score: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FullTextSearchResponse {
     pub hits: Vec<FullTextSearchHit>,
     pub total_hits: usize,
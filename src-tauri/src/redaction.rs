@@ -0,0 +1,327 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+pub const SETTINGS_KEY: &str = "redaction_settings";
+
+/// Bump when the built-in detectors change so stored audit results are
+/// recomputed
+const BUILTIN_VERSION: u32 = 1;
+
+/// Tokens longer than this are left to the other detectors; base64 images
+/// and thinking signatures would otherwise be flagged wholesale
+const ENTROPY_MAX_LEN: usize = 200;
+const ENTROPY_MIN_LEN: usize = 32;
+const ENTROPY_THRESHOLD: f64 = 4.5;
+
+/// (kind, pattern, capture group holding the secret)
+const BUILTIN_PATTERNS: &[(&str, &str, usize)] = &[
+    (
+        "private-key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----[^\x22]*?-----END [A-Z ]*PRIVATE KEY-----",
+        0,
+    ),
+    ("aws-access-key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", 0),
+    (
+        "aws-secret-key",
+        r"(?i)aws_secret_access_key\W{1,4}([A-Za-z0-9/+=]{40})",
+        1,
+    ),
+    ("github-token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b", 0),
+    ("github-token", r"\bgithub_pat_[A-Za-z0-9_]{22,}", 0),
+    ("anthropic-key", r"\bsk-ant-[A-Za-z0-9_-]{20,}", 0),
+    (
+        "openai-key",
+        r"\bsk-(?:proj-|svcacct-)?[A-Za-z0-9_-]{20,}",
+        0,
+    ),
+    ("slack-token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}", 0),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}",
+        0,
+    ),
+    (
+        "bearer-token",
+        r"(?i)\bbearer\s+([A-Za-z0-9._~+/-]{16,}=*)",
+        1,
+    ),
+    // KEY=value lines from .env files and shell exports
+    (
+        "secret-assignment",
+        r"(?i)\b[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_?KEY|PRIVATE_KEY|ACCESS_KEY)[A-Z0-9_]*\s*[=:]\s*'?([^\s'\x22\\]{8,})",
+        1,
+    ),
+];
+
+lazy_static! {
+    static ref BUILTIN: Vec<Detector> = BUILTIN_PATTERNS
+        .iter()
+        .map(|(kind, pattern, group)| Detector {
+            kind: (*kind).to_string(),
+            regex: Regex::new(pattern).expect("builtin redaction pattern"),
+            group: *group,
+        })
+        .collect();
+    static ref ENTROPY_CANDIDATE: Regex = Regex::new(r"[A-Za-z0-9+/_-]{32,}={0,2}").unwrap();
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CustomPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RedactionSettings {
+    /// Redact `read_session_file` and REST entry responses for non-local clients
+    pub redact_remote_reads: bool,
+    /// Flag long random-looking strings that no other detector matched
+    pub high_entropy: bool,
+    pub custom_patterns: Vec<CustomPattern>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            redact_remote_reads: true,
+            high_entropy: true,
+            custom_patterns: Vec::new(),
+        }
+    }
+}
+
+pub fn load_settings() -> RedactionSettings {
+    crate::settings_store::get::<RedactionSettings>(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &RedactionSettings) -> Result<(), String> {
+    Redactor::new(settings)?;
+    crate::settings_store::set(SETTINGS_KEY, settings)
+}
+
+/// Identifies the detector set implied by `settings`; results computed under
+/// a different fingerprint are stale
+pub fn fingerprint(settings: &RedactionSettings) -> String {
+    let custom = serde_json::to_string(&settings.custom_patterns).unwrap_or_default();
+    format!("v{BUILTIN_VERSION}:{}:{custom}", settings.high_entropy)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+struct Detector {
+    kind: String,
+    regex: Regex,
+    group: usize,
+}
+
+pub struct Redactor {
+    custom: Vec<Detector>,
+    high_entropy: bool,
+}
+
+impl Redactor {
+    pub fn new(settings: &RedactionSettings) -> Result<Self, String> {
+        let custom = settings
+            .custom_patterns
+            .iter()
+            .map(|p| {
+                let regex = Regex::new(&p.pattern)
+                    .map_err(|e| format!("Invalid redaction pattern '{}': {e}", p.name))?;
+                Ok(Detector {
+                    kind: p.name.clone(),
+                    regex,
+                    group: 0,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            custom,
+            high_entropy: settings.high_entropy,
+        })
+    }
+
+    /// Built-in detectors only
+    pub fn builtin() -> Self {
+        Self {
+            custom: Vec::new(),
+            high_entropy: true,
+        }
+    }
+
+    /// Built-in detectors plus the user's stored patterns
+    pub fn load() -> Self {
+        Self::new(&load_settings()).unwrap_or_else(|e| {
+            log::warn!("Ignoring custom redaction patterns: {e}");
+            Self::builtin()
+        })
+    }
+
+    /// Non-overlapping secrets in `text`, ordered by position
+    pub fn find(&self, text: &str) -> Vec<Finding> {
+        let mut found = Vec::new();
+        for detector in BUILTIN.iter() {
+            for caps in detector.regex.captures_iter(text) {
+                if let Some(m) = caps.get(detector.group).filter(|m| !m.is_empty()) {
+                    found.push(Finding {
+                        kind: detector.kind.clone(),
+                        start: m.start(),
+                        end: m.end(),
+                    });
+                }
+            }
+        }
+        for detector in &self.custom {
+            for m in detector.regex.find_iter(text) {
+                // User patterns may run into JSON quoting; stop there so
+                // redacted JSONL stays parseable
+                let len = m.as_str().find(['"', '\\']).unwrap_or(m.len());
+                if len > 0 {
+                    found.push(Finding {
+                        kind: detector.kind.clone(),
+                        start: m.start(),
+                        end: m.start() + len,
+                    });
+                }
+            }
+        }
+
+        // Earliest first, longest wins on overlap
+        found.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        let mut result: Vec<Finding> = Vec::with_capacity(found.len());
+        for finding in found {
+            if result.last().is_none_or(|last| finding.start >= last.end) {
+                result.push(finding);
+            }
+        }
+
+        // Entropy only fills the gaps left by the specific detectors
+        if self.high_entropy {
+            let specific = result.len();
+            for m in ENTROPY_CANDIDATE.find_iter(text) {
+                let overlaps = result[..specific]
+                    .iter()
+                    .any(|f| m.start() < f.end && f.start < m.end());
+                if !overlaps && looks_random(m.as_str()) {
+                    result.push(Finding {
+                        kind: "high-entropy".to_string(),
+                        start: m.start(),
+                        end: m.end(),
+                    });
+                }
+            }
+            result.sort_by_key(|f| f.start);
+        }
+        result
+    }
+
+    /// Replace each secret with `[REDACTED:<kind>]`.
+    ///
+    /// Replacements contain no quotes or backslashes, so redacting raw JSONL
+    /// keeps every line valid JSON.
+    pub fn redact(&self, text: &str) -> String {
+        let findings = self.find(text);
+        if findings.is_empty() {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        for f in findings {
+            out.push_str(&text[pos..f.start]);
+            out.push_str("[REDACTED:");
+            out.push_str(&sanitize_kind(&f.kind));
+            out.push(']');
+            pos = f.end;
+        }
+        out.push_str(&text[pos..]);
+        out
+    }
+}
+
+fn sanitize_kind(kind: &str) -> String {
+    kind.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn looks_random(token: &str) -> bool {
+    if !(ENTROPY_MIN_LEN..=ENTROPY_MAX_LEN).contains(&token.len()) {
+        return false;
+    }
+    let has_digit = token.chars().any(|c| c.is_ascii_digit());
+    let has_upper = token.chars().any(|c| c.is_ascii_uppercase());
+    let has_lower = token.chars().any(|c| c.is_ascii_lowercase());
+    has_digit && has_upper && has_lower && shannon_entropy(token) >= ENTROPY_THRESHOLD
+}
+
+fn shannon_entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let len = s.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether data bound for `ip` is redacted: the client is not local and
+/// `redact_remote_reads` is on
+pub fn redacts_for(ip: &IpAddr) -> bool {
+    !crate::auth::is_local(ip) && load_settings().redact_remote_reads
+}
+
+/// Redact every string in session data bound for `ip`, when
+/// [`redacts_for`] says so
+pub fn redact_remote_value(ip: &IpAddr, data: Value) -> Value {
+    if crate::auth::is_local(ip) {
+        return data;
+    }
+    let settings = load_settings();
+    if !settings.redact_remote_reads {
+        return data;
+    }
+    let redactor = Redactor::new(&settings).unwrap_or_else(|_| Redactor::builtin());
+    redact_strings(&redactor, data)
+}
+
+fn redact_strings(redactor: &Redactor, data: Value) -> Value {
+    match data {
+        Value::String(s) => Value::String(redactor.redact(&s)),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| redact_strings(redactor, item))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, redact_strings(redactor, v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
@@ -0,0 +1,822 @@
+use crate::auth::{self, Caller};
+use crate::command_registry::{self, CommandHost, CreateTagRequest};
+use crate::models::{FullTextSearchResponse, SessionEntry, SessionInfo};
+use crate::session_tree::SessionTree;
+use crate::stats::SessionStats;
+use crate::{FavoriteItem, SessionTagItem, TagItem};
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Path, Query};
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, on, MethodFilter, MethodRouter};
+use axum::{Json, Router};
+use futures_util::future::{BoxFuture, FutureExt};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+
+/// Maps the socket address to the client address used for auth
+/// (e.g. honouring X-Forwarded-For behind a reverse proxy)
+pub type ClientIp = fn(IpAddr, &HeaderMap) -> IpAddr;
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+type Handler = fn(RestRequest) -> BoxFuture<'static, Result<Value, RestError>>;
+
+// ─── Query & body types ──────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct SessionListQuery {
+    /// Only sessions started in this directory or below it
+    pub cwd: Option<String>,
+    /// Most recently modified first, at most this many
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct EntriesQuery {
+    /// Index of the first entry to return; pass the number of entries
+    /// already seen to follow a live session
+    #[serde(default)]
+    pub from: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    pub q: String,
+    /// `all` (default), `user` or `assistant`
+    #[serde(default = "default_role")]
+    pub role: String,
+    /// Glob over session paths
+    pub glob: Option<String>,
+    /// `any` (default) or `all` terms
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct StatsQuery {
+    /// Only sessions started in this directory or below it
+    pub cwd: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RenameBody {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AssignTagBody {
+    pub tag_id: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+fn default_role() -> String {
+    "all".to_string()
+}
+
+fn default_page_size() -> usize {
+    20
+}
+
+// ─── Requests & errors ───────────────────────────────────────
+
+pub struct RestRequest {
+    caller: Caller,
+    host: Arc<dyn CommandHost>,
+    params: HashMap<String, String>,
+    uri: Uri,
+    body: Bytes,
+}
+
+impl RestRequest {
+    fn param(&self, name: &str) -> String {
+        self.params.get(name).cloned().unwrap_or_default()
+    }
+
+    fn query<T: DeserializeOwned>(&self) -> Result<T, RestError> {
+        Query::<T>::try_from_uri(&self.uri)
+            .map(|Query(q)| q)
+            .map_err(|e| RestError::bad_request(e.body_text()))
+    }
+
+    fn body<T: DeserializeOwned>(&self) -> Result<T, RestError> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| RestError::bad_request(format!("Invalid request body: {e}")))
+    }
+
+    /// Run a registry command as the caller. Scope, read-only mode, allowed
+    /// paths, redaction and the audit log are all handled by the registry.
+    async fn run(&self, command: &str, payload: Value) -> Result<Value, RestError> {
+        command_registry::dispatch_as(&self.caller, command, payload, self.host.clone())
+            .await
+            .map_err(RestError::from_command)
+    }
+
+    /// File of the session named by the `{id}` path parameter
+    async fn session_path(&self) -> Result<String, RestError> {
+        let id = self.param("id");
+        crate::scanner::scan_sessions()
+            .await
+            .map_err(RestError::internal)?
+            .into_iter()
+            .find(|s| s.id == id)
+            .map(|s| s.path)
+            .ok_or_else(|| RestError::not_found(format!("No session with id {id}")))
+    }
+
+    /// `scan_sessions` as the caller, narrowed to `cwd` when given
+    async fn sessions(&self, cwd: Option<&str>) -> Result<Vec<SessionInfo>, RestError> {
+        let sessions: Vec<SessionInfo> = from_json(self.run("scan_sessions", Value::Null).await?)?;
+        Ok(sessions
+            .into_iter()
+            .filter(|s| cwd.is_none_or(|dir| under(&s.cwd, dir)))
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct RestError {
+    status: StatusCode,
+    message: String,
+}
+
+impl RestError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
+    fn internal(message: String) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message,
+        }
+    }
+
+    /// Status for an error returned by `dispatch_as`
+    fn from_command(message: String) -> Self {
+        let status = if message.starts_with("Forbidden") {
+            StatusCode::FORBIDDEN
+        } else if message.starts_with("Invalid payload") {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        Self { status, message }
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            cors_headers(),
+            Json(ErrorBody {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, RestError> {
+    serde_json::to_value(value).map_err(|e| RestError::internal(e.to_string()))
+}
+
+fn from_json<T: DeserializeOwned>(value: Value) -> Result<T, RestError> {
+    serde_json::from_value(value).map_err(|e| RestError::internal(e.to_string()))
+}
+
+fn under(cwd: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    cwd == dir || cwd.starts_with(&format!("{dir}/"))
+}
+
+// ─── Route table ─────────────────────────────────────────────
+
+/// A REST operation: a mapping from path, query and body onto registry
+/// commands, plus a transform of their responses
+struct Route {
+    method: Method,
+    /// OpenAPI-style template, e.g. `/api/v1/sessions/{id}`
+    path: &'static str,
+    operation_id: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    query: Option<SchemaFn>,
+    body: Option<SchemaFn>,
+    /// `None` answers 204 No Content
+    response: Option<SchemaFn>,
+    handler: Handler,
+}
+
+impl Route {
+    fn new(
+        method: Method,
+        path: &'static str,
+        operation_id: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+        handler: Handler,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            operation_id,
+            tag,
+            summary,
+            query: None,
+            body: None,
+            response: None,
+            handler,
+        }
+    }
+
+    fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(T::json_schema);
+        self
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        self.body = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+
+    fn returns<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+
+    fn path_params(&self) -> impl Iterator<Item = &'static str> {
+        self.path
+            .split('/')
+            .filter_map(|seg| seg.strip_prefix('{')?.strip_suffix('}'))
+    }
+
+    fn axum_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(
+                |seg| match seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => format!(":{name}"),
+                    None => seg.to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+fn routes() -> Vec<Route> {
+    vec![
+        Route::new(
+            Method::GET,
+            "/api/v1/sessions",
+            "listSessions",
+            "sessions",
+            "List sessions, most recently modified first",
+            |req| list_sessions(req).boxed(),
+        )
+        .query::<SessionListQuery>()
+        .returns::<Vec<SessionInfo>>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/sessions/{id}",
+            "getSession",
+            "sessions",
+            "Get one session",
+            |req| get_session(req).boxed(),
+        )
+        .returns::<SessionInfo>(),
+        Route::new(
+            Method::PATCH,
+            "/api/v1/sessions/{id}",
+            "renameSession",
+            "sessions",
+            "Rename a session",
+            |req| {
+                async move {
+                    let body: RenameBody = req.body()?;
+                    let path = req.session_path().await?;
+                    req.run(
+                        "rename_session",
+                        json!({ "path": path, "newName": body.name }),
+                    )
+                    .await
+                }
+                .boxed()
+            },
+        )
+        .body::<RenameBody>(),
+        Route::new(
+            Method::DELETE,
+            "/api/v1/sessions/{id}",
+            "deleteSession",
+            "sessions",
+            "Delete a session file",
+            |req| {
+                async move {
+                    let path = req.session_path().await?;
+                    req.run("delete_session", json!({ "path": path })).await
+                }
+                .boxed()
+            },
+        ),
+        Route::new(
+            Method::GET,
+            "/api/v1/sessions/{id}/entries",
+            "getSessionEntries",
+            "sessions",
+            "Parsed entries of a session, optionally starting at an offset",
+            |req| session_entries(req).boxed(),
+        )
+        .query::<EntriesQuery>()
+        .returns::<Vec<SessionEntry>>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/sessions/{id}/tree",
+            "getSessionTree",
+            "sessions",
+            "Branch tree of a session",
+            |req| {
+                async move {
+                    let path = req.session_path().await?;
+                    req.run("get_session_tree", json!({ "path": path })).await
+                }
+                .boxed()
+            },
+        )
+        .returns::<SessionTree>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/sessions/{id}/tags",
+            "getSessionTags",
+            "tags",
+            "Tags assigned to a session",
+            |req| session_tags(req).boxed(),
+        )
+        .returns::<Vec<TagItem>>(),
+        Route::new(
+            Method::POST,
+            "/api/v1/sessions/{id}/tags",
+            "assignSessionTag",
+            "tags",
+            "Tag a session",
+            |req| {
+                async move {
+                    let body: AssignTagBody = req.body()?;
+                    req.session_path().await?;
+                    req.run(
+                        "assign_tag",
+                        json!({ "sessionId": req.param("id"), "tagId": body.tag_id }),
+                    )
+                    .await
+                }
+                .boxed()
+            },
+        )
+        .body::<AssignTagBody>(),
+        Route::new(
+            Method::DELETE,
+            "/api/v1/sessions/{id}/tags/{tagId}",
+            "removeSessionTag",
+            "tags",
+            "Untag a session",
+            |req| {
+                async move {
+                    req.session_path().await?;
+                    req.run(
+                        "remove_tag_from_session",
+                        json!({ "sessionId": req.param("id"), "tagId": req.param("tagId") }),
+                    )
+                    .await
+                }
+                .boxed()
+            },
+        ),
+        Route::new(
+            Method::GET,
+            "/api/v1/search",
+            "search",
+            "search",
+            "Full-text search over messages",
+            |req| {
+                async move {
+                    let q: SearchQuery = req.query()?;
+                    req.run(
+                        "full_text_search",
+                        json!({
+                            "query": q.q,
+                            "roleFilter": q.role,
+                            "globPattern": q.glob,
+                            "page": q.page,
+                            "pageSize": q.page_size,
+                            "matchMode": q.mode,
+                        }),
+                    )
+                    .await
+                }
+                .boxed()
+            },
+        )
+        .query::<SearchQuery>()
+        .returns::<FullTextSearchResponse>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/tags",
+            "listTags",
+            "tags",
+            "List tags",
+            |req| async move { req.run("get_all_tags", Value::Null).await }.boxed(),
+        )
+        .returns::<Vec<TagItem>>(),
+        Route::new(
+            Method::POST,
+            "/api/v1/tags",
+            "createTag",
+            "tags",
+            "Create a tag",
+            |req| {
+                async move {
+                    let body: Value = req.body()?;
+                    req.run("create_tag", body).await
+                }
+                .boxed()
+            },
+        )
+        .body::<CreateTagRequest>()
+        .returns::<TagItem>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/favorites",
+            "listFavorites",
+            "favorites",
+            "List favorites",
+            |req| async move { req.run("get_all_favorites", Value::Null).await }.boxed(),
+        )
+        .returns::<Vec<FavoriteItem>>(),
+        Route::new(
+            Method::GET,
+            "/api/v1/stats",
+            "getStats",
+            "sessions",
+            "Aggregate statistics",
+            |req| {
+                async move {
+                    let q: StatsQuery = req.query()?;
+                    let sessions = req.sessions(q.cwd.as_deref()).await?;
+                    req.run("get_session_stats", json!({ "sessions": sessions }))
+                        .await
+                }
+                .boxed()
+            },
+        )
+        .query::<StatsQuery>()
+        .returns::<SessionStats>(),
+    ]
+}
+
+async fn list_sessions(req: RestRequest) -> Result<Value, RestError> {
+    let q: SessionListQuery = req.query()?;
+    let mut sessions = req.sessions(q.cwd.as_deref()).await?;
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+    if let Some(limit) = q.limit {
+        sessions.truncate(limit);
+    }
+    to_json(sessions)
+}
+
+async fn get_session(req: RestRequest) -> Result<Value, RestError> {
+    let path = req.session_path().await?;
+    // Missing from the caller's own listing: outside its allowed paths
+    let session = req
+        .sessions(None)
+        .await?
+        .into_iter()
+        .find(|s| s.path == path)
+        .ok_or_else(|| {
+            RestError::forbidden(format!(
+                "Forbidden: session {} is outside the token's allowed paths",
+                req.param("id")
+            ))
+        })?;
+    to_json(session)
+}
+
+async fn session_entries(req: RestRequest) -> Result<Value, RestError> {
+    let q: EntriesQuery = req.query()?;
+    let path = req.session_path().await?;
+    let entries: Vec<Value> = from_json(
+        req.run("get_session_entries", json!({ "path": path }))
+            .await?,
+    )?;
+    let limit = q.limit.unwrap_or(usize::MAX);
+    to_json(
+        entries
+            .into_iter()
+            .skip(q.from)
+            .take(limit)
+            .collect::<Vec<_>>(),
+    )
+}
+
+async fn session_tags(req: RestRequest) -> Result<Value, RestError> {
+    req.session_path().await?;
+    let id = req.param("id");
+    let assigned: Vec<SessionTagItem> =
+        from_json(req.run("get_all_session_tags", Value::Null).await?)?;
+    let mut assigned: Vec<_> = assigned
+        .into_iter()
+        .filter(|st| st.session_id == id)
+        .collect();
+    assigned.sort_by_key(|st| st.position);
+    let tags: Vec<TagItem> = from_json(req.run("get_all_tags", Value::Null).await?)?;
+    let tags: Vec<TagItem> = assigned
+        .iter()
+        .filter_map(|st| tags.iter().find(|t| t.id == st.tag_id).cloned())
+        .collect();
+    to_json(tags)
+}
+
+// ─── Router ──────────────────────────────────────────────────
+
+pub(crate) fn cors_headers() -> [(&'static str, &'static str); 3] {
+    [
+        ("access-control-allow-origin", "*"),
+        (
+            "access-control-allow-methods",
+            "GET, POST, PATCH, DELETE, OPTIONS",
+        ),
+        (
+            "access-control-allow-headers",
+            "content-type, authorization",
+        ),
+    ]
+}
+
+async fn handle(
+    route: &'static Route,
+    host: Arc<dyn CommandHost>,
+    client_ip: IpAddr,
+    headers: HeaderMap,
+    uri: Uri,
+    params: HashMap<String, String>,
+    body: Bytes,
+) -> Response {
+    let token = auth::request_token(&headers, &uri);
+    let Some(caller) = auth::authenticate(client_ip, token.as_deref(), "rest") else {
+        return RestError {
+            status: StatusCode::UNAUTHORIZED,
+            message: "Unauthorized".to_string(),
+        }
+        .into_response();
+    };
+    let request = RestRequest {
+        caller,
+        host,
+        params,
+        uri,
+        body,
+    };
+    match (route.handler)(request).await {
+        Ok(data) if data.is_null() && route.response.is_none() => {
+            (StatusCode::NO_CONTENT, cors_headers()).into_response()
+        }
+        Ok(data) => (StatusCode::OK, cors_headers(), Json(data)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ROUTES: Vec<Route> = routes();
+}
+
+/// REST routes under `/api/v1` plus the OpenAPI document, for merging into
+/// a server next to the `POST /api` command route. Commands run with `host`.
+pub fn router<S>(client_ip: ClientIp, host: Arc<dyn CommandHost>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let mut by_path: BTreeMap<String, MethodRouter<S>> = BTreeMap::new();
+    for route in ROUTES.iter() {
+        let filter = MethodFilter::try_from(route.method.clone()).expect("supported method");
+        let host = host.clone();
+        let handler = move |ConnectInfo(addr): ConnectInfo<SocketAddr>,
+                            params: Option<Path<HashMap<String, String>>>,
+                            headers: HeaderMap,
+                            uri: Uri,
+                            body: Bytes| async move {
+            let ip = client_ip(addr.ip(), &headers);
+            let params = params.map(|Path(p)| p).unwrap_or_default();
+            handle(route, host, ip, headers, uri, params, body).await
+        };
+        let entry = by_path
+            .entry(route.axum_path())
+            .or_insert_with(|| MethodRouter::new().options(preflight));
+        *entry = std::mem::take(entry).on(filter, handler);
+    }
+
+    let mut router = Router::new().route(OPENAPI_PATH, get(openapi_handler).options(preflight));
+    for (path, method_router) in by_path {
+        router = router.route(&path, method_router);
+    }
+    router
+}
+
+async fn preflight() -> impl IntoResponse {
+    (StatusCode::NO_CONTENT, cors_headers())
+}
+
+async fn openapi_handler() -> impl IntoResponse {
+    (StatusCode::OK, cors_headers(), Json(openapi()))
+}
+
+// ─── OpenAPI ─────────────────────────────────────────────────
+
+/// OpenAPI 3 description of the REST routes and the `POST /api` command route
+pub fn openapi() -> Value {
+    let mut requests = SchemaSettings::openapi3()
+        .for_deserialize()
+        .into_generator();
+    let mut responses = SchemaSettings::openapi3().for_serialize().into_generator();
+    let error_schema = responses.subschema_for::<ErrorBody>();
+
+    let mut paths: BTreeMap<&str, Map<String, Value>> = BTreeMap::new();
+    for route in ROUTES.iter() {
+        let mut parameters: Vec<Value> = route
+            .path_params()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        if let Some(query) = route.query {
+            parameters.extend(query_parameters(query(&mut requests)));
+        }
+
+        let mut operation = json!({
+            "operationId": route.operation_id,
+            "summary": route.summary,
+            "tags": [route.tag],
+            "responses": {
+                "default": {
+                    "description": "Error",
+                    "content": { "application/json": { "schema": error_schema } },
+                },
+            },
+        });
+        if !parameters.is_empty() {
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if let Some(body) = route.body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": body(&mut requests) } },
+            });
+        }
+        operation["responses"][if route.response.is_some() {
+            "200"
+        } else {
+            "204"
+        }] = match route.response {
+            Some(response) => json!({
+                "description": "OK",
+                "content": { "application/json": { "schema": response(&mut responses) } },
+            }),
+            None => json!({ "description": "No Content" }),
+        };
+
+        paths
+            .entry(route.path)
+            .or_default()
+            .insert(route.method.as_str().to_lowercase(), operation);
+    }
+
+    let command_names: Vec<&str> = command_registry::commands()
+        .iter()
+        .map(|c| c.name)
+        .collect();
+    let mut paths: Map<String, Value> = paths
+        .into_iter()
+        .map(|(path, ops)| (path.to_string(), Value::Object(ops)))
+        .collect();
+    paths.insert(
+        "/api".to_string(),
+        json!({
+            "post": {
+                "operationId": "runCommand",
+                "summary": "Run any command by name; see the list_commands command for per-command schemas",
+                "tags": ["rpc"],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["command"],
+                        "properties": {
+                            "command": { "type": "string", "enum": command_names },
+                            "payload": { "type": "object" },
+                        },
+                    } } },
+                },
+                "responses": {
+                    "200": {
+                        "description": "Command result",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "properties": {
+                                "success": { "type": "boolean" },
+                                "data": {},
+                                "error": { "type": "string" },
+                            },
+                        } } },
+                    },
+                },
+            },
+        }),
+    );
+
+    let mut schemas = requests.take_definitions(true);
+    schemas.extend(responses.take_definitions(true));
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Pi Session Manager",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+            },
+        },
+        "security": [{ "bearer": [] }],
+    })
+}
+
+fn query_parameters(schema: Schema) -> Vec<Value> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .map(|(name, prop)| {
+            let mut prop = prop.clone();
+            let description = prop.as_object_mut().and_then(|p| p.remove("description"));
+            let mut param = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&name.as_str()),
+                "schema": prop,
+            });
+            if let Some(description) = description {
+                param["description"] = description;
+            }
+            param
+        })
+        .collect()
+}
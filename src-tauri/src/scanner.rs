@@ -1,22 +1,50 @@
+use crate::command_registry::CommandHost;
 use crate::config::Config;
-use crate::models::{Content, Message, SessionEntry, SessionInfo, SessionsDiff};
+use crate::database::{self, Database};
+use crate::models::{SessionEntry, SessionInfo, SessionsDiff};
+use crate::session_cursor::{self, CursorUpdate, SessionCursor};
 use crate::sqlite_cache;
 use crate::write_buffer;
 use chrono::{DateTime, Duration, Utc};
-use serde_json::Value;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, trace, warn};
 
-/// Check if an error message indicates database corruption
-fn is_corruption_error(err: &str) -> bool {
-    err.contains("malformed")
-        || err.contains("disk image")
-        || err.contains("not a database")
-        || err.contains("vtable constructor failed")
+/// 扫描进度事件名
+pub const PROGRESS_EVENT: &str = "scan-progress";
+
+/// 进度事件的最小发送间隔
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// 缓冲中积累到这么多会话时，扫描线程把它们写入数据库，避免超出缓冲容量被丢弃
+const SCAN_FLUSH_BATCH: usize = 500;
+
+/// Repair whichever database file is damaged, then retry with a fresh
+/// service. A damaged user database is restored from the latest backup;
+/// the cache is only recreated when it is the damaged one. A user database
+/// that fails for any other reason is left alone.
+fn recover_database(config: &Config) {
+    database::close();
+    let recovered = sqlite_cache::get_db_path().and_then(|db_path| {
+        let user_path = crate::migrations::user_db_path(&db_path);
+        match sqlite_cache::open_user_db(&user_path) {
+            Ok(()) => sqlite_cache::recover_corrupted_db(&db_path, config),
+            Err(e) if sqlite_cache::is_corruption_error(&e) => {
+                warn!("[Recovery] User database unusable ({}). Restoring...", e);
+                sqlite_cache::recover_user_db(&db_path, config)
+            }
+            Err(e) => Err(e),
+        }
+    });
+    if let Err(e) = recovered {
+        warn!("[Recovery] Database not recovered: {}", e);
+    }
 }
 
 static SCAN_CACHE: Mutex<Option<Vec<SessionInfo>>> = Mutex::new(None);
@@ -30,9 +58,83 @@ pub fn invalidate_cache() {
     }
 }
 
+/// 一次扫描的解析进度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ScanProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_parsed: u64,
+    pub done: bool,
+}
+
+static PROGRESS_TX: OnceLock<broadcast::Sender<ScanProgress>> = OnceLock::new();
+
+fn progress_sender() -> &'static broadcast::Sender<ScanProgress> {
+    PROGRESS_TX.get_or_init(|| broadcast::channel(16).0)
+}
+
+/// 订阅扫描进度
+pub fn subscribe_progress() -> broadcast::Receiver<ScanProgress> {
+    progress_sender().subscribe()
+}
+
+/// 把扫描进度作为 `scan-progress` 事件转发给宿主，直到进程退出
+pub async fn forward_progress(host: Arc<dyn CommandHost>) {
+    let mut rx = subscribe_progress();
+    loop {
+        match rx.recv().await {
+            Ok(progress) => host.emit(
+                PROGRESS_EVENT,
+                serde_json::to_value(progress).unwrap_or_default(),
+            ),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// 节流发送进度：最多每 100ms 一次，结束时总会发送
+struct ProgressReporter {
+    progress: ScanProgress,
+    last_sent: Instant,
+}
+
+impl ProgressReporter {
+    fn start(files_total: usize) -> Self {
+        let reporter = Self {
+            progress: ScanProgress {
+                files_total,
+                ..Default::default()
+            },
+            last_sent: Instant::now(),
+        };
+        let _ = progress_sender().send(reporter.progress);
+        reporter
+    }
+
+    fn file_done(&mut self, bytes: u64) {
+        self.progress.files_done += 1;
+        self.progress.bytes_parsed += bytes;
+        if self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            let _ = progress_sender().send(self.progress);
+            self.last_sent = Instant::now();
+        }
+    }
+
+    fn finish(mut self) {
+        self.progress.done = true;
+        let _ = progress_sender().send(self.progress);
+    }
+}
+
+/// Bumped whenever the cached session list changes or is dropped
+pub fn cache_version() -> u64 {
+    CACHE_VERSION.load(Ordering::Relaxed)
+}
+
 /// Lightweight digest for HTTP polling — just version + count, no session data
 pub fn get_session_digest() -> (u64, usize) {
-    let version = CACHE_VERSION.load(Ordering::Relaxed);
+    let version = cache_version();
     let count = SCAN_CACHE
         .lock()
         .ok()
@@ -46,6 +148,35 @@ pub fn get_sessions_dir() -> Result<PathBuf, String> {
     Ok(home.join(".pi").join("agent").join("sessions"))
 }
 
+/// Every session file under the configured session directories
+pub fn list_session_files(config: &Config) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for sessions_dir in get_all_session_dirs(config) {
+        let Ok(entries) = fs::read_dir(&sessions_dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let skip = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n == "transcripts" || n == "subagent-artifacts");
+            if !path.is_dir() || skip {
+                continue;
+            }
+            if let Ok(dir_files) = fs::read_dir(&path) {
+                files.extend(
+                    dir_files
+                        .flatten()
+                        .map(|f| f.path())
+                        .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl")),
+                );
+            }
+        }
+    }
+    files
+}
+
 /// Returns all session directories: the default one plus any user-configured paths.
 pub fn get_all_session_dirs(config: &Config) -> Vec<PathBuf> {
     let mut dirs = vec![];
@@ -98,6 +229,14 @@ pub async fn scan_sessions() -> Result<Vec<SessionInfo>, String> {
 }
 
 pub async fn scan_sessions_with_config(config: &Config) -> Result<Vec<SessionInfo>, String> {
+    // 扫描全程同步读写文件和数据库，放到阻塞线程池避免占住异步运行时
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || scan_sessions_blocking(&config))
+        .await
+        .map_err(|e| format!("Scan task failed: {e}"))?
+}
+
+fn scan_sessions_blocking(config: &Config) -> Result<Vec<SessionInfo>, String> {
     let all_dirs = get_all_session_dirs(config);
     let realtime_cutoff = Utc::now() - Duration::days(config.realtime_cutoff_days);
     const MAX_RETRIES: usize = 1;
@@ -105,16 +244,13 @@ pub async fn scan_sessions_with_config(config: &Config) -> Result<Vec<SessionInf
 
     loop {
         attempt += 1;
-        // Initialize database connection (may fail if corrupted)
-        let conn = match sqlite_cache::init_db_with_config(config) {
-            Ok(conn) => conn,
+        // Get the database service (opening it may fail if corrupted)
+        let db = match database::get_with_config(config) {
+            Ok(db) => db,
             Err(e) => {
-                if is_corruption_error(&e) && attempt <= MAX_RETRIES {
+                if sqlite_cache::is_corruption_error(&e) && attempt <= MAX_RETRIES {
                     warn!("[Recovery] Database init failed (corruption suspected): {}. Attempting to recover...", e);
-                    // Attempt to delete corrupted DB and retry
-                    if let Ok(db_path) = sqlite_cache::get_db_path() {
-                        let _ = std::fs::remove_file(&db_path);
-                    }
+                    recover_database(config);
                     continue;
                 } else {
                     return Err(e);
@@ -124,90 +260,61 @@ pub async fn scan_sessions_with_config(config: &Config) -> Result<Vec<SessionInf
 
         // Perform the scan with error handling
         let scan_result = (|| -> Result<Vec<SessionInfo>, String> {
-            let mut sessions: Vec<SessionInfo> = vec![];
-
-            for sessions_dir in &all_dirs {
-                if !sessions_dir.exists() {
-                    continue;
-                }
-
-                let entries = match fs::read_dir(sessions_dir) {
-                    Ok(e) => e,
-                    Err(e) => {
-                        log::warn!("Failed to read sessions directory {sessions_dir:?}: {e}");
-                        continue;
-                    }
+            // 近期文件总是重新解析；更早的文件只在缓存缺失或过期时解析
+            let files = list_session_files(config);
+            let listed: HashSet<String> = files
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            let mut jobs = Vec::new();
+            for file_path in files {
+                let file_modified: DateTime<Utc> = match fs::metadata(&file_path) {
+                    Ok(m) => DateTime::from(m.modified().unwrap_or(std::time::SystemTime::now())),
+                    Err(_) => continue,
                 };
-
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        // Skip non-pi-session directories (gateway transcripts, subagent artifacts, etc.)
-                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                            if name == "transcripts" || name == "subagent-artifacts" {
-                                continue;
-                            }
-                        }
-                        if let Ok(files) = fs::read_dir(&path) {
-                            for file in files.flatten() {
-                                let file_path = file.path();
-                                if file_path
-                                    .extension()
-                                    .map(|ext| ext == "jsonl")
-                                    .unwrap_or(false)
-                                {
-                                    let path_str = file_path.to_string_lossy().to_string();
-
-                                    let metadata = fs::metadata(&file_path);
-                                    let file_modified: DateTime<Utc> = match metadata {
-                                        Ok(m) => DateTime::from(
-                                            m.modified().unwrap_or(std::time::SystemTime::now()),
-                                        ),
-                                        Err(_) => continue,
-                                    };
-
-                                    if file_modified > realtime_cutoff {
-                                        if let Ok((info, _entries)) = parse_session_info(&file_path)
-                                        {
-                                            sessions.push(info);
-                                            write_buffer::buffer_session_write(
-                                                sessions.last().unwrap(),
-                                                file_modified,
-                                            );
-                                        }
-                                    } else if let Some(cached_mtime) =
-                                        sqlite_cache::get_cached_file_modified(&conn, &path_str)?
-                                    {
-                                        if file_modified > cached_mtime {
-                                            if let Ok((info, _entries)) =
-                                                parse_session_info(&file_path)
-                                            {
-                                                write_buffer::buffer_session_write(
-                                                    &info,
-                                                    file_modified,
-                                                );
-                                            }
-                                        }
-                                    } else if let Ok((info, _entries)) =
-                                        parse_session_info(&file_path)
-                                    {
-                                        write_buffer::buffer_session_write(&info, file_modified);
-                                    }
-                                }
-                            }
+                let path_str = file_path.to_string_lossy();
+                let mut cached_mtime =
+                    db.read(|conn| sqlite_cache::get_cached_file_modified(conn, &path_str))?;
+                // 未缓存的路径可能是移动过来的会话：改指向新路径后按缓存继续
+                if cached_mtime.is_none()
+                    && relocate_if_moved(&db, &file_path, |old| listed.contains(old))?.is_some()
+                {
+                    cached_mtime =
+                        db.read(|conn| sqlite_cache::get_cached_file_modified(conn, &path_str))?;
+                }
+                if file_modified <= realtime_cutoff {
+                    if let Some(cached_mtime) = cached_mtime {
+                        if file_modified <= cached_mtime {
+                            continue;
                         }
                     }
                 }
+                jobs.push((file_path, file_modified));
             }
 
+            let mut sessions: Vec<SessionInfo> = Vec::with_capacity(jobs.len());
+            parse_in_parallel(
+                jobs,
+                config.scan_worker_count(),
+                |_, file_modified, info, update| {
+                    buffer_parsed(&info, file_modified, update);
+                    sessions.push(info);
+                    if write_buffer::get_buffer_stats().0 >= SCAN_FLUSH_BATCH {
+                        write_buffer::flush_to_db()?;
+                    }
+                    Ok(())
+                },
+            )?;
+
             let historical_sessions =
-                sqlite_cache::get_sessions_modified_before(&conn, realtime_cutoff)?;
+                db.read(|conn| sqlite_cache::get_sessions_modified_before(conn, realtime_cutoff))?;
 
-            for session in historical_sessions {
-                if !sessions.iter().any(|s| s.path == session.path) {
-                    sessions.push(session);
-                }
-            }
+            let parsed: HashSet<String> = sessions.iter().map(|s| s.path.clone()).collect();
+            sessions.extend(
+                historical_sessions
+                    .into_iter()
+                    .filter(|s| !parsed.contains(&s.path)),
+            );
 
             sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
 
@@ -232,12 +339,10 @@ pub async fn scan_sessions_with_config(config: &Config) -> Result<Vec<SessionInf
         match scan_result {
             Ok(sessions) => break Ok(sessions),
             Err(e) => {
-                if is_corruption_error(&e) && attempt <= MAX_RETRIES {
-                    warn!("[Recovery] Database corruption detected during scan: {}. Dropping connection and retrying...", e);
-                    // Connection will be dropped at end of loop iteration; delete DB and retry
-                    if let Ok(db_path) = sqlite_cache::get_db_path() {
-                        let _ = std::fs::remove_file(&db_path);
-                    }
+                if sqlite_cache::is_corruption_error(&e) && attempt <= MAX_RETRIES {
+                    warn!("[Recovery] Database corruption detected during scan: {}. Closing the database and retrying...", e);
+                    // Connections close once this iteration drops the service
+                    recover_database(config);
                     continue;
                 } else {
                     return Err(e);
@@ -247,159 +352,133 @@ pub async fn scan_sessions_with_config(config: &Config) -> Result<Vec<SessionInf
     }
 }
 
-/// 解析会话信息并提取消息条目
-/// 优化：使用 BufReader 流式读取，减少大文件内存占用
+/// 解析会话信息并提取消息条目（完整解析，不使用保存的游标）
 /// 返回：(SessionInfo, Vec<SessionEntry>) - 会话信息和消息条目列表
 pub fn parse_session_info(path: &Path) -> Result<(SessionInfo, Vec<SessionEntry>), String> {
-    let file = fs::File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
-
-    // 读取并解析头部
-    let header_line = lines
-        .next()
-        .ok_or("Empty session file")?
-        .map_err(|e| format!("Failed to read header: {e}"))?;
-
-    let header: Value =
-        serde_json::from_str(&header_line).map_err(|e| format!("Failed to parse header: {e}"))?;
-
-    if header["type"] != "session" {
-        return Err("Invalid session header".to_string());
-    }
-
-    let id = header["id"].as_str().unwrap_or("unknown").to_string();
-    let cwd = header["cwd"].as_str().unwrap_or("").to_string();
-    let timestamp_str = header["timestamp"].as_str().unwrap_or("");
-    let created = parse_timestamp(timestamp_str)?;
-
-    let metadata = fs::metadata(path).map_err(|e| format!("Failed to get metadata: {e}"))?;
-    let modified = DateTime::from(
-        metadata
-            .modified()
-            .map_err(|e| format!("Failed to get modified time: {e}"))?,
-    );
+    let update = SessionCursor::default().advance(path)?;
+    Ok((update.session_info(path, None), update.entries))
+}
 
-    let mut message_count = 0;
-    let mut first_message = String::new();
-    let mut all_messages = Vec::new();
-    let mut user_messages = Vec::new();
-    let mut assistant_messages = Vec::new();
-    let mut name: Option<String> = None;
-    let mut last_message = String::new();
-    let mut last_message_role = String::new();
-    let mut entries = Vec::new();
-
-    // 流式读取剩余行，减少内存占用
-    for line_result in lines {
-        let line = match line_result {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
+/// 增量解析：从上次保存的游标继续，只解析追加到文件末尾的字节
+/// 文件被改写（头部变化或变短）时退回完整解析
+pub fn parse_session_incremental(
+    db: &Database,
+    path: &Path,
+) -> Result<(SessionInfo, CursorUpdate), String> {
+    let (previous, cursor) = match stored_cursor(db, &path.to_string_lossy())? {
+        Some((info, cursor)) => (Some(info), cursor),
+        None => (None, SessionCursor::default()),
+    };
+    let update = cursor.advance(path)?;
+    let info = update.session_info(path, previous.as_ref());
+    Ok((info, update))
+}
 
-        if line.trim().is_empty() {
-            continue;
+/// 用 `workers` 个线程并行增量解析 `jobs`（文件路径与修改时间），
+/// 每个结果在调用线程上交给 `on_parsed`，因此数据库写入仍然只有一个写者。
+/// 解析失败的文件被跳过；`on_parsed` 返回错误时停止并返回该错误。
+/// 进度通过 `subscribe_progress` 广播。
+pub fn parse_in_parallel<F>(
+    jobs: Vec<(PathBuf, DateTime<Utc>)>,
+    workers: usize,
+    mut on_parsed: F,
+) -> Result<(), String>
+where
+    F: FnMut(&Path, DateTime<Utc>, SessionInfo, CursorUpdate) -> Result<(), String>,
+{
+    let mut progress = ProgressReporter::start(jobs.len());
+    let workers = workers.clamp(1, jobs.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::sync_channel(workers * 4);
+
+    // 工作线程从读连接池查询已保存的游标；数据库不可用时退回完整解析
+    let db = database::get().ok();
+
+    let result = std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (jobs, next, db) = (&jobs, &next, db.as_deref());
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((path, _)) = jobs.get(index) else {
+                    break;
+                };
+                let parsed = match db {
+                    Some(db) => parse_session_incremental(db, path),
+                    None => SessionCursor::default()
+                        .advance(path)
+                        .map(|update| (update.session_info(path, None), update)),
+                };
+                if tx.send((index, parsed)).is_err() {
+                    break;
+                }
+            });
         }
-
-        if let Ok(entry) = serde_json::from_str::<Value>(&line) {
-            if entry["type"] == "session_info" {
-                if let Some(n) = entry["name"].as_str() {
-                    name = Some(n.trim().to_string());
+        drop(tx);
+
+        // 接收端在循环结束时被丢弃，提前返回时工作线程随之退出
+        for (index, parsed) in rx {
+            let (path, file_modified) = &jobs[index];
+            let bytes = match parsed {
+                Ok((info, update)) => {
+                    let bytes = update.bytes_parsed();
+                    on_parsed(path, *file_modified, info, update)?;
+                    bytes
                 }
-            }
-
-            if entry["type"] == "message" {
-                let role = entry["message"]["role"].as_str().unwrap_or("");
-                if role == "user" || role == "assistant" {
-                    message_count += 1;
-
-                    let text = extract_message_text(&entry);
-                    if !text.is_empty() {
-                        all_messages.push(text.clone());
-                        if first_message.is_empty() && role == "user" {
-                            first_message = text.chars().take(100).collect();
-                        }
-                        // 更新最后一条消息
-                        last_message = text.chars().take(150).collect();
-                        last_message_role = role.to_string();
-
-                        // 收集用户和助手的消息文本
-                        if role == "user" {
-                            user_messages.push(text.clone());
-                        } else if role == "assistant" {
-                            assistant_messages.push(text.clone());
-                        }
-
-                        // 构建 SessionEntry 用于 message_entries 表
-                        let entry_id = entry["id"].as_str().unwrap_or("").to_string();
-                        let timestamp_str = entry["timestamp"].as_str().unwrap_or("");
-                        let timestamp = parse_timestamp(timestamp_str)?;
-
-                        let session_entry = SessionEntry {
-                            entry_type: "message".to_string(),
-                            id: entry_id,
-                            parent_id: None,
-                            timestamp,
-                            message: Some(Message {
-                                role: role.to_string(),
-                                content: vec![Content {
-                                    content_type: "text".to_string(),
-                                    text: Some(text),
-                                }],
-                            }),
-                        };
-                        entries.push(session_entry);
-                    }
+                Err(e) => {
+                    debug!("Failed to parse {}: {}", path.display(), e);
+                    0
                 }
-            }
+            };
+            progress.file_done(bytes);
         }
-    }
+        Ok(())
+    });
 
-    let all_messages_text = all_messages.join("\n");
-    let user_messages_text = user_messages.join("\n");
-    let assistant_messages_text = assistant_messages.join("\n");
-
-    Ok((
-        SessionInfo {
-            path: path.to_string_lossy().to_string(),
-            id,
-            cwd,
-            name,
-            created,
-            modified,
-            message_count,
-            first_message,
-            all_messages_text,
-            user_messages_text,
-            assistant_messages_text,
-            last_message,
-            last_message_role,
-        },
-        entries,
-    ))
+    progress.finish();
+    result
 }
 
-fn extract_message_text(entry: &Value) -> String {
-    if let Some(message) = entry.get("message") {
-        if let Some(content) = message.get("content") {
-            if content.is_array() {
-                for item in content.as_array().unwrap() {
-                    if let Some(text) = item.get("text") {
-                        if let Some(s) = text.as_str() {
-                            return s.to_string();
-                        }
-                    }
-                }
-            }
-        }
+/// 最近一次增量解析留下的会话信息和游标：优先内存缓冲，其次数据库
+pub fn stored_cursor(
+    db: &Database,
+    path: &str,
+) -> Result<Option<(SessionInfo, SessionCursor)>, String> {
+    if let Some(buffered) = write_buffer::get_buffered_cursor(path) {
+        return Ok(Some(buffered));
     }
-    String::new()
+    db.read(|conn| sqlite_cache::get_session_with_cursor(conn, path))
 }
 
-fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, String> {
-    DateTime::parse_from_rfc3339(s)
-        .map(|dt| dt.with_timezone(&Utc))
-        .map_err(|e| format!("Failed to parse timestamp: {e}"))
+/// 缓存里没有 `path` 时，按会话头部的标识查找缓存中的同一会话；若它的原路径
+/// 已不再是会话文件（`still_listed` 返回 false），说明文件被移动或会话目录被改配置，
+/// 把缓存行改指向新路径而不是重新解析。返回原路径。
+pub fn relocate_if_moved(
+    db: &Database,
+    path: &Path,
+    still_listed: impl Fn(&str) -> bool,
+) -> Result<Option<String>, String> {
+    let Ok(id) = session_cursor::read_session_id(path) else {
+        return Ok(None);
+    };
+    let Some(old_path) = db.read(|conn| sqlite_cache::find_session_path(conn, &id))? else {
+        return Ok(None);
+    };
+    let new_path = path.to_string_lossy().into_owned();
+    if old_path == new_path || still_listed(&old_path) {
+        return Ok(None);
+    }
+    let (from, to) = (old_path.clone(), new_path.clone());
+    db.write(move |conn| sqlite_cache::relocate_session(conn, &from, &to))?;
+    // 旧路径还在缓冲中的写入改挂到新路径，否则刷新时会按已不存在的旧文件写回
+    write_buffer::relocate(&old_path, &new_path);
+    Ok(Some(old_path))
+}
+
+/// 缓冲写入增量解析结果：会话连同游标，以及由游标累计值得到的详情
+fn buffer_parsed(info: &SessionInfo, file_modified: DateTime<Utc>, update: CursorUpdate) {
+    write_buffer::buffer_details_write(&info.path, file_modified, &update.cursor.state.details());
+    write_buffer::buffer_session_cursor_write(info, file_modified, Some(update.cursor));
 }
 
 /// Incremental update: re-parse changed files, update cache, return diff for frontend merge.
@@ -420,8 +499,9 @@ pub async fn rescan_changed_files(changed_paths: Vec<String>) -> Result<Sessions
     };
 
     let config = Config::load().unwrap_or_default();
-    let conn = sqlite_cache::init_db_with_config(&config)?;
+    let db = database::get_with_config(&config)?;
 
+    let batch = crate::tantivy_search::batch();
     for path_str in &changed_paths {
         let path = PathBuf::from(path_str);
 
@@ -435,22 +515,44 @@ pub async fn rescan_changed_files(changed_paths: Vec<String>) -> Result<Sessions
             continue;
         }
 
-        match parse_session_info(&path) {
-            Ok((info, entries)) => {
+        // A file that appeared under a new path may be a moved session
+        if db
+            .read(|conn| sqlite_cache::get_cached_file_modified(conn, path_str))?
+            .is_none()
+        {
+            if let Some(old_path) = relocate_if_moved(&db, &path, |old| Path::new(old).exists())? {
+                let before = sessions.len();
+                sessions.retain(|s| s.path != old_path);
+                if sessions.len() != before {
+                    diff.removed.push(old_path.clone());
+                }
+                log::info!("Session moved: {old_path} -> {path_str}");
+            }
+        }
+
+        match parse_session_incremental(&db, &path) {
+            Ok((info, update)) => {
                 let file_modified = match fs::metadata(&path).and_then(|m| m.modified()) {
                     Ok(mt) => DateTime::from(mt),
                     Err(_) => continue,
                 };
 
-                // Ensure session row exists (also populates message_entries via insert_message_entries)
-                if let Err(e) =
-                    sqlite_cache::upsert_session(&conn, &info, file_modified, Some(&entries))
-                {
-                    log::warn!("Failed to upsert session for {}: {}", info.path, e);
-                }
+                // Save the session with its cursor; only appended message entries are inserted
+                let (info, update) = db.write(move |conn| {
+                    if let Err(e) = sqlite_cache::upsert_session_with_cursor(
+                        conn,
+                        &info,
+                        file_modified,
+                        &update.cursor,
+                        Some(&update),
+                    ) {
+                        log::warn!("Failed to upsert session for {}: {}", info.path, e);
+                    }
+                    Ok((info, update))
+                })?;
 
                 // Buffer for stats cache updates (periodic flush)
-                write_buffer::buffer_session_write(&info, file_modified);
+                buffer_parsed(&info, file_modified, update);
 
                 diff.updated.push(info.clone());
 
@@ -465,6 +567,7 @@ pub async fn rescan_changed_files(changed_paths: Vec<String>) -> Result<Sessions
             }
         }
     }
+    drop(batch);
 
     if !diff.updated.is_empty() || !diff.removed.is_empty() {
         sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
@@ -1,7 +1,9 @@
 use crate::config::Config;
+use crate::database::{self, Database};
 use crate::scanner;
 use crate::sqlite_cache;
 use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 use tokio::time::{interval, Duration as TokioDuration};
@@ -43,60 +45,84 @@ impl ScannerScheduler {
     async fn scan_and_update(&self) -> Result<String, String> {
         let start = std::time::Instant::now();
 
-        let conn = sqlite_cache::init_db_with_config(&self.config)?;
+        let db = database::get_with_config(&self.config)?;
 
         let mut updated = 0;
         let mut added = 0;
+        let mut moved = 0;
         let mut skipped = 0;
 
-        let all_dirs = scanner::get_all_session_dirs(&self.config);
-
-        for sessions_dir in &all_dirs {
-            if !sessions_dir.exists() {
-                continue;
+        let files = scanner::list_session_files(&self.config);
+        let listed: HashSet<String> = files
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        let mut jobs = Vec::new();
+        let mut cached = HashSet::new();
+        for file_path in files {
+            let (mut result, mut file_modified) = self.check_file(&db, &file_path)?;
+            // A new path may be a moved session; its cached rows follow it
+            if matches!(result, FileUpdateResult::Added)
+                && scanner::relocate_if_moved(&db, &file_path, |old| listed.contains(old))?
+                    .is_some()
+            {
+                moved += 1;
+                (result, file_modified) = self.check_file(&db, &file_path)?;
             }
-
-            if let Ok(entries) = fs::read_dir(sessions_dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        if let Ok(files) = fs::read_dir(&path) {
-                            for file in files.flatten() {
-                                let file_path = file.path();
-                                if file_path
-                                    .extension()
-                                    .map(|ext| ext == "jsonl")
-                                    .unwrap_or(false)
-                                {
-                                    match self.process_file(&conn, &file_path)? {
-                                        FileUpdateResult::Updated => updated += 1,
-                                        FileUpdateResult::Added => added += 1,
-                                        FileUpdateResult::Skipped => skipped += 1,
-                                    }
-                                }
-                            }
-                        }
-                    }
+            match result {
+                FileUpdateResult::Skipped => skipped += 1,
+                FileUpdateResult::Updated => {
+                    cached.insert(file_path.clone());
+                    jobs.push((file_path, file_modified));
                 }
+                FileUpdateResult::Added => jobs.push((file_path, file_modified)),
             }
         }
 
+        let total = jobs.len();
+        let batch = crate::tantivy_search::batch();
+        scanner::parse_in_parallel(
+            jobs,
+            self.config.scan_worker_count(),
+            |file_path, file_modified, info, update| {
+                db.write(move |conn| {
+                    sqlite_cache::upsert_session_with_cursor(
+                        conn,
+                        &info,
+                        file_modified,
+                        &update.cursor,
+                        Some(&update),
+                    )
+                })?;
+                if cached.contains(file_path) {
+                    updated += 1;
+                } else {
+                    added += 1;
+                }
+                Ok(())
+            },
+        )?;
+        drop(batch);
+        // Files that failed to parse
+        skipped += total - added - updated;
+
         let elapsed = start.elapsed();
         info!(
-            "Scanner complete: +{} added, ~{} updated, {} skipped in {:?}",
-            added, updated, skipped, elapsed
+            "Scanner complete: +{} added, ~{} updated, {} moved, {} skipped in {:?}",
+            added, updated, moved, skipped, elapsed
         );
 
         Ok(format!(
-            "Scanned: +{added} added, ~{updated} updated, {skipped} skipped"
+            "Scanned: +{added} added, ~{updated} updated, {moved} moved, {skipped} skipped"
         ))
     }
 
-    fn process_file(
+    /// Whether a file needs parsing, compared with its cached modification time
+    fn check_file(
         &self,
-        conn: &rusqlite::Connection,
+        db: &Database,
         file_path: &std::path::Path,
-    ) -> Result<FileUpdateResult, String> {
+    ) -> Result<(FileUpdateResult, DateTime<Utc>), String> {
         let path_str = file_path.to_string_lossy().to_string();
 
         let metadata =
@@ -107,32 +133,21 @@ impl ScannerScheduler {
                 .map_err(|e| format!("Failed to get modified time: {e}"))?,
         );
 
-        let cached_mtime = sqlite_cache::get_cached_file_modified(conn, &path_str)?;
-
-        if let Some(cached) = cached_mtime {
-            if file_modified <= cached {
-                return Ok(FileUpdateResult::Skipped);
-            }
-        }
-
-        if let Ok((info, entries)) = scanner::parse_session_info(file_path) {
-            sqlite_cache::upsert_session(conn, &info, file_modified, Some(&entries))?;
-            return Ok(if cached_mtime.is_some() {
-                FileUpdateResult::Updated
-            } else {
-                FileUpdateResult::Added
-            });
-        }
-
-        Ok(FileUpdateResult::Skipped)
+        let result =
+            match db.read(|conn| sqlite_cache::get_cached_file_modified(conn, &path_str))? {
+                Some(cached) if file_modified <= cached => FileUpdateResult::Skipped,
+                Some(_) => FileUpdateResult::Updated,
+                None => FileUpdateResult::Added,
+            };
+        Ok((result, file_modified))
     }
 
     async fn auto_cleanup(&self) -> Result<String, String> {
         if let Some(cleanup_days) = self.config.auto_cleanup_days {
             let _cutoff = Utc::now() - Duration::days(cleanup_days);
 
-            let conn = sqlite_cache::init_db_with_config(&self.config)?;
-            let deleted = sqlite_cache::cleanup_missing_files(&conn)?;
+            let deleted = database::get_with_config(&self.config)?
+                .write(sqlite_cache::cleanup_missing_files)?;
 
             if deleted > 0 {
                 info!("Auto cleanup: removed {} missing session records", deleted);
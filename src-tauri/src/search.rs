@@ -1,4 +1,5 @@
-use crate::models::{Match, SearchResult, SessionInfo};
+use crate::models::{Content, Match, Message, SearchResult, SessionInfo};
+use crate::session_parser;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SearchMode {
@@ -170,7 +171,7 @@ struct MessageEntry {
     id: String,
     role: String,
     content: String,
-    timestamp: chrono::DateTime<chrono::Utc>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// 从 BufReader 解析会话条目
@@ -192,101 +193,76 @@ fn parse_session_entries_from_reader<R: std::io::BufRead>(
             continue;
         }
 
-        if let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) {
-            // 只处理消息条目
-            if entry["type"] != "message" {
-                continue;
-            }
+        let Some(entry) = serde_json::from_str::<serde_json::Value>(&line)
+            .ok()
+            .and_then(|value| session_parser::parse_entry(&value))
+        else {
+            continue;
+        };
+        // 只处理消息条目
+        let Some(message) = entry.message else {
+            continue;
+        };
 
-            if let Some(message) = entry.get("message") {
-                let role = message["role"].as_str().unwrap_or("unknown");
-                let timestamp_str = entry["timestamp"].as_str().unwrap_or("");
-                let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)
-                    .map(|dt| dt.with_timezone(&chrono::Utc))
-                    .unwrap_or_else(|_| chrono::Utc::now());
-
-                // 按角色过滤
-                let include = match role_filter {
-                    RoleFilter::All => true,
-                    RoleFilter::User => role == "user",
-                    RoleFilter::Assistant => role == "assistant",
-                };
-
-                if include {
-                    let empty_array = vec![];
-                    let content = message["content"].as_array().unwrap_or(&empty_array);
-
-                    let mut text = String::new();
-                    for item in content {
-                        if let Some(text_content) = item.get("text") {
-                            if let Some(s) = text_content.as_str() {
-                                text.push_str(s);
-                            }
-                        }
-
-                        // 如果 include_tools 为 true，包含 thinking 内容
-                        if include_tools {
-                            if let Some(thinking) = item.get("thinking") {
-                                if let Some(s) = thinking.as_str() {
-                                    text.push_str(s);
-                                }
-                            }
-                        }
-                    }
-
-                    if !text.is_empty() {
-                        entries.push(MessageEntry {
-                            id: entry["id"].as_str().unwrap_or("").to_string(),
-                            role: role.to_string(),
-                            content: text,
-                            timestamp,
-                        });
-                    }
-                }
-            }
+        // 按角色过滤
+        if !role_matches(role_filter, &message.role) {
+            continue;
+        }
+
+        let text = searchable_parts(&message, include_tools).concat();
+        if !text.is_empty() {
+            entries.push(MessageEntry {
+                id: entry.id,
+                role: message.role,
+                content: text,
+                timestamp: entry.timestamp,
+            });
         }
     }
 
     entries
 }
 
+/// Searchable parts of a message: text and tool output, plus thinking and
+/// tool-call arguments when `include_tools` is set
+fn searchable_parts(message: &Message, include_tools: bool) -> Vec<String> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            Content::Text { text } => Some(text.clone()),
+            Content::ToolResult { output, .. } => Some(output.clone()),
+            Content::Thinking { thinking } if include_tools => Some(thinking.clone()),
+            Content::ToolCall {
+                name, arguments, ..
+            } if include_tools => Some(format!("{name} {arguments}")),
+            _ => None,
+        })
+        .collect()
+}
+
+fn role_matches(role_filter: RoleFilter, role: &str) -> bool {
+    match role_filter {
+        RoleFilter::All => true,
+        RoleFilter::User => role == "user",
+        RoleFilter::Assistant => role == "assistant",
+    }
+}
+
 fn get_filtered_session_content(path: &str, role_filter: RoleFilter) -> Result<String, String> {
     let content =
         std::fs::read_to_string(path).map_err(|e| format!("Failed to read session file: {e}"))?;
 
     let mut full_text = String::new();
 
-    for line in content.lines() {
-        if line.trim().is_empty() {
+    for entry in session_parser::parse_entries(&content) {
+        let Some(message) = entry.message else {
             continue;
-        }
-
-        if let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) {
-            if entry["type"] == "message" {
-                if let Some(message) = entry.get("message") {
-                    let role = message["role"].as_str().unwrap_or("");
-
-                    // Filter by role
-                    let include = match role_filter {
-                        RoleFilter::All => true,
-                        RoleFilter::User => role == "user",
-                        RoleFilter::Assistant => role == "assistant",
-                    };
-
-                    if include {
-                        let empty_array = vec![];
-                        let content = message["content"].as_array().unwrap_or(&empty_array);
-
-                        for item in content {
-                            if let Some(text) = item.get("text") {
-                                if let Some(s) = text.as_str() {
-                                    full_text.push_str(s);
-                                    full_text.push('\n');
-                                }
-                            }
-                        }
-                    }
-                }
+        };
+        if role_matches(role_filter, &message.role) {
+            for part in searchable_parts(&message, false) {
+                full_text.push_str(&part);
+                full_text.push('\n');
             }
         }
     }
@@ -308,35 +284,14 @@ fn get_full_session_content(path: &str) -> Result<String, String> {
 
     let mut full_text = String::new();
 
-    for line in content.lines() {
-        if line.trim().is_empty() {
+    for entry in session_parser::parse_entries(&content) {
+        let Some(message) = entry.message else {
             continue;
-        }
-
-        if let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) {
-            if entry["type"] == "message" {
-                if let Some(message) = entry.get("message") {
-                    let empty_array = vec![];
-                    let content = message["content"].as_array().unwrap_or(&empty_array);
-
-                    for item in content {
-                        if let Some(text) = item.get("text") {
-                            if let Some(s) = text.as_str() {
-                                full_text.push_str(s);
-                                full_text.push('\n');
-                            }
-                        }
-
-                        // Include thinking content
-                        if let Some(thinking) = item.get("thinking") {
-                            if let Some(s) = thinking.as_str() {
-                                full_text.push_str(s);
-                                full_text.push('\n');
-                            }
-                        }
-                    }
-                }
-            }
+        };
+        // Include thinking content
+        for part in searchable_parts(&message, true) {
+            full_text.push_str(&part);
+            full_text.push('\n');
         }
     }
 
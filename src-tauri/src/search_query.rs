@@ -0,0 +1,533 @@
+//! Query grammar for `full_text_search`.
+//!
+//! ```text
+//! query    := or_expr
+//! or_expr  := and_expr ("OR" and_expr)*
+//! and_expr := unary (["AND"] unary)*
+//! unary    := ("-" | "NOT") unary | primary
+//! primary  := "(" or_expr ")" | field ":" value | "\"" phrase "\"" | word
+//! field    := role | cwd | model | tag | after | before
+//! ```
+//!
+//! Juxtaposed words are joined with the caller's default operator (the
+//! legacy `match_mode` any/all); field filters and exclusions always narrow
+//! the result. [`plan`] splits the parsed [`Expr`] into free text, compiled to
+//! an FTS5 MATCH expression, and field filters, compiled to SQL conditions
+//! over `message_entries m` joined with `sessions s`.
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+const FIELDS: [&str; 6] = ["role", "cwd", "model", "tag", "after", "before"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Single word; a trailing `*` makes it a prefix match
+    Term(String),
+    Phrase(String),
+    Field(FieldFilter),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldFilter {
+    /// `user` or `assistant`
+    Role(String),
+    /// Project directory (`~` expanded); matches subdirectories unless it contains a glob
+    Cwd(String),
+    /// `provider/model` glob; a bare model name matches any provider
+    Model(String),
+    /// Tag name or id
+    Tag(String),
+    /// Entries at or after this instant
+    After(DateTime<Utc>),
+    /// Entries strictly before this instant
+    Before(DateTime<Utc>),
+}
+
+/// Operator used between juxtaposed search terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultOperator {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+}
+
+/// Read a quoted string starting after the opening quote; an unterminated quote runs to the end
+fn read_quoted(chars: &[char], start: usize) -> (String, usize) {
+    let mut i = start;
+    let mut s = String::new();
+    while i < chars.len() && chars[i] != '"' {
+        s.push(chars[i]);
+        i += 1;
+    }
+    (s, (i + 1).min(chars.len()))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '"' => {
+                let (phrase, next) = read_quoted(&chars, i + 1);
+                tokens.push(Token::Phrase(phrase));
+                i = next;
+            }
+            '-' if chars.get(i + 1).is_some_and(|n| !n.is_whitespace()) => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '(' | ')' | '"')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                if let Some((name, rest)) = word.split_once(':') {
+                    let name = name.to_lowercase();
+                    if FIELDS.contains(&name.as_str()) {
+                        let value = if rest.is_empty() && chars.get(i) == Some(&'"') {
+                            let (quoted, next) = read_quoted(&chars, i + 1);
+                            i = next;
+                            quoted
+                        } else {
+                            rest.to_string()
+                        };
+                        if value.trim().is_empty() {
+                            return Err(format!("Missing value for '{name}:'"));
+                        }
+                        tokens.push(Token::Field(name, value.trim().to_string()));
+                        continue;
+                    }
+                }
+
+                match word.as_str() {
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    // Pure punctuation yields no tokens in either engine
+                    _ if !word.chars().any(char::is_alphanumeric) => {}
+                    _ => tokens.push(Token::Word(word)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn expand_home(path: &str) -> String {
+    if path == "~" || path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return format!("{}{}", home.to_string_lossy(), &path[1..]);
+        }
+    }
+    path.to_string()
+}
+
+/// Accepts RFC 3339, `YYYY-MM-DD` or `YYYY-MM` (midnight UTC)
+fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d"))
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("Invalid date '{value}', expected YYYY-MM-DD"))
+}
+
+fn parse_field(name: &str, value: &str) -> Result<FieldFilter, String> {
+    match name {
+        "role" => match value.to_lowercase().as_str() {
+            role @ ("user" | "assistant") => Ok(FieldFilter::Role(role.to_string())),
+            _ => Err(format!(
+                "Invalid role '{value}', expected user or assistant"
+            )),
+        },
+        "cwd" => Ok(FieldFilter::Cwd(expand_home(value))),
+        "model" => Ok(FieldFilter::Model(value.to_string())),
+        "tag" => Ok(FieldFilter::Tag(value.to_string())),
+        "after" => parse_date(value).map(FieldFilter::After),
+        "before" => parse_date(value).map(FieldFilter::Before),
+        _ => Err(format!("Unknown field '{name}'")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    default_op: DefaultOperator,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut branches = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            branches.push(self.parse_and()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Expr::Or(branches)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        // Positive text units are joined with the default operator; filters and
+        // exclusions are always required.
+        let mut units: Vec<Expr> = Vec::new();
+        let mut constraints: Vec<Expr> = Vec::new();
+        let mut explicit_and = false;
+
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::RParen) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                    explicit_and = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let item = self.parse_unary()?;
+            if matches!(item, Expr::Field(_) | Expr::Not(_)) {
+                constraints.push(item);
+            } else if explicit_and && !units.is_empty() {
+                let last = units.pop().unwrap();
+                units.push(match last {
+                    Expr::And(mut items) => {
+                        items.push(item);
+                        Expr::And(items)
+                    }
+                    other => Expr::And(vec![other, item]),
+                });
+            } else {
+                units.push(item);
+            }
+            explicit_and = false;
+        }
+
+        if units.is_empty() && constraints.is_empty() {
+            return Err(match self.peek() {
+                Some(Token::Or) => "Expected a search term before 'OR'".to_string(),
+                Some(Token::RParen) => "Empty parentheses".to_string(),
+                _ => "Expected a search term".to_string(),
+            });
+        }
+
+        let mut parts = Vec::new();
+        match units.len() {
+            0 => {}
+            1 => parts.push(units.pop().unwrap()),
+            _ => parts.push(match self.default_op {
+                DefaultOperator::And => Expr::And(units),
+                DefaultOperator::Or => Expr::Or(units),
+            }),
+        }
+        parts.extend(constraints);
+
+        Ok(if parts.len() == 1 {
+            parts.pop().unwrap()
+        } else {
+            Expr::And(parts)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(match self.parse_unary()? {
+                Expr::Not(inner) => *inner,
+                other => Expr::Not(Box::new(other)),
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => Ok(Expr::Term(word)),
+            Some(Token::Phrase(phrase)) => Ok(Expr::Phrase(phrase)),
+            Some(Token::Field(name, value)) => parse_field(&name, &value).map(Expr::Field),
+            Some(Token::Not) | Some(Token::And) | Some(Token::Or) | None => {
+                Err("Expected a search term".to_string())
+            }
+            Some(Token::RParen) => Err("Unexpected ')'".to_string()),
+        }
+    }
+}
+
+/// Parse a query string into an [`Expr`]
+pub fn parse(input: &str, default_op: DefaultOperator) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        default_op,
+    };
+    if parser.tokens.is_empty() {
+        return Err("Expected a search term".to_string());
+    }
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err("Unexpected ')'".to_string());
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: FieldFilter,
+    pub negated: bool,
+}
+
+/// A query split into its free-text part and top-level field filters
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchPlan {
+    /// Free text only: terms, phrases, And/Or, and Not directly under And
+    pub text: Expr,
+    pub filters: Vec<Filter>,
+}
+
+fn flatten_and(expr: Expr, out: &mut Vec<Expr>) {
+    match expr {
+        Expr::And(items) => items.into_iter().for_each(|i| flatten_and(i, out)),
+        other => out.push(other),
+    }
+}
+
+fn is_positive(expr: &Expr) -> bool {
+    !matches!(expr, Expr::Not(_))
+}
+
+/// Check that `expr` is expressible as an FTS5 query
+fn validate_text(expr: &Expr) -> Result<(), String> {
+    match expr {
+        Expr::Term(_) | Expr::Phrase(_) => Ok(()),
+        Expr::Field(_) => Err("Field filters cannot be combined with OR".to_string()),
+        Expr::Not(_) => Err("Exclusions need a search term in the same group".to_string()),
+        Expr::Or(items) => items.iter().try_for_each(validate_text),
+        Expr::And(items) => {
+            if !items.iter().any(is_positive) {
+                return Err("Exclusions need a search term in the same group".to_string());
+            }
+            items.iter().try_for_each(|item| match item {
+                Expr::Not(inner) => validate_text(inner),
+                other => validate_text(other),
+            })
+        }
+    }
+}
+
+/// Split a parsed query into free text and top-level field filters
+pub fn plan(expr: Expr) -> Result<SearchPlan, String> {
+    let mut items = Vec::new();
+    flatten_and(expr, &mut items);
+
+    let mut text = Vec::new();
+    let mut filters = Vec::new();
+    for item in items {
+        match item {
+            Expr::Field(field) => filters.push(Filter {
+                field,
+                negated: false,
+            }),
+            Expr::Not(inner) => match *inner {
+                Expr::Field(field) => filters.push(Filter {
+                    field,
+                    negated: true,
+                }),
+                other => text.push(Expr::Not(Box::new(other))),
+            },
+            other => text.push(other),
+        }
+    }
+
+    if !text.iter().any(is_positive) {
+        return Err("Query must contain at least one search term".to_string());
+    }
+    let text = if text.len() == 1 {
+        text.pop().unwrap()
+    } else {
+        Expr::And(text)
+    };
+    validate_text(&text)?;
+
+    Ok(SearchPlan { text, filters })
+}
+
+fn quote_fts5(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn to_fts5(expr: &Expr) -> String {
+    match expr {
+        Expr::Term(word) => match word.strip_suffix('*') {
+            Some(prefix) if !prefix.is_empty() => format!("{}*", quote_fts5(prefix)),
+            _ => quote_fts5(word),
+        },
+        Expr::Phrase(phrase) => quote_fts5(phrase),
+        Expr::Or(items) => format!(
+            "({})",
+            items.iter().map(to_fts5).collect::<Vec<_>>().join(" OR ")
+        ),
+        Expr::And(items) => {
+            let positive: Vec<String> = items
+                .iter()
+                .filter(|i| is_positive(i))
+                .map(to_fts5)
+                .collect();
+            let mut out = format!("({})", positive.join(" AND "));
+            for item in items {
+                if let Expr::Not(inner) = item {
+                    out = format!("({out} NOT {})", to_fts5(inner));
+                }
+            }
+            out
+        }
+        // Rejected by `plan`
+        Expr::Not(_) | Expr::Field(_) => String::new(),
+    }
+}
+
+/// Escape LIKE wildcards and the escape character itself
+fn escape_like(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Convert a glob (`*`, `?`) into a LIKE pattern using `\` as escape
+pub fn glob_to_like(pattern: &str) -> String {
+    let mut out = String::new();
+    for ch in pattern.chars() {
+        match ch {
+            '*' => out.push('%'),
+            '?' => out.push('_'),
+            other => out.push_str(&escape_like(&other.to_string())),
+        }
+    }
+    out
+}
+
+fn has_glob(s: &str) -> bool {
+    s.contains('*') || s.contains('?')
+}
+
+impl SearchPlan {
+    /// FTS5 MATCH expression for the free-text part
+    pub fn fts5_match(&self) -> String {
+        to_fts5(&self.text)
+    }
+
+    /// SQL conditions over `message_entries m` and `sessions s`, with their
+    /// positional parameters in order
+    pub fn sql_conditions(&self) -> (Vec<String>, Vec<String>) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        for filter in &self.filters {
+            let condition = match &filter.field {
+                FieldFilter::Role(role) => {
+                    params.push(role.clone());
+                    "m.role = ?".to_string()
+                }
+                FieldFilter::Cwd(cwd) if has_glob(cwd) => {
+                    params.push(glob_to_like(cwd));
+                    "s.cwd LIKE ? ESCAPE '\\'".to_string()
+                }
+                FieldFilter::Cwd(cwd) => {
+                    let cwd = cwd.trim_end_matches('/');
+                    params.push(cwd.to_string());
+                    params.push(format!("{}/%", escape_like(cwd)));
+                    "(s.cwd = ? OR s.cwd LIKE ? ESCAPE '\\')".to_string()
+                }
+                FieldFilter::Model(model) => {
+                    let like = glob_to_like(model);
+                    params.push(like.clone());
+                    params.push(format!("%/{like}"));
+                    "EXISTS (SELECT 1 FROM message_entries mm WHERE mm.session_path = m.session_path \
+                     AND (mm.model LIKE ? ESCAPE '\\' OR mm.model LIKE ? ESCAPE '\\'))"
+                        .to_string()
+                }
+                FieldFilter::Tag(tag) => {
+                    params.push(tag.clone());
+                    params.push(tag.clone());
+                    "EXISTS (SELECT 1 FROM session_tags st JOIN tags t ON t.id = st.tag_id \
+                     WHERE st.session_id = s.id AND (t.name = ? COLLATE NOCASE OR t.id = ?))"
+                        .to_string()
+                }
+                FieldFilter::After(at) => {
+                    params.push(at.to_rfc3339());
+                    "julianday(m.timestamp) >= julianday(?)".to_string()
+                }
+                FieldFilter::Before(at) => {
+                    params.push(at.to_rfc3339());
+                    "julianday(m.timestamp) < julianday(?)".to_string()
+                }
+            };
+            conditions.push(if filter.negated {
+                format!("NOT ({condition})")
+            } else {
+                condition
+            });
+        }
+
+        (conditions, params)
+    }
+}
@@ -0,0 +1,226 @@
+use crate::models::{Content, SessionEntry};
+use crate::redaction::{self, Finding, Redactor};
+use crate::sqlite_cache::{self, DbSecretFinding};
+use crate::{database, scanner, session_parser};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Characters of surrounding text kept on each side of a finding
+const PREVIEW_CONTEXT: usize = 24;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SecretAuditReport {
+    /// Sessions read in this run
+    pub scanned: usize,
+    /// Sessions whose stored results were still current
+    pub unchanged: usize,
+    pub sessions_with_findings: usize,
+    pub findings: Vec<DbSecretFinding>,
+}
+
+/// Audit every scanned session for likely secrets.
+///
+/// Results are kept in `secret_findings`; a session is only re-read when its
+/// file mtime or the detector settings changed, or when `full` is set.
+pub async fn audit_secrets(full: bool) -> Result<SecretAuditReport, String> {
+    let paths: Vec<String> = scanner::scan_sessions()
+        .await?
+        .into_iter()
+        .map(|s| s.path)
+        .collect();
+    tokio::task::spawn_blocking(move || audit_paths(&paths, full))
+        .await
+        .map_err(|e| format!("Secret audit task failed: {e}"))?
+}
+
+pub fn audit_paths(paths: &[String], full: bool) -> Result<SecretAuditReport, String> {
+    let settings = redaction::load_settings();
+    let redactor = Redactor::new(&settings)?;
+    let fingerprint = redaction::fingerprint(&settings);
+    let db = database::get()?;
+    let state = db.read(sqlite_cache::get_secret_audit_state)?;
+
+    let missing: Vec<String> = state
+        .keys()
+        .filter(|path| !Path::new(path).exists())
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        db.write(move |conn| {
+            missing
+                .iter()
+                .try_for_each(|path| sqlite_cache::delete_secret_audit(conn, path))
+        })?;
+    }
+
+    let mut scanned = 0;
+    let mut unchanged = 0;
+    for path in paths {
+        let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) else {
+            continue;
+        };
+        let file_modified = DateTime::<Utc>::from(modified).to_rfc3339();
+        let current = state
+            .get(path)
+            .is_some_and(|(m, d)| *m == file_modified && *d == fingerprint);
+        if current && !full {
+            unchanged += 1;
+            continue;
+        }
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                log::warn!("Secret audit skipped {path}: {e}");
+                continue;
+            }
+        };
+        let findings = scan_content(&redactor, path, &content);
+        let (path, fingerprint) = (path.clone(), fingerprint.clone());
+        db.write(move |conn| {
+            sqlite_cache::replace_secret_findings(
+                conn,
+                &path,
+                &file_modified,
+                &fingerprint,
+                &findings,
+            )
+        })?;
+        scanned += 1;
+    }
+
+    let findings = db.read(sqlite_cache::get_secret_findings)?;
+    let sessions_with_findings = findings
+        .iter()
+        .map(|f| f.session_path.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+    Ok(SecretAuditReport {
+        scanned,
+        unchanged,
+        sessions_with_findings,
+        findings,
+    })
+}
+
+/// Findings in one session file, one per detected secret
+pub fn scan_content(
+    redactor: &Redactor,
+    session_path: &str,
+    content: &str,
+) -> Vec<DbSecretFinding> {
+    let mut result = Vec::new();
+    for entry in session_parser::parse_entries(content) {
+        for text in entry_texts(&entry) {
+            let findings = redactor.find(&text);
+            for (i, finding) in findings.iter().enumerate() {
+                result.push(DbSecretFinding {
+                    session_path: session_path.to_string(),
+                    entry_id: entry.id.clone(),
+                    detector: finding.kind.clone(),
+                    preview: preview(&text, &findings, i),
+                });
+            }
+        }
+    }
+    result
+}
+
+/// Every piece of free text in an entry, including tool input and output
+fn entry_texts(entry: &SessionEntry) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut push_content = |content: &[Content]| {
+        for c in content {
+            match c {
+                Content::Text { text } => texts.push(text.clone()),
+                Content::Thinking { thinking } => texts.push(thinking.clone()),
+                Content::ToolCall { arguments, .. } => texts.push(arguments.to_string()),
+                Content::ToolResult { output, .. } => texts.push(output.clone()),
+                Content::Image { .. } => {}
+            }
+        }
+    };
+    if let Some(message) = &entry.message {
+        push_content(&message.content);
+    }
+    if let Some(custom) = &entry.custom_message {
+        push_content(&custom.content);
+    }
+    if let Some(compaction) = &entry.compaction {
+        texts.push(compaction.summary.clone());
+    }
+    if let Some(branch) = &entry.branch_summary {
+        texts.push(branch.summary.clone());
+    }
+    texts
+}
+
+/// One line of context around `findings[index]` with the secret masked and
+/// any other secret in view replaced by its detector name
+fn preview(text: &str, findings: &[Finding], index: usize) -> String {
+    let target = &findings[index];
+    let mut start = back_chars(text, target.start, PREVIEW_CONTEXT);
+    let mut end = forward_chars(text, target.end, PREVIEW_CONTEXT);
+    // Never cut a neighbouring secret in half
+    for f in findings {
+        if f.start < start && start < f.end {
+            start = f.start;
+        }
+        if f.start < end && end < f.end {
+            end = f.end;
+        }
+    }
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut pos = start;
+    for (i, f) in findings.iter().enumerate() {
+        if f.end <= start || f.start >= end {
+            continue;
+        }
+        out.push_str(&text[pos..f.start]);
+        if i == index {
+            out.push_str(&mask(&text[f.start..f.end]));
+        } else {
+            out.push_str(&format!("[{}]", f.kind));
+        }
+        pos = f.end;
+    }
+    out.push_str(&text[pos..end]);
+    if end < text.len() {
+        out.push('…');
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// First four and last two characters of longer secrets, nothing of short ones
+fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 16 {
+        return "****".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 2..].iter().collect();
+    format!("{head}****{tail}")
+}
+
+fn back_chars(text: &str, from: usize, n: usize) -> usize {
+    text[..from]
+        .char_indices()
+        .rev()
+        .nth(n.saturating_sub(1))
+        .map_or(0, |(i, _)| i)
+}
+
+fn forward_chars(text: &str, from: usize, n: usize) -> usize {
+    text[from..]
+        .char_indices()
+        .nth(n)
+        .map_or(text.len(), |(i, _)| from + i)
+}
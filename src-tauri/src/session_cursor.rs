@@ -0,0 +1,516 @@
+use crate::models::{SessionEntry, SessionInfo};
+use crate::session_parser::{self, SessionDetails};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How far a session file has been parsed, with the running aggregates
+/// needed to extend its `SessionInfo` and `SessionDetails` from there.
+/// Persisted in `session_cursors`, so an append to a large session only
+/// parses the new bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionCursor {
+    /// Bytes of the file read so far, including `partial`
+    pub offset: u64,
+    /// Lines consumed, counting the header
+    pub line_count: u64,
+    /// Start of a line pi has not finished writing yet
+    pub partial: Vec<u8>,
+    /// The last consumed line is complete JSON but has no newline yet
+    pub unterminated: bool,
+    /// SHA-256 of the header line; rewrites such as renames change it
+    pub header_hash: String,
+    pub state: CursorState,
+}
+
+/// Running aggregates over the consumed lines
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorState {
+    pub name: Option<String>,
+    pub message_count: usize,
+    pub first_message: String,
+    pub last_message: String,
+    pub last_message_role: String,
+    /// Every entry in file order, enough to find the active branch again
+    pub nodes: Vec<CursorNode>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub input_cost: f64,
+    pub output_cost: f64,
+    pub cache_read_cost: f64,
+    pub cache_write_cost: f64,
+    pub models: BTreeSet<String>,
+    pub first_message_time: Option<DateTime<Utc>>,
+    pub last_message_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorNode {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// `SessionDetails` counter the entry adds to while on the active branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counts: Option<Counted>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Counted {
+    User,
+    Assistant,
+    ToolResult,
+    CustomMessage,
+    Compaction,
+    BranchSummary,
+}
+
+struct Header {
+    id: String,
+    cwd: String,
+    created: DateTime<Utc>,
+}
+
+/// Result of [`SessionCursor::advance`]
+pub struct CursorUpdate {
+    pub cursor: SessionCursor,
+    /// Parsing started over at the top of the file: a new cursor, or the
+    /// file was rewritten or truncated since the previous one
+    pub reset: bool,
+    /// User and assistant messages with text parsed by this update, as
+    /// stored in `message_entries`
+    pub entries: Vec<SessionEntry>,
+    base_offset: u64,
+    base_hash: String,
+    header: Header,
+    modified: DateTime<Utc>,
+    all_texts: Vec<String>,
+    user_texts: Vec<String>,
+    assistant_texts: Vec<String>,
+}
+
+impl SessionCursor {
+    /// Position just past the last consumed line
+    pub fn consumed(&self) -> u64 {
+        self.offset - self.partial.len() as u64
+    }
+
+    /// Parse what was appended to `path` since this cursor. A cursor that no
+    /// longer matches the file (different header, shorter file) is discarded
+    /// and the whole file is parsed.
+    pub fn advance(&self, path: &Path) -> Result<CursorUpdate, String> {
+        let mut file = File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+        let metadata = file
+            .metadata()
+            .map_err(|e| format!("Failed to get metadata: {e}"))?;
+        let len = metadata.len();
+        let modified = DateTime::from(
+            metadata
+                .modified()
+                .map_err(|e| format!("Failed to get modified time: {e}"))?,
+        );
+
+        let (header_line, header_len) = read_header(&mut file)?;
+        let header_value: Value = serde_json::from_slice(&header_line)
+            .map_err(|e| format!("Failed to parse header: {e}"))?;
+        if header_value["type"] != "session" {
+            return Err("Invalid session header".to_string());
+        }
+        let header = Header {
+            id: session_identity(&header_value),
+            cwd: header_value["cwd"].as_str().unwrap_or("").to_string(),
+            created: DateTime::parse_from_rfc3339(header_value["timestamp"].as_str().unwrap_or(""))
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| format!("Failed to parse timestamp: {e}"))?,
+        };
+        let header_hash = sha256_hex(&header_line);
+
+        let resume = self.offset > 0 && self.header_hash == header_hash && len >= self.offset;
+        let (mut cursor, start) = if resume {
+            (self.clone(), self.offset)
+        } else {
+            let cursor = SessionCursor {
+                offset: header_len,
+                line_count: 1,
+                unterminated: header_len == header_line.len() as u64,
+                header_hash,
+                state: CursorState {
+                    // rename_session writes the name into the header
+                    name: header_value["name"].as_str().map(|n| n.trim().to_string()),
+                    ..CursorState::default()
+                },
+                ..SessionCursor::default()
+            };
+            (cursor, header_len)
+        };
+
+        let mut buf = std::mem::take(&mut cursor.partial);
+        file.seek(SeekFrom::Start(start))
+            .map_err(|e| format!("Failed to read session file: {e}"))?;
+        let read = file
+            .take(len.saturating_sub(start))
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("Failed to read session file: {e}"))?;
+        cursor.offset = start + read as u64;
+
+        if cursor.unterminated && !buf.is_empty() {
+            if buf[0] != b'\n' {
+                // The line counted as complete went on; only a full parse
+                // sees it the way readers of the whole file do
+                return SessionCursor::default().advance(path);
+            }
+            buf.remove(0);
+            cursor.unterminated = false;
+        }
+
+        let mut update = CursorUpdate {
+            cursor,
+            reset: !resume,
+            entries: Vec::new(),
+            base_offset: self.offset,
+            base_hash: self.header_hash.clone(),
+            header,
+            modified,
+            all_texts: Vec::new(),
+            user_texts: Vec::new(),
+            assistant_texts: Vec::new(),
+        };
+
+        let complete = buf
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |end| end + 1);
+        for line in buf[..complete].split_inclusive(|&b| b == b'\n') {
+            update.cursor.line_count += 1;
+            update.consume(line);
+        }
+        let tail = &buf[complete..];
+        if !tail.is_empty() && serde_json::from_slice::<Value>(tail).is_ok() {
+            // A finished line without its newline (rename_session writes
+            // files this way); readers of the whole file count it
+            update.cursor.line_count += 1;
+            update.consume(tail);
+            update.cursor.unterminated = true;
+        } else {
+            update.cursor.partial = tail.to_vec();
+            if complete > 0 {
+                update.cursor.unterminated = false;
+            }
+        }
+
+        Ok(update)
+    }
+}
+
+impl CursorUpdate {
+    /// Whether this update's entries are exactly what follows `stored`
+    pub fn extends(&self, stored: Option<&SessionCursor>) -> bool {
+        self.reset
+            || stored
+                .is_some_and(|c| c.offset == self.base_offset && c.header_hash == self.base_hash)
+    }
+
+    /// Bytes of the file this update advanced over
+    pub fn bytes_parsed(&self) -> u64 {
+        if self.reset {
+            self.cursor.offset
+        } else {
+            self.cursor.offset.saturating_sub(self.base_offset)
+        }
+    }
+
+    /// Session info for the file, extending `previous` (the info the
+    /// advanced cursor was saved with) unless parsing started over
+    pub fn session_info(&self, path: &Path, previous: Option<&SessionInfo>) -> SessionInfo {
+        let previous = previous.filter(|_| !self.reset);
+        let extend = |before: Option<&String>, texts: &[String]| {
+            let mut text = before.cloned().unwrap_or_default();
+            for t in texts {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(t);
+            }
+            text
+        };
+        let state = &self.cursor.state;
+        SessionInfo {
+            path: path.to_string_lossy().to_string(),
+            id: self.header.id.clone(),
+            cwd: self.header.cwd.clone(),
+            name: state.name.clone(),
+            created: self.header.created,
+            modified: self.modified,
+            message_count: state.message_count,
+            first_message: state.first_message.clone(),
+            all_messages_text: extend(previous.map(|p| &p.all_messages_text), &self.all_texts),
+            user_messages_text: extend(previous.map(|p| &p.user_messages_text), &self.user_texts),
+            assistant_messages_text: extend(
+                previous.map(|p| &p.assistant_messages_text),
+                &self.assistant_texts,
+            ),
+            last_message: state.last_message.clone(),
+            last_message_role: state.last_message_role.clone(),
+        }
+    }
+
+    fn consume(&mut self, line: &[u8]) {
+        let Ok(value) = serde_json::from_slice::<Value>(line) else {
+            return;
+        };
+        let state = &mut self.cursor.state;
+        if value["type"] == "session_info" {
+            if let Some(n) = value["name"].as_str() {
+                state.name = Some(n.trim().to_string());
+            }
+        }
+        let Some(entry) = session_parser::parse_entry(&value) else {
+            return;
+        };
+
+        let counts = match entry.entry_type.as_str() {
+            "message" => entry.message.as_ref().and_then(|m| match m.role.as_str() {
+                "user" => Some(Counted::User),
+                "assistant" => Some(Counted::Assistant),
+                "toolResult" => Some(Counted::ToolResult),
+                _ => None,
+            }),
+            "compaction" => Some(Counted::Compaction),
+            "branch_summary" => Some(Counted::BranchSummary),
+            "custom_message" => Some(Counted::CustomMessage),
+            _ => None,
+        };
+        state.nodes.push(CursorNode {
+            id: entry.id.clone(),
+            parent_id: entry.parent_id.clone(),
+            counts,
+        });
+
+        if entry.entry_type != "message" {
+            return;
+        }
+        if let Some(ts) = entry.timestamp {
+            state.first_message_time = Some(state.first_message_time.map_or(ts, |t| t.min(ts)));
+            state.last_message_time = Some(state.last_message_time.map_or(ts, |t| t.max(ts)));
+        }
+
+        let Some(message) = &entry.message else {
+            return;
+        };
+        if message.role == "assistant" {
+            if let Some(model) = &message.model {
+                state.models.insert(match &message.provider {
+                    Some(provider) => format!("{provider}/{model}"),
+                    None => model.clone(),
+                });
+            }
+            if let Some(usage) = &message.usage {
+                state.input_tokens += usage.input;
+                state.output_tokens += usage.output;
+                state.cache_read_tokens += usage.cache_read;
+                state.cache_write_tokens += usage.cache_write;
+                if let Some(cost) = &usage.cost {
+                    state.input_cost += cost.input;
+                    state.output_cost += cost.output;
+                    state.cache_read_cost += cost.cache_read;
+                    state.cache_write_cost += cost.cache_write;
+                }
+            }
+        }
+        if message.role != "user" && message.role != "assistant" {
+            return;
+        }
+
+        state.message_count += 1;
+        let text = message.text();
+        if text.is_empty() {
+            return;
+        }
+        if state.first_message.is_empty() && message.role == "user" {
+            state.first_message = text.chars().take(100).collect();
+        }
+        state.last_message = text.chars().take(150).collect();
+        state.last_message_role = message.role.clone();
+        self.all_texts.push(text.clone());
+        if message.role == "user" {
+            self.user_texts.push(text);
+        } else {
+            self.assistant_texts.push(text);
+        }
+        self.entries.push(entry);
+    }
+}
+
+impl CursorState {
+    /// `SessionDetails` of the consumed entries; matches
+    /// `session_parser::details_from_tree` on the same lines
+    pub fn details(&self) -> SessionDetails {
+        let nodes = &self.nodes;
+        let linear = nodes.iter().all(|n| n.parent_id.is_none());
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(nodes.len());
+        for (i, node) in nodes.iter().enumerate() {
+            let parent = if linear {
+                i.checked_sub(1)
+            } else {
+                node.parent_id
+                    .as_deref()
+                    .and_then(|id| index.get(id).copied())
+            };
+            parents.push(parent);
+            if !node.id.is_empty() {
+                index.insert(&node.id, i);
+            }
+        }
+
+        let mut details = SessionDetails {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+            cache_write_tokens: self.cache_write_tokens,
+            input_cost: self.input_cost,
+            output_cost: self.output_cost,
+            cache_read_cost: self.cache_read_cost,
+            cache_write_cost: self.cache_write_cost,
+            models: self.models.iter().cloned().collect(),
+            first_message_time: self.first_message_time,
+            last_message_time: self.last_message_time,
+            ..SessionDetails::default()
+        };
+        // Parents always come earlier in the file, so the walk ends
+        let mut current = nodes.len().checked_sub(1);
+        while let Some(i) = current {
+            match nodes[i].counts {
+                Some(Counted::User) => details.user_messages += 1,
+                Some(Counted::Assistant) => details.assistant_messages += 1,
+                Some(Counted::ToolResult) => details.tool_results += 1,
+                Some(Counted::CustomMessage) => details.custom_messages += 1,
+                Some(Counted::Compaction) => details.compactions += 1,
+                Some(Counted::BranchSummary) => details.branch_summaries += 1,
+                None => {}
+            }
+            current = parents[i];
+        }
+        details
+    }
+}
+
+/// Lines of a session file from `from_line` on, as `(total_lines, content)`.
+/// A stored cursor for the file lets the read start at its byte offset
+/// rather than the top. An unfinished last line is left out until pi
+/// completes it.
+pub fn read_lines_from(
+    path: &Path,
+    from_line: usize,
+    hint: Option<&SessionCursor>,
+) -> Result<(usize, String), String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to read session file: {e}"))?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to read session file: {e}"))?
+        .len();
+
+    let (mut line, start) = match hint {
+        Some(c)
+            if !c.unterminated
+                && c.line_count as usize <= from_line
+                && c.consumed() <= len
+                && read_header(&mut file)
+                    .is_ok_and(|(header, _)| sha256_hex(&header) == c.header_hash) =>
+        {
+            (c.line_count as usize, c.consumed())
+        }
+        _ => (0, 0),
+    };
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("Failed to read session file: {e}"))?;
+
+    let mut reader = BufReader::new(file);
+    let mut lines = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let n = reader
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| format!("Failed to read session file: {e}"))?;
+        if n == 0 {
+            break;
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        } else if serde_json::from_slice::<Value>(&buf).is_err() {
+            break;
+        }
+        if line >= from_line {
+            lines.push(String::from_utf8_lossy(&buf).into_owned());
+        }
+        line += 1;
+    }
+
+    Ok((line, lines.join("\n")))
+}
+
+/// Stable identity of a session: the header `id`, or for a header without
+/// one a hash of the header minus the `name` that renames rewrite. Either
+/// survives moving or renaming the file.
+fn session_identity(header: &Value) -> String {
+    if let Some(id) = header["id"].as_str().filter(|id| !id.is_empty()) {
+        return id.to_string();
+    }
+    let mut header = header.clone();
+    if let Some(fields) = header.as_object_mut() {
+        fields.remove("name");
+    }
+    format!(
+        "sha256-{}",
+        &sha256_hex(header.to_string().as_bytes())[..32]
+    )
+}
+
+/// Identity of the session file at `path`, from its header alone
+pub fn read_session_id(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {e}"))?;
+    let (header_line, _) = read_header(&mut file)?;
+    let header: Value =
+        serde_json::from_slice(&header_line).map_err(|e| format!("Failed to parse header: {e}"))?;
+    if header["type"] != "session" {
+        return Err("Invalid session header".to_string());
+    }
+    Ok(session_identity(&header))
+}
+
+/// First line of the file without its newline, and the bytes it takes up
+fn read_header(file: &mut File) -> Result<(Vec<u8>, u64), String> {
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to read header: {e}"))?;
+    let mut line = Vec::new();
+    BufReader::new(&mut *file)
+        .read_until(b'\n', &mut line)
+        .map_err(|e| format!("Failed to read header: {e}"))?;
+    if line.is_empty() {
+        return Err("Empty session file".to_string());
+    }
+    let len = line.len() as u64;
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok((line, len))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
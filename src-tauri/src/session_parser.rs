@@ -1,88 +1,252 @@
+use crate::models::{
+    BranchSummary, Compaction, Content, CustomMessage, Message, ModelChange, SessionEntry, Usage,
+    UsageCost,
+};
+use crate::session_tree::SessionTree;
+use base64::Engine;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 
+/// Parse every entry of a session file; unparseable lines are skipped
+pub fn parse_entries(jsonl_content: &str) -> Vec<SessionEntry> {
+    jsonl_content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| parse_entry(&value))
+        .collect()
+}
+
+/// Convert one raw session line into a `SessionEntry`.
+///
+/// Returns `None` for the `session` header. Lines without a valid timestamp
+/// are kept with `timestamp: None`; unknown entry types are kept with only
+/// the common fields set.
+pub fn parse_entry(value: &Value) -> Option<SessionEntry> {
+    let entry_type = value["type"].as_str()?;
+    if entry_type == "session" {
+        return None;
+    }
+    let id = str_field(value, "id");
+    let timestamp = parse_timestamp(&value["timestamp"]);
+
+    let mut entry = SessionEntry {
+        entry_type: entry_type.to_string(),
+        id,
+        parent_id: value["parentId"].as_str().map(String::from),
+        timestamp,
+        message: None,
+        compaction: None,
+        branch_summary: None,
+        custom_message: None,
+        model_change: None,
+    };
+
+    match entry_type {
+        "message" => entry.message = value.get("message").and_then(parse_message),
+        "compaction" => {
+            entry.compaction = Some(Compaction {
+                summary: str_field(value, "summary"),
+                first_kept_entry_id: value["firstKeptEntryId"].as_str().map(String::from),
+                tokens_before: value["tokensBefore"].as_u64(),
+            })
+        }
+        "branch_summary" => {
+            entry.branch_summary = Some(BranchSummary {
+                from_id: value["fromId"].as_str().map(String::from),
+                summary: str_field(value, "summary"),
+            })
+        }
+        "custom_message" => {
+            entry.custom_message = Some(CustomMessage {
+                custom_type: str_field(value, "customType"),
+                content: parse_content(&value["content"]),
+                display: value["display"].as_bool().unwrap_or(true),
+            })
+        }
+        "model_change" => {
+            entry.model_change = value["modelId"].as_str().map(|model_id| ModelChange {
+                provider: value["provider"].as_str().map(String::from),
+                model_id: model_id.to_string(),
+            })
+        }
+        _ => {}
+    }
+
+    Some(entry)
+}
+
+fn parse_message(message: &Value) -> Option<Message> {
+    let role = message["role"].as_str()?;
+
+    let content = match role {
+        "toolResult" => vec![Content::ToolResult {
+            tool_call_id: str_field(message, "toolCallId"),
+            is_error: message["isError"].as_bool().unwrap_or(false),
+            output: join_text(&parse_content(&message["content"])),
+        }],
+        "bashExecution" => {
+            let command = str_field(message, "command");
+            let output = str_field(message, "output");
+            vec![Content::ToolResult {
+                tool_call_id: String::new(),
+                is_error: message["exitCode"].as_i64().is_some_and(|code| code != 0),
+                output: format!("$ {command}\n{output}"),
+            }]
+        }
+        _ => parse_content(&message["content"]),
+    };
+
+    Some(Message {
+        role: role.to_string(),
+        content,
+        provider: message["provider"].as_str().map(String::from),
+        model: message["model"].as_str().map(String::from),
+        usage: message.get("usage").map(parse_usage),
+    })
+}
+
+/// Message content is either a plain string or an array of typed parts
+pub fn parse_content(content: &Value) -> Vec<Content> {
+    if let Some(text) = content.as_str() {
+        return vec![Content::Text {
+            text: text.to_string(),
+        }];
+    }
+    let Some(items) = content.as_array() else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .filter_map(|item| match item["type"].as_str()? {
+            "text" => Some(Content::Text {
+                text: str_field(item, "text"),
+            }),
+            "thinking" => Some(Content::Thinking {
+                thinking: str_field(item, "thinking"),
+            }),
+            "toolCall" => Some(Content::ToolCall {
+                name: str_field(item, "name"),
+                id: str_field(item, "id"),
+                arguments: item.get("arguments").cloned().unwrap_or(Value::Null),
+            }),
+            "image" => {
+                let data = item["data"].as_str().unwrap_or("");
+                let size = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .map(|bytes| bytes.len())
+                    .unwrap_or(data.len() * 3 / 4);
+                Some(Content::Image {
+                    mime: str_field(item, "mimeType"),
+                    size,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_usage(usage: &Value) -> Usage {
+    let cost = usage.get("cost").map(|cost| UsageCost {
+        input: cost["input"].as_f64().unwrap_or(0.0),
+        output: cost["output"].as_f64().unwrap_or(0.0),
+        cache_read: cost["cacheRead"].as_f64().unwrap_or(0.0),
+        cache_write: cost["cacheWrite"].as_f64().unwrap_or(0.0),
+    });
+    Usage {
+        input: usage["input"].as_u64().unwrap_or(0),
+        output: usage["output"].as_u64().unwrap_or(0),
+        cache_read: usage["cacheRead"].as_u64().unwrap_or(0),
+        cache_write: usage["cacheWrite"].as_u64().unwrap_or(0),
+        cost,
+    }
+}
+
+fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.as_str()?)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn str_field(value: &Value, key: &str) -> String {
+    value[key].as_str().unwrap_or("").to_string()
+}
+
+fn join_text(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(Content::as_text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Parse session file to extract detailed statistics
 pub fn parse_session_details(jsonl_content: &str) -> SessionDetails {
+    details_from_tree(&SessionTree::build(parse_entries(jsonl_content)))
+}
+
+/// Compute statistics from a session tree.
+///
+/// Message and entry counts only cover the active branch so rewound turns
+/// are not counted twice; tokens and cost include abandoned branches since
+/// that usage was still spent.
+pub fn details_from_tree(tree: &SessionTree) -> SessionDetails {
     let mut details = SessionDetails::default();
     let mut model_set: std::collections::HashSet<String> = std::collections::HashSet::new();
-    let mut first_message_time: Option<chrono::DateTime<chrono::Utc>> = None;
-    let mut last_message_time: Option<chrono::DateTime<chrono::Utc>> = None;
+    let mut first_message_time: Option<DateTime<Utc>> = None;
+    let mut last_message_time: Option<DateTime<Utc>> = None;
 
-    for line in jsonl_content.lines() {
-        if line.trim().is_empty() {
-            continue;
-        }
+    for node in &tree.nodes {
+        let entry = &node.entry;
+        let active = node.on_active_path;
+        match entry.entry_type.as_str() {
+            "message" => {
+                if let Some(message) = &entry.message {
+                    match message.role.as_str() {
+                        "user" if active => details.user_messages += 1,
+                        "assistant" => {
+                            if active {
+                                details.assistant_messages += 1;
+                            }
 
-        if let Ok(value) = serde_json::from_str::<Value>(line) {
-            let entry_type = value["type"].as_str().unwrap_or("unknown");
-
-            if entry_type == "message" {
-                if let Some(message) = value.get("message") {
-                    let role = message["role"].as_str().unwrap_or("unknown");
-
-                    // Count messages by role
-                    if role == "user" {
-                        details.user_messages += 1;
-                    } else if role == "assistant" {
-                        details.assistant_messages += 1;
-
-                        // Extract model and provider
-                        if let Some(model) = message["model"].as_str() {
-                            let provider = message["provider"].as_str().unwrap_or("unknown");
-                            let model_name = if provider != "unknown" {
-                                format!("{provider}/{model}")
-                            } else {
-                                model.to_string()
-                            };
-                            model_set.insert(model_name);
-                        }
+                            if let Some(model) = &message.model {
+                                let model_name = match &message.provider {
+                                    Some(provider) => format!("{provider}/{model}"),
+                                    None => model.clone(),
+                                };
+                                model_set.insert(model_name);
+                            }
 
-                        // Extract token usage
-                        if let Some(usage) = message.get("usage") {
-                            let input = usage["input"].as_u64().unwrap_or(0);
-                            let output = usage["output"].as_u64().unwrap_or(0);
-                            let cache_read = usage["cacheRead"].as_u64().unwrap_or(0);
-                            let cache_write = usage["cacheWrite"].as_u64().unwrap_or(0);
-
-                            details.input_tokens += input;
-                            details.output_tokens += output;
-                            details.cache_read_tokens += cache_read;
-                            details.cache_write_tokens += cache_write;
-
-                            // Extract cost
-                            if let Some(cost) = usage.get("cost") {
-                                let input_cost = cost["input"].as_f64().unwrap_or(0.0);
-                                let output_cost = cost["output"].as_f64().unwrap_or(0.0);
-                                let cache_read_cost = cost["cacheRead"].as_f64().unwrap_or(0.0);
-                                let cache_write_cost = cost["cacheWrite"].as_f64().unwrap_or(0.0);
-
-                                details.input_cost += input_cost;
-                                details.output_cost += output_cost;
-                                details.cache_read_cost += cache_read_cost;
-                                details.cache_write_cost += cache_write_cost;
+                            if let Some(usage) = &message.usage {
+                                details.input_tokens += usage.input;
+                                details.output_tokens += usage.output;
+                                details.cache_read_tokens += usage.cache_read;
+                                details.cache_write_tokens += usage.cache_write;
+
+                                if let Some(cost) = &usage.cost {
+                                    details.input_cost += cost.input;
+                                    details.output_cost += cost.output;
+                                    details.cache_read_cost += cost.cache_read;
+                                    details.cache_write_cost += cost.cache_write;
+                                }
                             }
                         }
-                    } else if role == "toolResult" {
-                        details.tool_results += 1;
+                        "toolResult" if active => details.tool_results += 1,
+                        _ => {}
                     }
                 }
 
                 // Track message timestamps
-                if let Some(timestamp_str) = value["timestamp"].as_str() {
-                    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(timestamp_str) {
-                        let utc_time = dt.with_timezone(&chrono::Utc);
-                        first_message_time =
-                            Some(first_message_time.unwrap_or(utc_time).min(utc_time));
-                        last_message_time =
-                            Some(last_message_time.unwrap_or(utc_time).max(utc_time));
-                    }
+                if let Some(ts) = entry.timestamp {
+                    first_message_time = Some(first_message_time.map_or(ts, |t| t.min(ts)));
+                    last_message_time = Some(last_message_time.map_or(ts, |t| t.max(ts)));
                 }
-            } else if entry_type == "compaction" {
-                details.compactions += 1;
-            } else if entry_type == "branch_summary" {
-                details.branch_summaries += 1;
-            } else if entry_type == "custom_message" {
-                details.custom_messages += 1;
             }
+            "compaction" if active => details.compactions += 1,
+            "branch_summary" if active => details.branch_summaries += 1,
+            "custom_message" if active => details.custom_messages += 1,
+            _ => {}
         }
     }
 
@@ -93,7 +257,7 @@ pub fn parse_session_details(jsonl_content: &str) -> SessionDetails {
     details
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct SessionDetails {
     pub user_messages: usize,
     pub assistant_messages: usize,
@@ -0,0 +1,377 @@
+use crate::auth::{Caller, Scope};
+use crate::command_registry::{CommandHost, TerminalCreateRequest};
+use crate::models::{SessionEntry, SessionInfo};
+use axum::http::StatusCode;
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+/// Event carrying newly appended entries of a subscribed session
+pub const APPENDED_EVENT: &str = "session-appended";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Follows a session file from a byte offset, parsing lines as pi completes them
+pub struct SessionTail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl SessionTail {
+    pub fn new(path: impl Into<PathBuf>, offset: u64) -> Self {
+        Self {
+            path: path.into(),
+            offset,
+        }
+    }
+
+    /// Start at the current end of the file, so only new entries are reported
+    pub fn at_end(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let offset = std::fs::metadata(&path)
+            .map_err(|e| format!("Failed to stat session file: {e}"))?
+            .len();
+        Ok(Self::new(path, offset))
+    }
+
+    /// Byte offset just past the last complete line read
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Entries from lines completed since the last call; `None` when there
+    /// are none. A partial last line is left for the next call. If the file
+    /// shrank (rewritten or truncated) reading restarts from the top and the
+    /// batch is marked as a reset.
+    pub fn poll(&mut self) -> Result<Option<TailBatch>, String> {
+        let mut file =
+            File::open(&self.path).map_err(|e| format!("Failed to open session file: {e}"))?;
+        let len = file
+            .metadata()
+            .map_err(|e| format!("Failed to stat session file: {e}"))?
+            .len();
+        let reset = len < self.offset;
+        if reset {
+            self.offset = 0;
+        }
+        if len == self.offset && !reset {
+            return Ok(None);
+        }
+
+        let mut buf = Vec::with_capacity((len - self.offset) as usize);
+        file.seek(SeekFrom::Start(self.offset))
+            .and_then(|_| file.take(len - self.offset).read_to_end(&mut buf))
+            .map_err(|e| format!("Failed to read session file: {e}"))?;
+        let complete = buf
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |end| end + 1);
+        self.offset += complete as u64;
+
+        let entries = buf[..complete]
+            .split(|&b| b == b'\n')
+            .filter_map(|line| serde_json::from_slice::<Value>(line).ok())
+            .filter_map(|value| crate::session_parser::parse_entry(&value))
+            .collect::<Vec<_>>();
+        if entries.is_empty() && !reset {
+            return Ok(None);
+        }
+        Ok(Some(TailBatch {
+            entries,
+            reset,
+            offset: self.offset,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct TailBatch {
+    pub entries: Vec<SessionEntry>,
+    /// The file was rewritten; earlier entries the subscriber holds are stale
+    pub reset: bool,
+    /// Resume point for a later subscription
+    pub offset: u64,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionAppended {
+    pub path: String,
+    pub session_id: String,
+    pub offset: u64,
+    pub reset: bool,
+    pub entries: Vec<SessionEntry>,
+}
+
+/// Payload of `subscribe_session` / `unsubscribe_session`, and the query of
+/// `/api/events?session=`
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SubscribeRequest {
+    /// Session path or id
+    #[serde(alias = "path", alias = "sessionId")]
+    pub session: String,
+    /// Byte offset to resume from; the end of the file when omitted
+    #[serde(default)]
+    pub offset: Option<u64>,
+}
+
+/// Reply to `subscribe_session`
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscribed {
+    pub path: String,
+    pub session_id: String,
+    /// Byte offset streaming starts from
+    pub offset: u64,
+}
+
+/// Query of `/api/events`; with `session` set only that session's appended
+/// entries are streamed
+#[derive(Debug, Default, Deserialize)]
+pub struct EventsQuery {
+    pub session: Option<String>,
+    pub offset: Option<u64>,
+}
+
+/// SSE response following one session for `/api/events?session=`
+pub async fn sse_response(caller: Caller, session: &str, offset: Option<u64>) -> Response {
+    let appended = match resolve(&caller, session).await {
+        Ok(info) => follow(caller.clone(), info, offset),
+        Err(e) => Err(e),
+    };
+    let appended = match appended {
+        Ok(appended) => appended,
+        Err(e) => {
+            let (status, outcome) = if e.starts_with("Forbidden") {
+                (StatusCode::FORBIDDEN, "denied")
+            } else if e.starts_with("Session not found") {
+                (StatusCode::NOT_FOUND, "error")
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, "error")
+            };
+            caller.audit("subscribe_session", outcome, Some(&e));
+            return (status, e).into_response();
+        }
+    };
+    caller.audit("subscribe_session", "ok", None);
+    let events = appended.map(|payload| {
+        Ok::<_, Infallible>(
+            SseEvent::default()
+                .event(APPENDED_EVENT)
+                .data(payload.to_string()),
+        )
+    });
+    (
+        [
+            ("access-control-allow-origin", "*"),
+            ("cache-control", "no-cache"),
+        ],
+        Sse::new(events).keep_alive(KeepAlive::default()),
+    )
+        .into_response()
+}
+
+/// Look up a session the caller may read by path or id. Only known session
+/// files can be followed.
+pub async fn resolve(caller: &Caller, session: &str) -> Result<SessionInfo, String> {
+    if !caller.allows(Scope::Read) {
+        return Err("Forbidden: subscribe_session requires the read scope".to_string());
+    }
+    let info = crate::scanner::scan_sessions()
+        .await?
+        .into_iter()
+        .find(|s| s.path == session || s.id == session)
+        .ok_or_else(|| format!("Session not found: {session}"))?;
+    if !caller.allows_cwd(&info.cwd) {
+        return Err("Forbidden: session is outside the token's allowed paths".to_string());
+    }
+    Ok(info)
+}
+
+/// Appended entries of one session as `SessionAppended` payloads, redacted
+/// for the caller. Ends when the file goes away or the caller's token expires.
+pub fn follow(
+    caller: Caller,
+    session: SessionInfo,
+    offset: Option<u64>,
+) -> Result<impl Stream<Item = Value> + Send, String> {
+    let tail = match offset {
+        Some(offset) => SessionTail::new(&session.path, offset),
+        None => SessionTail::at_end(&session.path)?,
+    };
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    Ok(stream::unfold(
+        (tail, interval, caller, session),
+        |(mut tail, mut interval, caller, session)| async move {
+            loop {
+                interval.tick().await;
+                if caller.grant.as_ref().is_some_and(|g| g.is_expired()) {
+                    return None;
+                }
+                let batch = match tail.poll() {
+                    Ok(Some(batch)) => batch,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::debug!("Stopped following {}: {e}", session.path);
+                        return None;
+                    }
+                };
+                let payload = SessionAppended {
+                    path: session.path.clone(),
+                    session_id: session.id.clone(),
+                    offset: batch.offset,
+                    reset: batch.reset,
+                    entries: batch.entries,
+                };
+                let value = serde_json::to_value(payload).unwrap_or(Value::Null);
+                let value = crate::redaction::redact_remote_value(&caller.ip, value);
+                return Some((value, (tail, interval, caller, session)));
+            }
+        },
+    ))
+}
+
+/// Live tails opened by one WebSocket connection. It is the command host for
+/// that connection: `subscribe_session` and `unsubscribe_session` land here,
+/// everything else goes to the app's host. Appended entries arrive on the
+/// receiver returned by [`Subscriptions::new`]; dropping the set stops them.
+pub struct Subscriptions {
+    caller: Caller,
+    host: Arc<dyn CommandHost>,
+    tx: mpsc::UnboundedSender<Value>,
+    tasks: Mutex<HashMap<String, AbortHandle>>,
+}
+
+impl Subscriptions {
+    pub fn new(
+        caller: Caller,
+        host: Arc<dyn CommandHost>,
+    ) -> (Arc<Self>, mpsc::UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let subscriptions = Self {
+            caller,
+            host,
+            tx,
+            tasks: Mutex::new(HashMap::new()),
+        };
+        (Arc::new(subscriptions), rx)
+    }
+
+    async fn subscribe(&self, request: SubscribeRequest) -> Result<Subscribed, String> {
+        let session = resolve(&self.caller, &request.session).await?;
+        let path = session.path.clone();
+        let session_id = session.id.clone();
+        let offset = match request.offset {
+            Some(offset) => offset,
+            None => SessionTail::at_end(&path)?.offset(),
+        };
+        let mut appended = Box::pin(follow(self.caller.clone(), session, Some(offset))?);
+        let tx = self.tx.clone();
+        let task = tokio::spawn(async move {
+            while let Some(payload) = appended.next().await {
+                if tx.send(payload).is_err() {
+                    break;
+                }
+            }
+        });
+        let previous = self
+            .tasks
+            .lock()
+            .map_err(|e| format!("Subscriptions lock poisoned: {e}"))?
+            .insert(path.clone(), task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        Ok(Subscribed {
+            path,
+            session_id,
+            offset,
+        })
+    }
+
+    async fn unsubscribe(&self, request: SubscribeRequest) -> Result<bool, String> {
+        let followed = |key: &str| self.tasks.lock().is_ok_and(|tasks| tasks.contains_key(key));
+        let key = if followed(&request.session) {
+            request.session
+        } else {
+            resolve(&self.caller, &request.session).await?.path
+        };
+        let removed = self
+            .tasks
+            .lock()
+            .map_err(|e| format!("Subscriptions lock poisoned: {e}"))?
+            .remove(&key);
+        if let Some(task) = &removed {
+            task.abort();
+        }
+        Ok(removed.is_some())
+    }
+}
+
+impl CommandHost for Subscriptions {
+    fn emit(&self, event: &str, payload: Value) {
+        self.host.emit(event, payload)
+    }
+
+    fn session_paths_changed(&self) {
+        self.host.session_paths_changed()
+    }
+
+    fn terminal_create(&self, request: TerminalCreateRequest) -> Result<String, String> {
+        self.host.terminal_create(request)
+    }
+
+    fn terminal_write(&self, id: &str, data: String) -> Result<(), String> {
+        self.host.terminal_write(id, data)
+    }
+
+    fn terminal_resize(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
+        self.host.terminal_resize(id, rows, cols)
+    }
+
+    fn terminal_close(&self, id: &str) -> Result<(), String> {
+        self.host.terminal_close(id)
+    }
+
+    fn shells(&self) -> Result<Vec<(String, String)>, String> {
+        self.host.shells()
+    }
+
+    fn subscribe_session(
+        &self,
+        request: SubscribeRequest,
+    ) -> BoxFuture<'_, Result<Subscribed, String>> {
+        Box::pin(self.subscribe(request))
+    }
+
+    fn unsubscribe_session(
+        &self,
+        request: SubscribeRequest,
+    ) -> BoxFuture<'_, Result<bool, String>> {
+        Box::pin(self.unsubscribe(request))
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        if let Ok(tasks) = self.tasks.get_mut() {
+            for task in tasks.values() {
+                task.abort();
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::{BranchSummary, SessionEntry};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Branching structure of a session.
+///
+/// pi appends every entry with a `parentId`; rewinding or forking simply
+/// appends the next entry under an earlier parent, so one file can hold
+/// several branches. The active leaf is the last entry written.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SessionTree {
+    /// Entries in file order
+    pub nodes: Vec<TreeNode>,
+    pub roots: Vec<String>,
+    pub active_leaf: Option<String>,
+    /// Ids from the root to the active leaf
+    pub active_path: Vec<String>,
+    /// Abandoned branches, in the order their leaves were written
+    pub branches: Vec<Branch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TreeNode {
+    pub entry: SessionEntry,
+    /// Resolved parent (differs from `entry.parent_id` for linear legacy
+    /// sessions and dangling parents)
+    pub parent: Option<String>,
+    pub children: Vec<String>,
+    pub depth: usize,
+    pub on_active_path: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Branch {
+    /// Entry on the active path the branch diverges from (`None` if the
+    /// branch has its own root)
+    pub fork_id: Option<String>,
+    pub leaf_id: String,
+    /// Ids from the first entry after the fork down to the leaf
+    pub entry_ids: Vec<String>,
+    pub message_count: usize,
+    /// Summary written when the user navigated away from this branch
+    pub summary: Option<BranchSummary>,
+}
+
+impl SessionTree {
+    /// Build the tree from parsed entries (see `session_parser::parse_entries`).
+    ///
+    /// Sessions written before parent links existed are treated as a single
+    /// linear branch; entries whose parent is missing become roots.
+    pub fn build(entries: Vec<SessionEntry>) -> Self {
+        let linear = entries.iter().all(|e| e.parent_id.is_none());
+
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let parent = if linear {
+                i.checked_sub(1)
+            } else {
+                entry
+                    .parent_id
+                    .as_ref()
+                    .and_then(|id| index.get(id).copied())
+            };
+            parents.push(parent);
+            if !entry.id.is_empty() {
+                index.insert(entry.id.clone(), i);
+            }
+        }
+
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); entries.len()];
+        let mut depths = vec![0usize; entries.len()];
+        for (i, parent) in parents.iter().enumerate() {
+            if let Some(p) = *parent {
+                children[p].push(i);
+                depths[i] = depths[p] + 1;
+            }
+        }
+
+        let path_to = |mut i: usize| {
+            let mut path = vec![i];
+            while let Some(p) = parents[i] {
+                path.push(p);
+                i = p;
+            }
+            path.reverse();
+            path
+        };
+
+        let active_leaf = entries.len().checked_sub(1);
+        let active_path = active_leaf.map(path_to).unwrap_or_default();
+        let on_active: HashSet<usize> = active_path.iter().copied().collect();
+
+        let summaries: Vec<&BranchSummary> = entries
+            .iter()
+            .filter_map(|e| e.branch_summary.as_ref())
+            .collect();
+
+        let branches = (0..entries.len())
+            .filter(|&i| children[i].is_empty() && Some(i) != active_leaf)
+            .map(|leaf| {
+                let mut ids = Vec::new();
+                let mut fork = None;
+                let mut cur = Some(leaf);
+                while let Some(i) = cur {
+                    if on_active.contains(&i) {
+                        fork = Some(i);
+                        break;
+                    }
+                    ids.push(i);
+                    cur = parents[i];
+                }
+                ids.reverse();
+
+                let entry_ids: Vec<String> = ids.iter().map(|&i| entries[i].id.clone()).collect();
+                let summary = summaries
+                    .iter()
+                    .rev()
+                    .find(|s| s.from_id.as_ref().is_some_and(|id| entry_ids.contains(id)))
+                    .map(|s| (*s).clone());
+
+                Branch {
+                    fork_id: fork.map(|i| entries[i].id.clone()),
+                    leaf_id: entries[leaf].id.clone(),
+                    message_count: ids
+                        .iter()
+                        .filter(|&&i| entries[i].message.is_some())
+                        .count(),
+                    entry_ids,
+                    summary,
+                }
+            })
+            .collect();
+
+        let id_of = |i: &usize| entries[*i].id.clone();
+        let roots = (0..entries.len())
+            .filter(|&i| parents[i].is_none())
+            .map(|i| id_of(&i))
+            .collect();
+        let active_path_ids = active_path.iter().map(id_of).collect();
+        let active_leaf_id = active_leaf.map(|i| id_of(&i));
+        let child_ids: Vec<Vec<String>> = children
+            .iter()
+            .map(|c| c.iter().map(id_of).collect())
+            .collect();
+        let parent_ids: Vec<Option<String>> =
+            parents.iter().map(|p| p.as_ref().map(id_of)).collect();
+
+        let nodes = entries
+            .into_iter()
+            .zip(child_ids)
+            .zip(parent_ids)
+            .enumerate()
+            .map(|(i, ((entry, children), parent))| TreeNode {
+                entry,
+                parent,
+                children,
+                depth: depths[i],
+                on_active_path: on_active.contains(&i),
+            })
+            .collect();
+
+        SessionTree {
+            nodes,
+            roots,
+            active_leaf: active_leaf_id,
+            active_path: active_path_ids,
+            branches,
+        }
+    }
+
+    /// Entries on the active branch, root first
+    pub fn active_entries(&self) -> impl Iterator<Item = &SessionEntry> {
+        self.nodes
+            .iter()
+            .filter(|n| n.on_active_path)
+            .map(|n| &n.entry)
+    }
+
+    /// Entries from the root down to `leaf_id`
+    pub fn path_to(&self, leaf_id: &str) -> Vec<&SessionEntry> {
+        let by_id: HashMap<&str, &TreeNode> = self
+            .nodes
+            .iter()
+            .map(|n| (n.entry.id.as_str(), n))
+            .collect();
+        let mut path = Vec::new();
+        let mut cur = by_id.get(leaf_id).copied();
+        while let Some(node) = cur {
+            path.push(&node.entry);
+            cur = node.parent.as_deref().and_then(|p| by_id.get(p).copied());
+        }
+        path.reverse();
+        path
+    }
+
+    /// Every root-to-leaf path, the active branch first
+    pub fn leaf_paths(&self) -> Vec<Vec<&SessionEntry>> {
+        let mut paths: Vec<Vec<&SessionEntry>> = vec![self.active_entries().collect()];
+        paths.extend(self.branches.iter().map(|b| self.path_to(&b.leaf_id)));
+        paths.retain(|p| !p.is_empty());
+        paths
+    }
+
+    /// Consume the tree, keeping only the active branch
+    pub fn into_active_entries(self) -> Vec<SessionEntry> {
+        self.nodes
+            .into_iter()
+            .filter(|n| n.on_active_path)
+            .map(|n| n.entry)
+            .collect()
+    }
+}
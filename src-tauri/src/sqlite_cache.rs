@@ -1,19 +1,19 @@
-use crate::config::Config;
+use crate::config::{Config, SearchEngine};
 use crate::models::{SessionEntry, SessionInfo};
-use crate::session_parser::SessionDetails;
+use crate::session_cursor::{CursorUpdate, SessionCursor};
+use crate::session_parser::{self, SessionDetails};
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use tracing::{debug, error, info, warn};
 
-/// Current schema version for migrations
-const LATEST_SCHEMA_VERSION: i64 = 2;
-
 pub fn get_db_path() -> Result<PathBuf, String> {
     // Allow explicit test override
     if let Ok(test_db) = std::env::var("PPM_TEST_DB") {
@@ -34,6 +34,13 @@ pub fn get_db_path() -> Result<PathBuf, String> {
     Ok(sessions_dir.join("sessions.db"))
 }
 
+/// The user database beside the cache: tags, favorites, settings, tokens
+/// and config snapshots. Unlike the cache it is never rebuilt
+/// from the session files; a damaged one is restored from a backup.
+pub fn get_user_db_path() -> Result<PathBuf, String> {
+    Ok(crate::migrations::user_db_path(&get_db_path()?))
+}
+
 pub fn init_db() -> Result<Connection, String> {
     let config = Config::load_config().unwrap_or_default();
     init_db_with_config(&config)
@@ -42,161 +49,127 @@ pub fn init_db() -> Result<Connection, String> {
 pub fn init_db_with_config(config: &Config) -> Result<Connection, String> {
     let db_path = get_db_path()?;
 
-    match open_and_init_db(&db_path, config) {
-        Ok(conn) => Ok(conn),
-        Err(e)
-            if e.contains("malformed")
-                || e.contains("disk image")
-                || e.contains("not a database")
-                || e.contains("vtable constructor failed") =>
-        {
-            // Attempt recovery: delete corrupted DB and recreate
+    // The user database is checked on its own first, so a damaged one is
+    // restored from a backup instead of being mistaken for a damaged cache
+    let user_path = crate::migrations::user_db_path(&db_path);
+    let conn = match open_user_db(&user_path) {
+        Ok(()) => match open_and_init_db(&db_path, config) {
+            Ok(conn) => conn,
+            Err(e) if is_corruption_error(&e) => {
+                warn!("[Recovery] Cache database corrupted ({}). Recreating...", e);
+                recover_corrupted_db(&db_path, config)?
+            }
+            Err(e) => return Err(e),
+        },
+        // Anything else (a lock, permissions, a failed migration) is returned
+        // as is; restoring a backup would drop the data added since
+        Err(e) if is_corruption_error(&e) => {
             warn!(
-                "[Recovery] Database corrupted ({}). Deleting and recreating...",
+                "[Recovery] User database unusable ({}). Restoring from backup...",
                 e
             );
-            if db_path.exists() {
-                // Backup corrupted DB before deletion
-                let backup_path = {
-                    let file_name = db_path.file_name().and_then(|s| s.to_str()).unwrap_or("db");
-                    let parent = db_path.parent().unwrap_or_else(|| Path::new("."));
-                    parent.join(format!(
-                        "{}.corrupted.{}",
-                        file_name,
-                        Utc::now().timestamp()
-                    ))
-                };
-                fs::copy(&db_path, &backup_path).map_err(|e| {
-                    format!("Failed to backup corrupted DB to {backup_path:?}: {e}")
-                })?;
-                info!("Backed up corrupted DB to {:?}", backup_path);
-                // Increment recovery counter
-                crate::metrics::inc_corruption_recovery();
-                fs::remove_file(&db_path)
-                    .map_err(|err| format!("Failed to delete corrupted DB: {err}"))?;
-            }
-            open_and_init_db(&db_path, config)
+            recover_user_db(&db_path, config)?
         }
-        Err(e) => Err(e),
-    }
-}
-
-/// Ensure the schema_version table exists and initialize to 0 if empty.
-fn ensure_schema_version_table(conn: &Connection) -> Result<(), String> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS schema_version (
-            version INTEGER NOT NULL
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create schema_version table: {e}"))?;
+        Err(e) => return Err(e),
+    };
 
-    // Check if any row exists; if not, insert version 0.
-    let count: i64 = conn
-        .query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
-        .unwrap_or(0);
-    if count == 0 {
-        conn.execute("INSERT INTO schema_version (version) VALUES (0)", [])
-            .map_err(|e| format!("Failed to insert initial schema version: {e}"))?;
-    }
-    Ok(())
+    sync_search_engine(&conn, config);
+    Ok(conn)
 }
 
-/// Get the current schema version (assumes table exists and has at least one row)
-fn get_current_version(conn: &Connection) -> Result<i64, String> {
-    let version: i64 = conn
-        .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
-            row.get(0)
-        })
-        .map_err(|e| format!("Failed to get schema version: {e}"))?;
-    Ok(version)
+/// Whether an SQLite error means the database file is damaged
+pub fn is_corruption_error(err: &str) -> bool {
+    err.contains("malformed")
+        || err.contains("disk image")
+        || err.contains("not a database")
+        || err.contains("vtable constructor failed")
 }
 
-/// Set the current schema version
-fn set_schema_version(conn: &Connection, version: i64) -> Result<(), String> {
-    conn.execute("UPDATE schema_version SET version = ?", params![version])
-        .map_err(|e| format!("Failed to set schema version: {e}"))?;
+/// Open the user database on its own, switch it to WAL, check its pages
+/// and apply pending user migrations
+pub fn open_user_db(path: &Path) -> Result<(), String> {
+    {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open user database {path:?}: {e}"))?;
+        conn.query_row("PRAGMA journal_mode=WAL;", [], |_| Ok(()))
+            .map_err(|e| format!("User database {path:?} is unreadable: {e}"))?;
+        let check =
+            quick_check(&conn).map_err(|e| format!("User database {path:?} is unreadable: {e}"))?;
+        if check != "ok" {
+            return Err(format!("User database {path:?} is malformed: {check}"));
+        }
+    }
+    crate::migrations::migrate_user_db(path)?;
     Ok(())
 }
 
-/// Run migrations from current_version+1 up to LATEST_SCHEMA_VERSION.
-fn apply_migrations(conn: &Connection, from_version: i64) -> Result<(), String> {
-    let mut current = from_version;
-    while current < LATEST_SCHEMA_VERSION {
-        current += 1;
-        match current {
-            1 => migration_1(conn)?,
-            2 => migration_2(conn)?,
-            _ => return Err(format!("Unknown migration version: {current}")),
-        }
-        // Update version after successful migration
-        set_schema_version(conn, current)?;
+/// Copy a damaged database file aside and remove it with its WAL
+fn move_aside(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("db");
+    let backup_path =
+        path.with_file_name(format!("{file_name}.corrupted.{}", Utc::now().timestamp()));
+    fs::copy(path, &backup_path)
+        .map_err(|e| format!("Failed to backup corrupted DB to {backup_path:?}: {e}"))?;
+    info!("Backed up corrupted DB to {:?}", backup_path);
+    crate::metrics::inc_corruption_recovery();
+    fs::remove_file(path).map_err(|err| format!("Failed to delete corrupted DB: {err}"))?;
+    // A stale WAL would be replayed into the new file
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(path.with_file_name(format!("{file_name}{suffix}")));
     }
     Ok(())
 }
 
-/// Migration to version 1: adds columns that were previously added via ad-hoc ALTER TABLE.
-fn migration_1(conn: &Connection) -> Result<(), String> {
-    // Helper to check if a column exists in a table
-    fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, String> {
-        let mut stmt = conn
-            .prepare(&format!("PRAGMA table_info({table})"))
-            .map_err(|e| format!("Failed to prepare PRAGMA table_info for {table}: {e}"))?;
-        let column_names: Vec<String> = stmt
-            .query_map([], |row| row.get(1))
-            .map_err(|e| format!("Failed to query columns for {table}: {e}"))?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| format!("Failed to collect columns for {table}: {e}"))?;
-        Ok(column_names.iter().any(|name| name == column))
-    }
+/// Move a corrupted cache database aside and create a fresh one. The user
+/// database is left alone; cached sessions are rebuilt by the next scan.
+pub fn recover_corrupted_db(db_path: &Path, config: &Config) -> Result<Connection, String> {
+    move_aside(db_path)?;
+    open_and_init_db(db_path, config)
+}
 
-    // For sessions table
-    if !column_exists(conn, "sessions", "last_message")? {
-        conn.execute("ALTER TABLE sessions ADD COLUMN last_message TEXT", [])
-            .map_err(|e| format!("Failed to add last_message column: {e}"))?;
-    }
-    if !column_exists(conn, "sessions", "last_message_role")? {
-        conn.execute("ALTER TABLE sessions ADD COLUMN last_message_role TEXT", [])
-            .map_err(|e| format!("Failed to add last_message_role column: {e}"))?;
-    }
-    if !column_exists(conn, "sessions", "user_messages_text")? {
-        conn.execute(
-            "ALTER TABLE sessions ADD COLUMN user_messages_text TEXT",
-            [],
-        )
-        .map_err(|e| format!("Failed to add user_messages_text column: {e}"))?;
-    }
-    if !column_exists(conn, "sessions", "assistant_messages_text")? {
-        conn.execute(
-            "ALTER TABLE sessions ADD COLUMN assistant_messages_text TEXT",
-            [],
-        )
-        .map_err(|e| format!("Failed to add assistant_messages_text column: {e}"))?;
+/// Move a damaged user database aside, create an empty one and import the
+/// newest readable backup into it. The cache database is left alone.
+pub fn recover_user_db(db_path: &Path, config: &Config) -> Result<Connection, String> {
+    let user_path = crate::migrations::user_db_path(db_path);
+    move_aside(&user_path)?;
+    open_user_db(&user_path)?;
+    let conn = open_and_init_db(db_path, config)?;
+    match crate::backup::restore_latest(&conn)? {
+        Some(backup) => info!("[Recovery] Restored user data from {:?}", backup),
+        None => warn!("[Recovery] No readable backup; user data starts empty"),
     }
+    Ok(conn)
+}
 
-    // For tags table
-    if !column_exists(conn, "tags", "auto_rules")? {
-        conn.execute("ALTER TABLE tags ADD COLUMN auto_rules TEXT", [])
-            .map_err(|e| format!("Failed to add auto_rules column: {e}"))?;
-    }
-    if !column_exists(conn, "tags", "parent_id")? {
-        conn.execute("ALTER TABLE tags ADD COLUMN parent_id TEXT", [])
-            .map_err(|e| format!("Failed to add parent_id column: {e}"))?;
+/// Open or close the Tantivy index to match `config.search_engine`.
+/// A freshly created index is backfilled from `message_entries`.
+pub(crate) fn sync_search_engine(conn: &Connection, config: &Config) {
+    match config.search_engine {
+        SearchEngine::Tantivy => match crate::tantivy_search::init_index() {
+            Ok(true) => {
+                if let Err(e) = crate::tantivy_search::rebuild_from_db(conn) {
+                    warn!("[Tantivy] Backfill failed: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => warn!("[Tantivy] Failed to open index: {}", e),
+        },
+        SearchEngine::Fts5 => crate::tantivy_search::close_index(),
     }
-
-    Ok(())
 }
 
-/// Migration to version 2: add performance indexes.
-fn migration_2(conn: &Connection) -> Result<(), String> {
-    // Composite index on session_path and timestamp for per-session ordering
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_message_entries_session_time ON message_entries(session_path, timestamp)",
-        [],
-    )
-    .map_err(|e| format!("Migration 2 failed: {e}"))?;
+/// Schema version of an open cache database
+pub fn schema_version(conn: &Connection) -> Result<i64, String> {
+    crate::migrations::current_version(conn, crate::migrations::Schema::Cache)
+}
 
-    Ok(())
+/// Result of `PRAGMA quick_check`; "ok" when the database is healthy
+pub fn quick_check(conn: &Connection) -> Result<String, String> {
+    conn.query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to check database: {e}"))
 }
 
 fn open_and_init_db(db_path: &Path, config: &Config) -> Result<Connection, String> {
@@ -216,107 +189,12 @@ fn open_and_init_db(db_path: &Path, config: &Config) -> Result<Connection, Strin
     conn.execute("PRAGMA foreign_keys=ON;", [])
         .map_err(|e| format!("Failed to enable foreign keys: {e}"))?;
 
-    // Ensure schema_version table exists for migrations
-    ensure_schema_version_table(&conn)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS sessions (
-            id TEXT PRIMARY KEY,
-            path TEXT NOT NULL UNIQUE,
-            cwd TEXT NOT NULL,
-            name TEXT,
-            created TEXT NOT NULL,
-            modified TEXT NOT NULL,
-            file_modified TEXT NOT NULL,
-            message_count INTEGER NOT NULL,
-            first_message TEXT,
-            all_messages_text TEXT,
-            user_messages_text TEXT,
-            assistant_messages_text TEXT,
-            last_message TEXT,
-            last_message_role TEXT,
-            cached_at TEXT NOT NULL,
-            access_count INTEGER DEFAULT 0,
-            last_accessed TEXT
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create table: {e}"))?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS session_details_cache (
-            path TEXT PRIMARY KEY,
-            file_modified TEXT NOT NULL,
-            user_messages INTEGER NOT NULL,
-            assistant_messages INTEGER NOT NULL,
-            input_tokens INTEGER NOT NULL,
-            output_tokens INTEGER NOT NULL,
-            cache_read_tokens INTEGER NOT NULL,
-            cache_write_tokens INTEGER NOT NULL,
-            input_cost REAL NOT NULL,
-            output_cost REAL NOT NULL,
-            cache_read_cost REAL NOT NULL,
-            cache_write_cost REAL NOT NULL,
-            models_json TEXT NOT NULL
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create table session_details_cache: {e}"))?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_modified ON sessions(modified DESC)",
-        [],
-    )
-    .map_err(|e| format!("Failed to create index idx_modified: {e}"))?;
-
-    conn.execute("CREATE INDEX IF NOT EXISTS idx_cwd ON sessions(cwd)", [])
-        .map_err(|e| format!("Failed to create index idx_cwd: {e}"))?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_file_modified ON sessions(file_modified)",
-        [],
-    )
-    .map_err(|e| format!("Failed to create index idx_file_modified: {e}"))?;
-
-    // Create favorites table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS favorites (
-            id TEXT PRIMARY KEY,
-            type TEXT NOT NULL CHECK(type IN ('session', 'project')),
-            name TEXT NOT NULL,
-            path TEXT NOT NULL,
-            added_at TEXT NOT NULL
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create favorites table: {e}"))?;
-
-    // Create tags table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tags (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            color TEXT NOT NULL DEFAULT 'info',
-            icon TEXT,
-            sort_order INTEGER NOT NULL DEFAULT 0,
-            is_builtin INTEGER NOT NULL DEFAULT 0,
-            created_at TEXT NOT NULL
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create tags table: {e}"))?;
+    // Tags, favorites and the other user data live in their own file
+    crate::migrations::attach_user_db(&conn, db_path)?;
+    conn.execute("PRAGMA user.synchronous=NORMAL;", [])
+        .map_err(|e| format!("Failed to set synchronous mode: {e}"))?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS session_tags (
-            session_id TEXT NOT NULL,
-            tag_id TEXT NOT NULL,
-            position INTEGER NOT NULL DEFAULT 0,
-            assigned_at TEXT NOT NULL,
-            PRIMARY KEY (session_id, tag_id)
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create session_tags table: {e}"))?;
+    crate::migrations::migrate(&conn, db_path)?;
 
     // Insert builtin tags based on system language
     let now = Utc::now().to_rfc3339();
@@ -355,58 +233,6 @@ fn open_and_init_db(db_path: &Path, config: &Config) -> Result<Connection, Strin
         ).ok();
     }
 
-    // Create message_entries table for per-message FTS (avoids re-reading files)
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS message_entries (
-            id TEXT PRIMARY KEY,
-            session_path TEXT NOT NULL,
-            role TEXT NOT NULL CHECK(role IN ('user', 'assistant')),
-            content TEXT NOT NULL,
-            timestamp TEXT NOT NULL,
-            FOREIGN KEY (session_path) REFERENCES sessions(path) ON DELETE CASCADE
-        )",
-        [],
-    )
-    .map_err(|e| format!("Failed to create message_entries table: {e}"))?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_message_entries_session ON message_entries(session_path)",
-        [],
-    )
-    .map_err(|e| format!("Failed to create index on message_entries: {e}"))?;
-
-    // Migrate message_entries schema: add missing columns (non-destructive)
-    {
-        let mut stmt = conn
-            .prepare("PRAGMA table_info(message_entries)")
-            .map_err(|e| e.to_string())?;
-        let me_columns: Vec<String> = stmt
-            .query_map([], |row| row.get(1))
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
-        let required_me_columns = ["id", "session_path", "role", "content", "timestamp"];
-        let mut migrated = false;
-        for &col in &required_me_columns {
-            if !me_columns.contains(&col.to_string()) {
-                eprintln!("[Migration] Adding missing column '{col}' to message_entries");
-                let sql = format!("ALTER TABLE message_entries ADD COLUMN {col}");
-                conn.execute(&sql, [])
-                    .map_err(|e| format!("Failed to add column {col}: {e}"))?;
-                migrated = true;
-            }
-        }
-        if migrated {
-            info!("[Migration] message_entries schema updated with missing columns");
-        }
-    }
-
-    // Apply versioned schema migrations if needed
-    let current_version = get_current_version(&conn)?;
-    if current_version < LATEST_SCHEMA_VERSION {
-        apply_migrations(&conn, current_version)?;
-    }
-
     if config.enable_fts5 {
         // init_fts5(&conn)?; // DISABLED: sessions_fts incompatible with sessions schema (TEXT PRIMARY KEY)
         // Comprehensive schema check for message-level FTS only
@@ -468,7 +294,14 @@ pub fn ensure_message_fts_schema(conn: &Connection) -> Result<(), String> {
         .map_err(|e| format!("Failed to read message_entries columns: {e}"))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Failed to collect message_entries columns: {e}"))?;
-    let required_me_columns = ["id", "session_path", "role", "content", "timestamp"];
+    let required_me_columns = [
+        "id",
+        "session_path",
+        "role",
+        "content",
+        "timestamp",
+        "model",
+    ];
     let mut migrated = false;
     for &col in &required_me_columns {
         if !me_columns.contains(&col.to_string()) {
@@ -763,6 +596,53 @@ pub fn upsert_session(
     session: &SessionInfo,
     file_modified: DateTime<Utc>,
     entries: Option<&[SessionEntry]>,
+) -> Result<(), String> {
+    write_session_row(conn, session, file_modified)?;
+    // Parsed without a cursor, so a stored one no longer matches the row
+    delete_session_cursor(conn, &session.path)?;
+
+    // Populate message_entries table if it exists (for per-message FTS)
+    if conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name='message_entries'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .map(|_| true)
+        .unwrap_or(false)
+    {
+        debug!(
+            "[Upsert] Updating message entries for session: {}",
+            session.path
+        );
+        // Clear existing entries for this session to avoid duplicates
+        delete_message_entries_for_session(conn, &session.path)?;
+        // Insert fresh entries (use pre-parsed if available to avoid re-reading file)
+        if let Some(entries) = entries {
+            upsert_message_entries(conn, &session.path, entries)?;
+        } else {
+            insert_message_entries(conn, session)?;
+        }
+        if let Err(e) =
+            crate::tantivy_search::index_session_from_db(conn, &session.path, &session.cwd)
+        {
+            warn!("[Tantivy] Failed to index session {}: {}", session.path, e);
+        }
+        debug!(
+            "[Upsert] Completed message entries for session: {}",
+            session.path
+        );
+    } else {
+        debug!("[Upsert] message_entries table does not exist, skipping");
+    }
+
+    Ok(())
+}
+
+fn write_session_row(
+    conn: &Connection,
+    session: &SessionInfo,
+    file_modified: DateTime<Utc>,
 ) -> Result<(), String> {
     conn.execute(
         "INSERT INTO sessions (id, path, cwd, name, created, modified, file_modified, message_count, first_message, all_messages_text, user_messages_text, assistant_messages_text, last_message, last_message_role, cached_at, access_count, last_accessed)
@@ -797,40 +677,151 @@ pub fn upsert_session(
         ],
     ).map_err(|e| format!("Failed to upsert session: {e}"))?;
 
-    // Populate message_entries table if it exists (for per-message FTS)
-    if conn
-        .query_row(
-            "SELECT name FROM sqlite_master WHERE type='table' AND name='message_entries'",
-            [],
-            |row| row.get::<_, String>(0),
-        )
-        .map(|_| true)
-        .unwrap_or(false)
-    {
-        debug!(
-            "[Upsert] Updating message entries for session: {}",
-            session.path
-        );
-        // Clear existing entries for this session to avoid duplicates
-        delete_message_entries_for_session(conn, &session.path)?;
-        // Insert fresh entries (use pre-parsed if available to avoid re-reading file)
-        if let Some(entries) = entries {
-            upsert_message_entries(conn, &session.path, entries)?;
-        } else {
-            insert_message_entries(conn, session)?;
+    Ok(())
+}
+
+/// Upsert a session parsed through a cursor and save the cursor with it.
+/// Only message entries appended since the stored cursor are inserted;
+/// `update` supplies them when it was advanced from that cursor, otherwise
+/// they are parsed from the file.
+pub fn upsert_session_with_cursor(
+    conn: &Connection,
+    session: &SessionInfo,
+    file_modified: DateTime<Utc>,
+    cursor: &SessionCursor,
+    update: Option<&CursorUpdate>,
+) -> Result<(), String> {
+    let stored = get_session_cursor(conn, &session.path)?;
+    write_session_row(conn, session, file_modified)?;
+
+    if message_entries_table_exists(conn) {
+        let parsed;
+        let update = match update {
+            Some(update) if update.extends(stored.as_ref()) => update,
+            _ => {
+                parsed = stored
+                    .unwrap_or_default()
+                    .advance(Path::new(&session.path))?;
+                &parsed
+            }
+        };
+        if update.reset {
+            delete_message_entries_for_session(conn, &session.path)?;
+        }
+        if update.reset || !update.entries.is_empty() {
+            insert_parsed_message_entries(conn, &session.path, &update.entries)?;
+            if let Err(e) =
+                crate::tantivy_search::index_session_from_db(conn, &session.path, &session.cwd)
+            {
+                warn!("[Tantivy] Failed to index session {}: {}", session.path, e);
+            }
+            debug!(
+                "[Upsert] Appended {} message entries for session: {}",
+                update.entries.len(),
+                session.path
+            );
         }
-        debug!(
-            "[Upsert] Completed message entries for session: {}",
-            session.path
-        );
-    } else {
-        debug!("[Upsert] message_entries table does not exist, skipping");
     }
 
+    save_session_cursor(conn, &session.path, cursor)
+}
+
+pub fn get_session_cursor(conn: &Connection, path: &str) -> Result<Option<SessionCursor>, String> {
+    let row = conn
+        .query_row(
+            "SELECT byte_offset, line_count, partial, unterminated, header_hash, state_json
+             FROM session_cursors WHERE path = ?",
+            params![path],
+            |row| {
+                Ok((
+                    SessionCursor {
+                        offset: row.get::<_, i64>(0)? as u64,
+                        line_count: row.get::<_, i64>(1)? as u64,
+                        partial: row.get(2)?,
+                        unterminated: row.get(3)?,
+                        header_hash: row.get(4)?,
+                        state: Default::default(),
+                    },
+                    row.get::<_, String>(5)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read session cursor: {e}"))?;
+
+    // A state that no longer deserializes just means a full reparse
+    Ok(row.and_then(|(cursor, state_json)| {
+        serde_json::from_str(&state_json)
+            .ok()
+            .map(|state| SessionCursor { state, ..cursor })
+    }))
+}
+
+pub fn save_session_cursor(
+    conn: &Connection,
+    path: &str,
+    cursor: &SessionCursor,
+) -> Result<(), String> {
+    let state_json = serde_json::to_string(&cursor.state)
+        .map_err(|e| format!("Failed to serialize session cursor: {e}"))?;
+    conn.execute(
+        "INSERT INTO session_cursors (path, byte_offset, line_count, partial, unterminated, header_hash, state_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(path) DO UPDATE SET
+            byte_offset = excluded.byte_offset,
+            line_count = excluded.line_count,
+            partial = excluded.partial,
+            unterminated = excluded.unterminated,
+            header_hash = excluded.header_hash,
+            state_json = excluded.state_json",
+        params![
+            path,
+            cursor.offset as i64,
+            cursor.line_count as i64,
+            &cursor.partial,
+            cursor.unterminated,
+            &cursor.header_hash,
+            state_json,
+        ],
+    )
+    .map_err(|e| format!("Failed to save session cursor: {e}"))?;
+    Ok(())
+}
+
+pub fn delete_session_cursor(conn: &Connection, path: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM session_cursors WHERE path = ?", params![path])
+        .map_err(|e| format!("Failed to delete session cursor: {e}"))?;
     Ok(())
 }
 
 pub fn get_session(conn: &Connection, path: &str) -> Result<Option<SessionInfo>, String> {
+    let session = read_session_row(conn, path)?;
+
+    if session.is_some() {
+        conn.execute(
+            "UPDATE sessions SET access_count = access_count + 1, last_accessed = ? WHERE path = ?",
+            params![Utc::now().to_rfc3339(), path],
+        )
+        .ok();
+    }
+
+    Ok(session)
+}
+
+/// The cached session together with the cursor it was parsed up to. Unlike
+/// `get_session` this is not counted as an access.
+pub fn get_session_with_cursor(
+    conn: &Connection,
+    path: &str,
+) -> Result<Option<(SessionInfo, SessionCursor)>, String> {
+    let Some(cursor) = get_session_cursor(conn, path)? else {
+        return Ok(None);
+    };
+    Ok(read_session_row(conn, path)?.map(|session| (session, cursor)))
+}
+
+/// The cached session without counting it as an access
+pub fn read_session_row(conn: &Connection, path: &str) -> Result<Option<SessionInfo>, String> {
     let mut stmt = conn.prepare(
         "SELECT id, path, cwd, name, created, modified, message_count, first_message, all_messages_text, user_messages_text, assistant_messages_text, last_message, last_message_role
          FROM sessions WHERE path = ?"
@@ -856,14 +847,6 @@ pub fn get_session(conn: &Connection, path: &str) -> Result<Option<SessionInfo>,
         })
         .ok();
 
-    if session.is_some() {
-        conn.execute(
-            "UPDATE sessions SET access_count = access_count + 1, last_accessed = ? WHERE path = ?",
-            params![Utc::now().to_rfc3339(), path],
-        )
-        .ok();
-    }
-
     Ok(session)
 }
 
@@ -966,6 +949,67 @@ pub fn get_sessions_modified_before(
     Ok(sessions)
 }
 
+/// Cached path of the session with this identity
+pub fn find_session_path(conn: &Connection, id: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT path FROM sessions WHERE id = ?",
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up session {id}: {e}"))
+}
+
+/// Point the cached rows of a moved session file at its new path, keeping
+/// its parsed messages, details, cursor and secret audit instead of
+/// rebuilding them. Favorites of the session follow it.
+pub fn relocate_session(conn: &Connection, old_path: &str, new_path: &str) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start relocation: {e}"))?;
+    // Rows referencing sessions(path) move in the same transaction as the
+    // session; foreign keys are checked once everything points at the new path
+    tx.execute_batch("PRAGMA defer_foreign_keys = ON;")
+        .map_err(|e| format!("Failed to defer foreign keys: {e}"))?;
+    for (table, column) in [
+        ("sessions", "path"),
+        ("message_entries", "session_path"),
+        ("session_details_cache", "path"),
+        ("session_cursors", "path"),
+        ("secret_audit_files", "path"),
+        ("secret_findings", "session_path"),
+    ] {
+        tx.execute(
+            &format!("UPDATE {table} SET {column} = ?1 WHERE {column} = ?2"),
+            params![new_path, old_path],
+        )
+        .map_err(|e| format!("Failed to relocate {table}: {e}"))?;
+    }
+    tx.execute(
+        "UPDATE favorites SET path = ?1 WHERE type = 'session' AND path = ?2",
+        params![new_path, old_path],
+    )
+    .map_err(|e| format!("Failed to relocate favorites: {e}"))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit relocation: {e}"))?;
+
+    if crate::tantivy_search::is_enabled() {
+        let cwd = read_session_row(conn, new_path)?
+            .map(|s| s.cwd)
+            .unwrap_or_default();
+        if let Err(e) = crate::tantivy_search::remove_session(old_path)
+            .and_then(|_| crate::tantivy_search::index_session_from_db(conn, new_path, &cwd))
+        {
+            warn!(
+                "[Tantivy] Failed to re-index moved session {}: {}",
+                new_path, e
+            );
+        }
+    }
+    info!("Relocated cached session {} -> {}", old_path, new_path);
+    Ok(())
+}
+
 pub fn get_cached_file_modified(
     conn: &Connection,
     path: &str,
@@ -999,6 +1043,7 @@ pub fn needs_reindexing(conn: &Connection, path: &str) -> Result<bool, String> {
 pub fn delete_session(conn: &Connection, path: &str) -> Result<(), String> {
     // Also delete from message_entries (FOREIGN KEY CASCADE should handle but we do explicitly for safety)
     let _ = delete_message_entries_for_session(conn, path);
+    let _ = crate::tantivy_search::remove_session(path);
 
     conn.execute("DELETE FROM sessions WHERE path = ?", params![path])
         .map_err(|e| format!("Failed to delete session: {e}"))?;
@@ -1113,6 +1158,101 @@ pub fn upsert_session_details_cache(
     Ok(())
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DbSecretFinding {
+    pub session_path: String,
+    pub entry_id: String,
+    pub detector: String,
+    pub preview: String,
+}
+
+/// Audit state per file: path -> (file_modified, detectors fingerprint)
+pub fn get_secret_audit_state(
+    conn: &Connection,
+) -> Result<std::collections::HashMap<String, (String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT path, file_modified, detectors FROM secret_audit_files")
+        .map_err(|e| format!("Failed to prepare secret audit query: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?)))
+        })
+        .map_err(|e| format!("Failed to query secret audit state: {e}"))?
+        .collect::<SqliteResult<_>>()
+        .map_err(|e| format!("Failed to collect secret audit state: {e}"))?;
+    Ok(rows)
+}
+
+/// Replace the findings for one file and record it as scanned
+pub fn replace_secret_findings(
+    conn: &Connection,
+    path: &str,
+    file_modified: &str,
+    detectors: &str,
+    findings: &[DbSecretFinding],
+) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to begin transaction: {e}"))?;
+    tx.execute(
+        "DELETE FROM secret_findings WHERE session_path = ?",
+        params![path],
+    )
+    .map_err(|e| format!("Failed to clear secret findings: {e}"))?;
+    tx.execute(
+        "INSERT INTO secret_audit_files (path, file_modified, detectors, scanned_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(path) DO UPDATE SET
+            file_modified = excluded.file_modified,
+            detectors = excluded.detectors,
+            scanned_at = excluded.scanned_at",
+        params![path, file_modified, detectors, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("Failed to record secret audit: {e}"))?;
+    for f in findings {
+        tx.execute(
+            "INSERT INTO secret_findings (session_path, entry_id, detector, preview)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![path, f.entry_id, f.detector, f.preview],
+        )
+        .map_err(|e| format!("Failed to insert secret finding: {e}"))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit secret findings: {e}"))
+}
+
+/// Forget audit results for files that no longer exist
+pub fn delete_secret_audit(conn: &Connection, path: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM secret_audit_files WHERE path = ?",
+        params![path],
+    )
+    .map_err(|e| format!("Failed to delete secret audit: {e}"))?;
+    Ok(())
+}
+
+pub fn get_secret_findings(conn: &Connection) -> Result<Vec<DbSecretFinding>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_path, entry_id, detector, preview FROM secret_findings
+             ORDER BY session_path, rowid",
+        )
+        .map_err(|e| format!("Failed to prepare secret findings query: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(DbSecretFinding {
+                session_path: row.get(0)?,
+                entry_id: row.get(1)?,
+                detector: row.get(2)?,
+                preview: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query secret findings: {e}"))?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to collect secret findings: {e}"))?;
+    Ok(rows)
+}
+
 pub fn vacuum(conn: &Connection) -> Result<(), String> {
     conn.execute("VACUUM", [])
         .map_err(|e| format!("Failed to vacuum database: {e}"))?;
@@ -1310,6 +1450,15 @@ pub fn delete_message_entries_for_session(
     Ok(())
 }
 
+/// `provider/model` as reported by `session_parser`, or the bare model name
+fn qualified_model(provider: Option<&str>, model: Option<&str>) -> Option<String> {
+    let model = model?;
+    Some(match provider {
+        Some(provider) => format!("{provider}/{model}"),
+        None => model.to_string(),
+    })
+}
+
 /// Insert message entries from a session file into message_entries table
 pub fn insert_message_entries(conn: &Connection, session: &SessionInfo) -> Result<(), String> {
     use serde_json::Value;
@@ -1339,47 +1488,43 @@ pub fn insert_message_entries(conn: &Connection, session: &SessionInfo) -> Resul
             continue;
         }
 
-        if let Ok(entry) = serde_json::from_str::<Value>(&line) {
-            if entry["type"] == "message" {
-                if let Some(message) = entry.get("message") {
-                    let role = message["role"].as_str().unwrap_or("");
-                    if role == "user" || role == "assistant" {
-                        let entry_id = entry["id"].as_str().unwrap_or("").to_string();
-                        if entry_id.is_empty() {
-                            continue;
-                        }
-
-                        let timestamp = entry["timestamp"].as_str().unwrap_or("").to_string();
-
-                        // Extract text content (exclude thinking)
-                        let mut content = String::new();
-                        if let Some(content_arr) = message["content"].as_array() {
-                            for item in content_arr {
-                                if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                    content.push_str(text);
-                                }
-                            }
-                        }
+        let Some(entry) = serde_json::from_str::<Value>(&line)
+            .ok()
+            .and_then(|value| session_parser::parse_entry(&value))
+        else {
+            continue;
+        };
+        let Some(message) = &entry.message else {
+            continue;
+        };
+        if entry.id.is_empty() || (message.role != "user" && message.role != "assistant") {
+            continue;
+        }
 
-                        if !content.is_empty() {
-                            // Insert into message_entries
-                            conn.execute(
-                                "INSERT OR REPLACE INTO message_entries (id, session_path, role, content, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
-                                params![
-                                    &entry_id,
-                                    &session.path,
-                                    role,
-                                    &content,
-                                    &timestamp,
-                                ],
-                            ).map_err(|e| format!("Failed to insert message entry (session: {}, entry: {}): {}", 
-                                session.path, entry_id, e))?;
-                            inserted_count += 1;
-                        }
-                    }
-                }
-            }
+        // Text parts only (thinking and tool calls are not indexed)
+        let content = message.text();
+        if content.is_empty() {
+            continue;
         }
+
+        conn.execute(
+            "INSERT OR REPLACE INTO message_entries (id, session_path, role, content, timestamp, model) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                &entry.id,
+                &session.path,
+                &message.role,
+                &content,
+                entry.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                qualified_model(message.provider.as_deref(), message.model.as_deref()),
+            ],
+        )
+        .map_err(|e| {
+            format!(
+                "Failed to insert message entry (session: {}, entry: {}): {}",
+                session.path, entry.id, e
+            )
+        })?;
+        inserted_count += 1;
     }
 
     debug!(
@@ -1421,39 +1566,59 @@ pub fn upsert_message_entries(
     )
     .map_err(|e| format!("Failed to delete existing message entries for {session_path}: {e}"))?;
 
-    // Insert each entry (could be batched but ok)
+    insert_parsed_message_entries(conn, session_path, entries)?;
+
+    debug!(
+        "Upserted {} message entries for session: {}",
+        entries.len(),
+        session_path
+    );
+    Ok(())
+}
+
+fn message_entries_table_exists(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT name FROM sqlite_master WHERE type='table' AND name='message_entries'",
+        [],
+        |row| row.get::<_, String>(0),
+    )
+    .is_ok()
+}
+
+/// Insert pre-parsed user and assistant messages with text, keeping the
+/// session's existing rows
+fn insert_parsed_message_entries(
+    conn: &Connection,
+    session_path: &str,
+    entries: &[SessionEntry],
+) -> Result<(), String> {
     for entry in entries {
         if let Some(ref msg) = entry.message {
-            // Concatenate all text content parts into a single string
-            let content = msg
-                .content
-                .iter()
-                .filter_map(|c| c.text.as_deref())
-                .collect::<String>();
+            // Only user/assistant text is indexed (the role column is constrained)
+            if msg.role != "user" && msg.role != "assistant" {
+                continue;
+            }
+            let content = msg.text();
 
             if content.is_empty() {
                 continue;
             }
 
             conn.execute(
-                "INSERT OR REPLACE INTO message_entries (id, session_path, role, content, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+                "INSERT OR REPLACE INTO message_entries (id, session_path, role, content, timestamp, model) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
                 params![
                     &entry.id,
                     session_path,
                     &msg.role,
                     &content,
-                    &entry.timestamp.to_rfc3339(),
+                    entry.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    qualified_model(msg.provider.as_deref(), msg.model.as_deref()),
                 ],
             )
             .map_err(|e| format!("Failed to insert message entry {}: {}", entry.id, e))?;
         }
     }
 
-    debug!(
-        "Upserted {} message entries for session: {}",
-        entries.len(),
-        session_path
-    );
     Ok(())
 }
 
@@ -1658,7 +1823,7 @@ pub fn full_rebuild_fts(conn: &Connection) -> Result<(), String> {
 // ============ Utility & Tag Management ============
 
 // Clear all cached session data (sessions table and session_details_cache table)
-// Note: favorites table is preserved
+// Note: tags, favorites and the other user data live in the user database and are untouched
 pub fn clear_all_cache(conn: &Connection) -> Result<(usize, usize), String> {
     // Delete all sessions
     let sessions_deleted = conn
@@ -1673,6 +1838,11 @@ pub fn clear_all_cache(conn: &Connection) -> Result<(usize, usize), String> {
     // Vacuum to reclaim space
     vacuum(conn)?;
 
+    // Rebuilding from the now-empty message_entries clears the Tantivy index
+    if let Err(e) = crate::tantivy_search::rebuild_from_db(conn) {
+        warn!("[Tantivy] Failed to clear index: {}", e);
+    }
+
     Ok((sessions_deleted, details_deleted))
 }
 
@@ -1725,6 +1895,13 @@ pub fn get_all_tags(conn: &Connection) -> Result<Vec<DbTag>, String> {
     Ok(tags)
 }
 
+/// Look up a tag by id or case-insensitive name
+pub fn find_tag(conn: &Connection, id_or_name: &str) -> Result<Option<DbTag>, String> {
+    Ok(get_all_tags(conn)?
+        .into_iter()
+        .find(|t| t.id == id_or_name || t.name.eq_ignore_ascii_case(id_or_name)))
+}
+
 pub fn create_tag(
     conn: &Connection,
     id: &str,
@@ -1818,6 +1995,31 @@ pub fn get_all_session_tags(conn: &Connection) -> Result<Vec<DbSessionTag>, Stri
     Ok(items)
 }
 
+/// Session id -> tag names, in assignment order
+pub fn get_session_tag_names(conn: &Connection) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT st.session_id, t.name FROM session_tags st
+             JOIN tags t ON t.id = st.tag_id
+             ORDER BY st.position",
+        )
+        .map_err(|e| format!("Failed to prepare session tag names statement: {e}"))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to query session tag names: {e}"))?;
+
+    let mut by_session: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (session_id, name) =
+            row.map_err(|e| format!("Failed to read session tag name: {e}"))?;
+        by_session.entry(session_id).or_default().push(name);
+    }
+    Ok(by_session)
+}
+
 pub fn assign_tag(conn: &Connection, session_id: &str, tag_id: &str) -> Result<(), String> {
     let max_pos: i64 = conn
         .query_row(
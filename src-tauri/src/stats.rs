@@ -3,11 +3,12 @@ use crate::session_parser::parse_session_details;
 use crate::sqlite_cache;
 use crate::write_buffer;
 use chrono::{Datelike, Timelike, Weekday};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionStats {
     pub total_sessions: usize,
     pub total_messages: usize,
@@ -25,7 +26,7 @@ pub struct SessionStats {
     pub token_details: TokenDetails,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionStatsInput {
     pub path: String,
     pub cwd: String,
@@ -33,7 +34,7 @@ pub struct SessionStatsInput {
     pub message_count: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TokenDetails {
     pub total_input: usize,
     pub total_output: usize,
@@ -43,7 +44,7 @@ pub struct TokenDetails {
     pub tokens_by_model: HashMap<String, ModelTokenStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ModelTokenStats {
     pub input: usize,
     pub output: usize,
@@ -52,13 +53,13 @@ pub struct ModelTokenStats {
     pub cost: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HeatmapPoint {
     pub date: String,
     pub level: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TimeDistributionPoint {
     pub hour: usize,
     pub message_count: usize,
@@ -90,7 +91,7 @@ pub fn calculate_stats_from_inputs(sessions: &[SessionStatsInput]) -> SessionSta
 
     log::trace!("Calculating stats for {total_sessions} sessions");
 
-    let conn = sqlite_cache::init_db().ok();
+    let db = crate::database::get().ok();
 
     let mut sessions_by_project: HashMap<String, usize> = HashMap::new();
     let mut sessions_by_model: HashMap<String, usize> = HashMap::new();
@@ -164,8 +165,8 @@ pub fn calculate_stats_from_inputs(sessions: &[SessionStatsInput]) -> SessionSta
         }
 
         // 2. 再检查数据库缓存
-        let cached_details = conn.as_ref().and_then(|conn| {
-            sqlite_cache::get_session_details_cache(conn, &session.path)
+        let cached_details = db.as_ref().and_then(|db| {
+            db.read(|conn| sqlite_cache::get_session_details_cache(conn, &session.path))
                 .ok()
                 .flatten()
                 .filter(|cached| cached.file_modified >= session_modified)
@@ -1,15 +1,719 @@
-// Tantivy search engine - placeholder for future implementation
-// Full implementation would require more complex setup
+//! Tantivy-backed full-text index over message entries.
+//!
+//! The index lives next to the SQLite cache (`tantivy_index/`) and holds one
+//! document per message entry. It is kept in sync from `message_entries`
+//! whenever `sqlite_cache::upsert_session` rewrites a session, so both
+//! engines always index exactly the same text. Writes made inside a
+//! [`batch`] are committed once when the batch ends rather than per session.
 
-use crate::models::{SearchResult, SessionInfo};
-use crate::search::{RoleFilter, SearchMode};
+use crate::models::{FullTextSearchHit, FullTextSearchResponse};
+use crate::search_query::{Expr, FieldFilter, Filter, SearchPlan};
+use crate::sqlite_cache;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tantivy::collector::{DocSetCollector, TopDocs};
+use tantivy::query::{
+    BooleanQuery, EmptyQuery, Occur, Query, QueryParser, RangeQuery, RegexQuery, TermQuery,
+    TermSetQuery,
+};
+use tantivy::schema::{
+    DateOptions, DateTimePrecision, Field, IndexRecordOption, Schema, Value, FAST, STORED, STRING,
+    TEXT,
+};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, SnippetGenerator, Term};
+use tracing::{debug, info, warn};
 
-pub fn search_sessions(sessions: &[SessionInfo], query: &str) -> Vec<SearchResult> {
-    // For now, delegate to the existing regex-based search
-    crate::search::search_sessions(sessions, query, SearchMode::Content, RoleFilter::All, true)
+const INDEX_DIR: &str = "tantivy_index";
+const WRITER_HEAP_BYTES: usize = 50_000_000;
+/// Ranked candidates read before per-session grouping; doubled while a
+/// deeper page still needs more
+const MAX_CANDIDATES: usize = 2000;
+/// Same per-session cap the FTS5 path applies
+const MAX_HITS_PER_SESSION: usize = 3;
+const SNIPPET_MAX_CHARS: usize = 160;
+
+#[derive(Clone, Copy)]
+struct Fields {
+    session_path: Field,
+    entry_id: Field,
+    role: Field,
+    cwd: Field,
+    timestamp: Field,
+    text: Field,
+}
+
+struct MessageIndex {
+    path: PathBuf,
+    index: Index,
+    reader: IndexReader,
+    writer: IndexWriter,
+    fields: Fields,
+    /// Documents were added or deleted since the last commit
+    dirty: bool,
+}
+
+lazy_static! {
+    static ref INDEX: Mutex<Option<MessageIndex>> = Mutex::new(None);
+}
+
+/// Number of open [`CommitBatch`]es; while non-zero, writes are not committed
+static BATCH_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// A single message entry as fed to the index
+pub struct IndexedEntry {
+    pub entry_id: String,
+    pub role: String,
+    pub text: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+fn build_schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    // Fast, so hits can be counted per session without loading documents
+    let session_path = builder.add_text_field("session_path", STRING | STORED | FAST);
+    let entry_id = builder.add_text_field("entry_id", STRING | STORED);
+    let role = builder.add_text_field("role", STRING | STORED);
+    let cwd = builder.add_text_field("cwd", STRING | STORED);
+    let timestamp = builder.add_date_field(
+        "timestamp",
+        DateOptions::default()
+            .set_stored()
+            .set_indexed()
+            .set_fast()
+            .set_precision(DateTimePrecision::Milliseconds),
+    );
+    let text = builder.add_text_field("text", TEXT | STORED);
+    (
+        builder.build(),
+        Fields {
+            session_path,
+            entry_id,
+            role,
+            cwd,
+            timestamp,
+            text,
+        },
+    )
+}
+
+/// Index directory, stored beside the SQLite cache (honours `PPM_TEST_DB`)
+pub fn get_index_path() -> Result<PathBuf, String> {
+    let db_path = sqlite_cache::get_db_path()?;
+    let parent = db_path.parent().unwrap_or_else(|| Path::new("."));
+    Ok(parent.join(INDEX_DIR))
+}
+
+fn open_index(path: &Path) -> Result<MessageIndex, String> {
+    fs::create_dir_all(path).map_err(|e| format!("Failed to create index dir: {e}"))?;
+    let (schema, fields) = build_schema();
+
+    let dir = tantivy::directory::MmapDirectory::open(path)
+        .map_err(|e| format!("Failed to open index dir: {e}"))?;
+    let index = match Index::open_or_create(dir, schema.clone()) {
+        Ok(index) => index,
+        Err(e) => {
+            // Schema changed or index is unreadable: start over, it is only a cache
+            warn!("[Tantivy] Recreating index at {:?}: {}", path, e);
+            fs::remove_dir_all(path).map_err(|e| format!("Failed to remove index dir: {e}"))?;
+            fs::create_dir_all(path).map_err(|e| format!("Failed to create index dir: {e}"))?;
+            Index::create_in_dir(path, schema)
+                .map_err(|e| format!("Failed to create index: {e}"))?
+        }
+    };
+
+    let writer = index
+        .writer(WRITER_HEAP_BYTES)
+        .map_err(|e| format!("Failed to open index writer: {e}"))?;
+    let reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()
+        .map_err(|e| format!("Failed to open index reader: {e}"))?;
+
+    Ok(MessageIndex {
+        path: path.to_path_buf(),
+        index,
+        reader,
+        writer,
+        fields,
+        dirty: false,
+    })
+}
+
+/// Open the on-disk index (once per location) and enable incremental updates.
+/// Returns true when the index is empty and should be backfilled.
+pub fn init_index() -> Result<bool, String> {
+    let path = get_index_path()?;
+    let mut guard = INDEX
+        .lock()
+        .map_err(|e| format!("Index lock poisoned: {e}"))?;
+
+    if guard.as_ref().map(|idx| idx.path != path).unwrap_or(true) {
+        // Drop any previous writer first so its directory lock is released
+        *guard = None;
+        *guard = Some(open_index(&path)?);
+        info!("[Tantivy] Index opened at {:?}", path);
+    }
+
+    let idx = guard.as_ref().unwrap();
+    Ok(idx.reader.searcher().num_docs() == 0)
+}
+
+/// Close the index; subsequent upserts stop updating it.
+pub fn close_index() {
+    if let Ok(mut guard) = INDEX.lock() {
+        *guard = None;
+    }
+}
+
+pub fn is_enabled() -> bool {
+    INDEX.lock().map(|g| g.is_some()).unwrap_or(false)
+}
+
+fn add_entries(idx: &MessageIndex, session_path: &str, cwd: &str, entries: &[IndexedEntry]) {
+    let f = idx.fields;
+    for entry in entries {
+        if let Err(e) = idx.writer.add_document(doc!(
+            f.session_path => session_path,
+            f.entry_id => entry.entry_id.as_str(),
+            f.role => entry.role.as_str(),
+            f.cwd => cwd,
+            f.timestamp => tantivy::DateTime::from_timestamp_millis(entry.timestamp.timestamp_millis()),
+            f.text => entry.text.as_str(),
+        )) {
+            warn!("[Tantivy] Failed to add entry {}: {}", entry.entry_id, e);
+        }
+    }
+}
+
+fn commit(idx: &mut MessageIndex) -> Result<(), String> {
+    idx.writer
+        .commit()
+        .map_err(|e| format!("Failed to commit index: {e}"))?;
+    idx.dirty = false;
+    idx.reader
+        .reload()
+        .map_err(|e| format!("Failed to reload index reader: {e}"))
+}
+
+/// Commit now, or leave it to the enclosing batch
+fn commit_or_defer(idx: &mut MessageIndex) -> Result<(), String> {
+    if BATCH_DEPTH.load(Ordering::SeqCst) > 0 {
+        idx.dirty = true;
+        return Ok(());
+    }
+    commit(idx)
+}
+
+/// Defers index commits until the returned guard (and any other open batch) is dropped.
+///
+/// Committing and reloading the reader per session dominates a bulk write, so
+/// buffer flushes and scans wrap their upserts in one batch.
+#[must_use = "the batch commits when dropped"]
+pub struct CommitBatch(());
+
+pub fn batch() -> CommitBatch {
+    BATCH_DEPTH.fetch_add(1, Ordering::SeqCst);
+    CommitBatch(())
+}
+
+impl Drop for CommitBatch {
+    fn drop(&mut self) {
+        if BATCH_DEPTH.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return;
+        }
+        let Ok(mut guard) = INDEX.lock() else {
+            return;
+        };
+        if let Some(idx) = guard.as_mut().filter(|idx| idx.dirty) {
+            if let Err(e) = commit(idx) {
+                warn!("[Tantivy] {}", e);
+            }
+        }
+    }
 }
 
-pub fn init_index(_sessions: &[SessionInfo]) -> Result<(), String> {
-    // Placeholder - would initialize Tantivy index
+/// Replace all documents of one session. No-op when the index is not open.
+pub fn index_session(
+    session_path: &str,
+    cwd: &str,
+    entries: &[IndexedEntry],
+) -> Result<(), String> {
+    let mut guard = INDEX
+        .lock()
+        .map_err(|e| format!("Index lock poisoned: {e}"))?;
+    let Some(idx) = guard.as_mut() else {
+        return Ok(());
+    };
+
+    idx.writer
+        .delete_term(Term::from_field_text(idx.fields.session_path, session_path));
+    add_entries(idx, session_path, cwd, entries);
+    commit_or_defer(idx)?;
+
+    debug!(
+        "[Tantivy] Indexed {} entries for session: {}",
+        entries.len(),
+        session_path
+    );
     Ok(())
 }
+
+/// Remove all documents of one session. No-op when the index is not open.
+pub fn remove_session(session_path: &str) -> Result<(), String> {
+    let mut guard = INDEX
+        .lock()
+        .map_err(|e| format!("Index lock poisoned: {e}"))?;
+    let Some(idx) = guard.as_mut() else {
+        return Ok(());
+    };
+
+    idx.writer
+        .delete_term(Term::from_field_text(idx.fields.session_path, session_path));
+    commit_or_defer(idx)
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// An entry's own timestamp, else its session's header time. Entries with
+/// neither are left out rather than dated "now", which would put them in
+/// every recent `after:` range.
+fn entry_timestamp(
+    entry_id: &str,
+    timestamp: &str,
+    created: Option<&str>,
+) -> Option<DateTime<Utc>> {
+    let parsed = parse_timestamp(timestamp).or_else(|| created.and_then(parse_timestamp));
+    if parsed.is_none() {
+        warn!(
+            "[Tantivy] Skipping entry {} without a valid timestamp",
+            entry_id
+        );
+    }
+    parsed
+}
+
+fn load_entries(conn: &Connection, session_path: &str) -> Result<Vec<IndexedEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.role, m.content, m.timestamp, s.created
+             FROM message_entries m LEFT JOIN sessions s ON s.path = m.session_path
+             WHERE m.session_path = ?",
+        )
+        .map_err(|e| format!("Failed to prepare message entries query: {e}"))?;
+    let rows = stmt
+        .query_map(params![session_path], |row| {
+            let entry_id: String = row.get(0)?;
+            let Some(timestamp) = entry_timestamp(
+                &entry_id,
+                &row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?.as_deref(),
+            ) else {
+                return Ok(None);
+            };
+            Ok(Some(IndexedEntry {
+                entry_id,
+                role: row.get(1)?,
+                text: row.get(2)?,
+                timestamp,
+            }))
+        })
+        .map_err(|e| format!("Failed to query message entries: {e}"))?
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect message entries: {e}"))?;
+    Ok(rows)
+}
+
+/// Re-index one session from the rows currently in `message_entries`
+pub fn index_session_from_db(
+    conn: &Connection,
+    session_path: &str,
+    cwd: &str,
+) -> Result<(), String> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    let entries = load_entries(conn, session_path)?;
+    index_session(session_path, cwd, &entries)
+}
+
+/// Drop the whole index and rebuild it from `message_entries`
+pub fn rebuild_from_db(conn: &Connection) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.session_path, m.role, m.content, m.timestamp, COALESCE(s.cwd, ''),
+                    s.created
+             FROM message_entries m LEFT JOIN sessions s ON s.path = m.session_path",
+        )
+        .map_err(|e| format!("Failed to prepare rebuild query: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let entry_id: String = row.get(0)?;
+            let Some(timestamp) = entry_timestamp(
+                &entry_id,
+                &row.get::<_, String>(4)?,
+                row.get::<_, Option<String>>(6)?.as_deref(),
+            ) else {
+                return Ok(None);
+            };
+            Ok(Some((
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(5)?,
+                IndexedEntry {
+                    entry_id,
+                    role: row.get(2)?,
+                    text: row.get(3)?,
+                    timestamp,
+                },
+            )))
+        })
+        .map_err(|e| format!("Failed to query message entries: {e}"))?
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect message entries: {e}"))?;
+
+    let mut guard = INDEX
+        .lock()
+        .map_err(|e| format!("Index lock poisoned: {e}"))?;
+    let Some(idx) = guard.as_mut() else {
+        return Ok(0);
+    };
+    if rows.is_empty() && idx.reader.searcher().num_docs() == 0 {
+        return Ok(0);
+    }
+
+    idx.writer
+        .delete_all_documents()
+        .map_err(|e| format!("Failed to clear index: {e}"))?;
+    let count = rows.len();
+    for (session_path, cwd, entry) in rows {
+        add_entries(idx, &session_path, &cwd, std::slice::from_ref(&entry));
+    }
+    commit(idx)?;
+
+    info!("[Tantivy] Rebuilt index with {} entries", count);
+    Ok(count)
+}
+
+/// Convert a glob (`*`, `?`) into a regex matching the whole value
+fn glob_to_regex(pattern: &str) -> String {
+    let mut out = String::new();
+    for ch in pattern.chars() {
+        match ch {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            other => out.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    out
+}
+
+fn regex_query(pattern: &str, field: Field) -> Result<Box<dyn Query>, String> {
+    RegexQuery::from_pattern(pattern, field)
+        .map(|q| Box::new(q) as Box<dyn Query>)
+        .map_err(|e| format!("Invalid pattern '{pattern}': {e}"))
+}
+
+/// Paths of the sessions satisfying one field filter, resolved through the
+/// same SQL conditions the FTS5 path uses
+fn matching_session_paths(conn: &Connection, field: &FieldFilter) -> Result<Vec<String>, String> {
+    let plan = SearchPlan {
+        text: Expr::And(vec![]),
+        filters: vec![Filter {
+            field: field.clone(),
+            negated: false,
+        }],
+    };
+    let (conditions, params) = plan.sql_conditions();
+    let sql = format!(
+        "SELECT s.path FROM sessions s WHERE EXISTS (
+            SELECT 1 FROM message_entries m WHERE m.session_path = s.path AND {}
+        )",
+        conditions.join(" AND ")
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare filter query: {e}"))?;
+    let paths = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| row.get(0))
+        .map_err(|e| format!("Failed to run filter query: {e}"))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("Failed to read filter row: {e}"))?;
+    Ok(paths)
+}
+
+/// Index query for one field filter. Role, cwd and time are indexed on every
+/// document; model and tag are per-session facts looked up in SQL.
+fn filter_query(
+    conn: &Connection,
+    fields: Fields,
+    field: &FieldFilter,
+) -> Result<Box<dyn Query>, String> {
+    let date = |at: &DateTime<Utc>| tantivy::DateTime::from_timestamp_millis(at.timestamp_millis());
+    Ok(match field {
+        FieldFilter::Role(role) => Box::new(TermQuery::new(
+            Term::from_field_text(fields.role, role),
+            IndexRecordOption::Basic,
+        )),
+        FieldFilter::Cwd(cwd) if cwd.contains(['*', '?']) => {
+            regex_query(&glob_to_regex(cwd), fields.cwd)?
+        }
+        FieldFilter::Cwd(cwd) => {
+            let cwd = cwd.trim_end_matches('/');
+            regex_query(&format!("{}(/.*)?", regex::escape(cwd)), fields.cwd)?
+        }
+        FieldFilter::After(at) => Box::new(RangeQuery::new_date_bounds(
+            "timestamp".to_string(),
+            Bound::Included(date(at)),
+            Bound::Unbounded,
+        )),
+        FieldFilter::Before(at) => Box::new(RangeQuery::new_date_bounds(
+            "timestamp".to_string(),
+            Bound::Unbounded,
+            Bound::Excluded(date(at)),
+        )),
+        FieldFilter::Model(_) | FieldFilter::Tag(_) => Box::new(TermSetQuery::new(
+            matching_session_paths(conn, field)?
+                .iter()
+                .map(|path| Term::from_field_text(fields.session_path, path)),
+        )),
+    })
+}
+
+/// Score, session path, document and timestamp of a ranked hit
+type Candidate = (f32, String, tantivy::TantivyDocument, DateTime<Utc>);
+
+/// Ranked documents with at most `MAX_HITS_PER_SESSION` per session
+fn capped_candidates(
+    searcher: &tantivy::Searcher,
+    f: Fields,
+    top_docs: Vec<(f32, tantivy::DocAddress)>,
+) -> Result<Vec<Candidate>, String> {
+    let mut per_session: HashMap<String, usize> = HashMap::new();
+    let mut candidates = Vec::new();
+    for (score, address) in top_docs {
+        let doc: tantivy::TantivyDocument = searcher
+            .doc(address)
+            .map_err(|e| format!("Failed to load document: {e}"))?;
+        let session_path = doc
+            .get_first(f.session_path)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let seen = per_session.entry(session_path.clone()).or_insert(0);
+        if *seen >= MAX_HITS_PER_SESSION {
+            continue;
+        }
+        *seen += 1;
+
+        let timestamp = doc
+            .get_first(f.timestamp)
+            .and_then(|v| v.as_datetime())
+            .and_then(|dt| DateTime::<Utc>::from_timestamp_millis(dt.into_timestamp_millis()))
+            .unwrap_or_default();
+        candidates.push((score, session_path, doc, timestamp));
+    }
+    Ok(candidates)
+}
+
+/// Hits left after the per-session cap, counted over every document the
+/// query matches rather than the ranked candidates
+fn count_capped_hits(searcher: &tantivy::Searcher, query: &dyn Query) -> Result<usize, String> {
+    let docs = searcher
+        .search(query, &DocSetCollector)
+        .map_err(|e| format!("Tantivy search failed: {e}"))?;
+    let mut per_segment: HashMap<(u32, u64), usize> = HashMap::new();
+    let mut columns = HashMap::new();
+    for address in docs {
+        let column = match columns.entry(address.segment_ord) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(
+                searcher
+                    .segment_reader(address.segment_ord)
+                    .fast_fields()
+                    .str("session_path")
+                    .map_err(|e| format!("Failed to read session paths: {e}"))?,
+            ),
+        };
+        if let Some(ord) = column
+            .as_ref()
+            .and_then(|c| c.term_ords(address.doc_id).next())
+        {
+            *per_segment.entry((address.segment_ord, ord)).or_insert(0) += 1;
+        }
+    }
+
+    // A session's documents may be spread over several segments
+    let mut per_session: HashMap<String, usize> = HashMap::new();
+    for ((segment, ord), count) in per_segment {
+        let mut path = String::new();
+        if let Some(column) = &columns[&segment] {
+            column
+                .ord_to_str(ord, &mut path)
+                .map_err(|e| format!("Failed to read session path: {e}"))?;
+        }
+        *per_session.entry(path).or_insert(0) += count;
+    }
+    Ok(per_session
+        .values()
+        .map(|n| (*n).min(MAX_HITS_PER_SESSION))
+        .sum())
+}
+
+/// Translate the free-text part of a query plan into a Tantivy query
+fn build_query(parser: &QueryParser, expr: &Expr) -> Box<dyn Query> {
+    let leaf = |text: &str| parser.parse_query_lenient(text).0;
+    match expr {
+        Expr::Term(word) => match word.strip_suffix('*') {
+            Some(prefix) if !prefix.is_empty() => {
+                leaf(&format!("\"{}\"*", prefix.replace('"', " ")))
+            }
+            _ => leaf(&format!("\"{}\"", word.replace('"', " "))),
+        },
+        Expr::Phrase(phrase) => leaf(&format!("\"{}\"", phrase.replace('"', " "))),
+        Expr::And(items) => Box::new(BooleanQuery::new(
+            items
+                .iter()
+                .map(|item| match item {
+                    Expr::Not(inner) => (Occur::MustNot, build_query(parser, inner)),
+                    other => (Occur::Must, build_query(parser, other)),
+                })
+                .collect(),
+        )),
+        Expr::Or(items) => Box::new(BooleanQuery::new(
+            items
+                .iter()
+                .map(|item| (Occur::Should, build_query(parser, item)))
+                .collect(),
+        )),
+        // Rejected by `search_query::plan`
+        Expr::Not(_) | Expr::Field(_) => Box::new(EmptyQuery),
+    }
+}
+
+/// BM25-ranked search over the index.
+///
+/// Mirrors the FTS5 path: at most three hits per session, `role_filter` of
+/// user/assistant and an optional session-path glob. Field filters in `plan`
+/// are part of the index query, so ranking and totals only see matching
+/// entries. Each hit carries an HTML
+/// snippet with matched terms wrapped in `<b>`; session names are resolved
+/// through `conn`.
+pub fn search(
+    conn: &Connection,
+    plan: &SearchPlan,
+    role_filter: Option<&str>,
+    glob_pattern: Option<&str>,
+    page: usize,
+    page_size: usize,
+) -> Result<FullTextSearchResponse, String> {
+    let guard = INDEX
+        .lock()
+        .map_err(|e| format!("Index lock poisoned: {e}"))?;
+    let idx = guard
+        .as_ref()
+        .ok_or_else(|| "Tantivy index is not initialized".to_string())?;
+    let f = idx.fields;
+
+    let parser = QueryParser::for_index(&idx.index, vec![f.text]);
+    let text_query = build_query(&parser, &plan.text);
+
+    let mut clauses = vec![(Occur::Must, text_query.box_clone())];
+    if let Some(role @ ("user" | "assistant")) = role_filter {
+        clauses.push((
+            Occur::Must,
+            Box::new(TermQuery::new(
+                Term::from_field_text(f.role, role),
+                IndexRecordOption::Basic,
+            )) as Box<dyn Query>,
+        ));
+    }
+    if let Some(pattern) = glob_pattern.filter(|p| !p.is_empty()) {
+        clauses.push((
+            Occur::Must,
+            regex_query(&glob_to_regex(pattern), f.session_path)?,
+        ));
+    }
+    for filter in &plan.filters {
+        let occur = if filter.negated {
+            Occur::MustNot
+        } else {
+            Occur::Must
+        };
+        clauses.push((occur, filter_query(conn, f, &filter.field)?));
+    }
+    let full_query = BooleanQuery::new(clauses);
+
+    let searcher = idx.reader.searcher();
+    let wanted = (page + 1) * page_size;
+    let mut limit = MAX_CANDIDATES;
+    let candidates = loop {
+        let top_docs = searcher
+            .search(&full_query, &TopDocs::with_limit(limit))
+            .map_err(|e| format!("Tantivy search failed: {e}"))?;
+        let exhausted = top_docs.len() < limit;
+        let candidates = capped_candidates(&searcher, f, top_docs)?;
+        if exhausted || candidates.len() >= wanted {
+            break candidates;
+        }
+        limit *= 2;
+    };
+
+    let total_hits = count_capped_hits(&searcher, &full_query)?;
+    let mut snippet_gen = SnippetGenerator::create(&searcher, &*text_query, f.text)
+        .map_err(|e| format!("Failed to create snippet generator: {e}"))?;
+    snippet_gen.set_max_num_chars(SNIPPET_MAX_CHARS);
+
+    let mut hits = Vec::new();
+    for (score, session_path, doc, timestamp) in candidates
+        .into_iter()
+        .skip(page * page_size)
+        .take(page_size)
+    {
+        let session = sqlite_cache::get_session(conn, &session_path)?;
+        let content = doc
+            .get_first(f.text)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let snippet = snippet_gen.snippet(&content).to_html();
+
+        hits.push(FullTextSearchHit {
+            session_id: session.as_ref().map(|s| s.id.clone()).unwrap_or_default(),
+            session_path,
+            session_name: session.and_then(|s| s.name),
+            entry_id: doc
+                .get_first(f.entry_id)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            role: doc
+                .get_first(f.role)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            content,
+            snippet: Some(snippet),
+            timestamp,
+            score,
+        });
+    }
+
+    Ok(FullTextSearchResponse {
+        has_more: (page + 1) * page_size < total_hits,
+        hits,
+        total_hits,
+    })
+}
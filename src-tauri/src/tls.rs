@@ -0,0 +1,157 @@
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+
+/// Certificate the HTTP and WebSocket servers present when TLS is on
+#[derive(Clone)]
+pub struct TlsIdentity {
+    config: Arc<rustls::ServerConfig>,
+    /// SHA-256 of the leaf certificate as colon-separated hex
+    pub fingerprint: String,
+    /// Generated by us; clients have to trust it by its fingerprint
+    pub self_signed: bool,
+}
+
+impl TlsIdentity {
+    /// Load the configured PEM files. Without either path a self-signed
+    /// certificate is generated on first use and reused afterwards, so its
+    /// fingerprint stays stable across restarts.
+    ///
+    /// `hosts` are extra names for a newly generated certificate, next to
+    /// `localhost` and the loopback addresses.
+    pub fn load(
+        cert_path: Option<&str>,
+        key_path: Option<&str>,
+        hosts: &[&str],
+    ) -> Result<Self, String> {
+        let cert_path = cert_path.map(str::trim).filter(|p| !p.is_empty());
+        let key_path = key_path.map(str::trim).filter(|p| !p.is_empty());
+        match (cert_path, key_path) {
+            (Some(cert), Some(key)) => Self::from_files(Path::new(cert), Path::new(key), false),
+            (None, None) => {
+                let dir = self_signed_dir()?;
+                let (cert, key) = (dir.join(CERT_FILE), dir.join(KEY_FILE));
+                if !cert.exists() || !key.exists() {
+                    generate_self_signed(&dir, hosts)?;
+                }
+                Self::from_files(&cert, &key, true)
+            }
+            _ => Err("tls_cert_path and tls_key_path must be set together".to_string()),
+        }
+    }
+
+    fn from_files(cert_path: &Path, key_path: &Path, self_signed: bool) -> Result<Self, String> {
+        let certs: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(cert_path)
+            .map_err(|e| format!("Failed to read {}: {e}", cert_path.display()))?
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid certificate in {}: {e}", cert_path.display()))?;
+        let leaf = certs
+            .first()
+            .ok_or_else(|| format!("No certificate in {}", cert_path.display()))?;
+        let fingerprint = fingerprint(leaf);
+        let key = PrivateKeyDer::from_pem_file(key_path)
+            .map_err(|e| format!("Failed to read private key {}: {e}", key_path.display()))?;
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("TLS setup failed: {e}"))?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| format!("Certificate and key do not match: {e}"))?;
+        // WebSocket upgrades need HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(Self {
+            config: Arc::new(config),
+            fingerprint,
+            self_signed,
+        })
+    }
+
+    pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
+        self.config.clone()
+    }
+
+    /// For servers that accept raw TCP streams (the standalone WebSocket port)
+    pub fn acceptor(&self) -> tokio_rustls::TlsAcceptor {
+        tokio_rustls::TlsAcceptor::from(self.server_config())
+    }
+}
+
+/// Where the generated self-signed certificate lives
+pub fn self_signed_dir() -> Result<PathBuf, String> {
+    let base = dirs::config_dir().ok_or("Cannot find config directory")?;
+    Ok(base.join("pi-session-manager").join("tls"))
+}
+
+fn generate_self_signed(dir: &Path, hosts: &[&str]) -> Result<(), String> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    for host in hosts {
+        let host = host.trim();
+        // Wildcard binds have no name of their own
+        if !host.is_empty() && host != "0.0.0.0" && host != "::" && !names.iter().any(|n| n == host)
+        {
+            names.push(host.to_string());
+        }
+    }
+    let generated = rcgen::generate_simple_self_signed(names)
+        .map_err(|e| format!("Failed to generate certificate: {e}"))?;
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let key_path = dir.join(KEY_FILE);
+    fs::write(&key_path, generated.key_pair.serialize_pem())
+        .map_err(|e| format!("Failed to write {}: {e}", key_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&key_path, fs::Permissions::from_mode(0o600));
+    }
+    let cert_path = dir.join(CERT_FILE);
+    fs::write(&cert_path, generated.cert.pem())
+        .map_err(|e| format!("Failed to write {}: {e}", cert_path.display()))?;
+    log::info!("Generated self-signed TLS certificate in {}", dir.display());
+    Ok(())
+}
+
+/// `AB:CD:…` SHA-256 digest of a DER certificate
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Serve `app` on `listener`, over TLS when an identity is given. Handlers
+/// receive `ConnectInfo<SocketAddr>` either way.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<&TlsIdentity>,
+) -> std::io::Result<()> {
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        None => axum::serve(listener, service).await,
+        Some(identity) => {
+            let config = RustlsConfig::from_config(identity.server_config());
+            axum_server::from_tcp_rustls(listener.into_std()?, config)
+                .serve(service)
+                .await
+        }
+    }
+}
@@ -0,0 +1,719 @@
+use crate::cli::{load_sessions, one_line, short_id, tag_names};
+use crate::database::{self, Database};
+use crate::export::{self, ExportOptions};
+use crate::models::{Content, SessionEntry, SessionInfo};
+use crate::session_tree::SessionTree;
+use crate::{commands, session_parser, sqlite_cache};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+const FTS_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Sessions,
+    Messages,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Tag,
+    Rename,
+    Export,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Search,
+    Prompt(PromptKind, String),
+}
+
+/// Work the event loop does outside `App`: async commands and leaving the
+/// alternate screen
+#[derive(Debug, Clone)]
+pub enum Action {
+    Quit,
+    Rename(SessionInfo, String),
+    Export(SessionInfo, String),
+    Resume(SessionInfo),
+}
+
+pub struct App {
+    db: Arc<Database>,
+    sessions: Vec<SessionInfo>,
+    /// Indices into `sessions` matching the current query, newest first
+    visible: Vec<usize>,
+    /// Session path -> first FTS snippet for the current query
+    snippets: HashMap<String, String>,
+    list_state: ListState,
+    focus: Focus,
+    mode: Mode,
+    query: String,
+    favorites: HashSet<String>,
+    tags: HashMap<String, Vec<String>>,
+    expand_tools: bool,
+    scroll: u16,
+    /// Path and active-branch entries of the session in the message pane
+    loaded: Option<(String, Vec<SessionEntry>)>,
+    status: String,
+    pi_command: String,
+}
+
+/// Open the session browser on the current terminal
+pub async fn run() -> Result<(), String> {
+    load_sessions().await?;
+    let pi_command = commands::load_app_settings_internal()
+        .await
+        .ok()
+        .and_then(|s| s["terminal"]["piCommandPath"].as_str().map(str::to_string))
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| "pi".to_string());
+    let mut app = App::load(pi_command)?;
+    let handle = tokio::runtime::Handle::current();
+
+    tokio::task::spawn_blocking(move || {
+        let mut terminal = ratatui::init();
+        let result = event_loop(&mut terminal, &mut app, &handle);
+        ratatui::restore();
+        result
+    })
+    .await
+    .map_err(|e| format!("TUI task failed: {e}"))?
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    handle: &tokio::runtime::Handle,
+) -> Result<(), String> {
+    loop {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(|e| format!("Failed to draw: {e}"))?;
+        let Event::Key(key) = event::read().map_err(|e| format!("Failed to read input: {e}"))?
+        else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            None => {}
+            Some(Action::Quit) => return Ok(()),
+            Some(Action::Rename(session, name)) => {
+                match handle.block_on(commands::rename_session(session.path.clone(), name.clone()))
+                {
+                    Ok(()) => app.renamed(&session.path, name),
+                    Err(e) => app.status = e,
+                }
+            }
+            Some(Action::Export(session, output)) => {
+                let format = format_for(&output);
+                let result = handle.block_on(export::export_session_with_options(
+                    &session.path,
+                    format,
+                    &output,
+                    &ExportOptions::default(),
+                ));
+                app.status = match result {
+                    Ok(()) => format!("Exported to {output}"),
+                    Err(e) => e,
+                };
+            }
+            Some(Action::Resume(session)) => {
+                ratatui::restore();
+                let mut command = std::process::Command::new(&app.pi_command);
+                command.arg("--session").arg(&session.path);
+                if Path::new(&session.cwd).is_dir() {
+                    command.current_dir(&session.cwd);
+                }
+                let result = command.status();
+                *terminal = ratatui::init();
+                app.status = match result {
+                    Ok(status) => format!("{} exited with {status}", app.pi_command),
+                    Err(e) => format!("Failed to run {}: {e}", app.pi_command),
+                };
+                app.loaded = None;
+            }
+        }
+    }
+}
+
+impl App {
+    /// Sessions from the `sessions` table, most recently modified first
+    pub fn load(pi_command: String) -> Result<Self, String> {
+        let db = database::get()?;
+        let sessions = db.read(sqlite_cache::get_all_sessions)?;
+        let favorites = db
+            .read(sqlite_cache::get_all_favorites)?
+            .into_iter()
+            .filter(|f| f.favorite_type == "session")
+            .map(|f| f.id)
+            .collect();
+        let mut app = Self {
+            db,
+            visible: (0..sessions.len()).collect(),
+            sessions,
+            snippets: HashMap::new(),
+            list_state: ListState::default().with_selected(Some(0)),
+            focus: Focus::Sessions,
+            mode: Mode::Normal,
+            query: String::new(),
+            favorites,
+            tags: tag_names(),
+            expand_tools: false,
+            scroll: 0,
+            loaded: None,
+            status: String::new(),
+            pi_command,
+        };
+        app.clamp_selection();
+        Ok(app)
+    }
+
+    pub fn visible_sessions(&self) -> Vec<&SessionInfo> {
+        self.visible.iter().map(|&i| &self.sessions[i]).collect()
+    }
+
+    pub fn selected(&self) -> Option<&SessionInfo> {
+        let i = self.list_state.selected()?;
+        self.visible.get(i).map(|&i| &self.sessions[i])
+    }
+
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        match self.mode.clone() {
+            Mode::Search => {
+                self.search_key(key);
+                None
+            }
+            Mode::Prompt(kind, input) => self.prompt_key(key, kind, input),
+            Mode::Normal => self.normal_key(key),
+        }
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) -> Option<Action> {
+        self.status.clear();
+        match (self.focus, key.code) {
+            (_, KeyCode::Char('q')) => return Some(Action::Quit),
+            (_, KeyCode::Char('/')) => {
+                self.mode = Mode::Search;
+                self.focus = Focus::Sessions;
+            }
+            (_, KeyCode::Char('x')) => self.expand_tools = !self.expand_tools,
+            (_, KeyCode::Char('f')) => self.toggle_favorite(),
+            (_, KeyCode::Char('t')) => self.open_prompt(PromptKind::Tag, String::new()),
+            (_, KeyCode::Char('r')) => {
+                let name = self.selected().and_then(|s| s.name.clone());
+                self.open_prompt(PromptKind::Rename, name.unwrap_or_default());
+            }
+            (_, KeyCode::Char('e')) => {
+                let file = self.selected().map(|s| format!("{}.md", short_id(&s.id)));
+                self.open_prompt(PromptKind::Export, file.unwrap_or_default());
+            }
+            (_, KeyCode::Char('R')) => return self.selected().cloned().map(Action::Resume),
+            (Focus::Sessions, KeyCode::Esc) if !self.query.is_empty() => {
+                self.query.clear();
+                self.refresh_filter();
+            }
+            (Focus::Sessions, KeyCode::Enter | KeyCode::Tab | KeyCode::Right) => {
+                self.focus = Focus::Messages;
+            }
+            (Focus::Sessions, KeyCode::Down | KeyCode::Char('j')) => self.move_selection(1),
+            (Focus::Sessions, KeyCode::Up | KeyCode::Char('k')) => self.move_selection(-1),
+            (Focus::Sessions, KeyCode::PageDown) => self.move_selection(10),
+            (Focus::Sessions, KeyCode::PageUp) => self.move_selection(-10),
+            (Focus::Messages, KeyCode::Esc | KeyCode::Tab | KeyCode::Left) => {
+                self.focus = Focus::Sessions;
+            }
+            (Focus::Messages, KeyCode::Down | KeyCode::Char('j')) => self.scroll_by(1),
+            (Focus::Messages, KeyCode::Up | KeyCode::Char('k')) => self.scroll_by(-1),
+            (Focus::Messages, KeyCode::PageDown | KeyCode::Char(' ')) => self.scroll_by(20),
+            (Focus::Messages, KeyCode::PageUp) => self.scroll_by(-20),
+            (Focus::Messages, KeyCode::Char('g')) => self.scroll = 0,
+            (Focus::Messages, KeyCode::Char('G')) => self.scroll = u16::MAX,
+            _ => {}
+        }
+        None
+    }
+
+    fn search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.mode = Mode::Normal,
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.query.clear();
+                self.refresh_filter();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh_filter();
+            }
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.refresh_filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn prompt_key(&mut self, key: KeyEvent, kind: PromptKind, mut input: String) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Backspace => {
+                input.pop();
+                self.mode = Mode::Prompt(kind, input);
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.mode = Mode::Prompt(kind, input);
+            }
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                let input = input.trim().to_string();
+                let session = self.selected()?.clone();
+                if input.is_empty() {
+                    return None;
+                }
+                match kind {
+                    PromptKind::Tag => self.toggle_tag(&session, &input),
+                    PromptKind::Rename => return Some(Action::Rename(session, input)),
+                    PromptKind::Export => return Some(Action::Export(session, input)),
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind, initial: String) {
+        if self.selected().is_some() {
+            self.mode = Mode::Prompt(kind, initial);
+        }
+    }
+
+    /// Re-run the search: FTS hits on message text plus name and first-message
+    /// matches, kept in list order
+    fn refresh_filter(&mut self) {
+        self.snippets.clear();
+        let query = self.query.trim().to_lowercase();
+        if query.is_empty() {
+            self.visible = (0..self.sessions.len()).collect();
+        } else {
+            let hits = self
+                .db
+                .read(|conn| sqlite_cache::search_message_fts(conn, &query, None, FTS_LIMIT));
+            match hits {
+                Ok(hits) => {
+                    for (_, path, role, snippet, _, _) in hits {
+                        let snippet = snippet.replace("<b>", "").replace("</b>", "");
+                        self.snippets
+                            .entry(path)
+                            .or_insert_with(|| format!("{role}: {}", one_line(&snippet, 200)));
+                    }
+                }
+                Err(e) => self.status = e,
+            }
+            self.visible = (0..self.sessions.len())
+                .filter(|&i| {
+                    let s = &self.sessions[i];
+                    self.snippets.contains_key(&s.path)
+                        || s.name
+                            .as_deref()
+                            .is_some_and(|n| n.to_lowercase().contains(&query))
+                        || s.first_message.to_lowercase().contains(&query)
+                })
+                .collect();
+        }
+        self.list_state.select(Some(0));
+        self.clamp_selection();
+    }
+
+    fn toggle_favorite(&mut self) {
+        let Some(session) = self.selected().cloned() else {
+            return;
+        };
+        let name = session
+            .name
+            .clone()
+            .unwrap_or_else(|| session.first_message.clone());
+        let (id, path) = (session.id.clone(), session.path.clone());
+        let toggled = self
+            .db
+            .write(move |conn| sqlite_cache::toggle_favorite(conn, &id, "session", &name, &path));
+        match toggled {
+            Ok(true) => {
+                self.favorites.insert(session.id);
+                self.status = "Added to favorites".to_string();
+            }
+            Ok(false) => {
+                self.favorites.remove(&session.id);
+                self.status = "Removed from favorites".to_string();
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    /// Assign the tag, or remove it when the session already has it
+    fn toggle_tag(&mut self, session: &SessionInfo, tag: &str) {
+        let tag = match self.db.read(|conn| sqlite_cache::find_tag(conn, tag)) {
+            Ok(Some(tag)) => tag,
+            Ok(None) => {
+                self.status = format!("Unknown tag: {tag}");
+                return;
+            }
+            Err(e) => {
+                self.status = e;
+                return;
+            }
+        };
+        let has_tag = self
+            .tags
+            .get(&session.id)
+            .is_some_and(|names| names.contains(&tag.name));
+        let (session_id, tag_id) = (session.id.clone(), tag.id.clone());
+        let result = self.db.write(move |conn| {
+            if has_tag {
+                sqlite_cache::remove_tag_from_session(conn, &session_id, &tag_id)
+            } else {
+                sqlite_cache::assign_tag(conn, &session_id, &tag_id)
+            }
+        });
+        self.status = match result {
+            Ok(()) if has_tag => format!("Removed tag {}", tag.name),
+            Ok(()) => format!("Tagged {}", tag.name),
+            Err(e) => e,
+        };
+        self.tags = tag_names();
+    }
+
+    fn renamed(&mut self, path: &str, name: String) {
+        if let Some(s) = self.sessions.iter_mut().find(|s| s.path == path) {
+            s.name = Some(name.clone());
+        }
+        self.status = format!("Renamed to {name}");
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        self.list_state
+            .select(Some((current + delta).max(0) as usize));
+        self.clamp_selection();
+    }
+
+    fn clamp_selection(&mut self) {
+        let last = self.visible.len().saturating_sub(1);
+        let selected = self.list_state.selected().unwrap_or(0).min(last);
+        self.list_state.select(Some(selected));
+        let path = self.selected().map(|s| s.path.clone());
+        if self.loaded.as_ref().map(|(p, _)| p) != path.as_ref() {
+            self.scroll = 0;
+        }
+    }
+
+    fn scroll_by(&mut self, delta: i32) {
+        self.scroll = (self.scroll as i32 + delta).clamp(0, u16::MAX as i32) as u16;
+    }
+
+    /// Active-branch entries of the selected session, read on first view
+    fn selected_entries(&mut self) -> &[SessionEntry] {
+        let path = self.selected().map(|s| s.path.clone());
+        if self.loaded.as_ref().map(|(p, _)| p) != path.as_ref() {
+            self.loaded = path.map(|path| {
+                let entries = fs::read_to_string(&path)
+                    .map(|content| {
+                        SessionTree::build(session_parser::parse_entries(&content))
+                            .into_active_entries()
+                    })
+                    .unwrap_or_default();
+                (path, entries)
+            });
+        }
+        self.loaded.as_ref().map_or(&[], |(_, entries)| entries)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, main_area, footer_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, message_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main_area);
+
+        self.draw_search(frame, search_area);
+        self.draw_list(frame, list_area);
+        self.draw_messages(frame, message_area);
+        self.draw_footer(frame, footer_area);
+    }
+
+    fn draw_search(&self, frame: &mut Frame, area: Rect) {
+        let active = self.mode == Mode::Search;
+        let text = if active || !self.query.is_empty() {
+            Line::from(vec![
+                Span::raw(self.query.clone()),
+                Span::styled(if active { "█" } else { "" }, Style::new().fg(Color::Gray)),
+            ])
+        } else {
+            Line::styled(
+                "press / to search messages",
+                Style::new().fg(Color::DarkGray),
+            )
+        };
+        let block = Block::bordered()
+            .title(" Search ")
+            .border_style(border(active));
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(4) as usize;
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|&i| {
+                let s = &self.sessions[i];
+                let star = if self.favorites.contains(&s.id) {
+                    "★ "
+                } else {
+                    ""
+                };
+                let title = s.name.as_deref().unwrap_or(&s.first_message);
+                let mut detail = match self.snippets.get(&s.path) {
+                    Some(snippet) => snippet.clone(),
+                    None => format!(
+                        "{} · {} msgs · {}",
+                        s.modified.format("%Y-%m-%d %H:%M"),
+                        s.message_count,
+                        s.cwd
+                    ),
+                };
+                if let Some(tags) = self.tags.get(&s.id) {
+                    detail = format!("#{} {detail}", tags.join(" #"));
+                }
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::styled(star, Style::new().fg(Color::Yellow)),
+                        Span::raw(one_line(title, width.max(2))),
+                    ]),
+                    Line::styled(
+                        one_line(&detail, width.max(2)),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect();
+        let title = format!(
+            " Sessions ({}/{}) ",
+            self.visible.len(),
+            self.sessions.len()
+        );
+        let list = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(border(self.focus == Focus::Sessions)),
+            )
+            .highlight_style(
+                Style::new()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_stateful_widget(list, area, &mut self.list_state);
+    }
+
+    fn draw_messages(&mut self, frame: &mut Frame, area: Rect) {
+        let expand = self.expand_tools;
+        let title = self
+            .selected()
+            .map(|s| format!(" {} ", s.name.as_deref().unwrap_or(&s.id)))
+            .unwrap_or_default();
+        let lines = render_entries(self.selected_entries(), expand);
+        let max_scroll = lines.len().saturating_sub(1).min(u16::MAX as usize) as u16;
+        self.scroll = self.scroll.min(max_scroll);
+        let paragraph = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(border(self.focus == Focus::Messages)),
+            );
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = match &self.mode {
+            Mode::Prompt(kind, input) => {
+                let label = match kind {
+                    PromptKind::Tag => "Toggle tag",
+                    PromptKind::Rename => "Rename to",
+                    PromptKind::Export => "Export to (.md .html .json .jsonl)",
+                };
+                Line::from(vec![
+                    Span::styled(format!("{label}: "), Style::new().fg(Color::Cyan)),
+                    Span::raw(format!("{input}█")),
+                ])
+            }
+            _ if !self.status.is_empty() => Line::raw(self.status.clone()),
+            _ => Line::styled(
+                "/ search  ⏎ open  x tools  f favorite  t tag  r rename  e export  R resume  q quit",
+                Style::new().fg(Color::DarkGray),
+            ),
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+/// Message pane lines; thinking and tool calls collapse to one line each
+/// unless `expand_tools` is set
+pub fn render_entries(entries: &[SessionEntry], expand_tools: bool) -> Vec<Line<'static>> {
+    let dim = Style::new().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    for entry in entries {
+        if let Some(compaction) = &entry.compaction {
+            lines.push(Line::styled(
+                format!("── compacted: {}", one_line(&compaction.summary, 120)),
+                dim,
+            ));
+            lines.push(Line::default());
+            continue;
+        }
+        if let Some(branch) = &entry.branch_summary {
+            lines.push(Line::styled(
+                format!("── branch: {}", one_line(&branch.summary, 120)),
+                dim,
+            ));
+            lines.push(Line::default());
+            continue;
+        }
+        let Some(message) = &entry.message else {
+            continue;
+        };
+
+        let (label, color) = match message.role.as_str() {
+            "user" => ("You", Color::Cyan),
+            "assistant" => ("Assistant", Color::Green),
+            "toolResult" | "bashExecution" => ("Tool", Color::Yellow),
+            other => (other, Color::Magenta),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                label.to_string(),
+                Style::new().fg(color).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                entry
+                    .timestamp
+                    .map(|t| format!("  {}", t.format("%Y-%m-%d %H:%M:%S")))
+                    .unwrap_or_default(),
+                dim,
+            ),
+        ]));
+
+        for content in &message.content {
+            match content {
+                Content::Text { text } => {
+                    lines.extend(text.lines().map(|l| Line::raw(l.to_string())));
+                }
+                Content::Thinking { thinking } if expand_tools => {
+                    lines.extend(thinking.lines().map(|l| Line::styled(l.to_string(), dim)));
+                }
+                Content::Thinking { thinking } => {
+                    let count = thinking.lines().count();
+                    lines.push(Line::styled(format!("▸ thinking ({count} lines)"), dim));
+                }
+                Content::ToolCall {
+                    name, arguments, ..
+                } => {
+                    let style = Style::new().fg(Color::Magenta);
+                    if expand_tools {
+                        lines.push(Line::styled(format!("▾ {name}"), style));
+                        let pretty = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                        lines.extend(pretty.lines().map(|l| Line::styled(format!("  {l}"), dim)));
+                    } else {
+                        lines.push(Line::styled(
+                            format!("▸ {name} {}", one_line(&tool_args(arguments), 80)),
+                            style,
+                        ));
+                    }
+                }
+                Content::ToolResult {
+                    output, is_error, ..
+                } => {
+                    let style = if *is_error {
+                        Style::new().fg(Color::Red)
+                    } else {
+                        dim
+                    };
+                    if expand_tools {
+                        lines.extend(output.lines().map(|l| Line::styled(l.to_string(), style)));
+                    } else {
+                        let count = output.lines().count();
+                        let first = output.lines().next().unwrap_or_default();
+                        lines.push(Line::styled(
+                            format!("▸ output ({count} lines) {}", one_line(first, 60)),
+                            style,
+                        ));
+                    }
+                }
+                Content::Image { mime, .. } => {
+                    lines.push(Line::styled(format!("[image {mime}]"), dim));
+                }
+            }
+        }
+        lines.push(Line::default());
+    }
+    lines
+}
+
+/// A short argument summary: the command or path when there is one
+fn tool_args(arguments: &Value) -> String {
+    for key in ["command", "path", "file_path", "pattern", "query", "url"] {
+        if let Some(value) = arguments[key].as_str() {
+            return value.to_string();
+        }
+    }
+    arguments.to_string()
+}
+
+fn format_for(output: &str) -> &'static str {
+    match Path::new(output).extension().and_then(|e| e.to_str()) {
+        Some("html") => "html",
+        Some("json") => "json",
+        Some("jsonl") => "openai-jsonl",
+        _ => "md",
+    }
+}
+
+fn border(active: bool) -> Style {
+    if active {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new().fg(Color::DarkGray)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::SessionInfo;
+use crate::session_cursor::SessionCursor;
 use crate::session_parser::SessionDetails;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
@@ -15,9 +16,29 @@ const MAX_DETAILS_BUFFER: usize = 1000;
 pub struct SessionCacheEntry {
     pub session: SessionInfo,
     pub file_modified: DateTime<Utc>,
+    /// 增量解析得到会话时对应的游标，与会话一起写入
+    pub cursor: Option<SessionCursor>,
     pub cached_at: Instant,
 }
 
+impl SessionCacheEntry {
+    /// 写入数据库；带游标的会话只追加新的消息条目
+    pub fn save(&self, conn: &rusqlite::Connection) -> Result<(), String> {
+        match &self.cursor {
+            Some(cursor) => crate::sqlite_cache::upsert_session_with_cursor(
+                conn,
+                &self.session,
+                self.file_modified,
+                cursor,
+                None,
+            ),
+            None => {
+                crate::sqlite_cache::upsert_session(conn, &self.session, self.file_modified, None)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DetailsCacheEntry {
     pub path: String,
@@ -56,6 +77,15 @@ fn get_buffer() -> &'static Mutex<WriteBuffer> {
 
 /// 缓冲写入会话缓存，减少数据库写入频率
 pub fn buffer_session_write(session: &SessionInfo, file_modified: DateTime<Utc>) {
+    buffer_session_cursor_write(session, file_modified, None);
+}
+
+/// 缓冲写入增量解析的会话及其游标
+pub fn buffer_session_cursor_write(
+    session: &SessionInfo,
+    file_modified: DateTime<Utc>,
+    cursor: Option<SessionCursor>,
+) {
     if let Ok(mut buffer) = get_buffer().lock() {
         let path = session.path.clone();
         buffer.sessions.insert(
@@ -63,6 +93,7 @@ pub fn buffer_session_write(session: &SessionInfo, file_modified: DateTime<Utc>)
             SessionCacheEntry {
                 session: session.clone(),
                 file_modified,
+                cursor,
                 cached_at: Instant::now(),
             },
         );
@@ -126,6 +157,13 @@ pub fn get_buffered_session(path: &str) -> Option<(SessionInfo, DateTime<Utc>)>
     None
 }
 
+/// 获取内存中缓冲的会话及其游标（仅增量解析写入的会话有游标）
+pub fn get_buffered_cursor(path: &str) -> Option<(SessionInfo, SessionCursor)> {
+    let buffer = get_buffer().lock().ok()?;
+    let entry = buffer.sessions.get(path)?;
+    Some((entry.session.clone(), entry.cursor.clone()?))
+}
+
 /// 获取内存中缓冲的详情（如果存在且未过期）
 pub fn get_buffered_details(path: &str) -> Option<(SessionDetails, DateTime<Utc>)> {
     if let Ok(buffer) = get_buffer().lock() {
@@ -136,6 +174,20 @@ pub fn get_buffered_details(path: &str) -> Option<(SessionDetails, DateTime<Utc>
     None
 }
 
+/// 会话文件被移动后，把缓冲中旧路径的写入改挂到新路径（旧文件已不存在，不能再按旧路径写入）
+pub fn relocate(old_path: &str, new_path: &str) {
+    if let Ok(mut buffer) = get_buffer().lock() {
+        if let Some(mut entry) = buffer.sessions.remove(old_path) {
+            entry.session.path = new_path.to_string();
+            buffer.sessions.insert(new_path.to_string(), entry);
+        }
+        if let Some(mut entry) = buffer.details.remove(old_path) {
+            entry.path = new_path.to_string();
+            buffer.details.insert(new_path.to_string(), entry);
+        }
+    }
+}
+
 /// 检查是否需要刷新缓冲并获取待写入的数据
 pub fn check_and_take_flush_data() -> Option<(Vec<SessionCacheEntry>, Vec<DetailsCacheEntry>)> {
     if let Ok(mut buffer) = get_buffer().lock() {
@@ -164,6 +216,43 @@ pub fn force_flush_all() -> Option<(Vec<SessionCacheEntry>, Vec<DetailsCacheEntr
     None
 }
 
+/// 在一个事务里写入取出的缓冲数据
+pub fn save_entries(
+    conn: &rusqlite::Connection,
+    sessions: &[SessionCacheEntry],
+    details: &[DetailsCacheEntry],
+) -> Result<(), String> {
+    // 全文索引在整批写完后只提交一次
+    let _batch = crate::tantivy_search::batch();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to begin transaction: {e}"))?;
+    for entry in details {
+        crate::sqlite_cache::upsert_session_details_cache(
+            &tx,
+            &entry.path,
+            entry.file_modified,
+            &entry.details,
+        )?;
+    }
+    for entry in sessions {
+        entry.save(&tx)?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit buffered writes: {e}"))
+}
+
+/// 立即把所有缓冲交给数据库写线程写入，返回写入的会话（CLI 等短生命周期进程使用）
+pub fn flush_to_db() -> Result<Vec<SessionInfo>, String> {
+    let Some((sessions, details)) = force_flush_all() else {
+        return Ok(Vec::new());
+    };
+    crate::database::get()?.write(move |conn| {
+        save_entries(conn, &sessions, &details)?;
+        Ok(sessions.into_iter().map(|entry| entry.session).collect())
+    })
+}
+
 /// 获取当前缓冲统计信息（用于调试）
 pub fn get_buffer_stats() -> (usize, usize, u64) {
     if let Ok(buffer) = get_buffer().lock() {
@@ -1,11 +1,14 @@
 use crate::app_state::{SharedAppState, WsEvent};
+use crate::auth::{self, Caller};
+use crate::tls::TlsIdentity;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tauri::Listener;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
 use tokio::sync::broadcast;
 use tokio_tungstenite::{accept_async, tungstenite::Message};
 
@@ -37,20 +40,25 @@ struct WsResponse {
     compressed: Option<bool>,
 }
 
-use crate::dispatch::{extract_string, extract_usize};
-
 pub struct WsAdapter {
     app_state: SharedAppState,
     bind_addr: String,
     port: u16,
+    tls: Option<TlsIdentity>,
 }
 
 impl WsAdapter {
-    pub fn new(app_state: SharedAppState, bind_addr: &str, port: u16) -> Self {
+    pub fn new(
+        app_state: SharedAppState,
+        bind_addr: &str,
+        port: u16,
+        tls: Option<TlsIdentity>,
+    ) -> Self {
         Self {
             app_state,
             bind_addr: bind_addr.to_string(),
             port,
+            tls,
         }
     }
 
@@ -63,15 +71,25 @@ impl WsAdapter {
             .await
             .map_err(|e| format!("Failed to bind: {e}"))?;
 
-        log::info!("WebSocket server listening on ws://{addr}");
+        let scheme = if self.tls.is_some() { "wss" } else { "ws" };
+        log::info!("WebSocket server listening on {scheme}://{addr}");
+        let acceptor = self.tls.as_ref().map(TlsIdentity::acceptor);
 
         self.clone().start_event_forwarding();
 
         while let Ok((stream, peer_addr)) = listener.accept().await {
             log::info!("New WebSocket connection from: {peer_addr}");
             let adapter = self.clone();
+            let acceptor = acceptor.clone();
             tokio::spawn(async move {
-                if let Err(e) = adapter.handle_connection(stream, peer_addr).await {
+                let result = match acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => adapter.handle_connection(stream, peer_addr).await,
+                        Err(e) => Err(e.into()),
+                    },
+                    None => adapter.handle_connection(stream, peer_addr).await,
+                };
+                if let Err(e) = result {
                     let msg = e.to_string();
                     if msg.contains("Connection reset")
                         || msg.contains("Broken pipe")
@@ -88,43 +106,48 @@ impl WsAdapter {
         Ok(())
     }
 
-    async fn handle_connection(
+    async fn handle_connection<S>(
         &self,
-        stream: TcpStream,
+        stream: S,
         peer_addr: SocketAddr,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
         let ws_stream = accept_async(stream).await?;
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
         // Non-local connections must authenticate (if auth enabled)
-        if crate::auth::is_auth_required(&peer_addr.ip()) {
-            let authed =
-                match tokio::time::timeout(std::time::Duration::from_secs(10), ws_receiver.next())
-                    .await
-                {
-                    Ok(Some(Ok(Message::Text(text)))) => {
-                        serde_json::from_str::<serde_json::Value>(&text)
-                            .ok()
-                            .and_then(|v| v.get("auth")?.as_str().map(String::from))
-                            .map(|t| crate::auth::validate(&t))
-                            .unwrap_or(false)
-                    }
-                    _ => false,
-                };
-
-            if !authed {
-                let _ = ws_sender
-                    .send(Message::Text(r#"{"error":"Unauthorized"}"#.to_string()))
-                    .await;
-                let _ = ws_sender.send(Message::Close(None)).await;
-                return Ok(());
+        let needs_auth = auth::is_auth_required(&peer_addr.ip());
+        let token = if needs_auth {
+            match tokio::time::timeout(std::time::Duration::from_secs(10), ws_receiver.next()).await
+            {
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|v| v.get("auth")?.as_str().map(String::from))
+                }
+                _ => None,
             }
+        } else {
+            None
+        };
+        let Some(caller) = auth::authenticate(peer_addr.ip(), token.as_deref(), "ws") else {
+            let _ = ws_sender
+                .send(Message::Text(r#"{"error":"Unauthorized"}"#.to_string()))
+                .await;
+            let _ = ws_sender.send(Message::Close(None)).await;
+            return Ok(());
+        };
+        if needs_auth {
             let _ = ws_sender
                 .send(Message::Text(r#"{"auth":"ok"}"#.to_string()))
                 .await;
         }
 
         let mut event_rx = self.app_state.subscribe_events();
+        let (tails, mut tail_rx) =
+            crate::session_tail::Subscriptions::new(caller.clone(), self.app_state.clone());
 
         loop {
             tokio::select! {
@@ -154,7 +177,14 @@ impl WsAdapter {
                                         }
                                     }
 
-                                    let result = dispatch(&self.app_state, &request.command, &request.payload).await;
+                                    // The connection's subscriptions host subscribe_session
+                                    let result = crate::command_registry::dispatch_as(
+                                        &caller,
+                                        &request.command,
+                                        request.payload.clone(),
+                                        tails.clone(),
+                                    )
+                                    .await;
                                     let accept_gzip = request.accept_gzip;
                                     let response = self.build_response(&request, result);
 
@@ -199,9 +229,24 @@ impl WsAdapter {
                     }
                 }
 
+                Some(payload) = tail_rx.recv() => {
+                    let ws_event = WsEvent {
+                        event_type: "event".to_string(),
+                        event: crate::session_tail::APPENDED_EVENT.to_string(),
+                        payload,
+                    };
+                    let event_text = serde_json::to_string(&ws_event)?;
+                    if ws_sender.send(Message::Text(event_text)).await.is_err() {
+                        break;
+                    }
+                }
+
                 event = event_rx.recv() => {
                     match event {
-                        Ok(ws_event) => {
+                        Ok(mut ws_event) => {
+                            if !crate::command_registry::event_for_caller(&caller, &ws_event.event, &mut ws_event.payload).await {
+                                continue;
+                            }
                             let event_text = serde_json::to_string(&ws_event)?;
                             if ws_sender.send(Message::Text(event_text)).await.is_err() {
                                 break;
@@ -221,31 +266,6 @@ impl WsAdapter {
         Ok(())
     }
 
-    async fn handle_request(&self, request: WsRequest) -> WsResponse {
-        log::debug!("Handling command: {} (id: {})", request.command, request.id);
-
-        let result = dispatch(&self.app_state, &request.command, &request.payload).await;
-
-        match result {
-            Ok(data) => WsResponse {
-                id: request.id,
-                command: request.command,
-                success: true,
-                data: Some(data),
-                error: None,
-                compressed: None,
-            },
-            Err(error) => WsResponse {
-                id: request.id,
-                command: request.command,
-                success: false,
-                data: None,
-                error: Some(error),
-                compressed: None,
-            },
-        }
-    }
-
     fn build_response(&self, request: &WsRequest, result: Result<Value, String>) -> WsResponse {
         match result {
             Ok(data) => WsResponse {
@@ -324,94 +344,24 @@ impl WsAdapter {
     }
 }
 
+/// Run a remote client's command with the desktop app as host (terminal,
+/// watcher restart, events to both the webview and WebSocket clients)
 pub async fn dispatch(
     app_state: &SharedAppState,
+    caller: &Caller,
     command: &str,
     payload: &Value,
 ) -> Result<Value, String> {
-    // GUI-only overrides that need AppState (terminal, save_session_paths with watcher)
-    match command {
-        "save_session_paths" => {
-            let paths: Vec<String> = serde_json::from_value(
-                payload
-                    .get("paths")
-                    .cloned()
-                    .unwrap_or(Value::Array(vec![])),
-            )
-            .map_err(|e| format!("Invalid paths: {e}"))?;
-            let app_handle = app_state.app_handle.clone();
-            crate::save_session_paths(paths, app_handle).await?;
-            return Ok(Value::Null);
-        }
-        "terminal_create" => {
-            let id = extract_string(payload, "id")?;
-            let cwd = extract_string(payload, "cwd")?;
-            let shell = extract_string(payload, "shell")?;
-            let rows = payload.get("rows").and_then(|v| v.as_u64()).unwrap_or(24) as u16;
-            let cols = payload.get("cols").and_then(|v| v.as_u64()).unwrap_or(80) as u16;
-            let app_handle = app_state.app_handle.clone();
-            let event_tx = app_state.event_tx.clone();
-            let manager = app_state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.create_session(id, app_handle, event_tx, cwd, shell, rows, cols)?;
-            return Ok(serde_json::json!("Terminal created"));
-        }
-        "terminal_write" => {
-            let id = extract_string(payload, "id")?;
-            let data = extract_string(payload, "data")?;
-            let manager = app_state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.write_to_session(&id, data)?;
-            return Ok(Value::Null);
-        }
-        "terminal_resize" => {
-            let id = extract_string(payload, "id")?;
-            let rows = payload.get("rows").and_then(|v| v.as_u64()).unwrap_or(24) as u16;
-            let cols = payload.get("cols").and_then(|v| v.as_u64()).unwrap_or(80) as u16;
-            let manager = app_state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.resize_session(&id, rows, cols)?;
-            return Ok(Value::Null);
-        }
-        "terminal_close" => {
-            let id = extract_string(payload, "id")?;
-            let manager = app_state
-                .terminal_manager
-                .lock()
-                .map_err(|e| format!("Failed to lock terminal manager: {e}"))?;
-            manager.close_session(&id)?;
-            return Ok(Value::Null);
-        }
-        "get_default_shell" => {
-            let shells = crate::commands::terminal::scan_shells();
-            let fallback = if cfg!(windows) { "cmd.exe" } else { "/bin/sh" };
-            return Ok(serde_json::json!(shells
-                .first()
-                .map(|(_, p)| p.as_str())
-                .unwrap_or(fallback)));
-        }
-        "get_available_shells" => {
-            return Ok(serde_json::json!(crate::commands::terminal::scan_shells()));
-        }
-        _ => {}
-    }
-
-    // Delegate to shared dispatch (pure business logic)
-    crate::dispatch::dispatch(command, payload).await
+    crate::command_registry::dispatch_as(caller, command, payload.clone(), app_state.clone()).await
 }
 
 pub async fn init_ws_adapter(
     app_state: SharedAppState,
     bind_addr: &str,
     port: u16,
+    tls: Option<TlsIdentity>,
 ) -> Result<Arc<WsAdapter>, String> {
-    let adapter = Arc::new(WsAdapter::new(app_state, bind_addr, port));
+    let adapter = Arc::new(WsAdapter::new(app_state, bind_addr, port, tls));
     let adapter_clone = adapter.clone();
 
     tokio::spawn(async move {
@@ -0,0 +1,272 @@
+#![allow(clippy::await_holding_lock)]
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use chrono::{Duration, Utc};
+use http_body_util::BodyExt;
+use lazy_static::lazy_static;
+use pi_session_manager::auth::{self, Caller, Scope, TokenOptions};
+use pi_session_manager::command_registry::{self, NoHost};
+use pi_session_manager::{rest_api, scanner, sqlite_cache};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use tower::ServiceExt;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+const API_SESSION: &str = r#"{"type":"session","version":3,"id":"api-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/api"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"hello api"}]}}
+"#;
+
+const SECRET_SESSION: &str = r#"{"type":"session","version":3,"id":"secret-2","timestamp":"2026-02-09T10:00:00Z","cwd":"/secret"}
+{"type":"message","id":"s1","parentId":null,"timestamp":"2026-02-09T10:00:01Z","message":{"role":"user","content":[{"type":"text","text":"hello secret"}]}}
+"#;
+
+const REMOTE: &str = "10.0.0.7:6000";
+
+fn remote_ip() -> IpAddr {
+    REMOTE.parse::<SocketAddr>().unwrap().ip()
+}
+
+fn caller(token: &str) -> Caller {
+    auth::authenticate(remote_ip(), Some(token), "http").expect("valid token")
+}
+
+async fn run(caller: &Caller, command: &str, payload: Value) -> Result<Value, String> {
+    command_registry::dispatch_as(caller, command, payload, Arc::new(NoHost)).await
+}
+
+async fn rest_get(app: &Router, uri: &str, token: &str) -> (StatusCode, Value) {
+    let mut request = Request::get(uri)
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let addr: SocketAddr = REMOTE.parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(addr));
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn test_token_scopes_paths_expiry_and_audit_log() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj--");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("api.jsonl"), API_SESSION).unwrap();
+    fs::write(dir.join("secret.jsonl"), SECRET_SESSION).unwrap();
+    let secret_path = dir.join("secret.jsonl").to_string_lossy().to_string();
+    let api_path = dir.join("api.jsonl").to_string_lossy().to_string();
+
+    sqlite_cache::init_db().unwrap();
+    auth::init().unwrap();
+    let read = auth::create_token(
+        "teammate",
+        None,
+        &TokenOptions {
+            scope: Scope::Read,
+            expires_at: Some(Utc::now() + Duration::days(7)),
+            allowed_paths: Vec::new(),
+        },
+    )
+    .unwrap();
+    let search = auth::create_token(
+        "searcher",
+        Some("search-only-token"),
+        &TokenOptions {
+            scope: Scope::Search,
+            ..TokenOptions::default()
+        },
+    )
+    .unwrap();
+    let scoped = auth::create_token(
+        "api-only",
+        Some("api-only-token"),
+        &TokenOptions {
+            scope: Scope::Write,
+            expires_at: None,
+            allowed_paths: vec!["/proj/".to_string()],
+        },
+    )
+    .unwrap();
+    let admin = auth::create_token("ops", None, &TokenOptions::default()).unwrap();
+    let past = TokenOptions {
+        expires_at: Some(Utc::now() - Duration::hours(1)),
+        ..TokenOptions::default()
+    };
+    assert!(auth::create_token("late", None, &past).is_err());
+
+    let keys = auth::list_tokens().unwrap();
+    let teammate = keys.iter().find(|k| k.name == "teammate").unwrap();
+    assert_eq!(teammate.scope, Scope::Read);
+    assert!(teammate.expires_at.is_some() && !teammate.expired);
+    let default = keys.iter().find(|k| k.name == "default").unwrap();
+    assert_eq!(default.scope, Scope::Admin);
+
+    // Local callers keep full access and are not audited
+    let local = auth::authenticate(IpAddr::from([127, 0, 0, 1]), None, "http").unwrap();
+    assert!(local.grant.is_none() && local.allows(Scope::Admin));
+    assert!(auth::authenticate(remote_ip(), None, "http").is_none());
+    assert!(auth::authenticate(remote_ip(), Some("bogus"), "ws").is_none());
+
+    // A read-only token can read but not write or open a terminal
+    let reader = caller(&read);
+    let sessions = run(&reader, "scan_sessions", Value::Null).await.unwrap();
+    assert_eq!(sessions.as_array().unwrap().len(), 2);
+    let err = run(&reader, "delete_session", json!({ "path": secret_path }))
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("Forbidden"), "{err}");
+    assert!(fs::metadata(&secret_path).is_ok());
+    let terminal = json!({ "id": "t1", "cwd": "/", "shell": "/bin/sh" });
+    let err = run(&reader, "terminal_create", terminal.clone())
+        .await
+        .unwrap_err();
+    assert!(err.contains("requires the admin scope"), "{err}");
+    assert!(run(&reader, "list_api_keys", Value::Null).await.is_err());
+
+    // Search tokens only search
+    let searcher = caller(&search);
+    assert!(run(&searcher, "scan_sessions", Value::Null).await.is_err());
+    let query = json!({ "query": "hello", "roleFilter": "all" });
+    run(&searcher, "full_text_search", query).await.unwrap();
+
+    // Admin tokens reach the terminal (which this host does not provide)
+    let err = run(&caller(&admin), "terminal_create", terminal)
+        .await
+        .unwrap_err();
+    assert!(!err.starts_with("Forbidden"), "{err}");
+
+    // Path-restricted tokens only see sessions under their directories
+    let api_only = caller(&scoped);
+    let sessions = run(&api_only, "scan_sessions", Value::Null).await.unwrap();
+    let ids: Vec<&str> = sessions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["api-1"]);
+    assert!(run(
+        &api_only,
+        "get_session_entries",
+        json!({ "path": api_path })
+    )
+    .await
+    .is_ok());
+    let err = run(
+        &api_only,
+        "get_session_entries",
+        json!({ "path": secret_path }),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.contains("outside the token's allowed paths"), "{err}");
+    let err = run(&api_only, "load_app_settings", Value::Null)
+        .await
+        .unwrap_err();
+    assert!(err.contains("path-restricted"), "{err}");
+
+    // Batch exports must name their sessions, so a query cannot reach others
+    let out = temp_dir
+        .path()
+        .join("out.zip")
+        .to_string_lossy()
+        .to_string();
+    for paths in [None, Some(Value::Null), Some(json!([]))] {
+        let mut payload =
+            json!({ "query": "hello", "format": "md", "archive": "zip", "outputPath": out });
+        if let Some(paths) = paths {
+            payload["paths"] = paths;
+        }
+        let err = run(&api_only, "export_sessions_batch", payload)
+            .await
+            .unwrap_err();
+        assert!(err.contains("must list the sessions"), "{err}");
+    }
+    assert!(fs::metadata(&out).is_err());
+
+    // The cached path index follows sessions that appear later
+    let late_path = dir.join("late.jsonl");
+    fs::write(&late_path, API_SESSION.replace("api-1", "api-late")).unwrap();
+    scanner::invalidate_cache();
+    let late_path = late_path.to_string_lossy().to_string();
+    assert!(run(
+        &api_only,
+        "get_session_entries",
+        json!({ "path": late_path })
+    )
+    .await
+    .is_ok());
+    fs::remove_file(&late_path).unwrap();
+    scanner::invalidate_cache();
+
+    // The REST routes apply the same rules
+    let app: Router = rest_api::router(|ip, _| ip, Arc::new(NoHost));
+    let (status, sessions) = rest_get(&app, "/api/v1/sessions", &scoped).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+    let (status, _) = rest_get(&app, "/api/v1/sessions/secret-2", &scoped).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = rest_get(&app, "/api/v1/sessions", &search).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = rest_get(&app, "/api/v1/search?q=hello", &search).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Expired tokens stop working
+    let db = temp_dir.path().join(".pi/agent/sessions/user.db");
+    let conn = Connection::open(&db).unwrap();
+    conn.execute(
+        "UPDATE auth_tokens SET expires_at = ?1 WHERE name = 'teammate'",
+        [(Utc::now() - Duration::minutes(1)).to_rfc3339()],
+    )
+    .unwrap();
+    auth::init().unwrap();
+    assert!(!auth::validate(&read));
+    assert!(auth::authenticate(remote_ip(), Some(&read), "http").is_none());
+    let keys = auth::list_tokens().unwrap();
+    assert!(keys.iter().find(|k| k.name == "teammate").unwrap().expired);
+
+    // Every remote request left an audit record
+    let log = auth::list_audit_log(500).unwrap();
+    let outcome = |command: &str, outcome: &str| {
+        log.iter()
+            .any(|e| e.command == command && e.outcome == outcome)
+    };
+    assert!(outcome("scan_sessions", "ok"));
+    assert!(outcome("delete_session", "denied"));
+    assert!(outcome("terminal_create", "denied"));
+    assert!(outcome("connect", "unauthorized"));
+    // REST routes are audited under the commands they run
+    assert!(outcome("scan_sessions", "denied"));
+    let denied = log.iter().find(|e| e.command == "delete_session").unwrap();
+    assert_eq!(denied.ip, "10.0.0.7");
+    assert_eq!(denied.transport, "http");
+    let preview = format!("{}…", &read[..8]);
+    assert_eq!(denied.token_preview.as_deref(), Some(preview.as_str()));
+    assert!(log.iter().all(|e| e.ip != "127.0.0.1"));
+    assert!(log.windows(2).all(|w| w[0].id > w[1].id));
+
+    let entries = run(&caller(&admin), "list_audit_log", json!({ "limit": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,260 @@
+#![allow(clippy::await_holding_lock)]
+use lazy_static::lazy_static;
+use pi_session_manager::config::Config;
+use pi_session_manager::{backup, database, settings_store, sqlite_cache};
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use tempfile::{tempdir, TempDir};
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn fresh_home() -> TempDir {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    fs::create_dir_all(temp_dir.path().join(".pi/agent/sessions")).unwrap();
+    temp_dir
+}
+
+/// A custom tag on a session, an auto rule, a renamed builtin and a favorite
+async fn curate() -> String {
+    let tag = pi_session_manager::create_tag("Urgent".into(), "warning".into(), None, None)
+        .await
+        .unwrap();
+    pi_session_manager::assign_tag("session-1".into(), tag.id.clone())
+        .await
+        .unwrap();
+    pi_session_manager::update_tag_auto_rules(tag.id.clone(), Some("[\"fix\"]".into()))
+        .await
+        .unwrap();
+    pi_session_manager::update_tag(
+        "builtin-done".into(),
+        Some("Shipped".into()),
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    pi_session_manager::add_favorite(
+        "session-1".into(),
+        "session".into(),
+        "First".into(),
+        "/p/s1.jsonl".into(),
+    )
+    .await
+    .unwrap();
+    settings_store::set("theme", &"dark").unwrap();
+    tag.id
+}
+
+async fn assert_curated(tag_id: &str) {
+    let tags = pi_session_manager::get_all_tags().await.unwrap();
+    let urgent = tags.iter().find(|t| t.id == tag_id).expect("custom tag");
+    assert_eq!(urgent.auto_rules.as_deref(), Some("[\"fix\"]"));
+    let done = tags.iter().find(|t| t.id == "builtin-done").unwrap();
+    assert_eq!(done.name, "Shipped");
+    let assigned = pi_session_manager::get_all_session_tags().await.unwrap();
+    assert!(assigned
+        .iter()
+        .any(|a| a.session_id == "session-1" && a.tag_id == tag_id));
+    assert!(pi_session_manager::is_favorite("session-1".into())
+        .await
+        .unwrap());
+    assert_eq!(
+        settings_store::get::<String>("theme").unwrap().as_deref(),
+        Some("dark")
+    );
+}
+
+#[tokio::test]
+async fn test_export_then_import_into_a_new_database() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let old_home = fresh_home();
+    let tag_id = curate().await;
+    let export_path = old_home.path().join("user-data.json");
+    let summary = pi_session_manager::export_user_data(export_path.to_string_lossy().into(), None)
+        .await
+        .unwrap();
+    assert_eq!(summary.rows["favorites"], 1);
+    assert_eq!(summary.rows["session_tags"], 1);
+    // Tokens stay behind unless asked for
+    assert!(!summary.rows.contains_key("auth_tokens"));
+    let data = backup::read_file(&export_path).unwrap();
+    assert_eq!(data.format, backup::FORMAT);
+    assert_eq!(data.version, backup::FORMAT_VERSION);
+
+    let _new_home = fresh_home();
+    pi_session_manager::import_user_data(export_path.to_string_lossy().into())
+        .await
+        .unwrap();
+    assert_curated(&tag_id).await;
+
+    // Importing again replaces rows instead of duplicating them
+    pi_session_manager::import_user_data(export_path.to_string_lossy().into())
+        .await
+        .unwrap();
+    assert_eq!(
+        pi_session_manager::get_all_session_tags()
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_import_rejects_other_formats() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = fresh_home();
+    let path = home.path().join("other.json");
+    fs::write(
+        &path,
+        r#"{"format":"something-else","version":1,"exported_at":"2026-01-01T00:00:00Z","schema_version":8,"tables":{}}"#,
+    )
+    .unwrap();
+    let err = pi_session_manager::import_user_data(path.to_string_lossy().into())
+        .await
+        .unwrap_err();
+    assert!(err.contains("Not a user data export"), "{err}");
+}
+
+#[tokio::test]
+async fn test_backups_rotate_and_are_only_taken_when_due() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    database::get().unwrap();
+
+    let config = Config {
+        backup_keep: 2,
+        ..Default::default()
+    };
+    let first = backup::backup_if_due(&config)
+        .unwrap()
+        .expect("no backup yet");
+    assert!(backup::backup_if_due(&config).unwrap().is_none());
+    for _ in 0..3 {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        backup::backup_now(config.backup_keep).unwrap();
+    }
+    let backups = backup::list_backups().unwrap();
+    assert_eq!(backups.len(), 2);
+    assert!(!backups.contains(&first));
+
+    let disabled = Config {
+        backup_interval_hours: 0,
+        ..Default::default()
+    };
+    assert!(backup::backup_if_due(&disabled).unwrap().is_none());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_backups_are_private_to_the_owner() {
+    use std::os::unix::fs::PermissionsExt;
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    database::get().unwrap();
+
+    let path = backup::backup_now(3).unwrap();
+    let mode = |p: &std::path::Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(mode(path.parent().unwrap()), 0o700);
+}
+
+#[tokio::test]
+async fn test_corrupted_cache_keeps_user_data() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = fresh_home();
+    let tag_id = curate().await;
+
+    database::close();
+    let db_path = sqlite_cache::get_db_path().unwrap();
+    fs::write(&db_path, "this is not a database").unwrap();
+    sqlite_cache::init_db().unwrap();
+
+    let sessions_dir = home.path().join(".pi/agent/sessions");
+    assert!(fs::read_dir(&sessions_dir).unwrap().any(|e| e
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .contains(".corrupted.")));
+    // Nothing to restore: the user database was never touched
+    assert!(backup::list_backups().unwrap().is_empty());
+    assert_curated(&tag_id).await;
+}
+
+#[tokio::test]
+async fn test_clear_cache_keeps_user_data() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = fresh_home();
+    let tag_id = curate().await;
+
+    pi_session_manager::clear_cache().await.unwrap();
+    assert!(home.path().join(".pi/agent/sessions/user.db").exists());
+    assert_curated(&tag_id).await;
+}
+
+#[tokio::test]
+async fn test_corrupted_user_database_is_restored_from_latest_backup() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = fresh_home();
+    let tag_id = curate().await;
+    backup::backup_now(3).unwrap();
+
+    database::close();
+    let user_path = sqlite_cache::get_user_db_path().unwrap();
+    fs::write(&user_path, "this is not a database").unwrap();
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(user_path.with_file_name(format!("user.db{suffix}")));
+    }
+    sqlite_cache::init_db().unwrap();
+
+    let sessions_dir = home.path().join(".pi/agent/sessions");
+    let moved_aside: Vec<String> = fs::read_dir(&sessions_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.contains(".corrupted."))
+        .collect();
+    // Only the user database was damaged; the cache is kept
+    assert_eq!(moved_aside.len(), 1, "{moved_aside:?}");
+    assert!(moved_aside[0].starts_with("user.db"));
+    assert_curated(&tag_id).await;
+}
+
+#[tokio::test]
+async fn test_failed_user_migration_keeps_the_user_database() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = fresh_home();
+    database::close();
+    let user_path = sqlite_cache::get_user_db_path().unwrap();
+    {
+        // A view where the user migration indexes a table
+        let conn = rusqlite::Connection::open(&user_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE favorites (id TEXT PRIMARY KEY);
+             INSERT INTO favorites VALUES ('session-1');
+             CREATE VIEW config_versions AS SELECT 1 AS file_path, 2 AS created_at;",
+        )
+        .unwrap();
+    }
+
+    let err = sqlite_cache::init_db().unwrap_err();
+    assert!(err.starts_with("Migration 1 "), "{err}");
+
+    let sessions_dir = home.path().join(".pi/agent/sessions");
+    assert!(!fs::read_dir(&sessions_dir).unwrap().any(|e| e
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .contains(".corrupted.")));
+    let conn = rusqlite::Connection::open(&user_path).unwrap();
+    let kept: i64 = conn
+        .query_row("SELECT COUNT(*) FROM favorites", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(kept, 1);
+}
@@ -0,0 +1,136 @@
+#![allow(clippy::await_holding_lock)]
+
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use pi_session_manager::batch_export::{self, ArchiveFormat, BatchExportRequest};
+use pi_session_manager::sqlite_cache;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn write_session(dir: &std::path::Path, id: &str, cwd: &str, text: &str) -> String {
+    let content = format!(
+        r#"{{"type":"session","version":3,"id":"{id}","timestamp":"2026-02-10T22:00:00Z","cwd":"{cwd}"}}
+{{"type":"message","id":"{id}-u","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{{"role":"user","content":[{{"type":"text","text":"{text}"}}]}}}}
+{{"type":"message","id":"{id}-a","parentId":"{id}-u","timestamp":"2026-02-10T22:00:02Z","message":{{"role":"assistant","provider":"anthropic","model":"claude-sonnet-4","content":[{{"type":"text","text":"done"}}],"usage":{{"input":100,"output":20,"cacheRead":0,"cacheWrite":0,"cost":{{"input":0.01,"output":0.02,"cacheRead":0,"cacheWrite":0}}}}}}}}"#
+    );
+    let path = dir.join(format!("{id}.jsonl"));
+    fs::write(&path, content).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+fn request(archive: ArchiveFormat, output_path: String) -> BatchExportRequest {
+    BatchExportRequest {
+        paths: vec![],
+        query: None,
+        tag: None,
+        project: None,
+        format: "md".to_string(),
+        options: Default::default(),
+        archive,
+        output_path,
+    }
+}
+
+#[tokio::test]
+async fn test_batch_export_zip_and_tar_gz() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+
+    let sessions_dir = temp_dir.path().join(".pi/agent/sessions/--work--");
+    fs::create_dir_all(&sessions_dir).unwrap();
+    write_session(&sessions_dir, "api-1", "/work/api", "fix the parser");
+    write_session(&sessions_dir, "api-2", "/work/api/sub", "deploy");
+    let web = write_session(&sessions_dir, "web-1", "/work/web", "fix the css");
+
+    let conn = sqlite_cache::init_db().unwrap();
+    sqlite_cache::assign_tag(&conn, "api-1", "builtin-important").unwrap();
+    drop(conn);
+
+    // Project filter into a zip, collecting progress events
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    let zip_path = temp_dir.path().join("out/api.zip");
+    let mut req = request(ArchiveFormat::Zip, zip_path.to_string_lossy().to_string());
+    req.project = Some("/work/api".to_string());
+    let result = batch_export::export_batch(req, move |p| sink.lock().unwrap().push(p))
+        .await
+        .unwrap();
+    assert_eq!(result.exported, 2);
+    assert!(result.failed.is_empty());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.first().unwrap().current, 0);
+    let last = events.last().unwrap();
+    assert!(last.done);
+    assert_eq!((last.current, last.total), (2, 2));
+
+    let mut zip = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+    let mut names: Vec<String> = zip.file_names().map(String::from).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["manifest.json", "sessions/api-1.md", "sessions/api-2.md"]
+    );
+    let mut manifest = String::new();
+    zip.by_name("manifest.json")
+        .unwrap()
+        .read_to_string(&mut manifest)
+        .unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    let by_id: HashMap<&str, &serde_json::Value> = manifest["sessions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["id"].as_str().unwrap(), s))
+        .collect();
+    assert_eq!(by_id["api-1"]["cwd"], "/work/api");
+    assert_eq!(by_id["api-1"]["tags"][0], "Important");
+    assert_eq!(by_id["api-1"]["total_tokens"], 120);
+    assert_eq!(by_id["api-2"]["details"]["user_messages"], 1);
+
+    // Tag + query filters into a tar.gz
+    let tar_path = temp_dir.path().join("tagged.tar.gz");
+    let mut req = request(ArchiveFormat::TarGz, tar_path.to_string_lossy().to_string());
+    req.tag = Some("important".to_string());
+    req.query = Some("parser".to_string());
+    let result = batch_export::export_batch(req, |_| {}).await.unwrap();
+    assert_eq!(result.exported, 1);
+
+    let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(&tar_path).unwrap()));
+    let mut names: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["manifest.json", "sessions/api-1.md"]);
+
+    // Queries use the full-text search grammar
+    let mut req = request(ArchiveFormat::Zip, zip_path.to_string_lossy().to_string());
+    req.query = Some("fix -css".to_string());
+    let result = batch_export::export_batch(req, |_| {}).await.unwrap();
+    assert_eq!(result.exported, 1);
+
+    // Explicit paths must be scanned sessions
+    let outside = write_session(temp_dir.path(), "outside", "/tmp", "secret");
+    let mut req = request(ArchiveFormat::Zip, zip_path.to_string_lossy().to_string());
+    req.paths = vec![web.clone(), outside];
+    let err = batch_export::export_batch(req, |_| {}).await.unwrap_err();
+    assert!(err.contains("Session not found"), "{err}");
+
+    let mut req = request(ArchiveFormat::Zip, tar_path.to_string_lossy().to_string());
+    req.paths = vec![web];
+    req.format = "pdf".to_string();
+    let err = batch_export::export_batch(req, |_| {}).await.unwrap_err();
+    assert!(err.contains("Unsupported format"), "{err}");
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "cli")]
+#![allow(clippy::await_holding_lock)]
+
+use lazy_static::lazy_static;
+use pi_session_manager::cli::{self, Command, ExportArgs, FilterArgs, ListArgs, TagCommand};
+use pi_session_manager::{scanner, sqlite_cache};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn write_session(path: &Path, id: &str, cwd: &str, text: &str, age_days: u64) {
+    let content = format!(
+        r#"{{"type":"session","version":3,"id":"{id}","timestamp":"2026-02-10T22:00:00Z","cwd":"{cwd}"}}
+{{"type":"message","id":"{id}-u","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{{"role":"user","content":[{{"type":"text","text":"{text}"}}]}}}}
+{{"type":"message","id":"{id}-a","parentId":"{id}-u","timestamp":"2026-02-10T22:00:02Z","message":{{"role":"assistant","content":[{{"type":"text","text":"done"}}]}}}}
+"#
+    );
+    fs::write(path, content).unwrap();
+    let mtime = SystemTime::now() - Duration::from_secs(age_days * 86_400);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+}
+
+async fn run_json(command: Command) -> Value {
+    let output = cli::run(command, true).await.unwrap();
+    assert!(!output.failed, "{}", output.text);
+    serde_json::from_str(&output.text).unwrap()
+}
+
+fn list(filter: FilterArgs) -> Command {
+    Command::List(ListArgs {
+        filter,
+        limit: None,
+    })
+}
+
+#[tokio::test]
+async fn test_cli_commands_without_server() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj--");
+    fs::create_dir_all(&dir).unwrap();
+    write_session(
+        &dir.join("a.jsonl"),
+        "aaaa-1111",
+        "/proj",
+        "fix the parser",
+        1,
+    );
+    // Older than the realtime cutoff: only reaches the cache through the CLI's flush
+    write_session(
+        &dir.join("b.jsonl"),
+        "bbbb-2222",
+        "/proj/web",
+        "style the page",
+        30,
+    );
+    write_session(&dir.join("c.jsonl"), "bbbb-3333", "/other", "unrelated", 5);
+
+    let all = run_json(list(FilterArgs::default())).await;
+    let ids: Vec<&str> = all
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["aaaa-1111", "bbbb-3333", "bbbb-2222"]);
+
+    let proj = run_json(list(FilterArgs {
+        cwd: Some("/proj".to_string()),
+        since: Some("10d".to_string()),
+        ..Default::default()
+    }))
+    .await;
+    assert_eq!(proj.as_array().unwrap().len(), 1);
+    assert_eq!(proj[0]["id"], "aaaa-1111");
+
+    // Ambiguous prefixes and unknown tags are errors
+    let err = cli::run(Command::Show { id: "bbbb".into() }, true)
+        .await
+        .unwrap_err();
+    assert!(err.contains("ambiguous"), "{err}");
+    let tag_add = |tag: &str| {
+        Command::Tag(TagCommand::Add {
+            id: "bbbb-2".into(),
+            tag: tag.into(),
+        })
+    };
+    assert!(cli::run(tag_add("Urgent"), true).await.is_err());
+
+    let conn = sqlite_cache::init_db().unwrap();
+    sqlite_cache::create_tag(&conn, "t-urgent", "Urgent", "#f00", None, None).unwrap();
+    run_json(tag_add("urgent")).await;
+    let tagged = run_json(list(FilterArgs {
+        tag: Some("Urgent".to_string()),
+        ..Default::default()
+    }))
+    .await;
+    assert_eq!(tagged[0]["id"], "bbbb-2222");
+    assert_eq!(tagged[0]["tags"][0], "Urgent");
+
+    run_json(Command::Rename {
+        id: "aaaa".into(),
+        name: "Parser fix".into(),
+    })
+    .await;
+    let shown = run_json(Command::Show { id: "aaaa".into() }).await;
+    assert_eq!(shown["session"]["name"], "Parser fix");
+    assert_eq!(shown["details"]["user_messages"], 1);
+
+    let out = temp_dir.path().join("a.md");
+    run_json(Command::Export(ExportArgs {
+        id: "aaaa-1111".into(),
+        format: "md".into(),
+        output: Some(out.to_string_lossy().to_string()),
+        drop_thinking: false,
+        all_branches: false,
+        min_messages: 0,
+        system_prompt: false,
+        redact: false,
+    }))
+    .await;
+    assert!(fs::read_to_string(&out).unwrap().contains("fix the parser"));
+
+    let reindexed = run_json(Command::Reindex { full: true }).await;
+    assert_eq!(reindexed["indexed"], 3);
+
+    let doctor = run_json(Command::Doctor).await;
+    assert!(doctor
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|check| check["ok"] == true));
+}
+
+#[test]
+fn test_parse_date_rejects_bad_input_without_panicking() {
+    for value in ["é", "7é", "é7d", "d", "7", "99999999999999w", "２d"] {
+        let err = cli::parse_date(value).unwrap_err();
+        assert!(err.starts_with("Invalid date"), "{value}: {err}");
+    }
+    let week_ago = cli::parse_date("1w").unwrap();
+    let age = chrono::Utc::now() - week_ago;
+    assert_eq!(age.num_days(), 7);
+    assert_eq!(
+        cli::parse_date("2026-02-10").unwrap().to_rfc3339(),
+        "2026-02-10T00:00:00+00:00"
+    );
+}
@@ -0,0 +1,160 @@
+#![allow(clippy::await_holding_lock)]
+
+use lazy_static::lazy_static;
+use pi_session_manager::command_registry::{self, CommandHost, NoHost};
+use pi_session_manager::{scanner, sqlite_cache, write_buffer};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+const SESSION: &str = r#"{"type":"session","version":3,"id":"reg-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"hello registry"}]}}
+"#;
+
+#[derive(Default)]
+struct RecordingHost {
+    events: Mutex<Vec<String>>,
+}
+
+impl CommandHost for RecordingHost {
+    fn emit(&self, event: &str, _payload: Value) {
+        self.events.lock().unwrap().push(event.to_string());
+    }
+
+    fn shells(&self) -> Result<Vec<(String, String)>, String> {
+        Ok(vec![("zsh".to_string(), "/bin/zsh".to_string())])
+    }
+}
+
+async fn call(command: &str, payload: Value) -> Result<Value, String> {
+    command_registry::dispatch(command, payload, Arc::new(NoHost)).await
+}
+
+#[tokio::test]
+async fn test_registry_validates_payloads_and_lists_schemas() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj--");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("reg.jsonl");
+    fs::write(&path, SESSION).unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    // Unknown and missing fields are rejected the same way for every command
+    let err = call("read_session_file", json!({ "path": path, "pth": 1 }))
+        .await
+        .unwrap_err();
+    assert!(err.contains("unknown field `pth`"), "{err}");
+    let err = call("get_all_tags", json!({ "verbose": true }))
+        .await
+        .unwrap_err();
+    assert!(err.contains("unknown field `verbose`"), "{err}");
+    let err = call("rename_session", json!({ "path": path }))
+        .await
+        .unwrap_err();
+    assert!(err.contains("missing field `newName`"), "{err}");
+    assert!(call("no_such_command", Value::Null)
+        .await
+        .unwrap_err()
+        .contains("Unknown command"));
+
+    // camelCase is canonical, snake_case is accepted as an alias
+    call(
+        "rename_session",
+        json!({ "path": path, "new_name": "Via alias" }),
+    )
+    .await
+    .unwrap();
+    let lines = call(
+        "read_session_file_incremental",
+        json!({ "path": path, "fromLine": 0 }),
+    )
+    .await
+    .unwrap();
+    assert!(lines[1].as_str().unwrap().contains("Via alias"));
+
+    let commands = call("list_commands", Value::Null).await.unwrap();
+    let rename = commands
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "rename_session")
+        .unwrap();
+    assert_eq!(rename["group"], "sessions");
+    let request = &rename["request"];
+    let schema = request["$defs"]["RenameSessionRequest"].clone();
+    assert_eq!(schema["additionalProperties"], false);
+    assert!(schema["properties"]["newName"].is_object());
+    assert_eq!(
+        command_registry::list_commands().len(),
+        command_registry::commands().len()
+    );
+
+    // Commands that used to exist on only some adapters
+    let stats = call("get_session_stats_light", json!({ "sessions": [] }))
+        .await
+        .unwrap();
+    assert_eq!(stats["total_sessions"], 0);
+    call("clear_cache", Value::Null).await.unwrap();
+
+    // Batch export reports progress through the host
+    let host = Arc::new(RecordingHost::default());
+    let out = temp_dir.path().join("out.zip");
+    let result = command_registry::dispatch(
+        "export_sessions_batch",
+        json!({
+            "paths": [path],
+            "format": "md",
+            "archive": "zip",
+            "outputPath": out.to_string_lossy(),
+        }),
+        host.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result["exported"], 1);
+    let events = host.events.lock().unwrap().clone();
+    assert_eq!(events.len(), 3);
+    assert!(events
+        .iter()
+        .all(|e| e == pi_session_manager::batch_export::PROGRESS_EVENT));
+    for payload in [
+        json!({ "paths": [path], "format": "md", "archive": "zip", "outputPath": "x.zip", "dryRun": true }),
+        json!({ "paths": [path], "format": "md", "archive": "zip", "outputPath": "x.zip", "options": { "dropThinkin": true } }),
+    ] {
+        let err = call("export_sessions_batch", payload).await.unwrap_err();
+        assert!(err.contains("unknown field"), "{err}");
+    }
+
+    // Deleting a session also drops its cache row
+    scanner::scan_sessions().await.unwrap();
+    write_buffer::flush_to_db().unwrap();
+    let conn = sqlite_cache::init_db().unwrap();
+    assert!(sqlite_cache::get_session(&conn, &path).unwrap().is_some());
+    call("delete_session", json!({ "path": path }))
+        .await
+        .unwrap();
+    assert!(sqlite_cache::get_session(&conn, &path).unwrap().is_none());
+    assert!(scanner::scan_sessions().await.unwrap().is_empty());
+
+    // Host capabilities
+    assert!(call("get_available_shells", Value::Null).await.is_err());
+    assert!(call("open_session_in_browser", json!({ "path": path }))
+        .await
+        .unwrap_err()
+        .contains("desktop-only"));
+    let shell = command_registry::dispatch("get_default_shell", Value::Null, host.clone())
+        .await
+        .unwrap();
+    assert_eq!(shell, "/bin/zsh");
+}
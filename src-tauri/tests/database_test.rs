@@ -0,0 +1,154 @@
+use lazy_static::lazy_static;
+use pi_session_manager::{database, sqlite_cache};
+use std::env;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tempfile::{tempdir, TempDir};
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn fresh_home() -> TempDir {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    fs::create_dir_all(temp_dir.path().join(".pi/agent/sessions")).unwrap();
+    temp_dir
+}
+
+#[test]
+fn test_concurrent_reads_and_writes_do_not_lock() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    let db = database::get().unwrap();
+
+    let writers: Vec<_> = (0..8)
+        .map(|w| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in 0..25 {
+                    db.write(move |conn| {
+                        let id = format!("fav-{w}-{i}");
+                        sqlite_cache::add_favorite(conn, &id, "session", &id, "/tmp/x.jsonl")
+                    })
+                    .unwrap();
+                }
+            })
+        })
+        .collect();
+    let readers: Vec<_> = (0..6)
+        .map(|_| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    db.read(sqlite_cache::get_all_favorites).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in writers.into_iter().chain(readers) {
+        handle.join().unwrap();
+    }
+
+    let favorites = db.read(sqlite_cache::get_all_favorites).unwrap();
+    assert_eq!(favorites.len(), 200);
+}
+
+#[test]
+fn test_nested_write_is_rejected_and_reads_are_read_only() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    let db = database::get().unwrap();
+
+    let inner = db.clone();
+    let nested = db.write(move |_| Ok(inner.write(|_| Ok(())))).unwrap();
+    assert!(nested.unwrap_err().contains("Nested"));
+
+    let result = db.read(|conn| sqlite_cache::add_favorite(conn, "f", "session", "f", "/f"));
+    assert!(result.is_err());
+    assert!(!db
+        .read(|conn| sqlite_cache::is_favorite(conn, "f"))
+        .unwrap());
+}
+
+#[test]
+fn test_service_reopens_after_close_or_file_removal() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    let first = database::get().unwrap();
+    first
+        .write(|conn| sqlite_cache::add_favorite(conn, "kept", "session", "kept", "/k"))
+        .unwrap();
+
+    database::close();
+    let second = database::get().unwrap();
+    assert!(!Arc::ptr_eq(&first, &second));
+    assert!(second
+        .read(|conn| sqlite_cache::is_favorite(conn, "kept"))
+        .unwrap());
+
+    let path = sqlite_cache::get_db_path().unwrap();
+    drop((first, second));
+    fs::remove_file(&path).unwrap();
+    let third = database::get().unwrap();
+    // Favorites are in the user database, which outlives the cache file
+    assert!(third
+        .read(|conn| sqlite_cache::is_favorite(conn, "kept"))
+        .unwrap());
+    assert_eq!(third.read(sqlite_cache::get_session_count).unwrap(), 0);
+}
+
+#[test]
+fn test_async_access_does_not_block_the_runtime() {
+    let _guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _home = fresh_home();
+    let db = database::get().unwrap();
+    // Driven from a sync test so the std guard is never held across an await
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async_access_round(db));
+}
+
+async fn async_access_round(db: Arc<database::Database>) {
+    // Every pooled reader is busy until this (single-threaded) runtime releases it
+    let (ready_tx, mut ready_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut releases = Vec::new();
+    for _ in 0..4 {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        releases.push(release_tx);
+        let (db, ready_tx) = (db.clone(), ready_tx.clone());
+        tokio::task::spawn_blocking(move || {
+            db.read(|_| {
+                ready_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Ok(())
+            })
+        });
+    }
+    for _ in 0..4 {
+        ready_rx.recv().await.unwrap();
+    }
+
+    let reading = tokio::spawn({
+        let db = db.clone();
+        async move {
+            db.read_async(|conn| sqlite_cache::is_favorite(conn, "a"))
+                .await
+        }
+    });
+    db.write_async(|conn| sqlite_cache::add_favorite(conn, "a", "session", "a", "/a"))
+        .await
+        .unwrap();
+    for release in releases {
+        release.send(()).unwrap();
+    }
+    reading.await.unwrap().unwrap();
+    assert!(db
+        .read_async(|conn| sqlite_cache::is_favorite(conn, "a"))
+        .await
+        .unwrap());
+}
@@ -0,0 +1,123 @@
+use pi_session_manager::export::{self, ExportOptions};
+use serde_json::Value;
+use std::fs;
+use tempfile::NamedTempFile;
+
+/// u1 -> a1 (thinking + tool call) -> t1 -> a2, then a fork from a1's result: u3 -> a3
+const SESSION: &str = r#"{"type":"session","version":3,"id":"sess-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/work"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"list files"}]}}
+{"type":"message","id":"a1","parentId":"u1","timestamp":"2026-02-10T22:00:02Z","message":{"role":"assistant","content":[{"type":"thinking","thinking":"use ls"},{"type":"toolCall","id":"call_1","name":"bash","arguments":{"command":"ls"}}]}}
+{"type":"message","id":"t1","parentId":"a1","timestamp":"2026-02-10T22:00:03Z","message":{"role":"toolResult","toolCallId":"call_1","toolName":"bash","content":[{"type":"text","text":"Cargo.toml"}],"isError":false}}
+{"type":"message","id":"a2","parentId":"t1","timestamp":"2026-02-10T22:00:04Z","message":{"role":"assistant","content":[{"type":"text","text":"One file."}]}}
+{"type":"message","id":"u3","parentId":"t1","timestamp":"2026-02-10T22:00:05Z","message":{"role":"user","content":[{"type":"text","text":"be brief"}]}}
+{"type":"message","id":"a3","parentId":"u3","timestamp":"2026-02-10T22:00:06Z","message":{"role":"assistant","content":[{"type":"text","text":"Cargo.toml"}]}}
+"#;
+
+fn lines(output: &str) -> Vec<Value> {
+    output
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_openai_jsonl_mapping() {
+    let out = export::render_session_with_options(
+        SESSION,
+        "openai-jsonl",
+        &ExportOptions::default(),
+        Some("You are pi."),
+    )
+    .unwrap();
+    let lines = lines(&out);
+    assert_eq!(lines.len(), 1, "active branch only by default");
+
+    let messages = lines[0]["messages"].as_array().unwrap();
+    let roles: Vec<&str> = messages
+        .iter()
+        .map(|m| m["role"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        roles,
+        ["system", "user", "assistant", "tool", "user", "assistant"]
+    );
+    assert_eq!(messages[0]["content"], "You are pi.");
+    assert!(messages[2]["content"].is_null());
+    assert_eq!(messages[2]["reasoning_content"], "use ls");
+    let call = &messages[2]["tool_calls"][0];
+    assert_eq!(call["id"], "call_1");
+    assert_eq!(call["function"]["name"], "bash");
+    assert_eq!(call["function"]["arguments"], r#"{"command":"ls"}"#);
+    assert_eq!(messages[3]["tool_call_id"], "call_1");
+    assert_eq!(messages[3]["content"], "Cargo.toml");
+}
+
+#[test]
+fn test_all_branches_and_drop_thinking() {
+    let options = ExportOptions {
+        drop_thinking: true,
+        active_branch_only: false,
+        ..Default::default()
+    };
+    let out = export::render_session_with_options(SESSION, "openai-jsonl", &options, None).unwrap();
+    let lines = lines(&out);
+    assert_eq!(lines.len(), 2);
+    assert!(!out.contains("reasoning_content"));
+    let last = |line: &Value| line["messages"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(last(&lines[0])["content"], "Cargo.toml");
+    assert_eq!(last(&lines[1])["content"], "One file.");
+}
+
+#[test]
+fn test_sharegpt_mapping() {
+    let out = export::render_session_with_options(
+        SESSION,
+        "sharegpt",
+        &ExportOptions::default(),
+        Some("You are pi."),
+    )
+    .unwrap();
+    let lines = lines(&out);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["id"], "sess-1");
+    assert_eq!(lines[0]["system"], "You are pi.");
+
+    let turns = lines[0]["conversations"].as_array().unwrap();
+    let from: Vec<&str> = turns.iter().map(|t| t["from"].as_str().unwrap()).collect();
+    assert_eq!(
+        from,
+        [
+            "human",
+            "gpt",
+            "function_call",
+            "observation",
+            "human",
+            "gpt"
+        ]
+    );
+    assert!(turns[1]["value"].as_str().unwrap().starts_with("<think>"));
+    let call: Value = serde_json::from_str(turns[2]["value"].as_str().unwrap()).unwrap();
+    assert_eq!(call["name"], "bash");
+    assert_eq!(call["arguments"]["command"], "ls");
+}
+
+#[tokio::test]
+async fn test_min_messages_option() {
+    let session = NamedTempFile::with_suffix(".jsonl").unwrap();
+    fs::write(session.path(), SESSION).unwrap();
+    let output = NamedTempFile::with_suffix(".jsonl").unwrap();
+    let export = |min_messages| {
+        let options = ExportOptions {
+            min_messages,
+            ..Default::default()
+        };
+        let session = session.path().to_str().unwrap().to_string();
+        let output = output.path().to_str().unwrap().to_string();
+        async move { export::export_session_with_options(&session, "sharegpt", &output, &options).await }
+    };
+
+    // Active branch: u1, a1, u3, a3
+    assert!(export(4).await.is_ok());
+    let err = export(5).await.unwrap_err();
+    assert!(err.contains("fewer than the minimum"), "{err}");
+}
@@ -5,16 +5,6 @@ mod tests {
 
     #[tokio::test]
     async fn test_export_html() {
-        // Skip if pi CLI is not available (e.g., in CI)
-        if std::process::Command::new("pi")
-            .arg("--version")
-            .output()
-            .is_err()
-        {
-            eprintln!("Skipping test_export_html: pi CLI not found");
-            return;
-        }
-
         // 创建一个临时会话文件
         let temp_session = NamedTempFile::with_suffix(".jsonl").unwrap();
         let session_content = r#"{"type":"session","name":"Test Session","timestamp":"2024-01-01T00:00:00Z"}
@@ -41,10 +31,13 @@ mod tests {
             output_content.contains("<!DOCTYPE html>"),
             "Should be valid HTML"
         );
-        // PI 的 export 命令生成的 HTML 可能不直接包含会话名称，但应该包含会话数据
         assert!(
-            output_content.contains("session-data") || output_content.contains("SESSION_DATA"),
-            "Should contain session data"
+            output_content.contains("<h1>Test Session</h1>"),
+            "Should contain session name"
+        );
+        assert!(
+            output_content.contains("Hi there!"),
+            "Should contain session messages"
         );
 
         println!("✅ HTML export test passed!");
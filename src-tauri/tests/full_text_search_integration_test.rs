@@ -295,6 +295,42 @@ async fn test_full_text_search_result_structure() {
     println!("✅ Result structure test passed!");
 }
 
+#[tokio::test]
+async fn test_full_text_search_entries_without_timestamp_use_session_created() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+
+    let _temp_dir = setup_test_db(&[(
+        "s1",
+        "/cwd",
+        &[("user", "undated entry"), ("user", "garbled entry")],
+    )]);
+
+    let config = Config::default();
+    let conn = sqlite_cache::init_db_with_config(&config).unwrap();
+    conn.execute(
+        "UPDATE message_entries SET timestamp = '' WHERE id = 's1-msg0'",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "UPDATE message_entries SET timestamp = 'not a date' WHERE id = 's1-msg1'",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let response: FullTextSearchResponse =
+        full_text_search("entry".to_string(), "all".to_string(), None, 0, 10, None)
+            .await
+            .unwrap();
+
+    // Every counted hit is returned, dated by the session header
+    assert_eq!(response.total_hits, 2);
+    assert_eq!(response.hits.len(), 2);
+    let created = chrono::DateTime::parse_from_rfc3339("2026-02-10T22:00:00Z").unwrap();
+    assert!(response.hits.iter().all(|h| h.timestamp == created));
+}
+
 #[tokio::test]
 async fn test_full_text_search_escaping_special_chars() {
     let _lock = TEST_DB_LOCK.lock().unwrap();
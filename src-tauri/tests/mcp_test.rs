@@ -0,0 +1,210 @@
+#![cfg(feature = "cli")]
+#![allow(clippy::await_holding_lock)]
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use lazy_static::lazy_static;
+use pi_session_manager::auth::Caller;
+use pi_session_manager::{mcp, scanner, sqlite_cache};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use tempfile::tempdir;
+use tower::ServiceExt;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+const FIX_SESSION: &str = r#"{"type":"session","version":3,"id":"fix-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/api"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"the migration deadlocks on startup"}]}}
+{"type":"message","id":"a1","parentId":"u1","timestamp":"2026-02-10T22:00:02Z","message":{"role":"assistant","model":"claude-x","content":[{"type":"text","text":"Take the advisory lock before opening the pool"},{"type":"toolCall","id":"c1","name":"bash","arguments":{"command":"cargo test"}}],"usage":{"input":10,"output":5}}}
+{"type":"message","id":"t1","parentId":"a1","timestamp":"2026-02-10T22:00:03Z","message":{"role":"toolResult","toolCallId":"c1","toolName":"bash","content":[{"type":"text","text":"ok"}],"isError":false}}
+{"type":"compaction","id":"k1","parentId":"t1","timestamp":"2026-02-10T22:00:04Z","summary":"Fixed the startup deadlock","firstKeptEntryId":"a1","tokensBefore":100}
+"#;
+
+const OTHER_SESSION: &str = r#"{"type":"session","version":3,"id":"other-2","timestamp":"2026-02-09T10:00:00Z","cwd":"/elsewhere"}
+{"type":"message","id":"o1","parentId":null,"timestamp":"2026-02-09T10:00:01Z","message":{"role":"user","content":[{"type":"text","text":"another deadlocks question"}]}}
+"#;
+
+fn local() -> Caller {
+    Caller::local("mcp")
+}
+
+async fn rpc(method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    mcp::handle_message(request, &local()).await.unwrap()
+}
+
+/// Call a tool and parse the JSON it returned as text
+async fn call_tool(name: &str, arguments: Value) -> (bool, Value) {
+    let reply = rpc(
+        "tools/call",
+        json!({ "name": name, "arguments": arguments }),
+    )
+    .await;
+    let result = &reply["result"];
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let value = serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()));
+    (result["isError"] == true, value)
+}
+
+#[tokio::test]
+async fn test_mcp_tools_over_jsonrpc_and_http() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj--");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("fix.jsonl"), FIX_SESSION).unwrap();
+    fs::write(dir.join("other.jsonl"), OTHER_SESSION).unwrap();
+
+    // Handshake
+    let init = rpc("initialize", json!({ "protocolVersion": "2025-03-26" })).await;
+    assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+    assert!(init["result"]["capabilities"]["tools"].is_object());
+    let init = rpc("initialize", json!({ "protocolVersion": "1999-01-01" })).await;
+    assert_eq!(init["result"]["protocolVersion"], "2025-06-18");
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    assert!(mcp::handle_message(notification, &local()).await.is_none());
+    assert_eq!(rpc("no/such", Value::Null).await["error"]["code"], -32601);
+
+    let tools = rpc("tools/list", Value::Null).await;
+    let tools = tools["result"]["tools"].as_array().unwrap();
+    let names: Vec<&str> = tools.iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        [
+            "search_sessions",
+            "get_session",
+            "list_recent_sessions_for_cwd",
+            "get_session_summary",
+            "list_tags"
+        ]
+    );
+    let search_schema = &tools[0]["inputSchema"];
+    assert_eq!(search_schema["type"], "object");
+    assert_eq!(search_schema["required"], json!(["query"]));
+    assert_eq!(tools[4]["inputSchema"]["properties"], json!({}));
+
+    // Search finds message text and honours the cwd filter
+    let (failed, hits) = call_tool("search_sessions", json!({ "query": "deadlocks" })).await;
+    assert!(!failed, "{hits}");
+    assert_eq!(hits.as_array().unwrap().len(), 2, "{hits}");
+    let fix = hits
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|h| h["session"]["id"] == "fix-1")
+        .unwrap();
+    assert!(fix["matches"][0]["snippet"]
+        .as_str()
+        .unwrap()
+        .contains("migration deadlocks"));
+    let (_, hits) = call_tool(
+        "search_sessions",
+        json!({ "query": "deadlocks", "cwd": "/proj" }),
+    )
+    .await;
+    assert_eq!(hits.as_array().unwrap().len(), 1);
+
+    // Transcript paging, with and without tool details
+    let (_, session) = call_tool("get_session", json!({ "id": "fix" })).await;
+    assert_eq!(session["total_messages"], 2);
+    assert_eq!(
+        session["messages"][1]["text"],
+        "Take the advisory lock before opening the pool"
+    );
+    assert!(session["messages"][1].get("tools").is_none());
+    assert!(session["next_from"].is_null());
+    let (_, session) = call_tool(
+        "get_session",
+        json!({ "id": "fix-1", "limit": 1, "include_tools": true }),
+    )
+    .await;
+    assert_eq!(session["total_messages"], 3);
+    assert_eq!(session["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(session["next_from"], 1);
+    let (_, session) = call_tool(
+        "get_session",
+        json!({ "id": "fix-1", "from": 1, "include_tools": true }),
+    )
+    .await;
+    assert_eq!(session["messages"][0]["tools"][0]["call"], "bash");
+    assert_eq!(session["messages"][1]["tools"][0]["output"], "ok");
+
+    let (_, summary) = call_tool("get_session_summary", json!({ "id": "fix-1" })).await;
+    assert_eq!(summary["cwd"], "/proj/api");
+    assert_eq!(summary["models"], json!(["claude-x"]));
+    assert_eq!(
+        summary["user_prompts"],
+        json!(["the migration deadlocks on startup"])
+    );
+    assert_eq!(summary["summaries"], json!(["Fixed the startup deadlock"]));
+
+    let (_, recent) = call_tool(
+        "list_recent_sessions_for_cwd",
+        json!({ "cwd": "/proj", "limit": 5 }),
+    )
+    .await;
+    assert_eq!(recent.as_array().unwrap().len(), 1);
+    assert_eq!(recent[0]["id"], "fix-1");
+
+    let conn = sqlite_cache::init_db().unwrap();
+    sqlite_cache::create_tag(&conn, "t-db", "Database", "#00f", None, None).unwrap();
+    sqlite_cache::assign_tag(&conn, "fix-1", "t-db").unwrap();
+    let (_, tags) = call_tool("list_tags", Value::Null).await;
+    let database = tags
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["name"] == "Database")
+        .unwrap();
+    assert_eq!(database["sessions"], 1);
+
+    // Bad arguments and unknown sessions are tool errors, unknown tools are protocol errors
+    let (failed, message) = call_tool("get_session", json!({ "session": "fix-1" })).await;
+    assert!(failed);
+    assert!(message.as_str().unwrap().contains("unknown field"));
+    let (failed, _) = call_tool("get_session_summary", json!({ "id": "nope" })).await;
+    assert!(failed);
+    let reply = rpc("tools/call", json!({ "name": "drop_tables" })).await;
+    assert_eq!(reply["error"]["code"], -32602);
+
+    // Streamable HTTP transport
+    let app: Router = mcp::router(|ip, _| ip);
+    let post = |body: String| {
+        let mut request = Request::post(mcp::MCP_PATH)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        app.clone().oneshot(request)
+    };
+    let response = post(json!({ "jsonrpc": "2.0", "id": "a", "method": "tools/list" }).to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let reply: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(reply["id"], "a");
+    assert_eq!(reply["result"]["tools"].as_array().unwrap().len(), 5);
+    let response =
+        post(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }).to_string())
+            .await
+            .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let response = post("{not json".to_string()).await.unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let reply: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(reply["error"]["code"], -32700);
+}
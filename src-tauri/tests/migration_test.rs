@@ -1,5 +1,6 @@
 use lazy_static::lazy_static;
 use pi_session_manager::config::Config;
+use pi_session_manager::migrations::{self, Schema};
 use pi_session_manager::sqlite_cache;
 use rusqlite::{params, Connection};
 use std::fs;
@@ -445,3 +446,183 @@ fn test_fts_rebuild_after_recreate() {
 
     // Cleanup: temp_dir is dropped automatically
 }
+
+fn table_names(conn: &Connection) -> Vec<String> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .unwrap();
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+fn backups_in(dir: &std::path::Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.to_string_lossy().contains(".pre-v"))
+        .collect()
+}
+
+#[test]
+fn test_fresh_database_runs_every_migration_without_backup() {
+    let _lock = MIGRATION_LOCK.lock().unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    std::env::set_var("PPM_TEST_DB", temp_dir.path().join("fresh.db"));
+    let conn = sqlite_cache::init_db_with_config(&Config::default()).unwrap();
+    std::env::remove_var("PPM_TEST_DB");
+
+    assert_eq!(
+        sqlite_cache::schema_version(&conn).unwrap(),
+        migrations::latest_version(Schema::Cache)
+    );
+    assert_eq!(
+        migrations::verify_schema(&conn).unwrap(),
+        Vec::<String>::new()
+    );
+    // User data lives in its own file, not in the rebuildable cache
+    let cache_tables = table_names(&conn);
+    let user_tables = table_names(&Connection::open(temp_dir.path().join("user.db")).unwrap());
+    for table in [
+        "settings",
+        "auth_tokens",
+        "audit_log",
+        "config_versions",
+        "tags",
+        "session_tags",
+        "favorites",
+    ] {
+        assert!(user_tables.contains(&table.to_string()), "missing {table}");
+        assert!(
+            !cache_tables.contains(&table.to_string()),
+            "{table} in cache"
+        );
+    }
+    assert!(cache_tables.contains(&"sessions".to_string()));
+    assert!(backups_in(temp_dir.path()).is_empty());
+}
+
+#[test]
+fn test_legacy_database_is_backed_up_then_upgraded() {
+    let _lock = MIGRATION_LOCK.lock().unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db_path = temp_dir.path().join("legacy.db");
+    {
+        // Tables created ad hoc before versioning, no schema_version
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL UNIQUE,
+                cwd TEXT NOT NULL,
+                name TEXT,
+                created TEXT NOT NULL,
+                modified TEXT NOT NULL,
+                file_modified TEXT NOT NULL,
+                message_count INTEGER NOT NULL,
+                first_message TEXT,
+                all_messages_text TEXT,
+                cached_at TEXT NOT NULL
+            );
+            INSERT INTO sessions VALUES ('s1', '/p/s1.jsonl', '/p', NULL, 't', 't', 't', 1, 'hi', 'hi', 't');
+            CREATE TABLE auth_tokens (
+                token TEXT PRIMARY KEY,
+                name TEXT,
+                created_at TEXT NOT NULL
+            );
+            INSERT INTO auth_tokens VALUES ('tok', 'default', 't');",
+        )
+        .unwrap();
+    }
+
+    let reports = migrations::dry_run(&db_path).unwrap();
+    let cache = reports.iter().find(|r| r.schema == Schema::Cache).unwrap();
+    assert_eq!(cache.current_version, 0);
+    assert_eq!(
+        cache.pending.len() as i64,
+        migrations::latest_version(Schema::Cache),
+        "every migration is pending"
+    );
+    assert!(!table_names(&Connection::open(&db_path).unwrap()).contains(&"schema_version".into()));
+
+    let conn = Connection::open(&db_path).unwrap();
+    let reports = migrations::migrate(&conn, &db_path).unwrap();
+    // Only the cache had data; the user database is new
+    let backups = backups_in(temp_dir.path());
+    assert_eq!(backups.len(), 1);
+    let cache = reports.iter().find(|r| r.schema == Schema::Cache).unwrap();
+    assert_eq!(cache.backup, Some(backups[0].display().to_string()));
+    let backup = Connection::open(&backups[0]).unwrap();
+    assert!(!table_names(&backup).contains(&"schema_version".into()));
+    let backed_up: i64 = backup
+        .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(backed_up, 1);
+
+    assert_eq!(
+        migrations::verify_schema(&conn).unwrap(),
+        Vec::<String>::new()
+    );
+    let scope: String = conn
+        .query_row(
+            "SELECT scope FROM auth_tokens WHERE token = 'tok'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(scope, "admin");
+    // The token moved out of the cache
+    assert!(!table_names(&conn).contains(&"auth_tokens".into()));
+    let user = Connection::open(temp_dir.path().join("user.db")).unwrap();
+    let moved: i64 = user
+        .query_row("SELECT COUNT(*) FROM auth_tokens", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(moved, 1);
+    assert!(migrations::dry_run(&db_path)
+        .unwrap()
+        .iter()
+        .all(|r| r.pending.is_empty()));
+}
+
+#[test]
+fn test_failed_migration_rolls_back_its_step() {
+    let _lock = MIGRATION_LOCK.lock().unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db_path = temp_dir.path().join("broken.db");
+    let conn = Connection::open(&db_path).unwrap();
+    // A view where migration 1 expects to index a table
+    conn.execute(
+        "CREATE VIEW message_entries AS SELECT 1 AS session_path",
+        [],
+    )
+    .unwrap();
+
+    let err = migrations::migrate(&conn, &db_path).unwrap_err();
+    assert!(err.starts_with("Migration 1 "), "{err}");
+    assert_eq!(
+        migrations::current_version(&conn, Schema::Cache).unwrap(),
+        0
+    );
+    assert!(!table_names(&conn).contains(&"sessions".into()));
+}
+
+#[test]
+fn test_verify_schema_reports_drift() {
+    let _lock = MIGRATION_LOCK.lock().unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db_path = temp_dir.path().join("drift.db");
+    let conn = Connection::open(&db_path).unwrap();
+    migrations::migrate(&conn, &db_path).unwrap();
+    conn.execute_batch(
+        "ALTER TABLE auth_tokens DROP COLUMN expires_at;
+         DROP INDEX idx_cwd;
+         DROP TABLE settings;",
+    )
+    .unwrap();
+
+    let problems = migrations::verify_schema(&conn).unwrap();
+    assert!(problems.contains(&"auth_tokens: missing column expires_at".to_string()));
+    assert!(problems.contains(&"missing index idx_cwd".to_string()));
+    assert!(problems.contains(&"missing table settings".to_string()));
+}
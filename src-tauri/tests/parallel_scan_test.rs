@@ -0,0 +1,119 @@
+use pi_session_manager::config::Config;
+use pi_session_manager::scanner::{self, ScanProgress};
+use pi_session_manager::write_buffer;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+use tokio::sync::broadcast;
+
+fn session_file(id: usize, messages: usize) -> String {
+    let mut content = format!(
+        r#"{{"type":"session","version":3,"id":"par-{id}","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/{}"}}"#,
+        id % 3
+    );
+    for m in 0..messages {
+        content.push_str(&format!(
+            "\n{{\"type\":\"message\",\"id\":\"m{m}\",\"timestamp\":\"2026-02-10T22:01:00Z\",\"message\":{{\"role\":\"user\",\"content\":[{{\"type\":\"text\",\"text\":\"message {m} of {id}\"}}]}}}}"
+        ));
+    }
+    content.push('\n');
+    content
+}
+
+/// Last progress event of the scan over `files_total` files
+fn final_progress(rx: &mut broadcast::Receiver<ScanProgress>, files_total: usize) -> ScanProgress {
+    let mut last = None;
+    while let Ok(progress) = rx.try_recv() {
+        if progress.files_total == files_total {
+            last = Some(progress);
+        }
+    }
+    last.expect("no progress event")
+}
+
+#[tokio::test]
+async fn test_parallel_scan_matches_serial_parse_and_reports_progress() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let root = temp_dir.path().join(".pi/agent/sessions");
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for id in 0..40 {
+        let dir = root.join(format!("--proj-{}--", id % 3));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("s{id}.jsonl"));
+        fs::write(&path, session_file(id, 1 + id % 5)).unwrap();
+        paths.push(path);
+    }
+    let total_bytes: u64 = paths.iter().map(|p| fs::metadata(p).unwrap().len()).sum();
+
+    // Every file is older than the cutoff and uncached, so all are parsed
+    let config = Config {
+        realtime_cutoff_days: 0,
+        scan_workers: 4,
+        ..Default::default()
+    };
+    let mut rx = scanner::subscribe_progress();
+    let sessions = scanner::scan_sessions_with_config(&config).await.unwrap();
+    assert_eq!(sessions.len(), 40);
+    for path in &paths {
+        let (expected, _) = scanner::parse_session_info(path).unwrap();
+        let info = sessions.iter().find(|s| s.path == expected.path).unwrap();
+        assert_eq!(info.message_count, expected.message_count);
+        assert_eq!(info.all_messages_text, expected.all_messages_text);
+    }
+    let progress = final_progress(&mut rx, 40);
+    assert!(progress.done);
+    assert_eq!(progress.files_done, 40);
+    assert_eq!(progress.bytes_parsed, total_bytes);
+
+    // Once cached, only the appended file is parsed, from its cursor
+    write_buffer::flush_to_db().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let appended = "{\"type\":\"message\",\"id\":\"late\",\"timestamp\":\"2026-02-10T22:02:00Z\",\"message\":{\"role\":\"user\",\"content\":[{\"type\":\"text\",\"text\":\"late\"}]}}\n";
+    OpenOptions::new()
+        .append(true)
+        .open(&paths[7])
+        .unwrap()
+        .write_all(appended.as_bytes())
+        .unwrap();
+
+    let sessions = scanner::scan_sessions_with_config(&config).await.unwrap();
+    assert_eq!(sessions.len(), 40);
+    let info = sessions
+        .iter()
+        .find(|s| Path::new(&s.path) == paths[7])
+        .unwrap();
+    assert_eq!(info.message_count, 4);
+    assert!(info.all_messages_text.ends_with("\nlate"));
+    let progress = final_progress(&mut rx, 1);
+    assert_eq!((progress.files_done, progress.done), (1, true));
+    assert_eq!(progress.bytes_parsed, appended.len() as u64);
+}
+
+#[test]
+fn test_parse_in_parallel_stops_on_callback_error() {
+    let temp_dir = tempdir().unwrap();
+    let jobs: Vec<_> = (0..20)
+        .map(|id| {
+            let path = temp_dir.path().join(format!("s{id}.jsonl"));
+            fs::write(&path, session_file(id, 2)).unwrap();
+            (path, chrono::Utc::now())
+        })
+        .collect();
+
+    let mut seen = 0;
+    let result = scanner::parse_in_parallel(jobs, 3, |_, _, _, _| {
+        seen += 1;
+        if seen == 5 {
+            return Err("disk full".to_string());
+        }
+        Ok(())
+    });
+    assert_eq!(result, Err("disk full".to_string()));
+    assert_eq!(seen, 5);
+}
@@ -0,0 +1,117 @@
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use pi_session_manager::auth::Caller;
+use pi_session_manager::command_registry::{self, NoHost};
+use pi_session_manager::{rest_api, scanner, ServerSettings};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tempfile::tempdir;
+use tower::ServiceExt;
+
+const SESSION: &str = r#"{"type":"session","version":3,"id":"ro-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/app"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"hello"}]}}
+"#;
+
+async fn run(command: &str, payload: Value) -> Result<Value, String> {
+    let caller = Caller::local("http");
+    command_registry::dispatch_as(&caller, command, payload, Arc::new(NoHost)).await
+}
+
+#[tokio::test]
+async fn test_read_only_mode_refuses_mutating_commands() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj-app--");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("ro.jsonl");
+    fs::write(&path, SESSION).unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let commands = command_registry::list_commands();
+    let mutating = |name: &str| commands.iter().find(|c| c.name == name).unwrap().mutating;
+    assert!(mutating("delete_session") && mutating("terminal_create"));
+    assert!(mutating("create_api_key") && mutating("save_pi_settings"));
+    assert!(!mutating("scan_sessions") && !mutating("list_api_keys"));
+
+    command_registry::set_read_only(true);
+
+    let sessions = run("scan_sessions", Value::Null).await.unwrap();
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+
+    let refused = [
+        ("delete_session", json!({ "path": path })),
+        ("rename_session", json!({ "path": path, "newName": "x" })),
+        ("toggle_resource", json!({})),
+        ("restore_config_version", json!({})),
+        (
+            "terminal_create",
+            json!({ "id": "t1", "cwd": "/", "shell": "/bin/sh" }),
+        ),
+        ("create_api_key", json!({ "name": "k" })),
+    ];
+    for (command, payload) in refused {
+        let err = run(command, payload).await.unwrap_err();
+        assert!(err.contains("read-only mode"), "{command}: {err}");
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), SESSION);
+
+    // REST writes are refused the same way
+    let app: Router = rest_api::router(|ip, _| ip, Arc::new(NoHost));
+    let mut request = Request::builder()
+        .method(Method::DELETE)
+        .uri("/api/v1/sessions/ro-1")
+        .body(Body::empty())
+        .unwrap();
+    let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(addr));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(fs::metadata(&path).is_ok());
+
+    // The desktop webview dispatches without a caller and is not restricted
+    command_registry::dispatch(
+        "rename_session",
+        json!({ "path": path, "newName": "renamed" }),
+        Arc::new(NoHost),
+    )
+    .await
+    .unwrap();
+
+    // Saving the server settings applies the mode without a restart; once on,
+    // only the desktop can turn it off again
+    let desktop_save = |settings: Value| {
+        command_registry::dispatch(
+            "save_server_settings",
+            json!({ "settings": settings }),
+            Arc::new(NoHost),
+        )
+    };
+    let mut settings = json!(ServerSettings::default());
+    desktop_save(settings.clone()).await.unwrap();
+    assert!(!command_registry::is_read_only());
+    settings["read_only"] = json!(true);
+    run("save_server_settings", json!({ "settings": settings }))
+        .await
+        .unwrap();
+    assert!(command_registry::is_read_only());
+    settings["read_only"] = json!(false);
+    let err = run("save_server_settings", json!({ "settings": settings }))
+        .await
+        .unwrap_err();
+    assert!(err.contains("read-only mode"), "{err}");
+    desktop_save(settings).await.unwrap();
+    assert!(!command_registry::is_read_only());
+
+    run("delete_session", json!({ "path": path }))
+        .await
+        .unwrap();
+    assert!(fs::metadata(&path).is_err());
+}
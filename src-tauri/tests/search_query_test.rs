@@ -0,0 +1,210 @@
+#![allow(clippy::type_complexity, clippy::await_holding_lock)]
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use pi_session_manager::commands::full_text_search;
+use pi_session_manager::config::Config;
+use pi_session_manager::search_query::{self, DefaultOperator, Expr, FieldFilter};
+use pi_session_manager::{scanner, sqlite_cache};
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn term(s: &str) -> Expr {
+    Expr::Term(s.to_string())
+}
+
+#[test]
+fn test_parse_default_operator_and_constraints() {
+    let expr =
+        search_query::parse("tokio runtime -blocking role:user", DefaultOperator::Or).unwrap();
+    assert_eq!(
+        expr,
+        Expr::And(vec![
+            Expr::Or(vec![term("tokio"), term("runtime")]),
+            Expr::Not(Box::new(term("blocking"))),
+            Expr::Field(FieldFilter::Role("user".to_string())),
+        ])
+    );
+
+    let expr = search_query::parse("a AND b c", DefaultOperator::Or).unwrap();
+    assert_eq!(
+        expr,
+        Expr::Or(vec![Expr::And(vec![term("a"), term("b")]), term("c")])
+    );
+}
+
+#[test]
+fn test_parse_groups_phrases_and_fields() {
+    let expr = search_query::parse(
+        r#"("error handling" OR panic) cwd:"/work/my api" after:2026-01"#,
+        DefaultOperator::And,
+    )
+    .unwrap();
+    let Expr::And(items) = expr else {
+        panic!("expected And");
+    };
+    assert_eq!(
+        items[0],
+        Expr::Or(vec![
+            Expr::Phrase("error handling".to_string()),
+            term("panic")
+        ])
+    );
+    assert_eq!(
+        items[1],
+        Expr::Field(FieldFilter::Cwd("/work/my api".to_string()))
+    );
+    let Expr::Field(FieldFilter::After(at)) = &items[2] else {
+        panic!("expected after:");
+    };
+    assert_eq!(at.to_rfc3339(), "2026-01-01T00:00:00+00:00");
+
+    // Unknown qualifiers are plain words
+    assert_eq!(
+        search_query::parse("http://x", DefaultOperator::Or).unwrap(),
+        term("http://x")
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    for bad in [
+        "(tokio",
+        "tokio)",
+        "OR tokio",
+        "role:system",
+        "after:yesterday",
+        "tag:",
+    ] {
+        assert!(
+            search_query::parse(bad, DefaultOperator::Or).is_err(),
+            "expected error for {bad:?}"
+        );
+    }
+
+    let plan = |q: &str| search_query::plan(search_query::parse(q, DefaultOperator::Or).unwrap());
+    assert!(plan("role:user").is_err(), "needs a search term");
+    assert!(plan("-tokio").is_err(), "exclusion alone");
+    assert!(plan("tokio OR role:user").is_err(), "filter inside OR");
+}
+
+#[test]
+fn test_compile_fts5_and_sql() {
+    let plan = search_query::plan(
+        search_query::parse(
+            r#"serde* "zero copy" -json -tag:archive model:claude-*"#,
+            DefaultOperator::And,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        plan.fts5_match(),
+        r#"(("serde"* AND "zero copy") NOT "json")"#
+    );
+
+    let (conditions, params) = plan.sql_conditions();
+    assert_eq!(conditions.len(), 2);
+    assert!(conditions[0].starts_with("NOT (EXISTS (SELECT 1 FROM session_tags"));
+    assert!(conditions[1].contains("mm.model LIKE ?"));
+    assert_eq!(params, vec!["archive", "archive", "claude-%", "%/claude-%"]);
+}
+
+fn make_session_file(id: &str, cwd: &str, messages: &[(&str, &str, &str)]) -> String {
+    let header = format!(
+        r#"{{"type":"session","version":3,"id":"{id}","timestamp":"2026-02-10T22:00:00Z","cwd":"{cwd}"}}"#
+    );
+    let mut lines = vec![header];
+    for (i, (role, day, text)) in messages.iter().enumerate() {
+        let model = if *role == "assistant" {
+            r#","provider":"anthropic","model":"claude-sonnet-4""#
+        } else {
+            ""
+        };
+        lines.push(format!(
+            r#"{{"type":"message","id":"{id}-msg{i}","parentId":null,"timestamp":"{day}T10:00:00Z","message":{{"role":"{role}","content":[{{"type":"text","text":"{text}"}}]{model}}}}}"#
+        ));
+    }
+    lines.join("\n")
+}
+
+#[tokio::test]
+async fn test_full_text_search_with_qualifiers() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+
+    let sessions_dir = temp_dir.path().join("sessions");
+    fs::create_dir_all(&sessions_dir).unwrap();
+    let conn = sqlite_cache::init_db_with_config(&Config::default()).unwrap();
+    let fixtures: [(&str, &str, &[(&str, &str, &str)]); 2] = [
+        (
+            "api",
+            "/work/api",
+            &[
+                ("user", "2026-01-05", "deploy the parser service"),
+                ("assistant", "2026-01-05", "parser deployed with docker"),
+            ],
+        ),
+        (
+            "web",
+            "/work/web",
+            &[("user", "2026-03-01", "parser bug in the frontend")],
+        ),
+    ];
+    for (id, cwd, messages) in fixtures {
+        let path = sessions_dir.join(format!("{id}.jsonl"));
+        fs::write(&path, make_session_file(id, cwd, messages)).unwrap();
+        let (session, entries) = scanner::parse_session_info(&path).unwrap();
+        sqlite_cache::upsert_session(&conn, &session, Utc::now(), Some(&entries)).unwrap();
+    }
+    sqlite_cache::assign_tag(&conn, "web", "builtin-important").unwrap();
+    drop(conn);
+
+    let search = |q: &str| {
+        let q = q.to_string();
+        async move {
+            let mut ids: Vec<String> =
+                full_text_search(q, "all".to_string(), None, 0, 20, Some("all".to_string()))
+                    .await
+                    .unwrap()
+                    .hits
+                    .into_iter()
+                    .map(|h| h.entry_id)
+                    .collect();
+            ids.sort();
+            ids
+        }
+    };
+
+    assert_eq!(search("parser").await.len(), 3);
+    assert_eq!(
+        search("parser cwd:/work/api").await,
+        ["api-msg0", "api-msg1"]
+    );
+    assert_eq!(search("parser cwd:/work").await.len(), 3);
+    assert_eq!(search("parser role:assistant").await, ["api-msg1"]);
+    assert_eq!(search("parser after:2026-02-01").await, ["web-msg0"]);
+    assert_eq!(search("parser before:2026-02-01").await.len(), 2);
+    assert_eq!(search("parser -docker").await, ["api-msg0", "web-msg0"]);
+    assert_eq!(search("parser model:anthropic/*").await.len(), 2);
+    assert_eq!(search("parser -model:claude-sonnet-4").await, ["web-msg0"]);
+    assert_eq!(search("parser tag:builtin-important").await, ["web-msg0"]);
+    assert_eq!(
+        search("(docker OR frontend) parser").await,
+        ["api-msg1", "web-msg0"]
+    );
+    assert_eq!(search("\"parser service\"").await, ["api-msg0"]);
+
+    let err = full_text_search("(parser".to_string(), "all".to_string(), None, 0, 20, None)
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("Invalid query"), "{err}");
+}
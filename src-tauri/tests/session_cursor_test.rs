@@ -0,0 +1,199 @@
+use pi_session_manager::session_cursor::{self, SessionCursor};
+use pi_session_manager::{read_session_file_incremental, scanner, session_parser, sqlite_cache};
+use rusqlite::params;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use tempfile::tempdir;
+
+const HEADER: &str = r#"{"type":"session","version":3,"id":"cur-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/big"}"#;
+
+fn message(id: &str, parent: &str, role: &str, text: &str) -> String {
+    format!(
+        r#"{{"type":"message","id":"{id}","parentId":"{parent}","timestamp":"2026-02-10T22:01:00Z","message":{{"role":"{role}","provider":"anthropic","model":"claude","content":[{{"type":"text","text":"{text}"}}],"usage":{{"input":10,"output":5,"cacheRead":0,"cacheWrite":0,"cost":{{"input":0.1,"output":0.2,"cacheRead":0,"cacheWrite":0}}}}}}}}"#
+    )
+}
+
+fn append(path: &Path, data: &str) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(data.as_bytes()).unwrap();
+}
+
+/// The cursor must end up where a full parse of the same file does
+fn assert_matches_full_parse(
+    path: &Path,
+    cursor: &SessionCursor,
+    info: &pi_session_manager::models::SessionInfo,
+) {
+    let (full, _) = scanner::parse_session_info(path).unwrap();
+    assert_eq!(info.message_count, full.message_count);
+    assert_eq!(info.name, full.name);
+    assert_eq!(info.first_message, full.first_message);
+    assert_eq!(info.last_message, full.last_message);
+    assert_eq!(info.all_messages_text, full.all_messages_text);
+    assert_eq!(info.user_messages_text, full.user_messages_text);
+    assert_eq!(info.assistant_messages_text, full.assistant_messages_text);
+
+    let mut expected = session_parser::parse_session_details(&fs::read_to_string(path).unwrap());
+    let mut details = cursor.state.details();
+    expected.models.sort();
+    details.models.sort();
+    assert_eq!(format!("{details:?}"), format!("{expected:?}"));
+}
+
+#[test]
+fn test_cursor_parses_only_appended_lines() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("s.jsonl");
+    fs::write(
+        &path,
+        format!(
+            "{HEADER}\n{}\n{}\n",
+            message("u1", "", "user", "first question"),
+            message("a1", "u1", "assistant", "first answer")
+        ),
+    )
+    .unwrap();
+
+    let update = SessionCursor::default().advance(&path).unwrap();
+    assert!(update.reset);
+    assert_eq!(update.entries.len(), 2);
+    assert_eq!(update.cursor.line_count, 3);
+    let info = update.session_info(&path, None);
+    assert_matches_full_parse(&path, &update.cursor, &info);
+    let cursor = update.cursor;
+
+    // A rewound turn branches off u1; pi is still writing the last line
+    let line = message("a2", "u1", "assistant", "second answer");
+    let (head, rest) = line.split_at(30);
+    append(
+        &path,
+        &format!("{}\n{head}", message("u2", "a1", "user", "abandoned")),
+    );
+    let update = cursor.advance(&path).unwrap();
+    assert!(!update.reset && update.extends(Some(&cursor)));
+    assert_eq!(update.entries.len(), 1);
+    assert_eq!(update.entries[0].id, "u2");
+    assert_eq!(update.cursor.partial, head.as_bytes());
+    assert_eq!(update.cursor.line_count, 4);
+    let info = update.session_info(&path, Some(&info));
+    let cursor = update.cursor;
+
+    append(&path, &format!("{rest}\n"));
+    let update = cursor.advance(&path).unwrap();
+    assert_eq!(update.entries.len(), 1);
+    assert_eq!(update.entries[0].id, "a2");
+    assert!(update.cursor.partial.is_empty());
+    assert_eq!(update.cursor.offset, fs::metadata(&path).unwrap().len());
+    let info = update.session_info(&path, Some(&info));
+    assert_matches_full_parse(&path, &update.cursor, &info);
+    let details = update.cursor.state.details();
+    assert_eq!((details.user_messages, details.assistant_messages), (1, 1));
+    assert_eq!(details.input_tokens, 20);
+    let cursor = update.cursor;
+
+    // Renaming rewrites the header, so the next update starts over
+    fs::write(
+        &path,
+        fs::read_to_string(&path).unwrap().replacen(
+            r#""cwd":"/proj/big"}"#,
+            r#""cwd":"/proj/big","name":"Big"}"#,
+            1,
+        ),
+    )
+    .unwrap();
+    let update = cursor.advance(&path).unwrap();
+    assert!(update.reset);
+    assert_eq!(update.entries.len(), 4);
+    let info = update.session_info(&path, Some(&info));
+    assert_eq!(info.name.as_deref(), Some("Big"));
+    assert_matches_full_parse(&path, &update.cursor, &info);
+}
+
+#[tokio::test]
+async fn test_rescan_appends_message_entries_and_reads_from_cursor() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj-big--");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("big.jsonl");
+    fs::write(
+        &path,
+        format!("{HEADER}\n{}\n", message("u1", "", "user", "hello")),
+    )
+    .unwrap();
+    let path_str = path.to_string_lossy().to_string();
+
+    let conn = sqlite_cache::init_db().unwrap();
+    scanner::rescan_changed_files(vec![path_str.clone()])
+        .await
+        .unwrap();
+    let stored = sqlite_cache::get_session_cursor(&conn, &path_str)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.line_count, 2);
+
+    append(
+        &path,
+        &format!(
+            "{}\n{}\n",
+            message("a1", "u1", "assistant", "appended answer"),
+            message("u2", "a1", "user", "follow up")
+        ),
+    );
+    let diff = scanner::rescan_changed_files(vec![path_str.clone()])
+        .await
+        .unwrap();
+    let info = &diff.updated[0];
+    assert_eq!(info.message_count, 3);
+    assert_eq!(info.all_messages_text, "hello\nappended answer\nfollow up");
+
+    let stored = sqlite_cache::get_session_cursor(&conn, &path_str)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.offset, fs::metadata(&path).unwrap().len());
+    assert_eq!(stored.line_count, 4);
+    let ids: Vec<String> = conn
+        .prepare("SELECT id FROM message_entries WHERE session_path = ? ORDER BY id")
+        .unwrap()
+        .query_map(params![path_str], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids, ["a1", "u1", "u2"]);
+
+    // Saving without a cursor drops the stored one
+    let (full, entries) = scanner::parse_session_info(&path).unwrap();
+    sqlite_cache::upsert_session(&conn, &full, full.modified, Some(&entries)).unwrap();
+    assert!(sqlite_cache::get_session_cursor(&conn, &path_str)
+        .unwrap()
+        .is_none());
+    scanner::rescan_changed_files(vec![path_str.clone()])
+        .await
+        .unwrap();
+
+    // The viewer's line numbering is unchanged
+    let (total, content) = read_session_file_incremental(path_str.clone(), 2)
+        .await
+        .unwrap();
+    assert_eq!(total, 4);
+    assert_eq!(content.lines().count(), 2);
+    assert!(content.starts_with(r#"{"type":"message","id":"a1""#));
+    let (total, content) = read_session_file_incremental(path_str.clone(), 4)
+        .await
+        .unwrap();
+    assert_eq!((total, content.as_str()), (4, ""));
+    let (total, content) = session_cursor::read_lines_from(&path, 0, None).unwrap();
+    assert_eq!((total, content.lines().count()), (4, 4));
+
+    // An unfinished line is not reported until pi completes it
+    append(&path, r#"{"type":"message","id":"a2""#);
+    let (total, _) = read_session_file_incremental(path_str.clone(), 4)
+        .await
+        .unwrap();
+    assert_eq!(total, 4);
+}
@@ -0,0 +1,78 @@
+use pi_session_manager::{scanner, session_cursor, sqlite_cache};
+use rusqlite::params;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+const HEADER: &str = r#"{"type":"session","version":3,"id":"moving-1","timestamp":"2026-03-01T09:00:00Z","cwd":"/proj/old"}"#;
+const MESSAGE: &str = r#"{"type":"message","id":"u1","parentId":"","timestamp":"2026-03-01T09:01:00Z","message":{"role":"user","content":[{"type":"text","text":"where did it go"}]}}"#;
+
+#[tokio::test]
+async fn test_moved_session_keeps_its_cache_rows() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let sessions_dir = temp_dir.path().join(".pi/agent/sessions");
+    let old_dir = sessions_dir.join("--proj-old--");
+    fs::create_dir_all(&old_dir).unwrap();
+    let old_path = old_dir.join("s.jsonl");
+    fs::write(&old_path, format!("{HEADER}\n{MESSAGE}\n")).unwrap();
+    let old_str = old_path.to_string_lossy().to_string();
+
+    let conn = sqlite_cache::init_db().unwrap();
+    scanner::rescan_changed_files(vec![old_str.clone()])
+        .await
+        .unwrap();
+    sqlite_cache::add_favorite(&conn, "moving-1", "session", "Moving", &old_str).unwrap();
+
+    // The project directory is renamed
+    let new_dir = sessions_dir.join("--proj-new--");
+    fs::rename(&old_dir, &new_dir).unwrap();
+    let new_str = new_dir.join("s.jsonl").to_string_lossy().to_string();
+    let diff = scanner::rescan_changed_files(vec![new_str.clone()])
+        .await
+        .unwrap();
+    assert_eq!(diff.removed, std::slice::from_ref(&old_str));
+    assert_eq!(diff.updated[0].path, new_str);
+
+    assert_eq!(
+        sqlite_cache::find_session_path(&conn, "moving-1").unwrap(),
+        Some(new_str.clone())
+    );
+    assert_eq!(sqlite_cache::get_session_count(&conn).unwrap(), 1);
+    let entries: Vec<String> = conn
+        .prepare("SELECT session_path FROM message_entries WHERE id = 'u1'")
+        .unwrap()
+        .query_map(params![], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(entries, std::slice::from_ref(&new_str));
+    assert!(sqlite_cache::get_session_cursor(&conn, &new_str)
+        .unwrap()
+        .is_some());
+    let favorites = sqlite_cache::get_all_favorites(&conn).unwrap();
+    assert_eq!(favorites[0].path, new_str);
+}
+
+#[test]
+fn test_header_without_id_gets_a_stable_identity() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("a.jsonl");
+    let header =
+        r#"{"type":"session","version":3,"timestamp":"2026-03-01T09:00:00Z","cwd":"/proj/x"}"#;
+    fs::write(&path, format!("{header}\n{MESSAGE}\n")).unwrap();
+    let id = session_cursor::read_session_id(&path).unwrap();
+    assert!(id.starts_with("sha256-"), "{id}");
+
+    // Neither the file name nor a later rename changes it
+    let renamed = temp_dir.path().join("b.jsonl");
+    let named = r#"{"type":"session","version":3,"timestamp":"2026-03-01T09:00:00Z","cwd":"/proj/x","name":"Renamed"}"#;
+    fs::write(&renamed, format!("{named}\n{MESSAGE}\n")).unwrap();
+    assert_eq!(session_cursor::read_session_id(&renamed).unwrap(), id);
+
+    fs::write(&path, format!("{HEADER}\n")).unwrap();
+    assert_eq!(session_cursor::read_session_id(&path).unwrap(), "moving-1");
+}
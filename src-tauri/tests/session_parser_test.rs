@@ -0,0 +1,174 @@
+use pi_session_manager::models::Content;
+use pi_session_manager::session_parser;
+use serde_json::json;
+use std::fs;
+use tempfile::NamedTempFile;
+
+const SESSION: &str = r#"{"type":"session","version":3,"id":"s1","name":"Parser Session","timestamp":"2026-02-10T22:00:00Z","cwd":"/work"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"look at this"},{"type":"image","mimeType":"image/png","data":"aGVsbG8="}]}}
+{"type":"message","id":"a1","parentId":"u1","timestamp":"2026-02-10T22:00:02Z","message":{"role":"assistant","provider":"anthropic","model":"claude-sonnet-4","content":[{"type":"thinking","thinking":"user wants a listing"},{"type":"text","text":"Listing files"},{"type":"toolCall","id":"call_1","name":"bash","arguments":{"command":"ls"}}],"usage":{"input":10,"output":5,"cacheRead":2,"cacheWrite":1,"cost":{"input":0.1,"output":0.2,"cacheRead":0.0,"cacheWrite":0.0}}}}
+{"type":"message","id":"t1","parentId":"a1","timestamp":"2026-02-10T22:00:03Z","message":{"role":"toolResult","toolCallId":"call_1","toolName":"bash","content":[{"type":"text","text":"Cargo.toml"}],"isError":false}}
+{"type":"message","id":"b1","parentId":"t1","timestamp":"2026-02-10T22:00:04Z","message":{"role":"bashExecution","command":"false","output":"","exitCode":1}}
+{"type":"model_change","id":"m1","parentId":"b1","timestamp":"2026-02-10T22:00:05Z","provider":"openai","modelId":"gpt-5"}
+{"type":"compaction","id":"c1","parentId":"m1","timestamp":"2026-02-10T22:00:06Z","summary":"Listed files","firstKeptEntryId":"a1","tokensBefore":1234}
+{"type":"branch_summary","id":"bs1","parentId":"c1","timestamp":"2026-02-10T22:00:07Z","fromId":"u1","summary":"Abandoned approach"}
+{"type":"custom_message","id":"cm1","parentId":"bs1","timestamp":"2026-02-10T22:00:08Z","customType":"reminder","content":"remember the tests","display":true}
+not json
+"#;
+
+#[test]
+fn test_parse_entries_is_lossless() {
+    let entries = session_parser::parse_entries(SESSION);
+    let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["u1", "a1", "t1", "b1", "m1", "c1", "bs1", "cm1"]);
+
+    let user = entries[0].message.as_ref().unwrap();
+    assert_eq!(user.text(), "look at this");
+    assert_eq!(
+        user.content[1],
+        Content::Image {
+            mime: "image/png".to_string(),
+            size: 5
+        }
+    );
+
+    let assistant = entries[1].message.as_ref().unwrap();
+    assert_eq!(entries[1].parent_id.as_deref(), Some("u1"));
+    assert_eq!(assistant.model.as_deref(), Some("claude-sonnet-4"));
+    assert_eq!(assistant.usage.as_ref().unwrap().cache_read, 2);
+    assert_eq!(
+        assistant.content[0],
+        Content::Thinking {
+            thinking: "user wants a listing".to_string()
+        }
+    );
+    assert_eq!(
+        assistant.content[2],
+        Content::ToolCall {
+            name: "bash".to_string(),
+            id: "call_1".to_string(),
+            arguments: json!({"command": "ls"}),
+        }
+    );
+    assert_eq!(assistant.text(), "Listing files");
+
+    assert_eq!(
+        entries[2].message.as_ref().unwrap().content,
+        [Content::ToolResult {
+            tool_call_id: "call_1".to_string(),
+            is_error: false,
+            output: "Cargo.toml".to_string(),
+        }]
+    );
+    let Content::ToolResult {
+        is_error, output, ..
+    } = &entries[3].message.as_ref().unwrap().content[0]
+    else {
+        panic!("expected bash output");
+    };
+    assert!(*is_error);
+    assert_eq!(output, "$ false\n");
+
+    let change = entries[4].model_change.as_ref().unwrap();
+    assert_eq!(change.provider.as_deref(), Some("openai"));
+    assert_eq!(change.model_id, "gpt-5");
+
+    let compaction = entries[5].compaction.as_ref().unwrap();
+    assert_eq!(compaction.first_kept_entry_id.as_deref(), Some("a1"));
+    assert_eq!(compaction.tokens_before, Some(1234));
+
+    assert_eq!(
+        entries[6]
+            .branch_summary
+            .as_ref()
+            .unwrap()
+            .from_id
+            .as_deref(),
+        Some("u1")
+    );
+
+    let custom = entries[7].custom_message.as_ref().unwrap();
+    assert_eq!(custom.custom_type, "reminder");
+    assert_eq!(
+        custom.content,
+        [Content::Text {
+            text: "remember the tests".to_string()
+        }]
+    );
+
+    // Serialized form is tagged by content type
+    let value = serde_json::to_value(&entries[1]).unwrap();
+    assert_eq!(value["message"]["content"][2]["type"], "toolCall");
+    assert_eq!(value["message"]["content"][2]["arguments"]["command"], "ls");
+    let value = serde_json::to_value(&entries[2]).unwrap();
+    assert_eq!(value["message"]["content"][0]["toolCallId"], "call_1");
+    assert_eq!(value["message"]["content"][0]["isError"], false);
+}
+
+#[test]
+fn test_entries_without_a_valid_timestamp_are_kept() {
+    let content = r#"{"type":"session","version":3,"id":"s2","timestamp":"2026-02-10T22:00:00Z","cwd":"/work"}
+{"type":"message","id":"u1","parentId":null,"message":{"role":"user","content":"no time"}}
+{"type":"message","id":"a1","parentId":"u1","timestamp":"yesterday","message":{"role":"assistant","content":"bad time"}}
+{"type":"message","id":"u2","parentId":"a1","timestamp":"2026-02-10T22:00:03Z","message":{"role":"user","content":"on time"}}
+"#;
+    let entries = session_parser::parse_entries(content);
+    let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["u1", "a1", "u2"]);
+    assert!(entries[0].timestamp.is_none() && entries[1].timestamp.is_none());
+
+    let details = session_parser::parse_session_details(content);
+    assert_eq!((details.user_messages, details.assistant_messages), (2, 1));
+    assert_eq!(details.first_message_time, details.last_message_time);
+}
+
+#[test]
+fn test_session_details_from_typed_entries() {
+    let details = session_parser::parse_session_details(SESSION);
+    assert_eq!(details.user_messages, 1);
+    assert_eq!(details.assistant_messages, 1);
+    assert_eq!(details.tool_results, 1);
+    assert_eq!(details.compactions, 1);
+    assert_eq!(details.branch_summaries, 1);
+    assert_eq!(details.custom_messages, 1);
+    assert_eq!(details.models, ["anthropic/claude-sonnet-4"]);
+    assert_eq!(details.total_tokens(), 15);
+    assert!((details.total_cost() - 0.3).abs() < 1e-9);
+    assert_eq!(
+        details.last_message_time.unwrap().to_rfc3339(),
+        "2026-02-10T22:00:04+00:00"
+    );
+}
+
+#[tokio::test]
+async fn test_markdown_export_renders_all_parts() {
+    let session = NamedTempFile::with_suffix(".jsonl").unwrap();
+    fs::write(session.path(), SESSION).unwrap();
+    let output = NamedTempFile::with_suffix(".md").unwrap();
+
+    pi_session_manager::export::export_session(
+        session.path().to_str().unwrap(),
+        "md",
+        output.path().to_str().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let md = fs::read_to_string(output.path()).unwrap();
+    for expected in [
+        "# Parser Session",
+        "**User** *2026-02-10T22:00:01Z*",
+        "*[image: image/png, 5 bytes]*",
+        "> user wants a listing",
+        "Tool call `bash`:",
+        "\"command\": \"ls\"",
+        "**Tool Result**",
+        "$ false",
+        "Model changed to `openai/gpt-5`",
+        "**Compaction**",
+        "Abandoned approach",
+        "**reminder**",
+    ] {
+        assert!(md.contains(expected), "missing {expected:?} in:\n{md}");
+    }
+}
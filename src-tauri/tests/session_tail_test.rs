@@ -0,0 +1,203 @@
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use pi_session_manager::auth::{self, Caller, TokenOptions};
+use pi_session_manager::command_registry::{self, CommandHost, NoHost};
+use pi_session_manager::session_tail::{self, SessionTail, Subscriptions};
+use pi_session_manager::{scanner, sqlite_cache};
+use serde_json::{json, Value};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+const HEADER: &str = r#"{"type":"session","version":3,"id":"tail-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj/live"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"start"}]}}
+"#;
+
+async fn run(
+    caller: &Caller,
+    host: &Arc<Subscriptions>,
+    command: &str,
+    payload: Value,
+) -> Result<Value, String> {
+    let host: Arc<dyn CommandHost> = host.clone();
+    command_registry::dispatch_as(caller, command, payload, host).await
+}
+
+fn message(id: &str, text: &str) -> String {
+    format!(
+        r#"{{"type":"message","id":"{id}","parentId":null,"timestamp":"2026-02-10T22:01:00Z","message":{{"role":"assistant","content":[{{"type":"text","text":"{text}"}}]}}}}"#
+    )
+}
+
+fn append(path: &Path, data: &str) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(data.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_tail_reads_complete_lines_from_offset() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("s.jsonl");
+    fs::write(&path, HEADER).unwrap();
+
+    let mut tail = SessionTail::new(&path, 0);
+    let batch = tail.poll().unwrap().unwrap();
+    assert_eq!(batch.entries.len(), 1);
+    assert_eq!(batch.offset, HEADER.len() as u64);
+    assert!(!batch.reset);
+    assert!(tail.poll().unwrap().is_none());
+
+    // A line pi is still writing is left for the next poll
+    let line = message("a1", "partial");
+    let (head, rest) = line.split_at(20);
+    append(&path, head);
+    assert!(tail.poll().unwrap().is_none());
+    assert_eq!(tail.offset(), HEADER.len() as u64);
+    append(&path, &format!("{rest}\n"));
+    let batch = tail.poll().unwrap().unwrap();
+    assert_eq!(batch.entries[0].id, "a1");
+    assert_eq!(tail.offset(), fs::metadata(&path).unwrap().len());
+
+    // Starting at the end only reports what comes later
+    let mut late = SessionTail::at_end(&path).unwrap();
+    append(&path, &format!("{}\n", message("a2", "more")));
+    let batch = late.poll().unwrap().unwrap();
+    assert_eq!(batch.entries.len(), 1);
+    assert_eq!(batch.entries[0].id, "a2");
+
+    // A rewritten, shorter file starts over
+    fs::write(&path, HEADER).unwrap();
+    let batch = late.poll().unwrap().unwrap();
+    assert!(batch.reset);
+    assert_eq!(batch.entries.len(), 1);
+    assert_eq!(batch.offset, HEADER.len() as u64);
+}
+
+#[tokio::test]
+async fn test_subscriptions_stream_appended_entries() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj-live--");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("live.jsonl");
+    fs::write(&path, HEADER).unwrap();
+    let path_str = path.to_string_lossy().to_string();
+
+    // Subscriptions are registry commands that only a connection can host
+    let commands = command_registry::list_commands();
+    assert!(["subscribe_session", "unsubscribe_session"]
+        .iter()
+        .all(|name| commands.iter().any(|c| c.name == *name && !c.mutating)));
+    let local = Caller::local("ws");
+    let err = command_registry::dispatch_as(
+        &local,
+        "subscribe_session",
+        json!({ "session": "tail-1" }),
+        Arc::new(NoHost),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.contains("WebSocket connection"), "{err}");
+
+    let (tails, mut rx) = Subscriptions::new(local.clone(), Arc::new(NoHost));
+    let scanned = run(&local, &tails, "scan_sessions", Value::Null).await;
+    assert_eq!(scanned.unwrap().as_array().unwrap().len(), 1);
+    let err = run(
+        &local,
+        &tails,
+        "subscribe_session",
+        json!({ "session": "tail-1", "from": 0 }),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.contains("unknown field"), "{err}");
+    let subscribed = run(
+        &local,
+        &tails,
+        "subscribe_session",
+        json!({ "session": "tail-1" }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(subscribed["path"], path_str.as_str());
+    assert_eq!(subscribed["offset"], HEADER.len() as u64);
+
+    append(&path, &format!("{}\n", message("a1", "live output")));
+    let appended = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(appended["sessionId"], "tail-1");
+    assert_eq!(appended["entries"][0]["id"], "a1");
+    let offset = appended["offset"].as_u64().unwrap();
+    assert_eq!(offset, fs::metadata(&path).unwrap().len());
+
+    let missing = run(
+        &local,
+        &tails,
+        "subscribe_session",
+        json!({ "session": "nope" }),
+    )
+    .await;
+    assert!(missing.unwrap_err().contains("not found"));
+
+    let removed = run(
+        &local,
+        &tails,
+        "unsubscribe_session",
+        json!({ "path": path_str }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(removed, Value::Bool(true));
+
+    // Tokens limited to other projects cannot follow the session
+    sqlite_cache::init_db().unwrap();
+    auth::init().unwrap();
+    let token = auth::create_token(
+        "elsewhere",
+        None,
+        &TokenOptions {
+            allowed_paths: vec!["/other".to_string()],
+            ..TokenOptions::default()
+        },
+    )
+    .unwrap();
+    let remote: IpAddr = "10.0.0.9".parse().unwrap();
+    let restricted = auth::authenticate(remote, Some(&token), "ws").unwrap();
+    let (tails, _rx) = Subscriptions::new(restricted.clone(), Arc::new(NoHost));
+    let denied = run(
+        &restricted,
+        &tails,
+        "subscribe_session",
+        json!({ "session": "tail-1" }),
+    )
+    .await;
+    assert!(denied.unwrap_err().starts_with("Forbidden"));
+    let response = session_tail::sse_response(restricted, "tail-1", None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // SSE replays from the requested offset
+    let response = session_tail::sse_response(Caller::local("sse"), &path_str, Some(0)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body();
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.starts_with("event: session-appended"), "{text}");
+    assert!(text.contains(r#""id":"u1""#) && text.contains(r#""id":"a1""#));
+
+    let response = session_tail::sse_response(Caller::local("sse"), "nope", None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,165 @@
+use pi_session_manager::session_parser;
+use pi_session_manager::session_tree::SessionTree;
+use std::fs;
+use tempfile::NamedTempFile;
+
+fn message(id: &str, parent: Option<&str>, second: u32, role: &str, text: &str) -> String {
+    let parent = parent.map_or("null".to_string(), |p| format!("\"{p}\""));
+    format!(
+        r#"{{"type":"message","id":"{id}","parentId":{parent},"timestamp":"2026-02-10T22:00:{second:02}Z","message":{{"role":"{role}","content":[{{"type":"text","text":"{text}"}}]}}}}"#
+    )
+}
+
+/// u1 -> a1 -> u2 -> a2 (abandoned), then rewound to a1: bs -> u3 -> a3 (active)
+fn forked_session() -> String {
+    [
+        r#"{"type":"session","version":3,"id":"s1","name":"Forked","timestamp":"2026-02-10T22:00:00Z","cwd":"/work"}"#.to_string(),
+        message("u1", None, 1, "user", "start"),
+        message("a1", Some("u1"), 2, "assistant", "ok"),
+        message("u2", Some("a1"), 3, "user", "try redis"),
+        message("a2", Some("u2"), 4, "assistant", "redis done"),
+        r#"{"type":"branch_summary","id":"bs","parentId":"a1","timestamp":"2026-02-10T22:00:05Z","fromId":"a2","summary":"Tried redis, too slow"}"#.to_string(),
+        message("u3", Some("bs"), 6, "user", "try sqlite"),
+        message("a3", Some("u3"), 7, "assistant", "sqlite done"),
+    ]
+    .join("\n")
+}
+
+#[test]
+fn test_tree_active_path_and_branches() {
+    let tree = SessionTree::build(session_parser::parse_entries(&forked_session()));
+
+    assert_eq!(tree.roots, ["u1"]);
+    assert_eq!(tree.active_leaf.as_deref(), Some("a3"));
+    assert_eq!(tree.active_path, ["u1", "a1", "bs", "u3", "a3"]);
+
+    let a1 = tree.nodes.iter().find(|n| n.entry.id == "a1").unwrap();
+    assert_eq!(a1.children, ["u2", "bs"]);
+    assert_eq!(a1.depth, 1);
+
+    assert_eq!(tree.branches.len(), 1);
+    let branch = &tree.branches[0];
+    assert_eq!(branch.fork_id.as_deref(), Some("a1"));
+    assert_eq!(branch.leaf_id, "a2");
+    assert_eq!(branch.entry_ids, ["u2", "a2"]);
+    assert_eq!(branch.message_count, 2);
+    assert_eq!(
+        branch.summary.as_ref().unwrap().summary,
+        "Tried redis, too slow"
+    );
+
+    let active: Vec<&str> = tree.active_entries().map(|e| e.id.as_str()).collect();
+    assert_eq!(active, tree.active_path);
+}
+
+#[test]
+fn test_tree_without_parent_links_is_linear() {
+    let content = [
+        message("u1", None, 1, "user", "a"),
+        message("a1", None, 2, "assistant", "b"),
+        message("u2", None, 3, "user", "c"),
+    ]
+    .join("\n");
+    let tree = SessionTree::build(session_parser::parse_entries(&content));
+
+    assert_eq!(tree.roots, ["u1"]);
+    assert_eq!(tree.active_path, ["u1", "a1", "u2"]);
+    assert!(tree.branches.is_empty());
+}
+
+#[tokio::test]
+async fn test_stats_and_export_follow_active_branch() {
+    let details = session_parser::parse_session_details(&forked_session());
+    assert_eq!(details.user_messages, 2);
+    assert_eq!(details.assistant_messages, 2);
+    assert_eq!(details.branch_summaries, 1);
+
+    let session = NamedTempFile::with_suffix(".jsonl").unwrap();
+    fs::write(session.path(), forked_session()).unwrap();
+    let output = NamedTempFile::with_suffix(".md").unwrap();
+    pi_session_manager::export::export_session(
+        session.path().to_str().unwrap(),
+        "md",
+        output.path().to_str().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let md = fs::read_to_string(output.path()).unwrap();
+    assert!(md.contains("sqlite done"));
+    assert!(md.contains("Tried redis, too slow"));
+    assert!(!md.contains("redis done"));
+}
+
+#[tokio::test]
+async fn test_get_session_tree_dispatch() {
+    let session = NamedTempFile::with_suffix(".jsonl").unwrap();
+    fs::write(session.path(), forked_session()).unwrap();
+
+    let value = pi_session_manager::dispatch::dispatch(
+        "get_session_tree",
+        &serde_json::json!({ "path": session.path().to_str().unwrap() }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(value["active_leaf"], "a3");
+    assert_eq!(value["branches"][0]["leaf_id"], "a2");
+    assert_eq!(value["nodes"].as_array().unwrap().len(), 7);
+}
+
+#[test]
+fn test_html_export_is_self_contained_and_branch_aware() {
+    let mut content = forked_session();
+    content.push('\n');
+    content.push_str(&message(
+        "u4",
+        Some("a3"),
+        8,
+        "user",
+        "```rust\\nfn main() {}\\n```\\n<script>alert(1)</script>",
+    ));
+
+    let html = pi_session_manager::html_export::render_session_html(&content);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(!html.contains("<script>"), "raw HTML must be escaped");
+    assert!(html.contains("&lt;script&gt;"));
+    // syntect emits inline-styled spans for highlighted code
+    assert!(html.contains("<span style="));
+    // Abandoned branch is collapsed with its summary
+    assert!(
+        html.contains("<details class=\"branch\"><summary>Abandoned branch: Tried redis, too slow")
+    );
+    assert!(html.contains("redis done"));
+    assert!(html.contains("<b>2</b>Branches"));
+}
+
+#[test]
+fn test_html_export_drops_unsafe_link_urls() {
+    let text =
+        "[docs](https://example.com/a) [x](javascript:alert(1)) [y]( JaVa\\tScript:alert(2)) \
+                ![img](data:image/svg+xml;base64,PHN2Zz4=) [rel](./notes.md) <vbscript:msgbox> \
+                [mail](mailto:dev@example.com)";
+    let html = pi_session_manager::html_export::render_session_html(&[
+        r#"{"type":"session","version":3,"id":"s1","timestamp":"2026-02-10T22:00:00Z","cwd":"/work"}"#.to_string(),
+        message("u1", None, 1, "user", text),
+    ]
+    .join("\n"));
+    assert!(html.contains("<a href=\"https://example.com/a\">docs</a>"));
+    assert!(html.contains("<a href=\"./notes.md\">rel</a>"));
+    assert!(html.contains("<a href=\"mailto:dev@example.com\">mail</a>"));
+    let lower = html.to_lowercase();
+    for scheme in ["javascript:", "data:", "vbscript:"] {
+        assert!(
+            !lower.contains(&format!("href=\"{scheme}")),
+            "{scheme} link kept"
+        );
+        assert!(
+            !lower.contains(&format!("src=\"{scheme}")),
+            "{scheme} image kept"
+        );
+    }
+    // The link text stays readable
+    assert!(html.contains("</a> x ["));
+    assert!(html.contains(" img <a"));
+}
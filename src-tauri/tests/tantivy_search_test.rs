@@ -0,0 +1,296 @@
+#![allow(clippy::type_complexity, clippy::await_holding_lock)]
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use pi_session_manager::commands::full_text_search;
+use pi_session_manager::config::{self, Config, SearchEngine};
+use pi_session_manager::{scanner, sqlite_cache, tantivy_search};
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn make_session_file(id: &str, cwd: &str, messages: &[(&str, &str)]) -> String {
+    let header = format!(
+        r#"{{"type":"session","version":3,"id":"{id}","timestamp":"2026-02-10T22:00:00Z","cwd":"{cwd}"}}"#
+    );
+    let mut lines = vec![header];
+    for (i, (role, text)) in messages.iter().enumerate() {
+        lines.push(format!(
+            r#"{{"type":"message","id":"{id}-msg{i}","parentId":null,"timestamp":"2026-02-10T22:00:{i:02}Z","message":{{"role":"{role}","content":[{{"type":"text","text":"{text}"}}]}}}}"#
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Point HOME at a temp dir, select the Tantivy engine and index the given sessions
+fn setup(sessions: &[(&str, &str, &[(&str, &str)])]) -> tempfile::TempDir {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+
+    let config = Config {
+        search_engine: SearchEngine::Tantivy,
+        ..Default::default()
+    };
+    config::save_config(&config).unwrap();
+
+    let sessions_dir = temp_dir.path().join("sessions");
+    fs::create_dir_all(&sessions_dir).unwrap();
+    let conn = sqlite_cache::init_db_with_config(&config).unwrap();
+    for (id, cwd, messages) in sessions {
+        let path = sessions_dir.join(format!("{id}.jsonl"));
+        fs::write(&path, make_session_file(id, cwd, messages)).unwrap();
+        let (session, entries) = scanner::parse_session_info(&path).unwrap();
+        sqlite_cache::upsert_session(&conn, &session, Utc::now(), Some(&entries)).unwrap();
+    }
+
+    temp_dir
+}
+
+#[tokio::test]
+async fn test_tantivy_ranking_snippets_and_filters() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = setup(&[
+        (
+            "sess1",
+            "/work/api",
+            &[
+                ("user", "tokio tokio tokio runtime question"),
+                ("assistant", "the runtime is fine"),
+            ],
+        ),
+        (
+            "sess2",
+            "/work/web",
+            &[
+                (
+                    "user",
+                    "a long unrelated message that mentions tokio only once among many other words",
+                ),
+                ("assistant", "tokio answer"),
+            ],
+        ),
+    ]);
+
+    assert!(tantivy_search::is_enabled());
+    assert!(temp_dir
+        .path()
+        .join(".pi/agent/sessions/tantivy_index/meta.json")
+        .exists());
+
+    let response = full_text_search("tokio".to_string(), "all".to_string(), None, 0, 10, None)
+        .await
+        .unwrap();
+    assert_eq!(response.total_hits, 3);
+    // BM25: the short, repetitive entry ranks first
+    assert_eq!(response.hits[0].entry_id, "sess1-msg0");
+    assert!(response.hits[0].score >= response.hits[1].score);
+    let snippet = response.hits[0].snippet.as_deref().unwrap();
+    assert!(snippet.contains("<b>tokio</b>"), "snippet: {snippet}");
+
+    let response = full_text_search(
+        "tokio".to_string(),
+        "assistant".to_string(),
+        None,
+        0,
+        10,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.total_hits, 1);
+    assert_eq!(response.hits[0].entry_id, "sess2-msg1");
+
+    let response = full_text_search(
+        "tokio".to_string(),
+        "all".to_string(),
+        Some("*sess1*".to_string()),
+        0,
+        10,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(response
+        .hits
+        .iter()
+        .all(|h| h.session_path.contains("sess1")));
+    assert_eq!(response.hits[0].session_id, "sess1");
+
+    // Query qualifiers are part of the index query
+    let response = full_text_search(
+        "tokio cwd:/work/web -answer".to_string(),
+        "all".to_string(),
+        None,
+        0,
+        10,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.total_hits, 1);
+    assert_eq!(response.hits[0].entry_id, "sess2-msg0");
+
+    let response = full_text_search(
+        "runtime question".to_string(),
+        "all".to_string(),
+        None,
+        0,
+        10,
+        Some("phrase".to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.total_hits, 1);
+}
+
+#[tokio::test]
+async fn test_tantivy_filters_reach_beyond_top_candidates() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = setup(&[]);
+    let config = config::load_config().unwrap();
+    let conn = sqlite_cache::init_db_with_config(&config).unwrap();
+
+    // 2500 short, highly ranked entries, then one weak match in another project
+    let write = |id: &str, cwd: &str, day: &str, texts: Vec<String>| {
+        let mut lines = vec![format!(
+            r#"{{"type":"session","version":3,"id":"{id}","timestamp":"{day}T00:00:00Z","cwd":"{cwd}"}}"#
+        )];
+        for (i, text) in texts.iter().enumerate() {
+            lines.push(format!(
+                r#"{{"type":"message","id":"{id}-msg{i}","parentId":null,"timestamp":"{day}T{:02}:{:02}:{:02}Z","message":{{"role":"user","content":[{{"type":"text","text":"{text}"}}]}}}}"#,
+                i / 3600,
+                i / 60 % 60,
+                i % 60
+            ));
+        }
+        let path = temp_dir.path().join(format!("sessions/{id}.jsonl"));
+        fs::write(&path, lines.join("\n")).unwrap();
+        let (session, entries) = scanner::parse_session_info(&path).unwrap();
+        sqlite_cache::upsert_session(&conn, &session, Utc::now(), Some(&entries)).unwrap();
+    };
+    for n in 0..5 {
+        write(
+            &format!("bulk{n}"),
+            "/work/web",
+            "2026-02-10",
+            vec!["tokio tokio".to_string(); 500],
+        );
+    }
+    write(
+        "rare",
+        "/work/api/server",
+        "2026-05-01",
+        vec![format!("tokio {}", "filler words ".repeat(40))],
+    );
+
+    let search = |query: &str| full_text_search(query.into(), "all".into(), None, 0, 10, None);
+    let response = search("tokio").await.unwrap();
+    assert_eq!(response.total_hits, 16);
+    // Every counted hit can be paged, including the one ranked past the candidates
+    let response = full_text_search("tokio".into(), "all".into(), None, 1, 10, None)
+        .await
+        .unwrap();
+    assert_eq!(response.hits.len(), 6);
+    assert!(!response.has_more);
+    assert!(response.hits.iter().any(|h| h.entry_id == "rare-msg0"));
+
+    let response = search("tokio cwd:/work/api").await.unwrap();
+    assert_eq!(response.total_hits, 1);
+    assert_eq!(response.hits[0].entry_id, "rare-msg0");
+
+    let response = search("tokio after:2026-04-01").await.unwrap();
+    assert_eq!(response.total_hits, 1);
+    let response = search("tokio before:2026-04-01 -cwd:/work/web")
+        .await
+        .unwrap();
+    assert_eq!(response.total_hits, 0);
+}
+
+#[tokio::test]
+async fn test_tantivy_incremental_update_and_delete() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = setup(&[("sess1", "/work/api", &[("user", "first draft about serde")])]);
+
+    let config = config::load_config().unwrap();
+    let conn = sqlite_cache::init_db_with_config(&config).unwrap();
+    let path = temp_dir.path().join("sessions/sess1.jsonl");
+
+    // Rewrite the session: old text must disappear from the index
+    fs::write(
+        &path,
+        make_session_file(
+            "sess1",
+            "/work/api",
+            &[("user", "second draft about rayon")],
+        ),
+    )
+    .unwrap();
+    let (session, entries) = scanner::parse_session_info(&path).unwrap();
+    sqlite_cache::upsert_session(&conn, &session, Utc::now(), Some(&entries)).unwrap();
+
+    let hits = |q: &str| {
+        let q = q.to_string();
+        async move {
+            full_text_search(q, "all".to_string(), None, 0, 10, None)
+                .await
+                .unwrap()
+                .total_hits
+        }
+    };
+    assert_eq!(hits("serde").await, 0);
+    assert_eq!(hits("rayon").await, 1);
+
+    sqlite_cache::delete_session(&conn, &session.path).unwrap();
+    assert_eq!(hits("rayon").await, 0);
+}
+
+#[tokio::test]
+async fn test_tantivy_batch_commits_once_and_bad_timestamps_use_session_time() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = setup(&[]);
+    let config = config::load_config().unwrap();
+    let conn = sqlite_cache::init_db_with_config(&config).unwrap();
+    let hits = |q: &str| {
+        let q = q.to_string();
+        async move {
+            full_text_search(q, "all".to_string(), None, 0, 10, None)
+                .await
+                .unwrap()
+                .total_hits
+        }
+    };
+
+    assert_eq!(hits("crossbeam").await, 0);
+
+    // Writes inside a batch become searchable when the batch ends
+    let batch = tantivy_search::batch();
+    for id in ["b1", "b2"] {
+        let path = temp_dir.path().join(format!("sessions/{id}.jsonl"));
+        fs::write(
+            &path,
+            make_session_file(id, "/work/api", &[("user", "batched crossbeam")]),
+        )
+        .unwrap();
+        let (session, entries) = scanner::parse_session_info(&path).unwrap();
+        sqlite_cache::upsert_session(&conn, &session, Utc::now(), Some(&entries)).unwrap();
+    }
+    assert_eq!(hits("crossbeam").await, 0);
+    drop(batch);
+    assert_eq!(hits("crossbeam").await, 2);
+
+    // An unreadable entry timestamp falls back to the session header time
+    conn.execute(
+        "UPDATE message_entries SET timestamp = 'not a date' WHERE session_path LIKE '%b1.jsonl'",
+        [],
+    )
+    .unwrap();
+    tantivy_search::rebuild_from_db(&conn).unwrap();
+    assert_eq!(hits("crossbeam after:2026-02-01").await, 2);
+    assert_eq!(hits("crossbeam after:2026-03-01").await, 0);
+}
@@ -0,0 +1,79 @@
+use axum::routing::get;
+use axum::Router;
+use pi_session_manager::tls::{self, TlsIdentity};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use std::env;
+use std::fs;
+use std::sync::Arc;
+use tempfile::tempdir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn test_self_signed_certificate_and_handshake() {
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("XDG_CONFIG_HOME");
+
+    let first = TlsIdentity::load(None, Some("  "), &["0.0.0.0"]).unwrap();
+    assert!(first.self_signed);
+    assert_eq!(first.fingerprint.len(), 32 * 3 - 1);
+
+    // The generated certificate is reused, so the fingerprint stays pinned
+    let again = TlsIdentity::load(None, None, &[]).unwrap();
+    assert_eq!(again.fingerprint, first.fingerprint);
+
+    let dir = tls::self_signed_dir().unwrap();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&key_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let cert = cert_path.to_string_lossy().to_string();
+    let key = key_path.to_string_lossy().to_string();
+    let configured = TlsIdentity::load(Some(&cert), Some(&key), &[]).unwrap();
+    assert!(!configured.self_signed);
+    assert_eq!(configured.fingerprint, first.fingerprint);
+
+    let err = TlsIdentity::load(Some(&cert), None, &[]).err().unwrap();
+    assert!(err.contains("set together"), "{err}");
+    let err = TlsIdentity::load(Some(&key), Some(&key), &[])
+        .err()
+        .unwrap();
+    assert!(err.contains("No certificate"), "{err}");
+
+    // Serve over TLS and complete a request with a client that trusts only this certificate
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let app = Router::new().route("/health", get(|| async { "ok" }));
+    tokio::spawn(async move { tls::serve(listener, app, Some(&first)).await });
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots
+        .add(CertificateDer::from_pem_file(&cert_path).unwrap())
+        .unwrap();
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let client = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+    let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let domain = ServerName::try_from("localhost").unwrap();
+    let mut stream = connector.connect(domain, tcp).await.unwrap();
+
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("ok"), "{response}");
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "cli")]
+#![allow(clippy::await_holding_lock)]
+
+use lazy_static::lazy_static;
+use pi_session_manager::cli::{self, Command};
+use pi_session_manager::tui::{Action, App, Focus};
+use pi_session_manager::{scanner, sqlite_cache};
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+lazy_static! {
+    static ref TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+}
+
+const TOOL_SESSION: &str = r#"{"type":"session","version":3,"id":"tool-1","timestamp":"2026-02-10T22:00:00Z","cwd":"/proj"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T22:00:01Z","message":{"role":"user","content":[{"type":"text","text":"why is the flaky test failing"}]}}
+{"type":"message","id":"a1","parentId":"u1","timestamp":"2026-02-10T22:00:02Z","message":{"role":"assistant","content":[{"type":"thinking","thinking":"check the logs\nthen rerun"},{"type":"toolCall","id":"c1","name":"bash","arguments":{"command":"cargo test"}}]}}
+{"type":"message","id":"t1","parentId":"a1","timestamp":"2026-02-10T22:00:03Z","message":{"role":"toolResult","toolCallId":"c1","toolName":"bash","content":[{"type":"text","text":"running 3 tests\nSECRET_DETAIL_LINE"}],"isError":false}}
+"#;
+
+const PLAIN_SESSION: &str = r#"{"type":"session","version":3,"id":"plain-2","timestamp":"2026-02-10T21:00:00Z","cwd":"/other"}
+{"type":"message","id":"u1","parentId":null,"timestamp":"2026-02-10T21:00:01Z","message":{"role":"user","content":[{"type":"text","text":"write a haiku"}]}}
+"#;
+
+fn press(app: &mut App, code: KeyCode) -> Option<Action> {
+    app.handle_key(KeyEvent::from(code))
+}
+
+fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+}
+
+fn screen(app: &mut App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut out = String::new();
+    for y in 0..buffer.area.height {
+        for x in 0..buffer.area.width {
+            out.push_str(buffer[(x, y)].symbol());
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_tui_search_actions_and_rendering() {
+    let _lock = TEST_DB_LOCK.lock().unwrap();
+    let temp_dir = tempdir().unwrap();
+    env::set_var("HOME", temp_dir.path());
+    env::remove_var("PPM_TEST_DB");
+    scanner::invalidate_cache();
+
+    let dir = temp_dir.path().join(".pi/agent/sessions/--proj--");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("tool.jsonl"), TOOL_SESSION).unwrap();
+    fs::write(dir.join("plain.jsonl"), PLAIN_SESSION).unwrap();
+    cli::run(Command::Reindex { full: true }, true)
+        .await
+        .unwrap();
+    let conn = sqlite_cache::init_db().unwrap();
+    sqlite_cache::create_tag(&conn, "t-bug", "Bug", "#f00", None, None).unwrap();
+
+    let mut app = App::load("pi".to_string()).unwrap();
+    assert_eq!(app.visible_sessions().len(), 2);
+
+    // Live FTS: the match is only in message text
+    press(&mut app, KeyCode::Char('/'));
+    type_text(&mut app, "flaky");
+    let visible: Vec<&str> = app
+        .visible_sessions()
+        .iter()
+        .map(|s| s.id.as_str())
+        .collect();
+    assert_eq!(visible, ["tool-1"]);
+    press(&mut app, KeyCode::Enter);
+
+    press(&mut app, KeyCode::Char('f'));
+    assert!(sqlite_cache::is_favorite(&conn, "tool-1").unwrap());
+    press(&mut app, KeyCode::Char('t'));
+    type_text(&mut app, "bug");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.status(), "Tagged Bug");
+    assert_eq!(sqlite_cache::get_all_session_tags(&conn).unwrap().len(), 1);
+
+    // Tool calls, their output and thinking start collapsed
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.focus(), Focus::Messages);
+    let text = screen(&mut app);
+    assert!(text.contains("★ why is the flaky test failing"), "{text}");
+    assert!(text.contains("▸ bash cargo test"), "{text}");
+    assert!(text.contains("▸ thinking (2 lines)"), "{text}");
+    assert!(
+        text.contains("▸ output (2 lines) running 3 tests"),
+        "{text}"
+    );
+    assert!(!text.contains("SECRET_DETAIL_LINE"));
+    press(&mut app, KeyCode::Char('x'));
+    assert!(screen(&mut app).contains("SECRET_DETAIL_LINE"));
+
+    press(&mut app, KeyCode::Char('r'));
+    type_text(&mut app, "Flaky CI");
+    match press(&mut app, KeyCode::Enter) {
+        Some(Action::Rename(session, name)) => {
+            assert_eq!(session.id, "tool-1");
+            assert_eq!(name, "Flaky CI");
+        }
+        other => panic!("expected rename, got {other:?}"),
+    }
+    assert!(matches!(
+        press(&mut app, KeyCode::Char('R')),
+        Some(Action::Resume(s)) if s.id == "tool-1"
+    ));
+
+    // Clearing the search brings every session back
+    press(&mut app, KeyCode::Tab);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.visible_sessions().len(), 2);
+    assert!(matches!(
+        press(&mut app, KeyCode::Char('q')),
+        Some(Action::Quit)
+    ));
+}
@@ -33,6 +33,7 @@ interface ServerSettings {
   http_port: number
   auth_enabled: boolean
   bind_addr: string
+  tls_enabled?: boolean
 }
 
 export default function Onboarding({ onComplete }: OnboardingProps) {
@@ -194,13 +195,13 @@ export default function Onboarding({ onComplete }: OnboardingProps) {
               </div>
               <ToggleRow
                 label={t('onboarding.steps.services.websocket')}
-                hint={`ws://${serverSettings.bind_addr}:${serverSettings.ws_port}`}
+                hint={`${serverSettings.tls_enabled ? 'wss' : 'ws'}://${serverSettings.bind_addr}:${serverSettings.ws_port}`}
                 checked={serverSettings.ws_enabled}
                 onChange={(v) => setServerSettings((s) => ({ ...s, ws_enabled: v }))}
               />
               <ToggleRow
                 label={t('onboarding.steps.services.httpApi')}
-                hint={`http://${serverSettings.bind_addr}:${serverSettings.http_port}/api`}
+                hint={`${serverSettings.tls_enabled ? 'https' : 'http'}://${serverSettings.bind_addr}:${serverSettings.http_port}/api`}
                 checked={serverSettings.http_enabled}
                 onChange={(v) => setServerSettings((s) => ({ ...s, http_enabled: v }))}
               />
@@ -0,0 +1,35 @@
+import { useTranslation } from 'react-i18next'
+import { useScanProgress } from '../hooks/useScanProgress'
+
+function formatMegabytes(bytes: number): string {
+  return (bytes / (1024 * 1024)).toFixed(1)
+}
+
+export default function ScanProgressBar() {
+  const { t } = useTranslation()
+  const progress = useScanProgress()
+
+  if (!progress || progress.files_total === 0) return null
+
+  const percent = Math.min(100, (progress.files_done / progress.files_total) * 100)
+
+  return (
+    <div className="px-3 py-2 border-b border-border/10">
+      <div className="flex items-center justify-between text-[11px] text-muted-foreground mb-1">
+        <span>
+          {t('session.list.scanning', {
+            done: progress.files_done,
+            total: progress.files_total,
+          })}
+        </span>
+        <span>{t('session.list.scannedSize', { size: formatMegabytes(progress.bytes_parsed) })}</span>
+      </div>
+      <div className="h-1 w-full bg-muted rounded overflow-hidden">
+        <div
+          className="h-full bg-primary transition-[width] duration-100"
+          style={{ width: `${percent}%` }}
+        />
+      </div>
+    </div>
+  )
+}
@@ -6,6 +6,7 @@ import { useVirtualizer } from '@tanstack/react-virtual'
 import type { SessionInfo, FavoriteItem, Tag } from '../types'
 import { Trash2, Search, Star, Clock, Tags } from 'lucide-react'
 import { SessionListSkeleton } from './Skeleton'
+import ScanProgressBar from './ScanProgressBar'
 import OpenInBrowserButton from './OpenInBrowserButton'
 import OpenInTerminalButton from './OpenInTerminalButton'
 import { SessionBadge } from './SessionBadge'
@@ -71,7 +72,12 @@ export default function SessionList({
   })
 
   if (loading) {
-    return <SessionListSkeleton showDirectory={showDirectory} />
+    return (
+      <>
+        <ScanProgressBar />
+        <SessionListSkeleton showDirectory={showDirectory} />
+      </>
+    )
   }
 
   if (sessions.length === 0) {
@@ -4,7 +4,8 @@
 
 import { useState, useEffect, useCallback } from 'react'
 import { useTranslation } from 'react-i18next'
-import { AlertTriangle, Plus, X, Copy, Trash2, Key, Shield, Server, FolderOpen, Settings2 } from 'lucide-react'
+import { AlertTriangle, Plus, X, Copy, Trash2, Key, Shield, Server, FolderOpen, Settings2, Database } from 'lucide-react'
+import { open, save } from '@tauri-apps/plugin-dialog'
 import { invoke } from '../../../transport'
 import Toggle from '../../ui/Toggle'
 import SettingsCard from '../SettingsCard'
@@ -15,6 +16,10 @@ interface ClearCacheResult {
   details_deleted: number
 }
 
+interface UserDataSummary {
+  rows: Record<string, number>
+}
+
 interface ServerSettings {
   ws_enabled: boolean
   ws_port: number
@@ -22,13 +27,23 @@ interface ServerSettings {
   http_port: number
   auth_enabled: boolean
   bind_addr: string
+  tls_enabled: boolean
+  tls_cert_path: string | null
+  tls_key_path: string | null
+  read_only: boolean
 }
 
+type KeyScope = 'search' | 'read' | 'write' | 'admin'
+
 interface TokenInfo {
   name: string
   key_preview: string
   created_at: string
   last_used: string | null
+  scope: KeyScope
+  expires_at: string | null
+  allowed_paths: string[]
+  expired: boolean
 }
 
 export default function AdvancedSettings({ settings, onUpdate }: AdvancedSettingsProps) {
@@ -40,6 +55,9 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
   const [keyMode, setKeyMode] = useState<'auto' | 'manual'>('auto')
   const [manualKey, setManualKey] = useState('')
   const [manualValue, setManualValue] = useState('')
+  const [keyScope, setKeyScope] = useState<KeyScope>('read')
+  const [expiresInDays, setExpiresInDays] = useState('')
+  const [allowedPaths, setAllowedPaths] = useState('')
   const [newKeyValue, setNewKeyValue] = useState<string | null>(null)
   const [creating, setCreating] = useState(false)
 
@@ -90,11 +108,16 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
         name,
         key: isManual ? key : undefined,
         value: isManual ? value : undefined,
+        scope: keyScope,
+        expiresInDays: parseInt(expiresInDays, 10) || undefined,
+        allowedPaths: allowedPaths.split(',').map((p) => p.trim()).filter(Boolean),
       })
       setNewKeyValue(created)
       setNewKeyName('')
       setManualKey('')
       setManualValue('')
+      setExpiresInDays('')
+      setAllowedPaths('')
       await loadApiKeys()
     } catch (e) {
       console.error('Failed to create API key:', e)
@@ -134,6 +157,40 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
     }
   }
 
+  const countRows = (summary: UserDataSummary) =>
+    Object.values(summary.rows).reduce((sum, n) => sum + n, 0)
+
+  const handleExportUserData = async () => {
+    const outputPath = await save({
+      filters: [{ name: 'JSON', extensions: ['json'] }],
+      defaultPath: `pi-user-data-${new Date().toISOString().slice(0, 10)}.json`
+    })
+    if (!outputPath) return
+    try {
+      const summary = await invoke<UserDataSummary>('export_user_data', { outputPath })
+      alert(t('settings.advanced.userDataExported', '已导出 {{rows}} 条记录', { rows: countRows(summary) }))
+    } catch (error) {
+      console.error('Failed to export user data:', error)
+      alert(t('settings.advanced.userDataExportFailed', '导出用户数据失败'))
+    }
+  }
+
+  const handleImportUserData = async () => {
+    const inputPath = await open({
+      multiple: false,
+      filters: [{ name: 'JSON', extensions: ['json'] }]
+    })
+    if (typeof inputPath !== 'string') return
+    if (!confirm(t('settings.advanced.importUserDataConfirm', '导入会覆盖同名的标签、收藏和设置，确定继续？'))) return
+    try {
+      const summary = await invoke<UserDataSummary>('import_user_data', { inputPath })
+      alert(t('settings.advanced.userDataImported', '已导入 {{rows}} 条记录', { rows: countRows(summary) }))
+    } catch (error) {
+      console.error('Failed to import user data:', error)
+      alert(t('settings.advanced.userDataImportFailed', '导入用户数据失败：{{error}}', { error: String(error) }))
+    }
+  }
+
   const isRemoteBind = serverSettings?.bind_addr === '0.0.0.0'
 
   const inputBase =
@@ -182,7 +239,7 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
               <div className="min-w-0">
                 <p className="text-sm font-medium text-foreground">WebSocket</p>
                 <p className="text-xs text-muted-foreground mt-0.5 font-mono">
-                  ws://{serverSettings.bind_addr}:{serverSettings.ws_port}
+                  {serverSettings.tls_enabled ? 'wss' : 'ws'}://{serverSettings.bind_addr}:{serverSettings.ws_port}
                 </p>
               </div>
               <Toggle
@@ -209,7 +266,7 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
               <div className="min-w-0">
                 <p className="text-sm font-medium text-foreground">HTTP API</p>
                 <p className="text-xs text-muted-foreground mt-0.5 font-mono">
-                  http://{serverSettings.bind_addr}:{serverSettings.http_port}/api
+                  {serverSettings.tls_enabled ? 'https' : 'http'}://{serverSettings.bind_addr}:{serverSettings.http_port}/api
                 </p>
               </div>
               <Toggle
@@ -243,6 +300,51 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
               />
             </div>
 
+            {/* Read-only */}
+            <div className="flex items-start justify-between gap-4 py-2 border-t border-border/60">
+              <div className="min-w-0">
+                <p className="text-sm font-medium text-foreground">{t('settings.advanced.readOnly', '只读模式')}</p>
+                <p className="text-xs text-muted-foreground mt-0.5">{t('settings.advanced.readOnlyHelp', '服务端拒绝删除、重命名、修改设置、终端和密钥管理等操作，本应用窗口不受影响')}</p>
+              </div>
+              <Toggle
+                checked={serverSettings.read_only}
+                onChange={(v) => updateServer('read_only', v)}
+              />
+            </div>
+
+            {/* TLS */}
+            <div className="flex items-start justify-between gap-4 py-2 border-t border-border/60">
+              <div className="min-w-0">
+                <p className="text-sm font-medium text-foreground">{t('settings.advanced.tls', 'TLS 加密')}</p>
+                <p className="text-xs text-muted-foreground mt-0.5">{t('settings.advanced.tlsHelp', '通过 HTTPS / WSS 提供服务，防止 Token 在局域网中明文传输')}</p>
+              </div>
+              <Toggle
+                checked={serverSettings.tls_enabled}
+                onChange={(v) => updateServer('tls_enabled', v)}
+              />
+            </div>
+            {serverSettings.tls_enabled && (
+              <div className="space-y-2">
+                <input
+                  type="text"
+                  value={serverSettings.tls_cert_path ?? ''}
+                  onChange={(e) => updateServer('tls_cert_path', e.target.value || null)}
+                  placeholder={t('settings.advanced.tlsCertPlaceholder', '证书路径（PEM，可选）')}
+                  className={`${inputBase} w-full font-mono`}
+                />
+                <input
+                  type="text"
+                  value={serverSettings.tls_key_path ?? ''}
+                  onChange={(e) => updateServer('tls_key_path', e.target.value || null)}
+                  placeholder={t('settings.advanced.tlsKeyPlaceholder', '私钥路径（PEM，可选）')}
+                  className={`${inputBase} w-full font-mono`}
+                />
+                <p className="text-xs text-muted-foreground">
+                  {t('settings.advanced.tlsSelfSignedHelp', '留空则自动生成自签名证书，其指纹会在启动时与认证 Token 一起打印')}
+                </p>
+              </div>
+            )}
+
             {serverDirty && (
               <div className="flex flex-wrap items-center gap-3 pt-2">
                 <button
@@ -279,12 +381,26 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
                     <div className="flex items-center gap-2 flex-wrap">
                       <span className="text-sm font-medium text-foreground">{k.name}</span>
                       <code className="text-xs text-muted-foreground font-mono truncate">{k.key_preview}</code>
+                      <span className="text-[10px] px-1.5 py-0.5 rounded bg-info/10 text-info">
+                        {t(`settings.advanced.keyScope${k.scope.charAt(0).toUpperCase()}${k.scope.slice(1)}`, k.scope)}
+                      </span>
+                      {k.expired && (
+                        <span className="text-[10px] px-1.5 py-0.5 rounded bg-red-500/10 text-red-400">
+                          {t('settings.advanced.keyExpired', '已过期')}
+                        </span>
+                      )}
                     </div>
                     <div className="text-xs text-muted-foreground mt-1">
                       {t('settings.advanced.keyCreated', '创建')}: {new Date(k.created_at).toLocaleDateString()}
                       {k.last_used && (
                         <> · {t('settings.advanced.keyLastUsed', '最后使用')}: {new Date(k.last_used).toLocaleDateString()}</>
                       )}
+                      {k.expires_at && !k.expired && (
+                        <> · {t('settings.advanced.keyExpires', '过期时间')}: {new Date(k.expires_at).toLocaleDateString()}</>
+                      )}
+                      {k.allowed_paths.length > 0 && (
+                        <div className="font-mono truncate">{k.allowed_paths.join(', ')}</div>
+                      )}
                     </div>
                   </div>
                   <button
@@ -374,6 +490,35 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
             )}
           </div>
 
+          <div className="grid grid-cols-1 md:grid-cols-3 gap-2">
+            <select
+              value={keyScope}
+              onChange={(e) => setKeyScope(e.target.value as KeyScope)}
+              className={selectBase}
+              title={t('settings.advanced.keyScope', '权限范围')}
+            >
+              <option value="search">{t('settings.advanced.keyScopeSearch', '仅搜索')}</option>
+              <option value="read">{t('settings.advanced.keyScopeRead', '只读')}</option>
+              <option value="write">{t('settings.advanced.keyScopeWrite', '读写（标签、收藏）')}</option>
+              <option value="admin">{t('settings.advanced.keyScopeAdmin', '管理员（终端、密钥、服务器）')}</option>
+            </select>
+            <input
+              type="number"
+              min={1}
+              value={expiresInDays}
+              onChange={(e) => setExpiresInDays(e.target.value)}
+              placeholder={t('settings.advanced.keyExpiresPlaceholder', '有效天数（留空为永久）')}
+              className={inputBase}
+            />
+            <input
+              type="text"
+              value={allowedPaths}
+              onChange={(e) => setAllowedPaths(e.target.value)}
+              placeholder={t('settings.advanced.keyAllowedPathsPlaceholder', '允许的项目目录，逗号分隔（留空为全部）')}
+              className={inputBase}
+            />
+          </div>
+
           <p className="text-xs text-muted-foreground">
             {keyMode === 'manual'
               ? t('settings.advanced.manualKeyHint', '手动模式下需要同时填写 Key 和 Value。')
@@ -508,6 +653,28 @@ export default function AdvancedSettings({ settings, onUpdate }: AdvancedSetting
         </div>
       </SettingsCard>
 
+      {/* User Data */}
+      <SettingsCard
+        title={t('settings.advanced.userData', '用户数据')}
+        description={t('settings.advanced.userDataHelp', '标签、收藏、设置与配置历史会定期自动备份；也可导出为 JSON 迁移到其他设备')}
+        icon={<Database className="h-4 w-4" />}
+      >
+        <div className="flex flex-wrap gap-3">
+          <button
+            onClick={handleExportUserData}
+            className="px-4 py-2 bg-info/10 text-info hover:bg-info/20 rounded-lg text-sm font-medium transition-colors"
+          >
+            {t('settings.advanced.exportUserData', '导出用户数据')}
+          </button>
+          <button
+            onClick={handleImportUserData}
+            className="px-4 py-2 bg-info/10 text-info hover:bg-info/20 rounded-lg text-sm font-medium transition-colors"
+          >
+            {t('settings.advanced.importUserData', '导入用户数据')}
+          </button>
+        </div>
+      </SettingsCard>
+
       {/* Actions */}
       <SettingsCard>
         <div className="flex flex-wrap gap-3">
@@ -0,0 +1,38 @@
+import { useEffect, useState } from 'react'
+import { listen } from '../transport'
+import type { ScanProgress } from '../types'
+
+/**
+ * 扫描进度 Hook
+ * 后端并行解析会话文件时推送 scan-progress，扫描结束后返回 null
+ */
+export function useScanProgress(enabled = true): ScanProgress | null {
+  const [progress, setProgress] = useState<ScanProgress | null>(null)
+
+  useEffect(() => {
+    if (!enabled) return
+
+    let unlisten: (() => void) | null = null
+    let disposed = false
+
+    listen<ScanProgress>('scan-progress', (event) => {
+      const next = event.payload
+      if (!next) return
+      setProgress(next.done ? null : next)
+    })
+      .then((fn) => {
+        if (disposed) fn()
+        else unlisten = fn
+      })
+      .catch((error) => {
+        console.error('[ScanProgress] Failed to setup listener:', error)
+      })
+
+    return () => {
+      disposed = true
+      if (unlisten) unlisten()
+    }
+  }, [enabled])
+
+  return progress
+}
@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: 'Keine Sitzungen gefunden',
     loading: 'Sitzungen werden geladen...',
+    scanning: 'Sitzungen werden gescannt {{done}}/{{total}}',
+    scannedSize: '{{size}} MB verarbeitet',
     untitled: 'Unbenannte Sitzung',
     unknownDirectory: 'Unbekanntes Verzeichnis',
     messages: 'Nachrichten',
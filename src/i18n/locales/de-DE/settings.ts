@@ -125,6 +125,22 @@ export const settings = {
     apiKeysHelp: 'Für Fernverbindungs-Authentifizierung über Authorization: Bearer <key>',
     keyCreated: 'Erstellt',
     keyLastUsed: 'Zuletzt verwendet',
+    keyScope: 'Berechtigung',
+    keyScopeSearch: 'Nur Suche',
+    keyScopeRead: 'Nur lesen',
+    keyScopeWrite: 'Lesen & schreiben (Tags, Favoriten)',
+    keyScopeAdmin: 'Admin (Terminal, Schlüssel, Server)',
+    keyExpiresPlaceholder: 'Gültig für Tage (leer = unbegrenzt)',
+    keyAllowedPathsPlaceholder: 'Erlaubte Projektverzeichnisse, kommagetrennt (leer = alle)',
+    keyExpires: 'Läuft ab',
+    keyExpired: 'Abgelaufen',
+    readOnly: 'Nur-Lese-Modus',
+    readOnlyHelp: 'Server-Clients können nicht löschen, umbenennen, Einstellungen ändern, Terminals öffnen oder Schlüssel verwalten. Dieses App-Fenster ist nicht betroffen',
+    tls: 'TLS-Verschlüsselung',
+    tlsHelp: 'Über HTTPS / WSS bereitstellen, damit Tokens nie im Klartext durchs LAN gehen',
+    tlsCertPlaceholder: 'Zertifikatspfad (PEM, optional)',
+    tlsKeyPlaceholder: 'Pfad zum privaten Schlüssel (PEM, optional)',
+    tlsSelfSignedHelp: 'Leer lassen, um ein selbstsigniertes Zertifikat zu erzeugen; sein Fingerabdruck wird beim Start neben dem Auth-Token ausgegeben',
     revokeKey: 'Widerrufen',
     revokeKeyConfirm: 'Möchten Sie diesen Schlüssel wirklich widerrufen? Diese Aktion kann nicht rückgängig gemacht werden.',
     newKeyCreated: 'Schlüssel erstellt. Bitte kopieren und speichern Sie ihn jetzt, der vollständige Schlüssel wird nicht erneut angezeigt.',
@@ -148,6 +164,15 @@ export const settings = {
     clearCacheConfirm: 'Möchten Sie wirklich alle zwischengespeicherten Daten löschen? Alle Sitzungs-Caches werden gelöscht, Favoriten bleiben erhalten.',
     cacheClearedDetail: 'Cache geleert: {{sessions}} Sitzungen, {{details}} Details',
     cacheClearFailed: 'Cache konnte nicht geleert werden',
+    userData: 'Benutzerdaten',
+    userDataHelp: 'Tags, Favoriten, Einstellungen und Konfigurationsverlauf werden automatisch gesichert; als JSON exportieren, um sie auf ein anderes Gerät zu übertragen',
+    exportUserData: 'Benutzerdaten exportieren',
+    importUserData: 'Benutzerdaten importieren',
+    userDataExported: '{{rows}} Einträge exportiert',
+    userDataExportFailed: 'Benutzerdaten konnten nicht exportiert werden',
+    importUserDataConfirm: 'Der Import ersetzt Tags, Favoriten und Einstellungen mit denselben IDs. Fortfahren?',
+    userDataImported: '{{rows}} Einträge importiert',
+    userDataImportFailed: 'Benutzerdaten konnten nicht importiert werden: {{error}}',
     showOnboarding: 'Einführung anzeigen',
     onboardingReset: 'Die Einführung wird beim nächsten Öffnen der App angezeigt',
   },
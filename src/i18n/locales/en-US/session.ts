@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: 'No sessions found',
     loading: 'Loading sessions...',
+    scanning: 'Scanning sessions {{done}}/{{total}}',
+    scannedSize: '{{size}} MB parsed',
     untitled: 'Untitled Session',
     unknownDirectory: 'Unknown directory',
     messages: 'messages',
@@ -127,6 +127,22 @@ export const settings = {
     apiKeysHelp: 'Used for remote connection authentication via Authorization: Bearer <key>',
     keyCreated: 'Created',
     keyLastUsed: 'Last used',
+    keyScope: 'Scope',
+    keyScopeSearch: 'Search only',
+    keyScopeRead: 'Read-only',
+    keyScopeWrite: 'Read & write (tags, favorites)',
+    keyScopeAdmin: 'Admin (terminal, keys, server)',
+    keyExpiresPlaceholder: 'Expires in days (blank = never)',
+    keyAllowedPathsPlaceholder: 'Allowed project dirs, comma-separated (blank = all)',
+    keyExpires: 'Expires',
+    keyExpired: 'Expired',
+    readOnly: 'Read-only mode',
+    readOnlyHelp: 'Server clients cannot delete, rename, change settings, open terminals or manage keys. This app window is not affected',
+    tls: 'TLS encryption',
+    tlsHelp: 'Serve over HTTPS / WSS so tokens never cross the LAN in cleartext',
+    tlsCertPlaceholder: 'Certificate path (PEM, optional)',
+    tlsKeyPlaceholder: 'Private key path (PEM, optional)',
+    tlsSelfSignedHelp: 'Leave empty to generate a self-signed certificate; its fingerprint is printed next to the auth token at startup',
     revokeKey: 'Revoke',
     revokeKeyConfirm: 'Are you sure you want to revoke this key? This action cannot be undone.',
     newKeyCreated: 'Key created. Please copy and save it now, the full key will not be shown again.',
@@ -159,6 +175,15 @@ export const settings = {
     clearCacheConfirm: 'Are you sure you want to clear all cached data? This will delete all session caches but keep favorites.',
     cacheClearedDetail: 'Cache cleared: {{sessions}} sessions, {{details}} details',
     cacheClearFailed: 'Failed to clear cache',
+    userData: 'User Data',
+    userDataHelp: 'Tags, favorites, settings and config history are backed up automatically; export them as JSON to move to another machine',
+    exportUserData: 'Export User Data',
+    importUserData: 'Import User Data',
+    userDataExported: 'Exported {{rows}} records',
+    userDataExportFailed: 'Failed to export user data',
+    importUserDataConfirm: 'Importing replaces tags, favorites and settings with the same IDs. Continue?',
+    userDataImported: 'Imported {{rows}} records',
+    userDataImportFailed: 'Failed to import user data: {{error}}',
     showOnboarding: 'Show Onboarding Guide',
     onboardingReset: 'Onboarding guide will show next time you open the app',
   },
@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: 'No se encontraron sesiones',
     loading: 'Cargando sesiones...',
+    scanning: 'Escaneando sesiones {{done}}/{{total}}',
+    scannedSize: '{{size}} MB analizados',
     untitled: 'Sesión sin título',
     unknownDirectory: 'Directorio desconocido',
     messages: 'mensajes',
@@ -125,6 +125,22 @@ export const settings = {
     apiKeysHelp: 'Usadas para autenticación de conexión remota vía Authorization: Bearer <key>',
     keyCreated: 'Creada',
     keyLastUsed: 'Último uso',
+    keyScope: 'Alcance',
+    keyScopeSearch: 'Solo búsqueda',
+    keyScopeRead: 'Solo lectura',
+    keyScopeWrite: 'Lectura y escritura (etiquetas, favoritos)',
+    keyScopeAdmin: 'Administrador (terminal, claves, servidor)',
+    keyExpiresPlaceholder: 'Caduca en días (vacío = nunca)',
+    keyAllowedPathsPlaceholder: 'Directorios de proyecto permitidos, separados por comas (vacío = todos)',
+    keyExpires: 'Caduca',
+    keyExpired: 'Caducada',
+    readOnly: 'Modo de solo lectura',
+    readOnlyHelp: 'Los clientes del servidor no pueden borrar, renombrar, cambiar ajustes, abrir terminales ni gestionar claves. Esta ventana no se ve afectada',
+    tls: 'Cifrado TLS',
+    tlsHelp: 'Servir por HTTPS / WSS para que los tokens no viajen en claro por la LAN',
+    tlsCertPlaceholder: 'Ruta del certificado (PEM, opcional)',
+    tlsKeyPlaceholder: 'Ruta de la clave privada (PEM, opcional)',
+    tlsSelfSignedHelp: 'Déjalo vacío para generar un certificado autofirmado; su huella se muestra junto al token al iniciar',
     revokeKey: 'Revocar',
     revokeKeyConfirm: '¿Seguro que quieres revocar esta clave? Esta acción no se puede deshacer.',
     newKeyCreated: 'Clave creada. Cópiala y guárdala ahora, la clave completa no se mostrará de nuevo.',
@@ -148,6 +164,15 @@ export const settings = {
     clearCacheConfirm: '¿Seguro que quieres limpiar todos los datos en caché? Se eliminarán todas las cachés de sesión pero se conservarán los favoritos.',
     cacheClearedDetail: 'Caché limpiada: {{sessions}} sesiones, {{details}} detalles',
     cacheClearFailed: 'Error al limpiar la caché',
+    userData: 'Datos de usuario',
+    userDataHelp: 'Las etiquetas, favoritos, ajustes e historial de configuración se respaldan automáticamente; expórtalos como JSON para llevarlos a otro equipo',
+    exportUserData: 'Exportar datos de usuario',
+    importUserData: 'Importar datos de usuario',
+    userDataExported: '{{rows}} registros exportados',
+    userDataExportFailed: 'Error al exportar los datos de usuario',
+    importUserDataConfirm: 'La importación reemplaza las etiquetas, favoritos y ajustes con los mismos ID. ¿Continuar?',
+    userDataImported: '{{rows}} registros importados',
+    userDataImportFailed: 'Error al importar los datos de usuario: {{error}}',
     showOnboarding: 'Mostrar guía de inicio',
     onboardingReset: 'La guía de inicio se mostrará la próxima vez que abras la aplicación',
   },
@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: 'Aucune session trouvée',
     loading: 'Chargement des sessions...',
+    scanning: 'Analyse des sessions {{done}}/{{total}}',
+    scannedSize: '{{size}} Mo analysés',
     untitled: 'Session sans titre',
     unknownDirectory: 'Répertoire inconnu',
     messages: 'messages',
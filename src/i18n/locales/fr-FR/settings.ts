@@ -125,6 +125,22 @@ export const settings = {
     apiKeysHelp: 'Utilisées pour l\'authentification des connexions distantes via Authorization: Bearer <key>',
     keyCreated: 'Créée',
     keyLastUsed: 'Dernière utilisation',
+    keyScope: 'Portée',
+    keyScopeSearch: 'Recherche uniquement',
+    keyScopeRead: 'Lecture seule',
+    keyScopeWrite: 'Lecture et écriture (tags, favoris)',
+    keyScopeAdmin: 'Administrateur (terminal, clés, serveur)',
+    keyExpiresPlaceholder: 'Expire dans (jours, vide = jamais)',
+    keyAllowedPathsPlaceholder: 'Dossiers de projet autorisés, séparés par des virgules (vide = tous)',
+    keyExpires: 'Expire',
+    keyExpired: 'Expirée',
+    readOnly: 'Mode lecture seule',
+    readOnlyHelp: 'Les clients du serveur ne peuvent ni supprimer, ni renommer, ni modifier les réglages, ni ouvrir de terminal, ni gérer les clés. Cette fenêtre n’est pas concernée',
+    tls: 'Chiffrement TLS',
+    tlsHelp: 'Servir en HTTPS / WSS pour que les jetons ne circulent jamais en clair sur le réseau local',
+    tlsCertPlaceholder: 'Chemin du certificat (PEM, facultatif)',
+    tlsKeyPlaceholder: 'Chemin de la clé privée (PEM, facultatif)',
+    tlsSelfSignedHelp: 'Laissez vide pour générer un certificat auto-signé ; son empreinte est affichée à côté du jeton au démarrage',
     revokeKey: 'Révoquer',
     revokeKeyConfirm: 'Êtes-vous sûr de vouloir révoquer cette clé ? Cette action est irréversible.',
     newKeyCreated: 'Clé créée. Veuillez la copier et la sauvegarder maintenant, la clé complète ne sera plus affichée.',
@@ -148,6 +164,15 @@ export const settings = {
     clearCacheConfirm: 'Êtes-vous sûr de vouloir vider toutes les données en cache ? Cela supprimera tous les caches de session mais conservera les favoris.',
     cacheClearedDetail: 'Cache vidé : {{sessions}} sessions, {{details}} détails',
     cacheClearFailed: 'Échec du vidage du cache',
+    userData: 'Données utilisateur',
+    userDataHelp: 'Les tags, favoris, paramètres et l\'historique de configuration sont sauvegardés automatiquement ; exportez-les en JSON pour les transférer sur une autre machine',
+    exportUserData: 'Exporter les données utilisateur',
+    importUserData: 'Importer les données utilisateur',
+    userDataExported: '{{rows}} enregistrements exportés',
+    userDataExportFailed: 'Échec de l\'exportation des données utilisateur',
+    importUserDataConfirm: 'L\'importation remplace les tags, favoris et paramètres ayant les mêmes identifiants. Continuer ?',
+    userDataImported: '{{rows}} enregistrements importés',
+    userDataImportFailed: 'Échec de l\'importation des données utilisateur : {{error}}',
     showOnboarding: 'Afficher le guide d\'accueil',
     onboardingReset: 'Le guide d\'accueil s\'affichera à la prochaine ouverture de l\'application',
   },
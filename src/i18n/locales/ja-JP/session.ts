@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: 'セッションが見つかりません',
     loading: 'セッション読み込み中...',
+    scanning: 'セッションをスキャン中 {{done}}/{{total}}',
+    scannedSize: '{{size}} MB 解析済み',
     untitled: '無題のセッション',
     unknownDirectory: '不明なディレクトリ',
     messages: '件のメッセージ',
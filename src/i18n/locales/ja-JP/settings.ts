@@ -125,6 +125,22 @@ export const settings = {
     apiKeysHelp: 'リモート接続認証用。Authorization: Bearer <key> で使用',
     keyCreated: '作成日',
     keyLastUsed: '最終使用',
+    keyScope: 'スコープ',
+    keyScopeSearch: '検索のみ',
+    keyScopeRead: '読み取り専用',
+    keyScopeWrite: '読み書き（タグ・お気に入り）',
+    keyScopeAdmin: '管理者（ターミナル・キー・サーバー）',
+    keyExpiresPlaceholder: '有効日数（空欄で無期限）',
+    keyAllowedPathsPlaceholder: '許可するプロジェクトディレクトリ（カンマ区切り、空欄で全て）',
+    keyExpires: '有効期限',
+    keyExpired: '期限切れ',
+    readOnly: '読み取り専用モード',
+    readOnlyHelp: 'サーバーのクライアントは削除・名前変更・設定変更・ターミナル・キー管理を行えません。このアプリのウィンドウには影響しません',
+    tls: 'TLS 暗号化',
+    tlsHelp: 'HTTPS / WSS で提供し、トークンが LAN 上を平文で流れないようにします',
+    tlsCertPlaceholder: '証明書のパス（PEM、任意）',
+    tlsKeyPlaceholder: '秘密鍵のパス（PEM、任意）',
+    tlsSelfSignedHelp: '空欄の場合は自己署名証明書を生成し、起動時にそのフィンガープリントを認証トークンと一緒に表示します',
     revokeKey: '取消',
     revokeKeyConfirm: 'このキーを取り消しますか？この操作は元に戻せません。',
     newKeyCreated: 'キーが作成されました。今すぐコピーして保存してください。完全なキーは再表示されません。',
@@ -148,6 +164,15 @@ export const settings = {
     clearCacheConfirm: 'すべてのキャッシュデータをクリアしますか？セッションキャッシュは削除されますが、お気に入りは保持されます。',
     cacheClearedDetail: 'キャッシュクリア完了：{{sessions}} セッション、{{details}} 件の詳細キャッシュ',
     cacheClearFailed: 'キャッシュのクリアに失敗',
+    userData: 'ユーザーデータ',
+    userDataHelp: 'タグ、お気に入り、設定、設定履歴は自動的にバックアップされます。JSON としてエクスポートして別のマシンへ移行できます',
+    exportUserData: 'ユーザーデータをエクスポート',
+    importUserData: 'ユーザーデータをインポート',
+    userDataExported: '{{rows}} 件をエクスポートしました',
+    userDataExportFailed: 'ユーザーデータのエクスポートに失敗',
+    importUserDataConfirm: 'インポートすると同じ ID のタグ、お気に入り、設定が置き換えられます。続行しますか？',
+    userDataImported: '{{rows}} 件をインポートしました',
+    userDataImportFailed: 'ユーザーデータのインポートに失敗: {{error}}',
     showOnboarding: 'オンボーディングガイドを再表示',
     onboardingReset: '次回アプリ起動時にガイドが表示されます',
   },
@@ -8,6 +8,8 @@ export const session = {
   list: {
     empty: '未找到会话',
     loading: '加载会话中...',
+    scanning: '正在扫描会话 {{done}}/{{total}}',
+    scannedSize: '已解析 {{size}} MB',
     untitled: '未命名会话',
     unknownDirectory: '未知目录',
     messages: '条消息',
@@ -127,6 +127,22 @@ export const settings = {
     apiKeysHelp: '用于远程连接认证，通过 Authorization: Bearer <key> 使用',
     keyCreated: '创建',
     keyLastUsed: '最后使用',
+    keyScope: '权限范围',
+    keyScopeSearch: '仅搜索',
+    keyScopeRead: '只读',
+    keyScopeWrite: '读写（标签、收藏）',
+    keyScopeAdmin: '管理员（终端、密钥、服务器）',
+    keyExpiresPlaceholder: '有效天数（留空为永久）',
+    keyAllowedPathsPlaceholder: '允许的项目目录，逗号分隔（留空为全部）',
+    keyExpires: '过期时间',
+    keyExpired: '已过期',
+    readOnly: '只读模式',
+    readOnlyHelp: '服务端拒绝删除、重命名、修改设置、终端和密钥管理等操作，本应用窗口不受影响',
+    tls: 'TLS 加密',
+    tlsHelp: '通过 HTTPS / WSS 提供服务，防止 Token 在局域网中明文传输',
+    tlsCertPlaceholder: '证书路径（PEM，可选）',
+    tlsKeyPlaceholder: '私钥路径（PEM，可选）',
+    tlsSelfSignedHelp: '留空则自动生成自签名证书，其指纹会在启动时与认证 Token 一起打印',
     revokeKey: '吊销',
     revokeKeyConfirm: '确定要吊销此密钥？此操作不可撤销。',
     newKeyCreated: '密钥已创建，请立即复制保存，此后不再显示完整密钥。',
@@ -159,6 +175,15 @@ export const settings = {
     clearCacheConfirm: '确定要清除所有缓存数据吗？这将删除所有会话缓存，但保留收藏夹。',
     cacheClearedDetail: '缓存已清除：{{sessions}} 个会话，{{details}} 个详情缓存',
     cacheClearFailed: '清除缓存失败',
+    userData: '用户数据',
+    userDataHelp: '标签、收藏、设置与配置历史会定期自动备份；也可导出为 JSON 迁移到其他设备',
+    exportUserData: '导出用户数据',
+    importUserData: '导入用户数据',
+    userDataExported: '已导出 {{rows}} 条记录',
+    userDataExportFailed: '导出用户数据失败',
+    importUserDataConfirm: '导入会覆盖同名的标签、收藏和设置，确定继续？',
+    userDataImported: '已导入 {{rows}} 条记录',
+    userDataImportFailed: '导入用户数据失败：{{error}}',
     showOnboarding: '重新显示新手引导',
     onboardingReset: '下次打开应用时将显示引导',
   },
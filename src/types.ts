@@ -21,6 +21,13 @@ export interface SessionsDiff {
   removed: string[]
 }
 
+export interface ScanProgress {
+  files_done: number
+  files_total: number
+  bytes_parsed: number
+  done: boolean
+}
+
 export interface FavoriteItem {
   type: 'session' | 'project'
   id: string
@@ -201,7 +208,7 @@ export interface Match {
   entry_id: string
   role: string
   snippet: string
-  timestamp: string
+  timestamp?: string
 }
 
 export interface FullTextSearchHit {
@@ -211,6 +218,7 @@ export interface FullTextSearchHit {
   entry_id: string
   role: string
   content: string
+  snippet?: string
   timestamp: string
   score: number
 }